@@ -21,6 +21,13 @@ pub struct TuiConfig {
     pub theme: String,
     pub page_size: usize,
     pub show_help_on_start: bool,
+    /// How the diff view opens: `unified` or `side-by-side`
+    #[serde(default = "default_diff_layout")]
+    pub diff_layout: String,
+}
+
+fn default_diff_layout() -> String {
+    "unified".to_string()
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -45,6 +52,7 @@ impl Default for TuiConfig {
             theme: "dark".to_string(),
             page_size: 20,
             show_help_on_start: false,
+            diff_layout: default_diff_layout(),
         }
     }
 }
@@ -110,6 +118,18 @@ mod tests {
         assert!(!config.git.auto_fetch);
     }
 
+    #[test]
+    fn test_config_without_diff_layout() {
+        // Files written before the setting existed still load
+        let config: Config = toml::from_str(
+            "[general]\nmax_commits = 50\ndate_format = \"%Y\"\nverbose = false\n\
+             [tui]\ntheme = \"light\"\npage_size = 20\nshow_help_on_start = false\n\
+             [git]\nauto_fetch = false\nfetch_remotes = false\n",
+        )
+        .unwrap();
+        assert_eq!(config.tui.diff_layout, "unified");
+    }
+
     #[test]
     fn test_config_serde_roundtrip() {
         let config = Config::default();
@@ -1,14 +1,12 @@
 use anyhow::{Context, Result};
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
 use openisl_git::{
-    add_paths, amend_commit, apply_patch, bisect_bad, bisect_good, bisect_reset, bisect_skip,
-    bisect_start, checkout, cherry_pick_commit, clone, commit, create_branch, create_tag,
-    delete_tag, fetch, get_blame, get_branches, get_commit_diff, get_commits, get_commits_filtered,
-    get_conflicted_files, get_current_branch, get_diff, get_file_at_revision, get_stash_list,
-    get_status, init, mark_resolved, merge, move_file, pull, push, rebase, remote_add, remote_list,
-    remote_remove, remove_file, reset, revert_commit, squash_commits, stage_all, stash_apply,
-    stash_drop, stash_pop, stash_push, tag_list, undo_last, ResetMode, SmartLogFormatter,
-    StatusType,
+    abbreviate_hash, apply_patch, clone, create_tag, delete_tag, edit_text, fetch, format_todo,
+    get_conflicted_files, get_file_at_revision, init, mark_resolved, move_file, parse_todo, push,
+    remote_add, remote_list, remote_remove, remove_file, tag_list, BisectState, BisectVerdict,
+    BlameOptions, ChangeKind, ChangePatch, ChangeSegment, Commit, FileDiff, HistoryEditOutcome,
+    HunkLineType, LogOrder, LogQuery, RebaseAction, RepoState, ResetMode, SmartLogFormatter,
+    SplitFile, SplitPart, StackMove, StopReason, Vcs, VcsRefType,
 };
 mod config;
 use config::Config;
@@ -29,12 +27,8 @@ enum Commands {
     Log {
         #[arg(long, help = "Show as ASCII in terminal")]
         simple: bool,
-        #[arg(short, long, help = "Show commits from specific branch")]
-        branch: Option<String>,
-        #[arg(long, help = "Include remote branches")]
-        remote: bool,
-        #[arg(short = 'n', long, help = "Maximum number of commits to show")]
-        max_count: Option<usize>,
+        #[command(flatten)]
+        filter: LogFilter,
     },
 
     #[command(about = "Launch interactive TUI for commit history")]
@@ -101,6 +95,8 @@ enum Commands {
     Show {
         #[arg(help = "Commit hash or revision to show")]
         commit: String,
+        #[arg(long, help = "Summarize the changed files instead of the patch")]
+        stat: bool,
     },
 
     #[command(about = "Show working tree status")]
@@ -112,6 +108,8 @@ enum Commands {
         staged: bool,
         #[arg(help = "Show changes for specific commit")]
         commit: Option<String>,
+        #[arg(long, help = "Summarize the changed files instead of the patch")]
+        stat: bool,
     },
 
     #[command(about = "Merge a branch or commit into the current branch")]
@@ -186,6 +184,17 @@ enum Commands {
     Blame {
         #[arg(help = "Path to annotate")]
         path: String,
+        #[arg(short, long, help = "Blame the file as of this revision")]
+        revision: Option<String>,
+        #[arg(short = 'w', help = "Ignore whitespace changes")]
+        ignore_whitespace: bool,
+        #[arg(short = 'M', help = "Follow lines moved or copied within the file")]
+        moves: bool,
+        #[arg(
+            short = 'C',
+            help = "Follow lines moved or copied from other files in the same commit"
+        )]
+        copies: bool,
     },
 
     #[command(about = "Configure openisl settings")]
@@ -198,6 +207,12 @@ enum Commands {
         theme: Option<String>,
         #[arg(long, help = "Set max commits")]
         max_commits: Option<usize>,
+        #[arg(
+            long,
+            value_name = "LAYOUT",
+            help = "Set how the TUI diff view opens (unified/side-by-side)"
+        )]
+        diff_layout: Option<String>,
     },
 
     #[command(about = "Manage git remotes")]
@@ -254,8 +269,37 @@ enum Commands {
         paths: Vec<String>,
     },
 
-    #[command(about = "Undo the last operation (destructive)")]
-    Undo,
+    #[command(about = "Continue the rebase, merge, cherry-pick or revert in progress")]
+    Continue,
+
+    #[command(about = "Abort the operation in progress (or end a bisect)")]
+    Abort,
+
+    #[command(about = "Skip the commit the operation in progress stopped at")]
+    Skip,
+
+    #[command(about = "Undo the last operation, or restore the state before OPERATION")]
+    Undo {
+        #[arg(help = "Operation id from `openisl oplog`")]
+        operation: Option<String>,
+    },
+
+    #[command(about = "Redo the last undone operation")]
+    Redo,
+
+    #[command(about = "Show where HEAD or a branch has pointed")]
+    Reflog {
+        #[arg(help = "Reference whose reflog to show (default: HEAD)")]
+        reference: Option<String>,
+        #[arg(short = 'n', long, help = "Maximum number of entries to show")]
+        max_count: Option<usize>,
+    },
+
+    #[command(about = "Show the operation log")]
+    Oplog {
+        #[arg(short = 'n', long, help = "Maximum number of operations to show")]
+        max_count: Option<usize>,
+    },
 
     #[command(about = "Squash commits up to a revision into one")]
     Squash {
@@ -264,6 +308,137 @@ enum Commands {
         #[arg(short, long, help = "Message for the squashed commit")]
         message: String,
     },
+
+    #[command(about = "Split a commit into several by picking hunks and lines")]
+    Split {
+        #[arg(default_value = "HEAD", help = "Commit to split")]
+        commit: String,
+    },
+
+    #[command(about = "Fold staged changes into the stack commits that last touched them")]
+    Absorb {
+        #[arg(short = 'n', long, help = "Only show where each hunk would go")]
+        dry_run: bool,
+    },
+
+    #[command(about = "Show the stack of draft commits above trunk")]
+    Stack,
+
+    #[command(about = "Move commits left on rewritten parents onto the new versions")]
+    Restack,
+
+    #[command(about = "Check out the next commit up the stack")]
+    Next {
+        #[arg(default_value_t = 1, help = "Number of commits to move")]
+        count: usize,
+    },
+
+    #[command(about = "Check out the previous commit down the stack")]
+    Prev {
+        #[arg(default_value_t = 1, help = "Number of commits to move")]
+        count: usize,
+    },
+}
+
+#[derive(Args)]
+struct LogFilter {
+    #[arg(help = "Revisions or ranges to show (e.g. main..feature)")]
+    revisions: Vec<String>,
+    #[arg(
+        short = 'r',
+        long = "rev",
+        value_name = "REVSET",
+        conflicts_with_all = [
+            "revisions", "branch", "remote", "skip", "since", "until", "author", "grep",
+            "fixed_strings", "ignore_case", "first_parent", "no_merges", "topo_order", "date_order", "paths", "follow",
+        ],
+        help = "Select commits with a revset, e.g. 'draft() & author(alice)'"
+    )]
+    rev: Option<String>,
+    #[arg(short, long, help = "Show commits from specific branch")]
+    branch: Option<String>,
+    #[arg(long, help = "Include remote branches")]
+    remote: bool,
+    #[arg(short = 'n', long, help = "Maximum number of commits to show")]
+    max_count: Option<usize>,
+    #[arg(long, help = "Skip this many commits before showing any")]
+    skip: Option<usize>,
+    #[arg(long, help = "Show commits newer than a date")]
+    since: Option<String>,
+    #[arg(long, help = "Show commits older than a date")]
+    until: Option<String>,
+    #[arg(long, help = "Only commits whose author matches a pattern")]
+    author: Option<String>,
+    #[arg(long, help = "Only commits whose message matches a pattern")]
+    grep: Option<String>,
+    #[arg(
+        short = 'F',
+        long,
+        help = "Match --author/--grep patterns literally, not as regexes"
+    )]
+    fixed_strings: bool,
+    #[arg(
+        short = 'i',
+        long = "regexp-ignore-case",
+        help = "Match --author/--grep patterns regardless of case"
+    )]
+    ignore_case: bool,
+    #[arg(long, help = "Follow only the first parent of merges")]
+    first_parent: bool,
+    #[arg(long, help = "Leave out merge commits")]
+    no_merges: bool,
+    #[arg(
+        long,
+        conflicts_with = "date_order",
+        help = "Keep lines of history together"
+    )]
+    topo_order: bool,
+    #[arg(long, help = "Order by commit date, parents after children")]
+    date_order: bool,
+    #[arg(
+        long,
+        value_name = "PATH",
+        conflicts_with = "paths",
+        help = "History of one file, following it across renames"
+    )]
+    follow: Option<String>,
+    #[arg(last = true, help = "Only commits touching these paths")]
+    paths: Vec<String>,
+}
+
+impl LogFilter {
+    fn to_query(&self) -> LogQuery {
+        let mut query = LogQuery {
+            revisions: self.revisions.clone(),
+            paths: self.paths.clone(),
+            since: self.since.clone(),
+            until: self.until.clone(),
+            author: self.author.clone(),
+            grep: self.grep.clone(),
+            fixed_strings: self.fixed_strings,
+            ignore_case: self.ignore_case,
+            first_parent: self.first_parent,
+            no_merges: self.no_merges,
+            max_count: self.max_count,
+            skip: self.skip,
+            ..LogQuery::default()
+        };
+        // `--branch` is kept as a shorthand for a single revision
+        if let Some(ref branch) = self.branch {
+            query = query.revision(branch.clone());
+        } else if self.remote && self.revisions.is_empty() {
+            query = query.remotes();
+        }
+        if let Some(ref path) = self.follow {
+            query = query.path(path.clone()).follow();
+        }
+        if self.topo_order {
+            query = query.order(LogOrder::Topo);
+        } else if self.date_order {
+            query = query.order(LogOrder::Date);
+        }
+        query
+    }
 }
 
 #[derive(Subcommand)]
@@ -287,6 +462,16 @@ enum BisectAction {
     },
     #[command(about = "Skip the current revision")]
     Skip,
+    #[command(about = "Test revisions with a command until the first bad one is found")]
+    Run {
+        #[arg(
+            required = true,
+            trailing_var_arg = true,
+            allow_hyphen_values = true,
+            help = "Test command; exit 0 is good, 125 skips, 1-127 is bad"
+        )]
+        command: Vec<String>,
+    },
     #[command(about = "End the bisect session")]
     Reset,
 }
@@ -321,13 +506,8 @@ fn main() -> Result<()> {
     let cli = Cli::parse();
 
     match &cli.command {
-        Commands::Log {
-            simple,
-            branch,
-            remote,
-            max_count,
-        } => {
-            cmd_log(*simple, branch.as_deref(), *remote, *max_count)?;
+        Commands::Log { simple, filter } => {
+            cmd_log(*simple, filter)?;
         }
         Commands::Tui => {
             cmd_tui()?;
@@ -356,14 +536,18 @@ fn main() -> Result<()> {
         Commands::Commit { message, amend } => {
             cmd_commit(message.as_deref(), *amend)?;
         }
-        Commands::Show { commit } => {
-            cmd_show(commit)?;
+        Commands::Show { commit, stat } => {
+            cmd_show(commit, *stat)?;
         }
         Commands::Status => {
             cmd_status()?;
         }
-        Commands::Diff { staged, commit } => {
-            cmd_diff(*staged, commit.as_deref())?;
+        Commands::Diff {
+            staged,
+            commit,
+            stat,
+        } => {
+            cmd_diff(*staged, commit.as_deref(), *stat)?;
         }
         Commands::Merge { target } => {
             cmd_merge(target)?;
@@ -400,16 +584,35 @@ fn main() -> Result<()> {
         } => {
             cmd_push(remote.as_deref(), branch.as_deref(), *tags, *set_upstream)?;
         }
-        Commands::Blame { path } => {
-            cmd_blame(path)?;
+        Commands::Blame {
+            path,
+            revision,
+            ignore_whitespace,
+            moves,
+            copies,
+        } => {
+            let options = BlameOptions {
+                revision: revision.clone(),
+                ignore_whitespace: *ignore_whitespace,
+                detect_moves: *moves,
+                detect_copies: *copies,
+            };
+            cmd_blame(path, &options)?;
         }
         Commands::Config {
             show,
             reset,
             theme,
             max_commits,
+            diff_layout,
         } => {
-            cmd_config(*show, *reset, theme.as_deref(), *max_commits)?;
+            cmd_config(
+                *show,
+                *reset,
+                theme.as_deref(),
+                *max_commits,
+                diff_layout.as_deref(),
+            )?;
         }
         Commands::Remote {
             list,
@@ -444,26 +647,73 @@ fn main() -> Result<()> {
         Commands::Resolve { list, paths } => {
             cmd_resolve(*list, paths)?;
         }
-        Commands::Undo => {
-            cmd_undo()?;
+        Commands::Undo { operation } => {
+            cmd_undo(operation.as_deref())?;
+        }
+        Commands::Redo => {
+            cmd_redo()?;
+        }
+        Commands::Reflog {
+            reference,
+            max_count,
+        } => {
+            cmd_reflog(reference.as_deref(), *max_count)?;
+        }
+        Commands::Oplog { max_count } => {
+            cmd_oplog(*max_count)?;
         }
         Commands::Squash { commit, message } => {
             cmd_squash(commit, message)?;
         }
+        Commands::Split { commit } => {
+            cmd_split(commit)?;
+        }
+        Commands::Absorb { dry_run } => {
+            cmd_absorb(*dry_run)?;
+        }
+        Commands::Continue => {
+            cmd_operation(Resume::Continue)?;
+        }
+        Commands::Abort => {
+            cmd_operation(Resume::Abort)?;
+        }
+        Commands::Skip => {
+            cmd_operation(Resume::Skip)?;
+        }
+        Commands::Stack => {
+            cmd_stack()?;
+        }
+        Commands::Restack => {
+            cmd_restack()?;
+        }
+        Commands::Next { count } => {
+            cmd_move_in_stack(StackMove::Next, *count)?;
+        }
+        Commands::Prev { count } => {
+            cmd_move_in_stack(StackMove::Prev, *count)?;
+        }
     }
 
     Ok(())
 }
 
-fn cmd_log(
-    simple: bool,
-    branch: Option<&str>,
-    remote: bool,
-    max_count: Option<usize>,
-) -> Result<()> {
+/// Open the repository in the current directory with the matching backend
+fn open_vcs() -> Result<Box<dyn Vcs>> {
     let repo_path = std::env::current_dir().context("Not in a directory")?;
+    openisl_git::vcs::open(&repo_path)
+}
+
+fn cmd_log(simple: bool, filter: &LogFilter) -> Result<()> {
+    let vcs = open_vcs()?;
+    if filter.follow.is_some() {
+        return cmd_log_follow(vcs.as_ref(), simple, filter);
+    }
 
-    let commits = get_commits_filtered(&repo_path, max_count, branch, remote)?;
+    let changes = match filter.rev {
+        Some(ref revset) => vcs.revset_log(revset, filter.max_count)?,
+        None => vcs.query_log(&filter.to_query())?,
+    };
+    let commits: Vec<Commit> = changes.into_iter().map(Into::into).collect();
 
     if simple {
         let formatter = SmartLogFormatter::new(commits, 80);
@@ -481,12 +731,54 @@ fn cmd_log(
     Ok(())
 }
 
+/// `log --follow`: each commit with what it did to the file
+fn cmd_log_follow(vcs: &dyn Vcs, simple: bool, filter: &LogFilter) -> Result<()> {
+    let versions = vcs.file_history(&filter.to_query())?;
+
+    if simple {
+        let commits = versions.into_iter().map(|v| v.commit).collect();
+        let formatter = SmartLogFormatter::new(commits, 80);
+        print!("{}", formatter.format());
+        return Ok(());
+    }
+
+    println!(
+        "History of {} ({} commits):\n",
+        filter.follow.as_deref().unwrap_or_default(),
+        versions.len()
+    );
+    for version in versions {
+        let commit = &version.commit;
+        println!("{} - {}", commit.short_hash, commit.summary);
+        println!("  Author: {} <{}>", commit.author, commit.email);
+        println!("  Date:   {}", commit.date);
+        match version.change.source() {
+            Some(from) => println!(
+                "  File:   {} {} -> {}\n",
+                version.change.letter(),
+                from,
+                version.path
+            ),
+            None => println!("  File:   {} {}\n", version.change.letter(), version.path),
+        }
+    }
+
+    Ok(())
+}
+
 fn cmd_tui() -> Result<()> {
-    let repo_path = std::env::current_dir().context("Not in a directory")?;
+    let vcs = open_vcs()?;
     let config = Config::load().context("Failed to load config")?;
-    let commits = get_commits(&repo_path, Some(config.general.max_commits))?;
-    let current_branch = get_current_branch(&repo_path)?.unwrap_or_else(|| "main".to_string());
-    openisl_tui::run_tui(commits, current_branch, Some(repo_path))
+    let commits = vcs
+        .log(Some(config.general.max_commits), None, false)?
+        .into_iter()
+        .map(Into::into)
+        .collect();
+    let current_branch = vcs.current_branch()?.unwrap_or_else(|| "main".to_string());
+    let options = openisl_tui::TuiOptions {
+        side_by_side_diff: config.tui.diff_layout == "side-by-side",
+    };
+    openisl_tui::run_tui_with(commits, current_branch, Some(vcs), options)
 }
 
 fn cmd_init() -> Result<()> {
@@ -512,24 +804,24 @@ fn cmd_clone(url: &str, destination: Option<&str>) -> Result<()> {
 }
 
 fn cmd_branch(name: Option<&str>, remote: bool, all: bool) -> Result<()> {
-    let repo_path = std::env::current_dir().context("Not in a directory")?;
+    let vcs = open_vcs()?;
 
     if let Some(branch_name) = name {
-        create_branch(&repo_path, branch_name)?;
+        vcs.create_branch(branch_name, None)?;
         println!("Created branch: {}", branch_name);
     } else {
-        let branches = get_branches(&repo_path, remote, all)?;
-        let current = get_current_branch(&repo_path)?;
+        let branches = vcs.branches()?;
+        let current = vcs.current_branch()?;
 
         let filtered_branches: Vec<_> = branches
             .iter()
             .filter(|b| {
                 if remote && !all {
-                    b.ref_type == openisl_git::RefType::Remote
+                    b.ref_type == VcsRefType::Remote
                 } else if all {
                     true
                 } else {
-                    b.ref_type != openisl_git::RefType::Remote
+                    b.ref_type != VcsRefType::Remote
                 }
             })
             .collect();
@@ -549,23 +841,24 @@ fn cmd_branch(name: Option<&str>, remote: bool, all: bool) -> Result<()> {
 }
 
 fn cmd_checkout(target: &str) -> Result<()> {
-    let repo_path = std::env::current_dir().context("Not in a directory")?;
-    checkout(&repo_path, target)?;
+    let vcs = open_vcs()?;
+    vcs.checkout(target)?;
     println!("Checked out '{}'", target);
     Ok(())
 }
 
 fn cmd_add(paths: &[String], all: bool) -> Result<()> {
-    let repo_path = std::env::current_dir().context("Not in a directory")?;
+    let vcs = open_vcs()?;
 
     if all {
-        stage_all(&repo_path)?;
+        vcs.stage_all()?;
         println!("Staged all changes");
     } else if paths.is_empty() {
         anyhow::bail!("No paths given. Use `openisl add <paths...>` or `openisl add -A`.");
     } else {
-        let path_refs: Vec<&str> = paths.iter().map(String::as_str).collect();
-        add_paths(&repo_path, &path_refs)?;
+        for path in paths {
+            vcs.stage(path)?;
+        }
         println!("Staged {} path(s)", paths.len());
     }
 
@@ -587,61 +880,133 @@ fn cmd_mv(from: &str, to: &str) -> Result<()> {
 }
 
 fn cmd_commit(message: Option<&str>, amend: bool) -> Result<()> {
-    let repo_path = std::env::current_dir().context("Not in a directory")?;
+    let vcs = open_vcs()?;
 
     if amend {
-        amend_commit(&repo_path, message)?;
+        vcs.amend(message)?;
         println!(
             "Amended commit{}",
             message.map(|_| " with new message").unwrap_or("")
         );
+        // Bring commits stacked on the old version along
+        let moved = vcs
+            .restack()
+            .context("Amended, but restacking the commits above failed")?;
+        if moved > 0 {
+            println!("Restacked {} commit(s)", moved);
+        }
     } else {
         let message = message.context("A commit message is required: use -m <message>")?;
-        commit(&repo_path, message)?;
+        vcs.commit(message)?;
         println!("Created commit");
     }
 
     Ok(())
 }
 
-fn cmd_show(commit: &str) -> Result<()> {
-    let repo_path = std::env::current_dir().context("Not in a directory")?;
-    print!("{}", get_commit_diff(&repo_path, commit)?);
+fn cmd_show(commit: &str, stat: bool) -> Result<()> {
+    let vcs = open_vcs()?;
+    if stat {
+        print_diff_stat(&vcs.change_diff_files(commit)?);
+    } else {
+        print!("{}", vcs.change_diff(commit)?);
+    }
     Ok(())
 }
 
+/// One line per changed file, then the totals
+fn print_diff_stat(files: &[FileDiff]) {
+    if files.is_empty() {
+        println!("No changes");
+        return;
+    }
+    for file in files {
+        let mut line = format!("{} ", file.change_kind.code());
+        match (&file.old_path, file.change_kind) {
+            (Some(old), ChangeKind::Renamed | ChangeKind::Copied) => {
+                line.push_str(&format!("{} -> {}", old, file.path()));
+                if let Some(similarity) = file.similarity {
+                    line.push_str(&format!(" ({}%)", similarity));
+                }
+            }
+            _ => line.push_str(file.path()),
+        }
+        if file.is_binary {
+            line.push_str(" | binary");
+        } else if !file.hunks.is_empty() {
+            line.push_str(&format!(" | +{} -{}", file.additions(), file.deletions()));
+        }
+        if let (true, Some(old), Some(new)) = (file.mode_changed(), &file.old_mode, &file.new_mode)
+        {
+            line.push_str(&format!(" | mode {} -> {}", old, new));
+        }
+        println!("{}", line);
+    }
+    let additions: usize = files.iter().map(FileDiff::additions).sum();
+    let deletions: usize = files.iter().map(FileDiff::deletions).sum();
+    println!(
+        "{} file(s) changed, {} insertion(s), {} deletion(s)",
+        files.len(),
+        additions,
+        deletions
+    );
+}
+
 fn cmd_status() -> Result<()> {
-    let repo_path = std::env::current_dir().context("Not in a directory")?;
+    let vcs = open_vcs()?;
 
-    let files = get_status(&repo_path)?;
+    let status = vcs.repo_status()?;
+    let branch = &status.branch;
 
-    if files.is_empty() {
+    match branch.head {
+        Some(ref head) => print!("On branch {}", head),
+        None => print!("HEAD detached"),
+    }
+    if let Some(ref upstream) = branch.upstream {
+        print!(
+            " (tracking {}, ahead {}, behind {})",
+            upstream, branch.ahead, branch.behind
+        );
+    }
+    println!();
+    let state = vcs.repo_state()?;
+    if !state.is_clean() {
+        print_repo_state(&state);
+    }
+
+    if status.files.is_empty() {
         println!("Working tree is clean");
     } else {
         println!("Changes:");
-        for file in files {
-            let status_str = match file.status {
-                StatusType::Modified => "Modified",
-                StatusType::Added => "Added",
-                StatusType::Deleted => "Deleted",
-                StatusType::Untracked => "Untracked",
-                StatusType::ModifiedStaged => "Modified (staged)",
-                StatusType::AddedStaged => "Added (staged)",
-                StatusType::DeletedStaged => "Deleted (staged)",
-                StatusType::Renamed => "Renamed",
-                StatusType::Conflicted => "Conflicted",
-            };
-            println!("{}: {}", status_str, file.path);
+        for file in status.files {
+            let mut line = format!("{}: {}", file.description(), file.path);
+            if let Some(ref original) = file.original_path {
+                line.push_str(&format!(" (from {})", original));
+            }
+            if let Some(submodule) = file.submodule {
+                if submodule.commit_changed {
+                    line.push_str(" [new commits]");
+                }
+                if submodule.has_tracked_changes || submodule.has_untracked_changes {
+                    line.push_str(" [modified content]");
+                }
+            }
+            println!("{}", line);
         }
     }
 
     Ok(())
 }
 
-fn cmd_diff(staged: bool, commit: Option<&str>) -> Result<()> {
-    let repo_path = std::env::current_dir().context("Not in a directory")?;
+fn cmd_diff(staged: bool, commit: Option<&str>, stat: bool) -> Result<()> {
+    let vcs = open_vcs()?;
+
+    if stat {
+        print_diff_stat(&vcs.diff_files(commit, staged)?);
+        return Ok(());
+    }
 
-    let diff = get_diff(&repo_path, commit, staged)?;
+    let diff = vcs.diff(commit, staged)?;
 
     if diff.is_empty() {
         println!("No changes");
@@ -653,21 +1018,76 @@ fn cmd_diff(staged: bool, commit: Option<&str>) -> Result<()> {
 }
 
 fn cmd_merge(target: &str) -> Result<()> {
-    let repo_path = std::env::current_dir().context("Not in a directory")?;
-    let output = merge(&repo_path, target, false)?;
+    let vcs = open_vcs()?;
+    let output = vcs.merge(target)?;
     print!("{}", output);
     Ok(())
 }
 
 fn cmd_rebase(upstream: Option<&str>, interactive: bool) -> Result<()> {
-    let repo_path = std::env::current_dir().context("Not in a directory")?;
-    let output = rebase(&repo_path, upstream, interactive)?;
+    let vcs = open_vcs()?;
+    if interactive {
+        return cmd_rebase_interactive(vcs.as_ref(), upstream.unwrap_or("@{upstream}"));
+    }
+    let output = vcs.rebase(upstream)?;
     print!("{}", output);
     Ok(())
 }
 
+fn cmd_rebase_interactive(vcs: &dyn Vcs, upstream: &str) -> Result<()> {
+    let repo_path = vcs.repo_path();
+    let query = LogQuery::new().revision(format!("{}..HEAD", upstream));
+    let mut commits = vcs.query_log(&query)?;
+    if commits.is_empty() {
+        println!(
+            "Nothing to rebase: HEAD has no commits on top of {}",
+            upstream
+        );
+        return Ok(());
+    }
+    commits.reverse();
+
+    let listed: Vec<(String, String)> = commits
+        .iter()
+        .map(|c| (c.id.clone(), c.summary.clone()))
+        .collect();
+    let todo = edit_text(repo_path, "OPENISL-REBASE-TODO", &format_todo(&listed))?;
+    let hashes: Vec<String> = commits.iter().map(|c| c.id.clone()).collect();
+    let mut steps = parse_todo(&todo, &hashes)?;
+
+    for step in &mut steps {
+        if step.action != RebaseAction::Reword {
+            continue;
+        }
+        let commit = commits
+            .iter()
+            .find(|c| c.id.starts_with(&step.commit))
+            .with_context(|| format!("{} is not in the rebased range", step.commit))?;
+        let message = edit_text(repo_path, "OPENISL-REWORD-MSG", &commit.message)?;
+        if message.is_empty() {
+            anyhow::bail!("Aborting reword of {}: empty message", commit.short_id);
+        }
+        step.message = Some(message);
+    }
+
+    match vcs.rebase_interactive(upstream, &steps)? {
+        HistoryEditOutcome::Completed { commits } => {
+            println!("Rewrote {} commit(s) onto {}", commits, upstream);
+        }
+        HistoryEditOutcome::Stopped {
+            progress,
+            reason: StopReason::Edit,
+        } => {
+            println!("Stopped at {} to edit", progress);
+            println!("Amend the commit, then run `openisl continue`");
+        }
+        outcome @ HistoryEditOutcome::Stopped { .. } => outcome.into_result()?,
+    }
+    Ok(())
+}
+
 fn cmd_reset(hard: bool, soft: bool, target: Option<&str>) -> Result<()> {
-    let repo_path = std::env::current_dir().context("Not in a directory")?;
+    let vcs = open_vcs()?;
     let mode = if hard {
         ResetMode::Hard
     } else if soft {
@@ -675,53 +1095,61 @@ fn cmd_reset(hard: bool, soft: bool, target: Option<&str>) -> Result<()> {
     } else {
         ResetMode::Mixed
     };
-    let output = reset(&repo_path, mode, target)?;
-    print!("{}", output);
+    let target = target.unwrap_or("HEAD");
+    vcs.reset(target, mode)?;
+    println!("Reset to '{}'", target);
     Ok(())
 }
 
 fn cmd_cherry_pick(commit: &str) -> Result<()> {
-    let repo_path = std::env::current_dir().context("Not in a directory")?;
-    cherry_pick_commit(&repo_path, commit)?;
+    let vcs = open_vcs()?;
+    vcs.cherry_pick(commit)?;
     println!("Cherry-picked '{}'", commit);
     Ok(())
 }
 
 fn cmd_revert(commit: &str) -> Result<()> {
-    let repo_path = std::env::current_dir().context("Not in a directory")?;
-    revert_commit(&repo_path, commit)?;
+    let vcs = open_vcs()?;
+    vcs.revert(commit)?;
     println!("Reverted '{}'", commit);
     Ok(())
 }
 
 fn cmd_stash(action: &StashAction) -> Result<()> {
-    let repo_path = std::env::current_dir().context("Not in a directory")?;
+    let vcs = open_vcs()?;
 
     match action {
         StashAction::List => {
-            let stashes = get_stash_list(&repo_path)?;
+            let stashes = vcs.saved_work()?;
             if stashes.is_empty() {
                 println!("No stashes found");
             } else {
                 for stash in &stashes {
-                    println!("{} {} ({})", stash.name, stash.message, stash.hash);
+                    println!(
+                        "{} {} ({} file(s), +{} -{})",
+                        stash.id,
+                        stash.message.as_deref().unwrap_or(""),
+                        stash.files_affected.len(),
+                        stash.change_count.additions,
+                        stash.change_count.deletions
+                    );
                 }
             }
         }
         StashAction::Push { message } => {
-            stash_push(&repo_path, message.as_deref())?;
+            vcs.save_work(message.as_deref())?;
             println!("Created stash");
         }
         StashAction::Pop { stash } => {
-            stash_pop(&repo_path, stash.as_deref())?;
+            vcs.pop_saved_work(stash.as_deref())?;
             println!("Popped stash");
         }
         StashAction::Apply { stash } => {
-            stash_apply(&repo_path, stash.as_deref())?;
+            vcs.apply_saved_work(stash.as_deref())?;
             println!("Applied stash");
         }
         StashAction::Drop { stash } => {
-            stash_drop(&repo_path, stash.as_deref())?;
+            vcs.drop_saved_work(stash.as_deref())?;
             println!("Dropped stash");
         }
     }
@@ -737,8 +1165,8 @@ fn cmd_fetch(remote: Option<&str>, prune: bool) -> Result<()> {
 }
 
 fn cmd_pull(rebase: bool) -> Result<()> {
-    let repo_path = std::env::current_dir().context("Not in a directory")?;
-    print!("{}", pull(&repo_path, rebase)?);
+    let vcs = open_vcs()?;
+    print!("{}", vcs.pull(rebase)?);
     Ok(())
 }
 
@@ -762,9 +1190,28 @@ fn cmd_push(
     Ok(())
 }
 
-fn cmd_blame(path: &str) -> Result<()> {
-    let repo_path = std::env::current_dir().context("Not in a directory")?;
-    print!("{}", get_blame(&repo_path, path)?);
+fn cmd_blame(path: &str, options: &BlameOptions) -> Result<()> {
+    let vcs = open_vcs()?;
+    let blame = vcs.blame(path, options)?;
+    let author_width = blame
+        .lines
+        .iter()
+        .map(|line| line.author.chars().count())
+        .max()
+        .unwrap_or(0)
+        .min(20);
+    let number_width = blame.lines.len().to_string().len();
+    for line in &blame.lines {
+        let author: String = line.author.chars().take(author_width).collect();
+        println!(
+            "{} ({:<author_width$} {} {:>number_width$}) {}",
+            abbreviate_hash(&line.commit),
+            author,
+            line.date.format("%Y-%m-%d"),
+            line.final_line,
+            line.content,
+        );
+    }
     Ok(())
 }
 
@@ -773,6 +1220,7 @@ fn cmd_config(
     reset: bool,
     theme: Option<&str>,
     max_commits: Option<usize>,
+    diff_layout: Option<&str>,
 ) -> Result<()> {
     if reset {
         let config = Config::default();
@@ -804,9 +1252,24 @@ fn cmd_config(
         modified = true;
     }
 
-    if show || (theme.is_none() && max_commits.is_none()) {
+    if let Some(layout) = diff_layout {
+        match layout {
+            "unified" | "side-by-side" => {
+                config.tui.diff_layout = layout.to_string();
+                println!("Diff layout set to: {}", layout);
+                modified = true;
+            }
+            _ => {
+                eprintln!("Invalid diff layout. Use 'unified' or 'side-by-side'.");
+                std::process::exit(1);
+            }
+        }
+    }
+
+    if show || (theme.is_none() && max_commits.is_none() && diff_layout.is_none()) {
         println!("Current Configuration:");
         println!("  Theme: {}", config.tui.theme);
+        println!("  Diff Layout: {}", config.tui.diff_layout);
         println!("  Max Commits: {}", config.general.max_commits);
         println!("  Date Format: {}", config.general.date_format);
         println!("  Auto Fetch: {}", config.git.auto_fetch);
@@ -901,30 +1364,101 @@ fn cmd_apply(patch: &str, cached: bool) -> Result<()> {
 }
 
 fn cmd_bisect(action: &BisectAction) -> Result<()> {
-    let repo_path = std::env::current_dir().context("Not in a directory")?;
+    let vcs = open_vcs()?;
 
-    match action {
+    let state = match action {
         BisectAction::Start { bad, good } => {
-            let log = bisect_start(&repo_path, bad, good)?;
-            print!("{}", log);
-            println!("\nBisecting: check out the listed revision, test it, then mark it good/bad.");
+            if vcs.bisect_state()?.is_some() {
+                vcs.bisect_reset()?;
+            }
+            vcs.bisect_mark(BisectVerdict::Bad, Some(bad))?;
+            vcs.bisect_mark(BisectVerdict::Good, Some(good))?
         }
         BisectAction::Good { revision } => {
-            print!("{}", bisect_good(&repo_path, revision.as_deref())?);
+            vcs.bisect_mark(BisectVerdict::Good, revision.as_deref())?
         }
         BisectAction::Bad { revision } => {
-            print!("{}", bisect_bad(&repo_path, revision.as_deref())?);
+            vcs.bisect_mark(BisectVerdict::Bad, revision.as_deref())?
         }
-        BisectAction::Skip => {
-            print!("{}", bisect_skip(&repo_path)?);
+        BisectAction::Skip => vcs.bisect_mark(BisectVerdict::Skip, None)?,
+        BisectAction::Run { command } => {
+            let command = shell_command(command);
+            vcs.bisect_run(&command, &mut |step| {
+                let verdict = match step.verdict {
+                    BisectVerdict::Good => "good",
+                    BisectVerdict::Bad => "bad",
+                    BisectVerdict::Skip => "skip",
+                };
+                println!(
+                    "{}: {} (exit {}); {}",
+                    describe_commit(vcs.as_ref(), &step.commit),
+                    verdict,
+                    step.exit_code,
+                    step.state.summary()
+                );
+                for line in step.output.trim_end().lines() {
+                    println!("    {}", line);
+                }
+            })?
         }
         BisectAction::Reset => {
-            bisect_reset(&repo_path)?;
+            vcs.bisect_reset()?;
             println!("Bisect session ended.");
+            return Ok(());
         }
+    };
+    print_bisect_state(vcs.as_ref(), &state);
+    Ok(())
+}
+
+/// A single argument is taken as a shell command line; several are quoted
+/// word by word, so `bisect run sh -c '...'` keeps its script intact
+fn shell_command(words: &[String]) -> String {
+    if let [line] = words {
+        return line.clone();
     }
+    let quote = |word: &String| {
+        let plain = !word.is_empty()
+            && word
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "-_./=:,+@%".contains(c));
+        match plain {
+            true => word.clone(),
+            false => format!("'{}'", word.replace('\'', "'\\''")),
+        }
+    };
+    words.iter().map(quote).collect::<Vec<_>>().join(" ")
+}
 
-    Ok(())
+/// Short hash and subject of a commit
+fn describe_commit(vcs: &dyn Vcs, commit: &str) -> String {
+    format!(
+        "{} {}",
+        abbreviate_hash(commit),
+        summary_of(vcs, commit).unwrap_or_default()
+    )
+}
+
+/// Subject line of a change
+fn summary_of(vcs: &dyn Vcs, id: &str) -> Result<String> {
+    let query = LogQuery::new().revision(id).max_count(1);
+    let change = vcs.query_log(&query)?.into_iter().next();
+    Ok(change.map(|c| c.summary).unwrap_or_default())
+}
+
+fn print_bisect_state(vcs: &dyn Vcs, state: &BisectState) {
+    println!(
+        "{} good, {} skipped; {}",
+        state.good.len(),
+        state.skipped.len(),
+        state.summary()
+    );
+    if let Some(ref first_bad) = state.first_bad {
+        println!("First bad commit: {}", describe_commit(vcs, first_bad));
+        println!("Run `openisl bisect reset` to end the session.");
+    } else if let Some(ref current) = state.current {
+        println!("Testing {}", describe_commit(vcs, current));
+    }
 }
 
 fn cmd_resolve(list: bool, paths: &[String]) -> Result<()> {
@@ -952,16 +1486,333 @@ fn cmd_resolve(list: bool, paths: &[String]) -> Result<()> {
     Ok(())
 }
 
-fn cmd_undo() -> Result<()> {
-    let repo_path = std::env::current_dir().context("Not in a directory")?;
-    undo_last(&repo_path)?;
-    println!("Undid the last operation.");
+fn cmd_undo(operation: Option<&str>) -> Result<()> {
+    let vcs = open_vcs()?;
+    vcs.undo_operation(operation)?;
+    match operation {
+        Some(id) => println!("Restored the state before operation {}", id),
+        None => println!("Undid the last operation"),
+    }
+    Ok(())
+}
+
+fn cmd_redo() -> Result<()> {
+    let vcs = open_vcs()?;
+    vcs.redo_operation()?;
+    println!("Redid the last undone operation");
+    Ok(())
+}
+
+fn cmd_reflog(reference: Option<&str>, max_count: Option<usize>) -> Result<()> {
+    let vcs = open_vcs()?;
+    let entries = vcs.reflog(reference, max_count)?;
+    if entries.is_empty() {
+        println!("No reflog entries for {}", reference.unwrap_or("HEAD"));
+        return Ok(());
+    }
+
+    for entry in &entries {
+        let selector = entry.refs.first().map(|r| r.name.as_str()).unwrap_or("");
+        let short_id = abbreviate_hash(&entry.id);
+        let message = if entry.description.is_empty() {
+            entry.action.clone()
+        } else {
+            format!("{}: {}", entry.action, entry.description)
+        };
+        println!(
+            "{} {} {} {}",
+            short_id,
+            selector,
+            entry.timestamp.format("%Y-%m-%d %H:%M:%S UTC"),
+            message
+        );
+    }
+    Ok(())
+}
+
+fn cmd_split(commit: &str) -> Result<()> {
+    let vcs = open_vcs()?;
+    let summary = summary_of(vcs.as_ref(), commit)?;
+
+    let mut parts: Vec<SplitPart> = Vec::new();
+    loop {
+        let files = vcs.split_files(commit, &parts)?;
+        println!(
+            "Choose the changes for commit {} of the split",
+            parts.len() + 1
+        );
+        let patches = select_changes(files)?;
+        let selected = patches
+            .iter()
+            .any(|patch| patch.segments.contains(&ChangeSegment::Include));
+        if !selected {
+            break;
+        }
+
+        let template = format!(
+            "\n# Message for commit {} of the split. The rest of the change keeps\n# its message: {}\n",
+            parts.len() + 1,
+            summary
+        );
+        let message = edit_text(vcs.repo_path(), "OPENISL-SPLIT-MSG", &template)?;
+        if message.is_empty() {
+            anyhow::bail!("Aborting split: empty message");
+        }
+        parts.push(SplitPart { patches, message });
+        if ask("Split off another commit? [y,N]")? != "y" {
+            break;
+        }
+    }
+
+    if parts.is_empty() {
+        println!("Nothing selected; the commit is unchanged");
+        return Ok(());
+    }
+    vcs.split_change(commit, &parts)?;
+    println!("Split '{}' into {} commits", commit, parts.len() + 1);
+    Ok(())
+}
+
+/// Walk the hunks of each file, like `git add -p`, and collect the chosen
+/// lines as patches
+fn select_changes(files: Vec<SplitFile>) -> Result<Vec<ChangePatch>> {
+    let mut patches = Vec::new();
+    let mut done = false;
+    for mut file in files {
+        if done {
+            break;
+        }
+        if file.hunks.is_empty() {
+            let include = ask(&format!("Include {} (no line changes)? [y,n,q]", file.path))?;
+            done = include == "q";
+            file.is_selected = include == "y";
+            patches.push(file.selected_patch());
+            continue;
+        }
+
+        println!("\n--- {}", file.path);
+        for hunk in &mut file.hunks {
+            println!("{}", hunk.header);
+            for line in &hunk.lines {
+                println!("{}", line.content);
+            }
+            let changes = hunk
+                .lines
+                .iter_mut()
+                .filter(|line| line.line_type != HunkLineType::Context);
+            match ask("Include this hunk? [y,n,s(elect lines),q]")?.as_str() {
+                "y" => changes.for_each(|line| line.is_selected = true),
+                "s" => {
+                    for line in changes {
+                        line.is_selected = ask(&format!("  {} [y,n]", line.content))? == "y";
+                    }
+                }
+                "q" => {
+                    done = true;
+                    break;
+                }
+                _ => {}
+            }
+        }
+        patches.push(file.selected_patch());
+    }
+    Ok(patches)
+}
+
+/// Ask a question on the terminal and return the lowercased answer
+fn ask(question: &str) -> Result<String> {
+    use std::io::Write;
+
+    print!("{} ", question);
+    std::io::stdout().flush()?;
+    let mut answer = String::new();
+    if std::io::stdin().read_line(&mut answer)? == 0 {
+        anyhow::bail!("Aborted: no input");
+    }
+    Ok(answer.trim().to_lowercase())
+}
+
+fn cmd_absorb(dry_run: bool) -> Result<()> {
+    let vcs = open_vcs()?;
+    let hunks = vcs.absorb(dry_run)?;
+
+    let mut targets: Vec<&str> = Vec::new();
+    for hunk in &hunks {
+        match hunk.commit {
+            Some(ref commit) => {
+                println!(
+                    "{} {} -> {}",
+                    hunk.path,
+                    hunk.header,
+                    abbreviate_hash(commit)
+                );
+                if !targets.contains(&commit.as_str()) {
+                    targets.push(commit);
+                }
+            }
+            None => println!("{} {} (left staged)", hunk.path, hunk.header),
+        }
+    }
+
+    let absorbed = hunks.iter().filter(|h| h.commit.is_some()).count();
+    let left = hunks.len() - absorbed;
+    if dry_run {
+        println!(
+            "\nWould absorb {} hunk(s) into {} commit(s); {} would stay staged",
+            absorbed,
+            targets.len(),
+            left
+        );
+    } else {
+        println!(
+            "\nAbsorbed {} hunk(s) into {} commit(s); {} left staged",
+            absorbed,
+            targets.len(),
+            left
+        );
+    }
+    Ok(())
+}
+
+/// What to do with the operation in progress
+#[derive(Clone, Copy)]
+enum Resume {
+    Continue,
+    Abort,
+    Skip,
+}
+
+fn cmd_operation(resume: Resume) -> Result<()> {
+    let vcs = open_vcs()?;
+    let state = vcs.repo_state()?;
+    let Some(operation) = state.operation() else {
+        anyhow::bail!("No operation in progress");
+    };
+    let done = match resume {
+        Resume::Continue => {
+            vcs.continue_operation()?;
+            "Finished"
+        }
+        Resume::Abort => {
+            vcs.abort_operation()?;
+            "Aborted"
+        }
+        Resume::Skip => {
+            vcs.skip_operation()?;
+            "Skipped a commit of"
+        }
+    };
+
+    let after = vcs.repo_state()?;
+    if after.is_clean() {
+        println!("{} the {}", done, operation);
+    } else {
+        print_repo_state(&after);
+    }
+    Ok(())
+}
+
+/// The operation in progress and how to go on with it
+fn print_repo_state(state: &RepoState) {
+    let mut actions = Vec::new();
+    if state.can_continue() {
+        actions.push("`openisl continue`");
+    }
+    if state.can_skip() {
+        actions.push("`openisl skip`");
+    }
+    actions.push("`openisl abort`");
+    println!("{}; use {}", state, actions.join(", "));
+}
+
+fn cmd_stack() -> Result<()> {
+    let vcs = open_vcs()?;
+    let stack = vcs.stack()?;
+    if stack.is_empty() {
+        println!("No draft commits above trunk");
+        return Ok(());
+    }
+
+    for entry in &stack {
+        let marker = if entry.is_head {
+            "@"
+        } else if entry.successor.is_some() {
+            "x"
+        } else {
+            "o"
+        };
+        let mut line = format!(
+            "{}  {} {}",
+            marker,
+            abbreviate_hash(&entry.hash),
+            entry.summary
+        );
+        if !entry.branches.is_empty() {
+            line.push_str(&format!(" [{}]", entry.branches.join(", ")));
+        }
+        if let Some(ref successor) = entry.successor {
+            line.push_str(&format!(" (rewritten as {})", abbreviate_hash(successor)));
+        } else if entry.obsolete {
+            line.push_str(" (obsolete: parent rewritten)");
+        }
+        println!("{}", line);
+    }
+
+    let obsolete = stack.iter().filter(|e| e.obsolete).count();
+    if obsolete > 0 {
+        println!(
+            "\n{} commit(s) sit on rewritten parents; run `openisl restack`",
+            obsolete
+        );
+    }
+    Ok(())
+}
+
+fn cmd_restack() -> Result<()> {
+    let vcs = open_vcs()?;
+    match vcs.restack()? {
+        0 => println!("Nothing to restack"),
+        moved => println!("Restacked {} commit(s)", moved),
+    }
+    Ok(())
+}
+
+fn cmd_move_in_stack(direction: StackMove, count: usize) -> Result<()> {
+    let vcs = open_vcs()?;
+    let target = vcs.stack_target(direction, count)?;
+    vcs.checkout(&target)?;
+    println!("Checked out '{}'", target);
+    Ok(())
+}
+
+fn cmd_oplog(max_count: Option<usize>) -> Result<()> {
+    let vcs = open_vcs()?;
+    let operations = vcs.operation_log()?;
+    if operations.is_empty() {
+        println!("No operations recorded yet");
+        return Ok(());
+    }
+
+    for op in operations.iter().take(max_count.unwrap_or(usize::MAX)) {
+        let head = op
+            .refs
+            .first()
+            .map(|r| format!(" [{}]", r))
+            .unwrap_or_default();
+        println!(
+            "{} {} {}{}",
+            op.id,
+            op.timestamp.format("%Y-%m-%d %H:%M:%S UTC"),
+            op.description,
+            head
+        );
+    }
     Ok(())
 }
 
 fn cmd_squash(commit: &str, message: &str) -> Result<()> {
-    let repo_path = std::env::current_dir().context("Not in a directory")?;
-    squash_commits(&repo_path, commit, message)?;
+    let vcs = open_vcs()?;
+    vcs.squash(commit, message)?;
     println!("Squashed commits up to '{}' into one", commit);
     Ok(())
 }
@@ -975,16 +1826,176 @@ mod tests {
         let args = vec!["openisl", "log", "--max-count", "10"];
         let cli = Cli::parse_from(&args);
         match &cli.command {
-            Commands::Log {
-                simple: _,
-                branch: _,
-                remote: _,
-                max_count,
+            Commands::Log { simple: _, filter } => {
+                assert_eq!(filter.max_count, Some(10));
+            }
+            _ => panic!("Expected Log command"),
+        }
+    }
+
+    #[test]
+    fn test_cli_parse_log_query() {
+        let args = vec![
+            "openisl",
+            "log",
+            "main..feature",
+            "--author",
+            "alice",
+            "--no-merges",
+            "--topo-order",
+            "-F",
+            "--skip",
+            "20",
+            "--",
+            "src",
+        ];
+        let cli = Cli::parse_from(&args);
+        match &cli.command {
+            Commands::Log { simple: _, filter } => {
+                let query = filter.to_query();
+                assert_eq!(query.revisions, vec!["main..feature"]);
+                assert_eq!(query.paths, vec!["src"]);
+                assert_eq!(query.author.as_deref(), Some("alice"));
+                assert!(query.no_merges);
+                assert!(query.fixed_strings && !query.ignore_case);
+                assert_eq!(query.order, LogOrder::Topo);
+                assert_eq!(query.skip, Some(20));
+            }
+            _ => panic!("Expected Log command"),
+        }
+    }
+
+    #[test]
+    fn test_cli_parse_log_follow() {
+        let args = vec!["openisl", "log", "--follow", "src/new.rs", "-n", "5"];
+        let cli = Cli::parse_from(&args);
+        match &cli.command {
+            Commands::Log { simple: _, filter } => {
+                let query = filter.to_query();
+                assert_eq!(query.paths, vec!["src/new.rs"]);
+                assert!(query.follow);
+                assert_eq!(query.max_count, Some(5));
+            }
+            _ => panic!("Expected Log command"),
+        }
+
+        let conflicting = vec!["openisl", "log", "--follow", "a.rs", "--", "b.rs"];
+        assert!(Cli::try_parse_from(&conflicting).is_err());
+    }
+
+    #[test]
+    fn test_cli_parse_diff_stat() {
+        let cli = Cli::parse_from(["openisl", "diff", "--staged", "--stat"]);
+        match cli.command {
+            Commands::Diff {
+                staged,
+                commit,
+                stat,
             } => {
-                assert_eq!(*max_count, Some(10));
+                assert!(staged && stat);
+                assert_eq!(commit, None);
+            }
+            _ => panic!("Expected Diff command"),
+        }
+        let cli = Cli::parse_from(["openisl", "show", "HEAD~1", "--stat"]);
+        assert!(matches!(
+            cli.command,
+            Commands::Show { ref commit, stat: true } if commit == "HEAD~1"
+        ));
+    }
+
+    #[test]
+    fn test_cli_parse_config_diff_layout() {
+        let cli = Cli::parse_from(["openisl", "config", "--diff-layout", "side-by-side"]);
+        match cli.command {
+            Commands::Config {
+                diff_layout, theme, ..
+            } => {
+                assert_eq!(diff_layout.as_deref(), Some("side-by-side"));
+                assert_eq!(theme, None);
+            }
+            _ => panic!("Expected Config command"),
+        }
+    }
+
+    #[test]
+    fn test_cli_parse_log_revset() {
+        let args = vec!["openisl", "log", "-r", "draft() & author(alice)", "-n", "5"];
+        let cli = Cli::parse_from(&args);
+        match &cli.command {
+            Commands::Log { simple: _, filter } => {
+                assert_eq!(filter.rev.as_deref(), Some("draft() & author(alice)"));
+                assert_eq!(filter.max_count, Some(5));
             }
             _ => panic!("Expected Log command"),
         }
+
+        let conflicting = vec!["openisl", "log", "-r", "heads()", "--author", "alice"];
+        assert!(Cli::try_parse_from(&conflicting).is_err());
+    }
+
+    #[test]
+    fn test_cli_parse_undo_redo_reflog_oplog() {
+        let cli = Cli::parse_from(["openisl", "undo"]);
+        assert!(matches!(cli.command, Commands::Undo { operation: None }));
+
+        let cli = Cli::parse_from(["openisl", "undo", "1a2b3c4d5e6f"]);
+        match &cli.command {
+            Commands::Undo { operation } => {
+                assert_eq!(operation.as_deref(), Some("1a2b3c4d5e6f"))
+            }
+            _ => panic!("Expected Undo command"),
+        }
+
+        let cli = Cli::parse_from(["openisl", "redo"]);
+        assert!(matches!(cli.command, Commands::Redo));
+
+        let cli = Cli::parse_from(["openisl", "reflog", "main", "-n", "5"]);
+        match &cli.command {
+            Commands::Reflog {
+                reference,
+                max_count,
+            } => {
+                assert_eq!(reference.as_deref(), Some("main"));
+                assert_eq!(*max_count, Some(5));
+            }
+            _ => panic!("Expected Reflog command"),
+        }
+
+        let cli = Cli::parse_from(["openisl", "oplog", "-n", "10"]);
+        assert!(matches!(
+            cli.command,
+            Commands::Oplog {
+                max_count: Some(10)
+            }
+        ));
+    }
+
+    #[test]
+    fn test_cli_parse_stack_commands() {
+        let cli = Cli::parse_from(["openisl", "stack"]);
+        assert!(matches!(cli.command, Commands::Stack));
+
+        let cli = Cli::parse_from(["openisl", "split"]);
+        assert!(matches!(cli.command, Commands::Split { ref commit } if commit == "HEAD"));
+
+        let cli = Cli::parse_from(["openisl", "absorb", "--dry-run"]);
+        assert!(matches!(cli.command, Commands::Absorb { dry_run: true }));
+
+        let cli = Cli::parse_from(["openisl", "continue"]);
+        assert!(matches!(cli.command, Commands::Continue));
+
+        let cli = Cli::parse_from(["openisl", "skip"]);
+        assert!(matches!(cli.command, Commands::Skip));
+
+        let cli = Cli::parse_from(["openisl", "restack"]);
+        assert!(matches!(cli.command, Commands::Restack));
+
+        let cli = Cli::parse_from(["openisl", "next"]);
+        assert!(matches!(cli.command, Commands::Next { count: 1 }));
+
+        let cli = Cli::parse_from(["openisl", "prev", "3"]);
+        assert!(matches!(cli.command, Commands::Prev { count: 3 }));
     }
 
     #[test]
@@ -1098,6 +2109,26 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_cli_parse_blame_options() {
+        let args = vec!["openisl", "blame", "src/lib.rs", "-w", "-C", "-r", "HEAD~2"];
+        let cli = Cli::parse_from(&args);
+        match &cli.command {
+            Commands::Blame {
+                path,
+                revision,
+                ignore_whitespace,
+                moves,
+                copies,
+            } => {
+                assert_eq!(path, "src/lib.rs");
+                assert_eq!(revision.as_deref(), Some("HEAD~2"));
+                assert!(*ignore_whitespace && *copies && !*moves);
+            }
+            _ => panic!("Expected Blame command"),
+        }
+    }
+
     #[test]
     fn test_cli_parse_bisect_start() {
         let args = vec!["openisl", "bisect", "start", "badhash", "goodhash"];
@@ -1114,6 +2145,21 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_cli_parse_bisect_run() {
+        let args = vec!["openisl", "bisect", "run", "sh", "-c", "test -f ok"];
+        let cli = Cli::parse_from(&args);
+        match cli.command {
+            Commands::Bisect {
+                action: BisectAction::Run { command },
+            } => {
+                assert_eq!(command, ["sh", "-c", "test -f ok"]);
+                assert_eq!(shell_command(&command), "sh -c 'test -f ok'");
+            }
+            _ => panic!("Expected bisect run"),
+        }
+    }
+
     #[test]
     fn test_cli_parse_resolve() {
         let args = vec!["openisl", "resolve", "a.txt"];
@@ -1,34 +1,97 @@
 use crate::error::GitError;
+use crate::runner::{GitInvocation, GitOutput, GitRunner, ProcessRunner};
 use anyhow::Result;
+use std::cell::RefCell;
 use std::collections::HashMap;
-use std::io::Write;
 use std::path::{Path, PathBuf};
-use std::process::{Command, Output, Stdio};
+use std::process::{Command, Output};
+use std::sync::Arc;
 
-pub fn run(args: &[&str], cwd: Option<&Path>) -> Result<String> {
-    let output = run_raw(args, cwd)?;
+thread_local! {
+    static RUNNER: RefCell<Option<Arc<dyn GitRunner>>> = const { RefCell::new(None) };
+}
 
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(GitError::CommandFailed(stderr.to_string()).into());
+/// Run `f` with every git invocation on this thread going through `runner`
+/// instead of the `git` executable. The previous runner is restored afterwards.
+pub fn with_runner<T>(runner: Arc<dyn GitRunner>, f: impl FnOnce() -> T) -> T {
+    struct Restore(Option<Arc<dyn GitRunner>>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            RUNNER.with(|current| *current.borrow_mut() = self.0.take());
+        }
+    }
+
+    let _restore = Restore(RUNNER.with(|current| current.borrow_mut().replace(runner)));
+    f()
+}
+
+fn execute(invocation: GitInvocation) -> Result<GitOutput> {
+    match RUNNER.with(|current| current.borrow().clone()) {
+        Some(runner) => runner.run(&invocation),
+        None => ProcessRunner.run(&invocation),
+    }
+}
+
+fn invocation(args: &[&str], cwd: Option<&Path>) -> GitInvocation {
+    GitInvocation {
+        cwd: cwd.map(Path::to_path_buf),
+        ..GitInvocation::new(args)
+    }
+}
+
+/// Turn a failed git invocation into the matching `GitError`
+fn failure(output: &GitOutput) -> GitError {
+    GitError::from_output(
+        &String::from_utf8_lossy(&output.stdout),
+        &String::from_utf8_lossy(&output.stderr),
+    )
+}
+
+fn stdout_or_failure(output: GitOutput) -> Result<String> {
+    if !output.success() {
+        return Err(failure(&output).into());
     }
 
     Ok(String::from_utf8_lossy(&output.stdout).to_string())
 }
 
+pub fn run(args: &[&str], cwd: Option<&Path>) -> Result<String> {
+    stdout_or_failure(run_raw(args, cwd)?)
+}
+
 pub fn run_success(args: &[&str], cwd: Option<&Path>) -> Result<()> {
-    let output = run_raw(args, cwd)?;
+    run(args, cwd).map(|_| ())
+}
+
+pub fn run_raw(args: &[&str], cwd: Option<&Path>) -> Result<GitOutput> {
+    execute(invocation(args, cwd))
+}
+
+/// Run another VCS executable (`hg`, `sl`, ...) and return its stdout
+pub fn run_program(
+    program: &str,
+    args: &[&str],
+    cwd: Option<&Path>,
+    env: &[(&str, &str)],
+) -> Result<String> {
+    let output = run_program_raw(program, args, cwd, env)?;
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
         return Err(GitError::CommandFailed(stderr.to_string()).into());
     }
 
-    Ok(())
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
 }
 
-pub fn run_raw(args: &[&str], cwd: Option<&Path>) -> Result<Output> {
-    let mut cmd = Command::new("git");
+pub fn run_program_raw(
+    program: &str,
+    args: &[&str],
+    cwd: Option<&Path>,
+    env: &[(&str, &str)],
+) -> Result<Output> {
+    let mut cmd = Command::new(program);
 
     for arg in args {
         cmd.arg(arg);
@@ -38,6 +101,10 @@ pub fn run_raw(args: &[&str], cwd: Option<&Path>) -> Result<Output> {
         cmd.current_dir(dir);
     }
 
+    for (key, value) in env {
+        cmd.env(key, value);
+    }
+
     let output = cmd.output()?;
 
     Ok(output)
@@ -82,58 +149,17 @@ pub fn run_with_env(
     cwd: Option<&Path>,
     env: &HashMap<&str, &str>,
 ) -> Result<String> {
-    let mut cmd = Command::new("git");
-
-    for arg in args {
-        cmd.arg(arg);
-    }
-
-    if let Some(dir) = cwd {
-        cmd.current_dir(dir);
-    }
-
-    for (key, value) in env {
-        cmd.env(key, value);
-    }
-
-    let output = cmd.output()?;
-
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(GitError::CommandFailed(stderr.to_string()).into());
-    }
-
-    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    let mut invocation = invocation(args, cwd);
+    invocation.env = env
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect();
+    stdout_or_failure(execute(invocation)?)
 }
 
 /// Run a git command with stdin input
 pub fn run_with_stdin(args: &[&str], cwd: Option<&Path>, stdin_data: &str) -> Result<String> {
-    let mut cmd = Command::new("git");
-
-    for arg in args {
-        cmd.arg(arg);
-    }
-
-    if let Some(dir) = cwd {
-        cmd.current_dir(dir);
-    }
-
-    cmd.stdin(Stdio::piped());
-    cmd.stdout(Stdio::piped());
-    cmd.stderr(Stdio::piped());
-
-    let mut child = cmd.spawn()?;
-
-    if let Some(ref mut stdin) = child.stdin {
-        stdin.write_all(stdin_data.as_bytes())?;
-    }
-
-    let output = child.wait_with_output()?;
-
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(GitError::CommandFailed(stderr.to_string()).into());
-    }
-
-    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    let mut invocation = invocation(args, cwd);
+    invocation.stdin = Some(stdin_data.to_string());
+    stdout_or_failure(execute(invocation)?)
 }
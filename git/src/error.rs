@@ -1,3 +1,4 @@
+use std::path::PathBuf;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -11,6 +12,30 @@ pub enum GitError {
     #[error("git command failed: {0}")]
     CommandFailed(String),
 
+    #[error("merge conflict{}", in_paths(.paths))]
+    MergeConflict { paths: Vec<String> },
+
+    #[error("nothing to commit")]
+    NothingToCommit,
+
+    #[error("local changes would be overwritten{}", in_paths(.paths))]
+    DirtyWorkingTree { paths: Vec<String> },
+
+    #[error("unknown revision: {0}")]
+    UnknownRevision(String),
+
+    #[error("push rejected, remote has changes not present locally: {0}")]
+    NonFastForward(String),
+
+    #[error("authentication failed: {0}")]
+    AuthenticationFailed(String),
+
+    #[error("repository is locked by another git process: {}", .0.display())]
+    IndexLocked(PathBuf),
+
+    #[error("a rebase is already in progress")]
+    RebaseInProgress,
+
     #[error("failed to parse git output: {0}")]
     ParseError(String),
 
@@ -20,3 +45,242 @@ pub enum GitError {
     #[error("unknown error: {0}")]
     Unknown(String),
 }
+
+fn in_paths(paths: &[String]) -> String {
+    if paths.is_empty() {
+        String::new()
+    } else {
+        format!(" in {}", paths.join(", "))
+    }
+}
+
+impl GitError {
+    /// Classify the output of a failed git command.
+    ///
+    /// Git prints some failures (conflicts, "nothing to commit") on stdout, so
+    /// both streams are inspected. Unrecognised failures become
+    /// `CommandFailed` with the original stderr.
+    pub fn from_output(stdout: &str, stderr: &str) -> Self {
+        let combined = format!("{}\n{}", stderr, stdout);
+
+        if let Some(lock) = combined.lines().find_map(|line| {
+            let rest = line.split("Unable to create '").nth(1)?;
+            let path = rest.split('\'').next()?;
+            path.ends_with(".lock").then(|| PathBuf::from(path))
+        }) {
+            return GitError::IndexLocked(lock);
+        }
+
+        if combined.contains("already a rebase-merge directory")
+            || combined.contains("already a rebase-apply directory")
+        {
+            return GitError::RebaseInProgress;
+        }
+
+        if combined.contains("would be overwritten by") {
+            return GitError::DirtyWorkingTree {
+                paths: indented_paths_after(&combined, "would be overwritten by"),
+            };
+        }
+        if combined.contains("You have unstaged changes")
+            || combined.contains("Your index contains uncommitted changes")
+        {
+            return GitError::DirtyWorkingTree { paths: Vec::new() };
+        }
+
+        if combined.lines().any(|l| l.starts_with("CONFLICT ("))
+            || combined.contains("Automatic merge failed")
+            || combined.contains("needs merge")
+        {
+            let mut paths: Vec<String> = combined
+                .lines()
+                .filter_map(|line| {
+                    if line.starts_with("CONFLICT (") {
+                        line.split("Merge conflict in ").nth(1)
+                    } else {
+                        line.strip_suffix(": needs merge")
+                    }
+                })
+                .map(|path| path.trim().to_string())
+                .collect();
+            paths.dedup();
+            return GitError::MergeConflict { paths };
+        }
+
+        if combined.contains("nothing to commit")
+            || combined.contains("nothing added to commit")
+            || combined.contains("no changes added to commit")
+        {
+            return GitError::NothingToCommit;
+        }
+
+        if let Some(revision) = unknown_revision(&combined) {
+            return GitError::UnknownRevision(revision);
+        }
+
+        if combined.contains("[rejected]") || combined.contains("Updates were rejected") {
+            let reference = combined
+                .lines()
+                .find(|l| l.contains("[rejected]"))
+                .and_then(|l| l.split("->").nth(1))
+                .and_then(|r| r.split_whitespace().next())
+                .unwrap_or_default()
+                .to_string();
+            return GitError::NonFastForward(reference);
+        }
+
+        if combined.contains("Authentication failed")
+            || combined.contains("Permission denied (publickey")
+            || combined.contains("could not read Username")
+            || combined.contains("terminal prompts disabled")
+        {
+            return GitError::AuthenticationFailed(stderr.trim().to_string());
+        }
+
+        GitError::CommandFailed(stderr.to_string())
+    }
+
+    /// A short suggestion for recovering from this error, if there is one
+    pub fn suggestion(&self) -> Option<&'static str> {
+        match self {
+            GitError::MergeConflict { .. } => {
+                Some("resolve the conflicts, stage the files, then continue")
+            }
+            GitError::NothingToCommit => Some("stage some changes first"),
+            GitError::DirtyWorkingTree { .. } => Some("commit or stash your changes first"),
+            GitError::NonFastForward(_) => {
+                Some("pull first, or push with --force-with-lease to overwrite")
+            }
+            GitError::AuthenticationFailed(_) => Some("check your credentials for the remote"),
+            GitError::IndexLocked(_) => {
+                Some("if no other git process is running, remove the lock file")
+            }
+            GitError::RebaseInProgress => Some("continue or abort the rebase in progress"),
+            _ => None,
+        }
+    }
+}
+
+/// Collect the tab-indented file list git prints below `marker`
+fn indented_paths_after(output: &str, marker: &str) -> Vec<String> {
+    output
+        .lines()
+        .skip_while(|line| !line.contains(marker))
+        .skip(1)
+        .take_while(|line| line.starts_with('\t'))
+        .map(|line| line.trim().to_string())
+        .collect()
+}
+
+fn unknown_revision(output: &str) -> Option<String> {
+    const PATTERNS: &[(&str, &str)] = &[
+        ("ambiguous argument '", "'"),
+        ("bad revision '", "'"),
+        ("invalid reference: ", "\n"),
+        ("Not a valid object name ", "\n"),
+        ("not a valid object name: '", "'"),
+        ("unknown revision ", "\n"),
+    ];
+
+    // `git checkout <name>` reports an unknown branch as an unmatched pathspec
+    if output.contains("known to git") {
+        if let Some(rest) = output.split("pathspec '").nth(1) {
+            return rest.split('\'').next().map(str::to_string);
+        }
+    }
+
+    for (prefix, terminator) in PATTERNS {
+        if let Some(rest) = output.split(prefix).nth(1) {
+            let revision = rest.split(terminator).next().unwrap_or_default().trim();
+            return Some(revision.to_string());
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classifies_index_lock() {
+        let stderr = "fatal: Unable to create '/repo/.git/index.lock': File exists.\n\n\
+                      Another git process seems to be running in this repository";
+        match GitError::from_output("", stderr) {
+            GitError::IndexLocked(path) => {
+                assert_eq!(path, PathBuf::from("/repo/.git/index.lock"))
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_classifies_merge_conflict() {
+        let stdout = "Auto-merging a.txt\n\
+                      CONFLICT (content): Merge conflict in a.txt\n\
+                      Automatic merge failed; fix conflicts and then commit the result.\n";
+        match GitError::from_output(stdout, "") {
+            GitError::MergeConflict { paths } => assert_eq!(paths, vec!["a.txt"]),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_classifies_dirty_checkout() {
+        let stderr =
+            "error: Your local changes to the following files would be overwritten by checkout:\n\
+                      \ta.txt\n\
+                      \tsrc/b.rs\n\
+                      Please commit your changes or stash them before you switch branches.\n\
+                      Aborting\n";
+        match GitError::from_output("", stderr) {
+            GitError::DirtyWorkingTree { paths } => assert_eq!(paths, vec!["a.txt", "src/b.rs"]),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_classifies_push_rejection() {
+        let stderr = "To github.com:o/r.git\n \
+                      ! [rejected]        main -> main (fetch first)\n\
+                      error: failed to push some refs to 'github.com:o/r.git'\n\
+                      hint: Updates were rejected because the remote contains work that you do\n";
+        match GitError::from_output("", stderr) {
+            GitError::NonFastForward(reference) => assert_eq!(reference, "main"),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_classifies_simple_failures() {
+        assert!(matches!(
+            GitError::from_output("nothing to commit, working tree clean\n", ""),
+            GitError::NothingToCommit
+        ));
+        assert!(matches!(
+            GitError::from_output(
+                "",
+                "fatal: ambiguous argument 'nope': unknown revision or path not in the working tree.\n"
+            ),
+            GitError::UnknownRevision(r) if r == "nope"
+        ));
+        assert!(matches!(
+            GitError::from_output(
+                "",
+                "fatal: It seems that there is already a rebase-merge directory, and\n"
+            ),
+            GitError::RebaseInProgress
+        ));
+        assert!(matches!(
+            GitError::from_output(
+                "",
+                "fatal: Authentication failed for 'https://example.com/r.git/'\n"
+            ),
+            GitError::AuthenticationFailed(_)
+        ));
+        assert!(matches!(
+            GitError::from_output("", "fatal: something else\n"),
+            GitError::CommandFailed(s) if s == "fatal: something else\n"
+        ));
+    }
+}
@@ -2,25 +2,49 @@ pub mod command;
 pub mod error;
 pub mod models;
 pub mod operations;
+pub mod runner;
 pub mod vcs;
 
-pub use command::{find_repo_root, is_git_repo};
+#[cfg(test)]
+mod test_support;
+
+pub use command::{find_repo_root, is_git_repo, with_runner};
 pub use error::GitError;
-pub use models::{Commit, GitRef, RefType};
+pub use models::{abbreviate_hash, Commit, GitRef, RefType, Trailer};
 pub use operations::{
-    add_paths, amend_commit, apply_patch, bisect_bad, bisect_good, bisect_reset, bisect_skip,
-    bisect_start, checkout, checkout_commit, cherry_pick_commit, clone, commit, create_branch,
-    create_branch_from_commit, create_tag, delete_tag, drop_commit, fetch, get_blame, get_branches,
-    get_commit_diff, get_commit_message, get_commits, get_commits_filtered, get_conflicted_files,
-    get_current_branch, get_diff, get_file_at_revision, get_file_diff_hunks, get_file_hunks,
-    get_refs_for_commit, get_staged_files, get_stash_list, get_status, get_sync_state,
-    has_staged_changes, has_unstaged_changes, init, mark_resolved, merge, move_file,
-    open_in_editor, pull, push, rebase, remote_add, remote_list, remote_remove, remove_file, reset,
-    revert_commit, reword_commit, squash_commits, stage_all, stage_file, stage_hunk,
-    stage_hunk_by_lines, stage_hunk_lines, stash_apply, stash_drop, stash_pop, stash_push,
-    tag_commit, tag_list, undo_last, unstage_all, unstage_file, unstage_hunk, unstage_hunk_lines,
-    DiffHunk, FileStatus, Hunk, HunkLine, HunkLineType, Remote, ResetMode, SmartLogFormatter,
-    StashEntry, StatusType, Tag,
+    abort_operation, absorb, add_paths, amend_commit, apply_patch, bisect_bad, bisect_good,
+    bisect_mark, bisect_reset, bisect_run, bisect_skip, bisect_start, bisect_state, checkout,
+    checkout_commit, cherry_pick_commit, clone, commit, continue_operation, create_branch,
+    create_branch_from_commit, create_tag, delete_tag, drop_commit, edit_history, edit_text, fetch,
+    find_trunk, format_todo, get_blame, get_branches, get_commit_diff, get_commit_file_diffs,
+    get_commit_message, get_commits, get_commits_filtered, get_commits_in_revset,
+    get_commits_matching, get_conflicted_files, get_current_branch, get_diff, get_file_at_revision,
+    get_file_diff_hunks, get_file_diffs, get_file_history, get_file_hunks,
+    get_file_version_content, get_file_version_diff, get_operations, get_reflog,
+    get_refs_for_commit, get_repo_status, get_stack, get_staged_files, get_stash_list, get_status,
+    get_successors, get_sync_state, get_tree_diff_hunks, has_staged_changes, has_unstaged_changes,
+    init, mark_resolved, merge, move_file, needs_restack, open_in_editor, parse_diff,
+    parse_porcelain_v2, parse_todo, pull, push, read_conflict_file, rebase, rebase_progress,
+    record_operation, record_successors, redo_operation, remote_add, remote_list, remote_remove,
+    remove_file, repo_state, reset, resolve_revset, restack, revert_commit, reword_commit,
+    skip_operation, split_commit, split_files, squash_commits, stack_target, stage_all, stage_file,
+    stage_hunk, stage_hunk_by_lines, stage_hunk_lines, stash_apply, stash_drop, stash_pop,
+    stash_push, tag_commit, tag_list, undo_last, undo_operation, unstage_all, unstage_file,
+    unstage_hunk, unstage_hunk_lines, write_resolution, BisectState, BisectStep, BisectVerdict,
+    Blame, BlameCommit, BlameLine, BlameOptions, BranchStatus, ChangeKind, ConflictChunk,
+    ConflictFile, ConflictKind, ConflictRegion, ConflictSide, FileChange, FileDiff, FileStatus,
+    FileVersion, HistoryEditOutcome, Hunk, HunkLine, HunkLineType, LogOrder, LogQuery, Operation,
+    OperationKind, RebaseAction, RebaseProgress, RebaseStep, ReflogEntry, Remote, RepoState,
+    RepoStatus, ResetMode, Resolution, Revset, SmartLogFormatter, SplitFile, StackEntry, StackMove,
+    StashEntry, StatusType, StopReason, SubmoduleStatus, Tag, TreeMerge, OPLOG_REF,
+};
+
+pub use runner::{
+    FakeRunner, GitInvocation, GitOutput, GitRunner, ProcessRunner, RecordedCall, RecordingRunner,
 };
 
-pub use vcs::{Change, Ref, RefType as VcsRefType, SyncState};
+pub use vcs::{
+    Change, ChangeCount, ChangePatch, ChangeSegment, GitBackend, HgBackend, HistoryEditAction,
+    HistoryEditPlan, HistoryEditPlanEntry, HistoryPoint, JjBackend, Ref, RefType as VcsRefType,
+    SavedWork, SplitPart, SyncState, Vcs,
+};
@@ -13,6 +13,10 @@ pub struct Commit {
     pub date: DateTime<Utc>,
     pub parent_hashes: Vec<String>,
     pub refs: Vec<GitRef>,
+    pub committer: String,
+    pub committer_email: String,
+    pub committer_date: DateTime<Utc>,
+    pub trailers: Vec<Trailer>,
 }
 
 impl fmt::Display for Commit {
@@ -21,6 +25,45 @@ impl fmt::Display for Commit {
     }
 }
 
+/// A `Key: value` trailer from the end of a commit message, such as
+/// `Signed-off-by`, `Co-authored-by` or `Change-Id`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Trailer {
+    pub key: String,
+    pub value: String,
+}
+
+impl Trailer {
+    /// Parse a single `Key: value` line
+    pub fn parse_line(line: &str) -> Option<Self> {
+        let (key, value) = line.split_once(':')?;
+        let valid_key = !key.is_empty()
+            && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+            && !key.starts_with('-');
+        valid_key.then(|| Self {
+            key: key.to_string(),
+            value: value.trim().to_string(),
+        })
+    }
+
+    /// Trailers in the last paragraph of `message`. The paragraph only counts
+    /// as a trailer block when every line in it is a trailer.
+    pub fn parse_message(message: &str) -> Vec<Self> {
+        let message = message.trim_end();
+        let Some((_, last)) = message.rsplit_once("\n\n") else {
+            return Vec::new();
+        };
+        let trailers: Option<Vec<Self>> = last.lines().map(Self::parse_line).collect();
+        trailers.unwrap_or_default()
+    }
+}
+
+impl fmt::Display for Trailer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.key, self.value)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GitRef {
     pub name: String,
@@ -46,3 +89,12 @@ pub enum RefType {
     Tag,
     Remote,
 }
+
+/// Abbreviate a full commit hash to its first seven characters. Anything
+/// else, such as a branch name or an already short prefix, is kept as is.
+pub fn abbreviate_hash(hash: &str) -> &str {
+    match hash.len() >= 40 && hash.chars().all(|c| c.is_ascii_hexdigit()) {
+        true => &hash[..7],
+        false => hash,
+    }
+}
@@ -1,3 +1,4 @@
+pub mod absorb;
 pub mod apply;
 pub mod bisect;
 pub mod blame;
@@ -7,15 +8,24 @@ pub mod checkout;
 pub mod commit;
 pub mod diff;
 pub mod editor;
+pub mod file_history;
+pub mod history_edit;
 pub mod hunk;
 pub mod log;
 pub mod merge;
+pub mod oplog;
 pub mod rebase;
+pub mod reflog;
 pub mod remote;
 pub mod repo;
+pub mod repo_state;
 pub mod reset;
 pub mod resolve;
+pub mod revset;
+pub mod rewrite;
 pub mod smart_log;
+pub mod split;
+pub mod stack;
 pub mod stage;
 pub mod stash;
 pub mod status;
@@ -23,9 +33,13 @@ pub mod sync;
 pub mod tag;
 pub mod undo;
 
+pub use absorb::{absorb, AbsorbedHunk};
 pub use apply::apply_patch;
-pub use bisect::{bisect_bad, bisect_good, bisect_reset, bisect_skip, bisect_start};
-pub use blame::get_blame;
+pub use bisect::{
+    bisect_bad, bisect_good, bisect_mark, bisect_reset, bisect_run, bisect_skip, bisect_start,
+    bisect_state, BisectState, BisectStep, BisectVerdict,
+};
+pub use blame::{get_blame, Blame, BlameCommit, BlameLine, BlameOptions};
 pub use branch::{
     create_branch, create_branch_from_commit, get_branches, get_current_branch, get_refs_for_commit,
 };
@@ -35,29 +49,57 @@ pub use commit::{
     amend_commit, cherry_pick_commit, commit, drop_commit, get_commit_message, revert_commit,
     reword_commit, squash_commits, tag_commit,
 };
-pub use diff::{get_commit_diff, get_diff};
-pub use editor::open_in_editor;
+pub use diff::{
+    get_commit_diff, get_commit_file_diffs, get_diff, get_file_diffs, parse_diff, FileDiff,
+};
+pub use editor::{edit_text, open_in_editor};
+pub use file_history::{
+    get_file_history, get_file_version_content, get_file_version_diff, FileChange, FileVersion,
+};
+pub use history_edit::{
+    edit_history, format_todo, parse_todo, rebase_progress, HistoryEditOutcome, RebaseAction,
+    RebaseProgress, RebaseStep, StopReason,
+};
 pub use hunk::{
-    get_file_diff_hunks, stage_hunk, stage_hunk_lines, unstage_hunk, unstage_hunk_lines, Hunk,
-    HunkLine, HunkLineType,
+    get_file_diff_hunks, get_tree_diff_hunks, stage_hunk, stage_hunk_lines, unstage_hunk,
+    unstage_hunk_lines, Hunk, HunkLine, HunkLineType,
 };
-pub use log::{get_commits, get_commits_filtered};
+pub use log::{get_commits, get_commits_filtered, get_commits_matching, LogOrder, LogQuery};
 pub use merge::merge;
+pub use oplog::{
+    get_operations, record_operation, redo_operation, undo_operation, Operation, OperationKind,
+    OPLOG_REF,
+};
 pub use rebase::rebase;
+pub use reflog::{get_reflog, ReflogEntry};
 pub use remote::{fetch, pull, push, remote_add, remote_list, remote_remove, Remote};
 pub use repo::{clone, init};
+pub use repo_state::{abort_operation, continue_operation, repo_state, skip_operation, RepoState};
 pub use reset::{reset, ResetMode};
-pub use resolve::{get_conflicted_files, mark_resolved};
+pub use resolve::{
+    get_conflicted_files, mark_resolved, read_conflict_file, write_resolution, ConflictChunk,
+    ConflictFile, ConflictRegion, ConflictSide, Resolution,
+};
+pub use revset::{get_commits_in_revset, resolve_revset, Revset};
+pub use rewrite::{merge_trees, TreeMerge};
 pub use smart_log::SmartLogFormatter;
+pub use split::{split_commit, split_files, SplitFile};
+pub use stack::{
+    find_trunk, get_stack, get_successors, needs_restack, record_successors, restack, stack_target,
+    StackEntry, StackMove,
+};
 pub use stage::{
     add_paths, get_file_hunks, get_staged_files, get_unstaged_files, has_staged_changes,
     has_unstaged_changes, move_file, remove_file, stage_all, stage_file, stage_hunk_by_lines,
-    unstage_all, unstage_file, DiffHunk,
+    unstage_all, unstage_file,
 };
 pub use stash::{
     get_stash_list, stash_apply, stash_drop, stash_pop, stash_push, stash_show, StashEntry,
 };
-pub use status::{get_status, FileStatus, StatusType};
+pub use status::{
+    get_repo_status, get_status, parse_porcelain_v2, BranchStatus, ChangeKind, ConflictKind,
+    FileStatus, RepoStatus, StatusType, SubmoduleStatus,
+};
 pub use sync::get_sync_state;
 pub use tag::{create_tag, delete_tag, show_tag, tag_list, Tag};
 pub use undo::undo_last;
@@ -0,0 +1,414 @@
+use crate::command::{run, run_raw};
+use crate::error::GitError;
+use crate::operations::history_edit::{rebase_progress, RebaseAction, RebaseStep};
+use crate::operations::hunk::{tree_diff_hunks, Hunk, HunkLineType};
+use crate::operations::{rewrite, split, stack};
+use crate::vcs::{ChangePatch, ChangeSegment};
+use anyhow::{bail, Context, Result};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+/// Where one staged hunk went
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AbsorbedHunk {
+    pub path: String,
+    /// Hunk header, e.g. `@@ -3 +3 @@`
+    pub header: String,
+    /// Commit the hunk belongs to (as it was before absorbing), or `None` when
+    /// it is ambiguous and stays staged
+    pub commit: Option<String>,
+}
+
+/// A staged file with the owner of each of its hunks
+struct StagedFile {
+    path: String,
+    hunks: Vec<Hunk>,
+    owners: Vec<Option<String>>,
+}
+
+fn tree_of(repo_path: &Path, commit: &str) -> Result<String> {
+    Ok(run(
+        &["rev-parse", "--verify", &format!("{}^{{tree}}", commit)],
+        Some(repo_path),
+    )?
+    .trim()
+    .to_string())
+}
+
+/// Commits that last touched lines `start..=end` of `path` at `head`
+fn blame_owners(
+    repo_path: &Path,
+    head: &str,
+    path: &str,
+    start: usize,
+    end: usize,
+) -> Result<HashSet<String>> {
+    let range = format!("{},{}", start, end);
+    let output = run(
+        &["blame", "--porcelain", "-L", &range, head, "--", path],
+        Some(repo_path),
+    )
+    .with_context(|| format!("Failed to blame '{}'", path))?;
+    let mut owners = HashSet::new();
+    for line in output.lines().filter(|line| !line.starts_with('\t')) {
+        let fields: Vec<&str> = line.split(' ').collect();
+        let is_header = fields.len() >= 3
+            && fields[0].len() >= 40
+            && fields[0].chars().all(|c| c.is_ascii_hexdigit());
+        if is_header {
+            owners.insert(fields[0].to_string());
+        }
+    }
+    Ok(owners)
+}
+
+/// The draft commit a hunk of the diff against `head` belongs to, if exactly
+/// one does
+fn hunk_owner(
+    repo_path: &Path,
+    head: &str,
+    path: &str,
+    hunk: &Hunk,
+    line_count: usize,
+    drafts: &HashSet<String>,
+) -> Result<Option<String>> {
+    let (start, end) = if hunk.old_lines > 0 {
+        (hunk.old_start, hunk.old_start + hunk.old_lines - 1)
+    } else {
+        // Inserted after line `old_start`: the lines on either side
+        (hunk.old_start.max(1), (hunk.old_start + 1).min(line_count))
+    };
+    if line_count == 0 || start > end {
+        return Ok(None);
+    }
+    let owners = blame_owners(repo_path, head, path, start, end)?;
+    Ok(match owners.into_iter().collect::<Vec<_>>()[..] {
+        [ref owner] if drafts.contains(owner) => Some(owner.clone()),
+        _ => None,
+    })
+}
+
+/// Fold each staged hunk into the draft commit that owns its lines, leaving
+/// ambiguous hunks staged, then restack. With `dry_run`, only report where
+/// the hunks would go.
+pub fn absorb(repo_path: &Path, dry_run: bool) -> Result<Vec<AbsorbedHunk>> {
+    if rebase_progress(repo_path)?.is_some() {
+        return Err(GitError::RebaseInProgress.into());
+    }
+    let head = run(&["rev-parse", "--verify", "HEAD"], Some(repo_path))?;
+    let head = head.trim().to_string();
+    let head_tree = tree_of(repo_path, &head)?;
+    let index_tree =
+        run(&["write-tree"], Some(repo_path)).context("Resolve conflicts before absorbing")?;
+    let index_tree = index_tree.trim().to_string();
+    if index_tree == head_tree {
+        bail!("Nothing staged to absorb");
+    }
+
+    let trunk = stack::find_trunk(repo_path)?;
+    let drafts = run(
+        &["rev-list", "--reverse", "HEAD", "--not", &trunk],
+        Some(repo_path),
+    )?;
+    let drafts: Vec<&str> = drafts.lines().collect();
+    let draft_set: HashSet<String> = drafts.iter().map(|d| d.to_string()).collect();
+
+    let names = run(
+        &[
+            "diff",
+            "--no-renames",
+            "--name-only",
+            "-z",
+            &head_tree,
+            &index_tree,
+        ],
+        Some(repo_path),
+    )?;
+    let mut files = Vec::new();
+    let mut report = Vec::new();
+    for path in names.split('\0').filter(|name| !name.is_empty()) {
+        let hunks = tree_diff_hunks(repo_path, &head_tree, &index_tree, Path::new(path), 0)?;
+        if hunks.is_empty() {
+            report.push(AbsorbedHunk {
+                path: path.to_string(),
+                header: "binary or mode change".to_string(),
+                commit: None,
+            });
+            continue;
+        }
+        let old = run_raw(
+            &["cat-file", "blob", &format!("{}:{}", head, path)],
+            Some(repo_path),
+        )?;
+        let line_count = match old.success() {
+            true => old.stdout.split_inclusive(|b| *b == b'\n').count(),
+            false => 0,
+        };
+        let mut owners = Vec::with_capacity(hunks.len());
+        for hunk in &hunks {
+            let owner = hunk_owner(repo_path, &head, path, hunk, line_count, &draft_set)?;
+            report.push(AbsorbedHunk {
+                path: path.to_string(),
+                header: hunk.header.clone(),
+                commit: owner.clone(),
+            });
+            owners.push(owner);
+        }
+        files.push(StagedFile {
+            path: path.to_string(),
+            hunks,
+            owners,
+        });
+    }
+
+    let targets: Vec<&str> = drafts
+        .iter()
+        .copied()
+        .filter(|draft| report.iter().any(|h| h.commit.as_deref() == Some(*draft)))
+        .collect();
+    if dry_run || targets.is_empty() {
+        return Ok(report);
+    }
+
+    // One fixup commit per target, each on top of the last
+    let mut fixup_of: HashMap<&str, String> = HashMap::new();
+    let mut parent = head.clone();
+    let mut tree = head_tree.clone();
+    let mut taken: HashSet<&str> = HashSet::new();
+    for &target in &targets {
+        taken.insert(target);
+        let patches: Vec<ChangePatch> = files
+            .iter()
+            .map(|file| ChangePatch {
+                file_path: file.path.clone(),
+                segments: file
+                    .hunks
+                    .iter()
+                    .zip(&file.owners)
+                    .flat_map(|(hunk, owner)| {
+                        let include = owner.as_deref().is_some_and(|o| taken.contains(o));
+                        let changes = hunk
+                            .lines
+                            .iter()
+                            .filter(|line| line.line_type != HunkLineType::Context)
+                            .count();
+                        let segment = match include {
+                            true => ChangeSegment::Include,
+                            false => ChangeSegment::Exclude,
+                        };
+                        std::iter::repeat_n(segment, changes)
+                    })
+                    .collect(),
+            })
+            .collect();
+        tree = split::apply_patches(repo_path, &head_tree, &index_tree, &patches)?;
+        let message = format!("fixup! {}", target);
+        let fixup = run(
+            &["commit-tree", &tree, "-p", &parent, "-m", &message],
+            Some(repo_path),
+        )?;
+        parent = fixup.trim().to_string();
+        fixup_of.insert(target, parent.clone());
+    }
+
+    let base = run_raw(
+        &[
+            "rev-parse",
+            "--verify",
+            "--quiet",
+            &format!("{}^", targets[0]),
+        ],
+        Some(repo_path),
+    )?;
+    let base = base
+        .success()
+        .then(|| String::from_utf8_lossy(&base.stdout).trim().to_string());
+    let range = match base {
+        Some(ref base) => format!("{}..HEAD", base),
+        None => "HEAD".to_string(),
+    };
+    let merges = run(&["rev-list", "--min-parents=2", &range], Some(repo_path))?;
+    if !merges.trim().is_empty() {
+        bail!("Cannot absorb into commits below a merge");
+    }
+    let commits = run(&["rev-list", "--reverse", &range], Some(repo_path))?;
+    let mut steps = Vec::new();
+    for commit in commits.lines() {
+        steps.push(RebaseStep::new(commit, RebaseAction::Pick));
+        if let Some(fixup) = fixup_of.get(commit) {
+            steps.push(RebaseStep::new(fixup, RebaseAction::Fixup));
+        }
+    }
+
+    let Some(replayed) = rewrite::replay(repo_path, base.as_deref(), &steps)? else {
+        bail!("The staged changes conflict with the commits they belong to; nothing was absorbed");
+    };
+    let tip = replayed.tip.context("Absorbing left no commits")?;
+    if tree_of(repo_path, &tip)? != tree {
+        bail!("Absorbing would change the result of the stack; nothing was absorbed");
+    }
+    rewrite::update_head_ref(repo_path, &tip, &head, "absorb")?;
+
+    let fixups: HashSet<&String> = fixup_of.values().collect();
+    let rewritten: Vec<(String, String)> = replayed
+        .rewritten
+        .into_iter()
+        .filter(|(old, _)| !fixups.contains(old))
+        .collect();
+    stack::record_successors(repo_path, &rewritten)?;
+    if stack::needs_restack(repo_path).unwrap_or(false) {
+        stack::restack(repo_path)?;
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{create_test_repo, git};
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_absorb_into_owning_commits() {
+        let dir = create_test_repo();
+        let path = dir.path();
+        fs::write(path.join("trunk.txt"), "trunk\n").unwrap();
+        git(path, &["add", "."]);
+        git(path, &["commit", "-m", "Trunk"]);
+        git(path, &["checkout", "-b", "feature"]);
+        fs::write(path.join("a.txt"), "a1\na2\na3\n").unwrap();
+        git(path, &["add", "."]);
+        git(path, &["commit", "-m", "Add a"]);
+        fs::write(path.join("b.txt"), "b1\nb2\nb3\n").unwrap();
+        git(path, &["add", "."]);
+        git(path, &["commit", "-m", "Add b"]);
+
+        fs::write(path.join("a.txt"), "a1\nA2\na3\n").unwrap();
+        fs::write(path.join("b.txt"), "b1\nb2\nB3\n").unwrap();
+        fs::write(path.join("trunk.txt"), "TRUNK\n").unwrap();
+        git(path, &["add", "."]);
+        fs::write(path.join("b.txt"), "b1\nb2\nB3\nunstaged\n").unwrap();
+
+        let preview = absorb(path, true).unwrap();
+        assert_eq!(preview.len(), 3);
+        assert!(preview
+            .iter()
+            .any(|h| h.path == "trunk.txt" && h.commit.is_none()));
+        assert_eq!(git(path, &["log", "-1", "--format=%s"]), "Add b");
+
+        let report = absorb(path, false).unwrap();
+        assert_eq!(report, preview);
+        assert_eq!(git(path, &["log", "--format=%s"]), "Add b\nAdd a\nTrunk");
+        assert_eq!(git(path, &["show", "HEAD~1:a.txt"]), "a1\nA2\na3");
+        assert_eq!(git(path, &["show", "HEAD:b.txt"]), "b1\nb2\nB3");
+        // The trunk change stays staged and the unstaged line is untouched
+        assert_eq!(git(path, &["diff", "--cached", "--name-only"]), "trunk.txt");
+        assert_eq!(git(path, &["diff", "--name-only"]), "b.txt");
+    }
+
+    /// A feature branch on a trunk commit, with `f.txt` written by two commits
+    fn two_owner_repo() -> (TempDir, String, String) {
+        let dir = create_test_repo();
+        let path = dir.path();
+        git(path, &["commit", "--allow-empty", "-m", "Trunk"]);
+        git(path, &["checkout", "-b", "feature"]);
+        fs::write(path.join("f.txt"), "a1\na2\n").unwrap();
+        git(path, &["add", "."]);
+        git(path, &["commit", "-m", "Add a"]);
+        let a = git(path, &["rev-parse", "HEAD"]);
+        fs::write(path.join("f.txt"), "a1\na2\nb1\nb2\n").unwrap();
+        git(path, &["commit", "-am", "Add b"]);
+        let b = git(path, &["rev-parse", "HEAD"]);
+        (dir, a, b)
+    }
+
+    #[test]
+    fn test_absorb_insertions_and_mixed_hunks() {
+        let (dir, a, b) = two_owner_repo();
+        let path = dir.path();
+
+        // Inside one commit's lines, on the seam of two, and at the end
+        fs::write(path.join("f.txt"), "a1\nnew a\na2\nseam\nb1\nb2\nnew b\n").unwrap();
+        git(path, &["add", "."]);
+        let owners: Vec<Option<String>> = absorb(path, true)
+            .unwrap()
+            .into_iter()
+            .map(|h| h.commit)
+            .collect();
+        assert_eq!(owners, [Some(a.clone()), None, Some(b.clone())]);
+
+        // One hunk changing lines of both commits
+        fs::write(path.join("f.txt"), "a1\nA2\nB1\nb2\n").unwrap();
+        git(path, &["add", "."]);
+        let report = absorb(path, false).unwrap();
+        assert_eq!(report.len(), 1);
+        assert_eq!(report[0].commit, None);
+        assert_eq!(git(path, &["rev-parse", "HEAD"]), b);
+        assert_eq!(git(path, &["diff", "--cached", "--name-only"]), "f.txt");
+    }
+
+    #[test]
+    fn test_absorb_into_root_commit() {
+        let dir = create_test_repo();
+        let path = dir.path();
+        git(path, &["checkout", "-b", "feature"]);
+        fs::write(path.join("f.txt"), "root\n").unwrap();
+        git(path, &["add", "."]);
+        git(path, &["commit", "-m", "Root"]);
+        fs::write(path.join("g.txt"), "g\n").unwrap();
+        git(path, &["add", "."]);
+        git(path, &["commit", "-m", "Add g"]);
+        // A trunk that shares no history with the feature
+        git(path, &["checkout", "--orphan", "main"]);
+        git(path, &["commit", "--allow-empty", "-m", "Trunk"]);
+        git(path, &["checkout", "-f", "feature"]);
+
+        fs::write(path.join("f.txt"), "ROOT\n").unwrap();
+        git(path, &["add", "."]);
+        absorb(path, false).unwrap();
+        assert_eq!(git(path, &["log", "--format=%s"]), "Add g\nRoot");
+        assert_eq!(git(path, &["show", "HEAD~1:f.txt"]), "ROOT");
+        assert_eq!(git(path, &["status", "--porcelain"]), "");
+    }
+
+    #[test]
+    fn test_absorb_below_merge_fails() {
+        let (dir, a, _) = two_owner_repo();
+        let path = dir.path();
+        git(path, &["checkout", "-b", "side", &a]);
+        fs::write(path.join("side.txt"), "side\n").unwrap();
+        git(path, &["add", "."]);
+        git(path, &["commit", "-m", "Side"]);
+        git(path, &["checkout", "feature"]);
+        git(path, &["merge", "--no-ff", "-m", "Merge side", "side"]);
+        let head = git(path, &["rev-parse", "HEAD"]);
+
+        fs::write(path.join("f.txt"), "A1\na2\nb1\nb2\n").unwrap();
+        git(path, &["add", "."]);
+        let err = absorb(path, false).unwrap_err();
+        assert!(format!("{}", err).contains("below a merge"));
+        assert_eq!(git(path, &["rev-parse", "HEAD"]), head);
+        assert_eq!(git(path, &["diff", "--cached", "--name-only"]), "f.txt");
+    }
+
+    #[test]
+    fn test_absorb_restacks_descendants() {
+        let (dir, _, _) = two_owner_repo();
+        let path = dir.path();
+        git(path, &["checkout", "-b", "stacked"]);
+        fs::write(path.join("s.txt"), "s\n").unwrap();
+        git(path, &["add", "."]);
+        git(path, &["commit", "-m", "Stacked"]);
+        git(path, &["checkout", "feature"]);
+
+        fs::write(path.join("f.txt"), "A1\na2\nb1\nb2\n").unwrap();
+        git(path, &["add", "."]);
+        absorb(path, false).unwrap();
+        let head = git(path, &["rev-parse", "HEAD"]);
+        assert_eq!(git(path, &["rev-parse", "stacked~1"]), head);
+        assert_eq!(git(path, &["show", "stacked~2:f.txt"]), "A1\na2");
+        assert_eq!(git(path, &["show", "stacked:s.txt"]), "s");
+        assert!(!stack::needs_restack(path).unwrap());
+    }
+}
@@ -1,41 +1,302 @@
 use crate::command::{run, run_success};
-use anyhow::{Context, Result};
-use std::path::Path;
+use crate::models::abbreviate_hash;
+use anyhow::{bail, Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// How a tested revision behaved
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BisectVerdict {
+    Good,
+    Bad,
+    Skip,
+}
+
+/// A bisect session
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BisectState {
+    /// Commits marked good
+    pub good: Vec<String>,
+    /// The newest commit marked bad
+    pub bad: Option<String>,
+    pub skipped: Vec<String>,
+    /// Commits between the good ones and the bad one, the bad one included
+    pub candidates: Vec<String>,
+    /// Revision checked out for testing, until the first bad one is found
+    pub current: Option<String>,
+    /// Candidates left to test
+    pub remaining: usize,
+    /// Tests needed at most to find the first bad commit
+    pub steps: usize,
+    pub first_bad: Option<String>,
+}
+
+impl BisectState {
+    /// Whether the session has run out of revisions without a first bad
+    /// commit, because the rest were skipped
+    pub fn only_skipped_left(&self) -> bool {
+        self.first_bad.is_none() && self.remaining == 0 && !self.good.is_empty()
+    }
+
+    /// One-line summary, as shown after each mark
+    pub fn summary(&self) -> String {
+        match (&self.first_bad, &self.bad, self.good.is_empty()) {
+            (Some(first_bad), _, _) => {
+                format!("{} is the first bad commit", abbreviate_hash(first_bad))
+            }
+            (None, None, true) => "Waiting for a good and a bad commit".to_string(),
+            (None, None, false) => "Waiting for a bad commit".to_string(),
+            (None, Some(_), true) => "Waiting for a good commit".to_string(),
+            _ if self.only_skipped_left() => {
+                "Only skipped commits are left; the first bad commit is among them".to_string()
+            }
+            _ => format!(
+                "{} revision(s) left to test (about {} step(s))",
+                self.remaining, self.steps
+            ),
+        }
+    }
+}
+
+fn git_dir(repo_path: &Path) -> Result<PathBuf> {
+    let dir = run(&["rev-parse", "--absolute-git-dir"], Some(repo_path))?;
+    Ok(PathBuf::from(dir.trim()))
+}
+
+/// The session in progress, or `None` when not bisecting
+pub fn bisect_state(repo_path: &Path) -> Result<Option<BisectState>> {
+    let git_dir = git_dir(repo_path)?;
+    if !git_dir.join("BISECT_START").exists() {
+        return Ok(None);
+    }
+    // `git bisect start --term-new/--term-old` renames the refs
+    let terms = fs::read_to_string(git_dir.join("BISECT_TERMS")).unwrap_or_default();
+    let mut terms = terms.lines();
+    let bad_term = terms.next().unwrap_or("bad").to_string();
+    let good_term = terms.next().unwrap_or("good").to_string();
+
+    let refs = run(
+        &[
+            "for-each-ref",
+            "--format=%(refname) %(objectname)",
+            "refs/bisect/",
+        ],
+        Some(repo_path),
+    )?;
+    let mut state = BisectState::default();
+    for line in refs.lines() {
+        let Some((name, hash)) = line.split_once(' ') else {
+            continue;
+        };
+        let Some(name) = name.strip_prefix("refs/bisect/") else {
+            continue;
+        };
+        if name == bad_term {
+            state.bad = Some(hash.to_string());
+        } else if name.starts_with(&format!("{}-", good_term)) {
+            state.good.push(hash.to_string());
+        } else if name.starts_with("skip-") {
+            state.skipped.push(hash.to_string());
+        }
+    }
+
+    let Some(ref bad) = state.bad else {
+        return Ok(Some(state));
+    };
+    if state.good.is_empty() {
+        return Ok(Some(state));
+    }
+    let mut args = vec!["rev-list", bad.as_str(), "--not"];
+    args.extend(state.good.iter().map(String::as_str));
+    let candidates = run(&args, Some(repo_path))?;
+    state.candidates = candidates.lines().map(str::to_string).collect();
+
+    let untested = state
+        .candidates
+        .iter()
+        .filter(|c| *c != bad && !state.skipped.contains(c))
+        .count();
+    state.remaining = untested;
+    state.steps = (usize::BITS - untested.leading_zeros()) as usize;
+    if state.candidates.len() == 1 {
+        state.first_bad = Some(bad.clone());
+    } else if untested > 0 {
+        let head = run(&["rev-parse", "HEAD"], Some(repo_path))?;
+        state.current = Some(head.trim().to_string());
+    }
+    Ok(Some(state))
+}
+
+/// Mark `revision` (the checked-out one by default), starting a session if
+/// there is none, and let git check out the next candidate
+pub fn bisect_mark(
+    repo_path: &Path,
+    verdict: BisectVerdict,
+    revision: Option<&str>,
+) -> Result<BisectState> {
+    if bisect_state(repo_path)?.is_none() {
+        run_success(&["bisect", "start"], Some(repo_path))
+            .with_context(|| "Failed to start bisect")?;
+    }
+    let word = match verdict {
+        BisectVerdict::Good => "good",
+        BisectVerdict::Bad => "bad",
+        BisectVerdict::Skip => "skip",
+    };
+    let mut args = vec!["bisect", word];
+    if let Some(rev) = revision {
+        args.push(rev);
+    }
+    run(&args, Some(repo_path)).with_context(|| format!("Failed to mark revision as {}", word))?;
+    bisect_state(repo_path)?.context("The bisect session ended unexpectedly")
+}
 
 /// Start a bisect session between a known-good and a known-bad revision.
-pub fn bisect_start(repo_path: &Path, bad: &str, good: &str) -> Result<String> {
+pub fn bisect_start(repo_path: &Path, bad: &str, good: &str) -> Result<BisectState> {
     run_success(&["bisect", "start"], Some(repo_path)).with_context(|| "Failed to start bisect")?;
     run_success(&["bisect", "bad", bad], Some(repo_path))
         .with_context(|| format!("Failed to mark '{}' as bad", bad))?;
     run_success(&["bisect", "good", good], Some(repo_path))
         .with_context(|| format!("Failed to mark '{}' as good", good))?;
-    run(&["bisect", "log"], Some(repo_path)).with_context(|| "Failed to read bisect state")
+    bisect_state(repo_path)?.context("Failed to read bisect state")
 }
 
 /// Mark the current revision as good and get the next candidate to test.
-pub fn bisect_good(repo_path: &Path, revision: Option<&str>) -> Result<String> {
-    let mut args = vec!["bisect", "good"];
-    if let Some(rev) = revision {
-        args.push(rev);
-    }
-    run(&args, Some(repo_path)).with_context(|| "Failed to mark revision as good")
+pub fn bisect_good(repo_path: &Path, revision: Option<&str>) -> Result<BisectState> {
+    bisect_mark(repo_path, BisectVerdict::Good, revision)
 }
 
 /// Mark the current revision as bad and get the next candidate to test.
-pub fn bisect_bad(repo_path: &Path, revision: Option<&str>) -> Result<String> {
-    let mut args = vec!["bisect", "bad"];
-    if let Some(rev) = revision {
-        args.push(rev);
-    }
-    run(&args, Some(repo_path)).with_context(|| "Failed to mark revision as bad")
+pub fn bisect_bad(repo_path: &Path, revision: Option<&str>) -> Result<BisectState> {
+    bisect_mark(repo_path, BisectVerdict::Bad, revision)
 }
 
 /// Skip the current revision and test the next candidate instead.
-pub fn bisect_skip(repo_path: &Path) -> Result<String> {
-    run(&["bisect", "skip"], Some(repo_path)).with_context(|| "Failed to skip revision")
+pub fn bisect_skip(repo_path: &Path) -> Result<BisectState> {
+    bisect_mark(repo_path, BisectVerdict::Skip, None)
 }
 
 /// End the bisect session and return to the pre-bisect HEAD.
 pub fn bisect_reset(repo_path: &Path) -> Result<()> {
     run_success(&["bisect", "reset"], Some(repo_path)).with_context(|| "Failed to reset bisect")
 }
+
+/// One tested revision of `bisect_run`
+#[derive(Debug, Clone)]
+pub struct BisectStep {
+    pub commit: String,
+    /// Exit code of the test command
+    pub exit_code: i32,
+    pub verdict: BisectVerdict,
+    /// Output of the test command, stdout then stderr
+    pub output: String,
+    /// The session after marking the revision
+    pub state: BisectState,
+}
+
+/// Test candidates with `command` (run by `sh` in the repository) until the
+/// first bad commit is found, like `git bisect run`: exit code 0 is good, 125
+/// skips, 1 to 127 is bad, and anything else (or a signal) stops the run.
+/// `on_step` sees each tested revision.
+pub fn bisect_run(
+    repo_path: &Path,
+    command: &str,
+    mut on_step: impl FnMut(&BisectStep),
+) -> Result<BisectState> {
+    let Some(mut state) = bisect_state(repo_path)? else {
+        bail!("No bisect in progress; start one with a good and a bad commit");
+    };
+    if state.bad.is_none() || state.good.is_empty() {
+        bail!("{} before running", state.summary());
+    }
+
+    while state.first_bad.is_none() && !state.only_skipped_left() {
+        let commit = state
+            .current
+            .clone()
+            .context("No revision is checked out for testing")?;
+        let output = Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .current_dir(repo_path)
+            .output()
+            .with_context(|| format!("Failed to run '{}'", command))?;
+        let exit_code = match output.status.code() {
+            Some(code) if code < 128 => code,
+            Some(code) => bail!(
+                "'{}' exited with {}; stopping the bisect run",
+                command,
+                code
+            ),
+            None => bail!(
+                "'{}' was killed by a signal; stopping the bisect run",
+                command
+            ),
+        };
+        let verdict = match exit_code {
+            0 => BisectVerdict::Good,
+            125 => BisectVerdict::Skip,
+            _ => BisectVerdict::Bad,
+        };
+        state = bisect_mark(repo_path, verdict, None)?;
+        if state.first_bad.is_none() && state.current.as_ref() == Some(&commit) {
+            bail!("The bisect did not move past {}", abbreviate_hash(&commit));
+        }
+        on_step(&BisectStep {
+            commit,
+            exit_code,
+            verdict,
+            output: String::from_utf8_lossy(&output.stdout).to_string()
+                + &String::from_utf8_lossy(&output.stderr),
+            state: state.clone(),
+        });
+    }
+    Ok(state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{create_test_repo, git};
+
+    #[test]
+    fn test_bisect_run_finds_first_bad() {
+        let dir = create_test_repo();
+        let path = dir.path();
+        let mut commits = Vec::new();
+        for i in 1..=8 {
+            fs::write(path.join("n.txt"), format!("{}\n", i)).unwrap();
+            git(path, &["add", "n.txt"]);
+            git(path, &["commit", "-m", &format!("Set {}", i)]);
+            commits.push(git(path, &["rev-parse", "HEAD"]));
+        }
+        assert_eq!(bisect_state(path).unwrap(), None);
+
+        // Marks can come one at a time, starting the session
+        let state = bisect_bad(path, None).unwrap();
+        assert_eq!(state.summary(), "Waiting for a good commit");
+        let state = bisect_good(path, Some(&commits[0])).unwrap();
+        assert_eq!(state.candidates.len(), 7);
+        assert_eq!(state.remaining, 6);
+        assert_eq!(state.steps, 3);
+        assert!(state.current.is_some());
+
+        // 6 is the first commit with a number above 5
+        let mut tested = Vec::new();
+        let state = bisect_run(path, "test $(cat n.txt) -le 5", |step| {
+            tested.push((step.commit.clone(), step.verdict));
+        })
+        .unwrap();
+        assert_eq!(state.first_bad.as_deref(), Some(commits[5].as_str()));
+        assert!(state.summary().ends_with("is the first bad commit"));
+        assert!(!tested.is_empty() && tested.len() <= 3);
+        assert!(tested
+            .iter()
+            .all(|(commit, verdict)| (*verdict == BisectVerdict::Good)
+                == (commits.iter().position(|c| c == commit).unwrap() < 5)));
+
+        bisect_reset(path).unwrap();
+        assert_eq!(git(path, &["rev-parse", "HEAD"]), commits[7]);
+    }
+}
@@ -1,9 +1,253 @@
 use crate::command::run;
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, TimeZone, Utc};
+use std::collections::HashMap;
 use std::path::Path;
 
-/// Annotate each line of a file with the commit that last touched it (`git blame`).
-pub fn get_blame(repo_path: &Path, path: &str) -> Result<String> {
-    run(&["blame", "--", path], Some(repo_path))
-        .with_context(|| format!("Failed to blame '{}'", path))
+/// How to blame a file
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BlameOptions {
+    /// Revision to blame at; the working tree when `None`
+    pub revision: Option<String>,
+    /// Ignore whitespace changes (`-w`)
+    pub ignore_whitespace: bool,
+    /// Follow lines moved or copied within the file (`-M`)
+    pub detect_moves: bool,
+    /// Follow lines moved or copied from other files changed in the same
+    /// commit (`-C`)
+    pub detect_copies: bool,
+}
+
+impl BlameOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Blame the file as of `revision`
+    pub fn at(mut self, revision: impl Into<String>) -> Self {
+        self.revision = Some(revision.into());
+        self
+    }
+
+    fn args(&self) -> Vec<&str> {
+        let mut args = Vec::new();
+        if self.ignore_whitespace {
+            args.push("-w");
+        }
+        if self.detect_moves {
+            args.push("-M");
+        }
+        if self.detect_copies {
+            args.push("-C");
+        }
+        args.extend(self.revision.as_deref());
+        args
+    }
+}
+
+/// A commit that owns lines of a blamed file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlameCommit {
+    pub hash: String,
+    pub author: String,
+    pub author_email: String,
+    pub date: DateTime<Utc>,
+    pub summary: String,
+    /// Parent commit and the file's path there, when the commit has one
+    pub previous: Option<(String, String)>,
+    /// The oldest commit reached (a root commit, or the edge of a range)
+    pub boundary: bool,
+}
+
+impl BlameCommit {
+    /// Whether the lines are changes not committed yet
+    pub fn is_uncommitted(&self) -> bool {
+        self.hash.bytes().all(|b| b == b'0')
+    }
+}
+
+/// One line of a blamed file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlameLine {
+    /// Hash of the commit that last changed the line
+    pub commit: String,
+    pub author: String,
+    pub date: DateTime<Utc>,
+    /// Line number in that commit's version of the file (1-based)
+    pub original_line: usize,
+    /// Line number in the blamed version (1-based)
+    pub final_line: usize,
+    /// Path in that commit's version, which differs after a rename or copy
+    pub original_path: String,
+    pub content: String,
+}
+
+/// A blamed file
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Blame {
+    pub path: String,
+    pub options: BlameOptions,
+    pub lines: Vec<BlameLine>,
+    /// Metadata of each commit owning lines, by hash
+    pub commits: HashMap<String, BlameCommit>,
+}
+
+impl Blame {
+    /// The commit owning `line`
+    pub fn commit_of(&self, line: &BlameLine) -> Option<&BlameCommit> {
+        self.commits.get(&line.commit)
+    }
+
+    /// Parse `git blame --porcelain` output
+    pub fn parse(path: &str, options: BlameOptions, output: &str) -> Result<Self> {
+        let mut blame = Blame {
+            path: path.to_string(),
+            options,
+            ..Default::default()
+        };
+        let mut current: Option<(String, usize, usize)> = None;
+        let mut filenames: HashMap<String, String> = HashMap::new();
+
+        for line in output.lines() {
+            if let Some(content) = line.strip_prefix('\t') {
+                let Some((hash, original_line, final_line)) = current.take() else {
+                    bail!("Unexpected line in blame output: {}", line);
+                };
+                let commit = blame
+                    .commits
+                    .get(&hash)
+                    .with_context(|| format!("No metadata for commit {}", hash))?;
+                blame.lines.push(BlameLine {
+                    author: commit.author.clone(),
+                    date: commit.date,
+                    original_line,
+                    final_line,
+                    original_path: filenames.get(&hash).cloned().unwrap_or_default(),
+                    content: content.to_string(),
+                    commit: hash,
+                });
+                continue;
+            }
+            let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+            let is_header = key.len() >= 40 && key.bytes().all(|b| b.is_ascii_hexdigit());
+            if is_header {
+                let mut numbers = value.split(' ').map(|n| n.parse::<usize>().unwrap_or(0));
+                let original_line = numbers.next().unwrap_or(0);
+                let final_line = numbers.next().unwrap_or(0);
+                blame
+                    .commits
+                    .entry(key.to_string())
+                    .or_insert_with(|| BlameCommit {
+                        hash: key.to_string(),
+                        author: String::new(),
+                        author_email: String::new(),
+                        date: DateTime::<Utc>::default(),
+                        summary: String::new(),
+                        previous: None,
+                        boundary: false,
+                    });
+                current = Some((key.to_string(), original_line, final_line));
+                continue;
+            }
+            let Some((ref hash, _, _)) = current else {
+                continue;
+            };
+            let Some(commit) = blame.commits.get_mut(hash) else {
+                continue;
+            };
+            match key {
+                "author" => commit.author = value.to_string(),
+                "author-mail" => {
+                    commit.author_email = value.trim_matches(['<', '>']).to_string();
+                }
+                "author-time" => {
+                    if let Some(date) = value
+                        .parse()
+                        .ok()
+                        .and_then(|t| Utc.timestamp_opt(t, 0).single())
+                    {
+                        commit.date = date;
+                    }
+                }
+                "summary" => commit.summary = value.to_string(),
+                "boundary" => commit.boundary = true,
+                "previous" => {
+                    commit.previous = value
+                        .split_once(' ')
+                        .map(|(parent, path)| (parent.to_string(), path.to_string()));
+                }
+                "filename" => {
+                    filenames.insert(hash.clone(), value.to_string());
+                }
+                _ => {}
+            }
+        }
+        Ok(blame)
+    }
+}
+
+/// Annotate each line of a file with the commit that last touched it.
+pub fn get_blame(repo_path: &Path, path: &str, options: &BlameOptions) -> Result<Blame> {
+    let mut args = vec!["blame", "--porcelain"];
+    args.extend(options.args());
+    args.extend(["--", path]);
+    let output =
+        run(&args, Some(repo_path)).with_context(|| format!("Failed to blame '{}'", path))?;
+    Blame::parse(path, options.clone(), &output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{create_test_repo, git};
+    use std::fs;
+
+    #[test]
+    fn test_blame_options_and_parent() {
+        let dir = create_test_repo();
+        let path = dir.path();
+        fs::write(path.join("a.txt"), "one\ntwo\nthree\n").unwrap();
+        git(path, &["add", "a.txt"]);
+        git(path, &["commit", "-m", "Add a"]);
+        let first = git(path, &["rev-parse", "HEAD"]);
+        fs::write(path.join("a.txt"), "one\n  two\nthree\n").unwrap();
+        git(path, &["commit", "-am", "Reindent"]);
+        let second = git(path, &["rev-parse", "HEAD"]);
+        fs::write(path.join("a.txt"), "one\n  two\nthree\nfour\n").unwrap();
+
+        let blame = get_blame(path, "a.txt", &BlameOptions::new()).unwrap();
+        let owners: Vec<&str> = blame.lines.iter().map(|l| l.commit.as_str()).collect();
+        assert_eq!(
+            owners[..3],
+            [first.as_str(), second.as_str(), first.as_str()]
+        );
+        assert!(blame.commit_of(&blame.lines[3]).unwrap().is_uncommitted());
+        assert_eq!(blame.lines[1].content, "  two");
+        assert_eq!(blame.lines[2].final_line, 3);
+        assert_eq!(blame.lines[2].original_line, 3);
+        assert_eq!(blame.lines[0].author, "Test User");
+        assert_eq!(blame.lines[0].original_path, "a.txt");
+        // Each commit's metadata is kept once
+        assert_eq!(blame.commits.len(), 3);
+        let reindent = &blame.commits[&second];
+        assert_eq!(reindent.summary, "Reindent");
+        assert_eq!(reindent.author_email, "test@example.com");
+        assert_eq!(
+            reindent.previous,
+            Some((first.clone(), "a.txt".to_string()))
+        );
+        assert!(blame.commits[&first].previous.is_none());
+
+        // Past the reindent, at a revision
+        let options = BlameOptions {
+            ignore_whitespace: true,
+            ..BlameOptions::new().at("HEAD")
+        };
+        let blame = get_blame(path, "a.txt", &options).unwrap();
+        assert_eq!(blame.lines.len(), 3);
+        assert!(blame.lines.iter().all(|l| l.commit == first));
+
+        let parent = get_blame(path, "a.txt", &BlameOptions::new().at(&first)).unwrap();
+        assert_eq!(parent.lines[1].content, "two");
+    }
 }
@@ -8,7 +8,7 @@ pub fn get_branches(repo_path: &Path, remote: bool, all: bool) -> Result<Vec<Git
     if remote || all {
         args.push("-a");
     }
-    args.push("--format=%(refname:short)|%(refname:short)");
+    args.push("--format=%(refname)|%(refname:short)");
 
     let output = run(&args, Some(repo_path)).with_context(|| "Failed to get git branches")?;
 
@@ -19,14 +19,15 @@ pub fn get_branches(repo_path: &Path, remote: bool, all: bool) -> Result<Vec<Git
         }
 
         let parts: Vec<&str> = line.splitn(2, '|').collect();
-        let name = parts[0].to_string();
-        let ref_type = if name.starts_with("refs/heads/") {
+        let full_name = parts[0];
+        let name = parts.get(1).unwrap_or(&full_name).to_string();
+        let ref_type = if full_name.starts_with("refs/heads/") {
             RefType::Branch
-        } else if name.starts_with("refs/remotes/") {
+        } else if full_name.starts_with("refs/remotes/") {
             RefType::Remote
-        } else if name.starts_with("refs/tags/") {
+        } else if full_name.starts_with("refs/tags/") {
             RefType::Tag
-        } else if name == "HEAD" {
+        } else if full_name == "HEAD" {
             RefType::Head
         } else {
             // Default to branch for local refs without prefix
@@ -3,13 +3,13 @@ use anyhow::{Context, Result};
 use std::path::Path;
 
 pub fn checkout(repo_path: &Path, target: &str) -> Result<()> {
-    let args = vec!["checkout", "--", target];
+    let args = vec!["checkout", target, "--"];
     run(&args, Some(repo_path)).with_context(|| format!("Failed to checkout '{}'", target))?;
     Ok(())
 }
 
 pub fn checkout_commit(repo_path: &Path, commit_hash: &str) -> Result<()> {
-    let args = vec!["checkout", "--", commit_hash];
+    let args = vec!["checkout", commit_hash, "--"];
     run(&args, Some(repo_path))
         .with_context(|| format!("Failed to checkout commit '{}'", commit_hash))?;
     Ok(())
@@ -18,6 +18,9 @@ pub fn checkout_commit(repo_path: &Path, commit_hash: &str) -> Result<()> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::error::GitError;
+    use crate::test_support::{create_test_repo, git};
+    use std::fs;
 
     #[test]
     fn test_checkout_non_existent() {
@@ -25,4 +28,29 @@ mod tests {
         let result = checkout(&repo_path, "non-existent-branch-12345");
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_checkout_reports_structured_errors() {
+        let dir = create_test_repo();
+        let path = dir.path();
+        fs::write(path.join("a.txt"), "one\n").unwrap();
+        git(path, &["add", "a.txt"]);
+        git(path, &["commit", "-m", "First"]);
+        git(path, &["checkout", "-b", "other"]);
+        fs::write(path.join("a.txt"), "two\n").unwrap();
+        git(path, &["commit", "-am", "Second"]);
+        fs::write(path.join("a.txt"), "dirty\n").unwrap();
+
+        let err = checkout(path, "main").unwrap_err();
+        match err.downcast_ref::<GitError>() {
+            Some(GitError::DirtyWorkingTree { paths }) => assert_eq!(paths, &["a.txt"]),
+            other => panic!("unexpected {:?}", other),
+        }
+
+        let err = checkout(path, "no-such-branch").unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<GitError>(),
+            Some(GitError::UnknownRevision(r)) if r == "no-such-branch"
+        ));
+    }
 }
@@ -1,7 +1,7 @@
-use crate::command::{run, run_success, run_with_env};
-use anyhow::{Context, Result};
-use std::collections::HashMap;
-use std::fs;
+use crate::command::{run, run_success};
+use crate::operations::history_edit::{edit_history, RebaseAction, RebaseStep};
+use crate::operations::rewrite::update_head_ref;
+use anyhow::{bail, Context, Result};
 use std::path::Path;
 
 /// Create a commit from the currently staged changes.
@@ -22,7 +22,8 @@ pub fn amend_commit(repo_path: &Path, amend_message: Option<&str>) -> Result<()>
     Ok(())
 }
 
-/// Reword a commit's message. For HEAD, uses amend. For other commits, uses interactive rebase.
+/// Reword a commit's message. For HEAD, uses amend. For other commits, runs a
+/// history edit that rewrites the commits above it.
 pub fn reword_commit(repo_path: &Path, commit_hash: &str, message: &str) -> Result<()> {
     // Check if the commit is HEAD
     let head =
@@ -38,93 +39,56 @@ pub fn reword_commit(repo_path: &Path, commit_hash: &str, message: &str) -> Resu
         run_success(&["commit", "--amend", "-m", message], Some(repo_path))
             .with_context(|| "Failed to amend HEAD commit")?;
     } else {
-        // Complex case: need to use interactive rebase
-        // Find the parent of the target commit
-        let parent = run(
-            &["rev-parse", &format!("{}^", commit_hash)],
-            Some(repo_path),
-        )
-        .with_context(|| format!("Failed to get parent of {}", commit_hash))?;
-        let parent = parent.trim();
-
-        let short_hash = &target[..7.min(target.len())];
-
-        // Create a temporary directory for our editor scripts
-        let temp_dir = std::env::temp_dir().join(format!("openisl-reword-{}", std::process::id()));
-        fs::create_dir_all(&temp_dir)?;
-
-        // Create the sequence editor script that changes 'pick <hash>' to 'reword <hash>'
-        let seq_editor_path = temp_dir.join("seq-editor.sh");
-        let seq_editor_script = format!(
-            "#!/bin/sh\nsed -i.bak 's/^pick {}/reword {}/g' \"$1\"\n",
-            short_hash, short_hash
-        );
-        fs::write(&seq_editor_path, &seq_editor_script)?;
-
-        // Make it executable
-        #[cfg(unix)]
-        {
-            use std::os::unix::fs::PermissionsExt;
-            let mut perms = fs::metadata(&seq_editor_path)?.permissions();
-            perms.set_mode(0o755);
-            fs::set_permissions(&seq_editor_path, perms)?;
-        }
-
-        // Create the message editor script that writes our message
-        let msg_editor_path = temp_dir.join("msg-editor.sh");
-        // Escape single quotes in the message for shell
-        let escaped_message = message.replace('\'', "'\\''");
-        let msg_editor_script = format!("#!/bin/sh\nprintf '%s' '{}' > \"$1\"\n", escaped_message);
-        fs::write(&msg_editor_path, &msg_editor_script)?;
-
-        #[cfg(unix)]
-        {
-            use std::os::unix::fs::PermissionsExt;
-            let mut perms = fs::metadata(&msg_editor_path)?.permissions();
-            perms.set_mode(0o755);
-            fs::set_permissions(&msg_editor_path, perms)?;
-        }
-
-        // Run the rebase with our custom editors
-        let mut env = HashMap::new();
-        let seq_editor_str = seq_editor_path.to_string_lossy();
-        let msg_editor_str = msg_editor_path.to_string_lossy();
-        env.insert("GIT_SEQUENCE_EDITOR", seq_editor_str.as_ref());
-        env.insert("GIT_EDITOR", msg_editor_str.as_ref());
-
-        let result = run_with_env(&["rebase", "-i", parent], Some(repo_path), &env);
-
-        // Clean up temp files
-        let _ = fs::remove_dir_all(&temp_dir);
-
-        result.with_context(|| format!("Failed to reword commit {}", commit_hash))?;
+        let step = RebaseStep::new(target, RebaseAction::Reword).with_message(message);
+        edit_history(repo_path, None, &[step])?
+            .into_result()
+            .with_context(|| format!("Failed to reword commit {}", commit_hash))?;
     }
 
     Ok(())
 }
 
 pub fn drop_commit(repo_path: &Path, commit_hash: &str) -> Result<()> {
-    run_success(
-        &[
-            "rebase",
-            "--onto",
-            &format!("^{}", commit_hash),
-            commit_hash,
-        ],
-        Some(repo_path),
-    )
+    edit_history(
+        repo_path,
+        None,
+        &[RebaseStep::new(commit_hash, RebaseAction::Drop)],
+    )?
+    .into_result()
     .with_context(|| format!("Failed to drop commit {}", commit_hash))?;
     Ok(())
 }
 
+/// Squash every commit after `commit_hash`, along with the staged changes,
+/// into a single commit with `message`. Merges in the range are flattened.
 pub fn squash_commits(repo_path: &Path, commit_hash: &str, message: &str) -> Result<()> {
-    run_success(&["reset", "--soft", commit_hash], Some(repo_path))
-        .with_context(|| format!("Failed to reset to {}", commit_hash))?;
-
-    run_success(&["commit", "-m", message], Some(repo_path))
-        .with_context(|| "Failed to create squashed commit")?;
+    let base = run(
+        &[
+            "rev-parse",
+            "--verify",
+            &format!("{}^{{commit}}", commit_hash),
+        ],
+        Some(repo_path),
+    )
+    .with_context(|| format!("Failed to resolve commit {}", commit_hash))?;
+    let head =
+        run(&["rev-parse", "--verify", "HEAD"], Some(repo_path)).context("Failed to get HEAD")?;
+    let tree =
+        run(&["write-tree"], Some(repo_path)).context("Resolve conflicts before squashing")?;
+    let base_tree = run(
+        &["rev-parse", &format!("{}^{{tree}}", base.trim())],
+        Some(repo_path),
+    )?;
+    if tree == base_tree && base.trim() == head.trim() {
+        bail!("Nothing to squash after {}", commit_hash);
+    }
 
-    Ok(())
+    let squashed = run(
+        &["commit-tree", tree.trim(), "-p", base.trim(), "-m", message],
+        Some(repo_path),
+    )
+    .context("Failed to create squashed commit")?;
+    update_head_ref(repo_path, squashed.trim(), head.trim(), "squash")
 }
 
 pub fn get_commit_message(repo_path: &Path, commit_hash: &str) -> Result<String> {
@@ -165,7 +129,9 @@ pub fn revert_commit(repo_path: &Path, commit_hash: &str) -> Result<()> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_support::{commit_file, create_test_repo, git};
     use std::env::current_dir;
+    use std::fs;
 
     #[test]
     fn test_get_commit_message() {
@@ -173,4 +139,34 @@ mod tests {
         let result = get_commit_message(&repo_path, "HEAD");
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_squash_folds_staged_changes_and_merges() {
+        let dir = create_test_repo();
+        let path = dir.path();
+        let base = commit_file(path, "base.txt", "base\n", "Base");
+        commit_file(path, "a.txt", "a\n", "Add a");
+        git(path, &["checkout", "-q", "-b", "side", &base]);
+        commit_file(path, "b.txt", "b\n", "Add b");
+        git(path, &["checkout", "-q", "main"]);
+        git(
+            path,
+            &["merge", "-q", "--no-ff", "-m", "Merge side", "side"],
+        );
+        fs::write(path.join("staged.txt"), "staged\n").unwrap();
+        git(path, &["add", "staged.txt"]);
+        fs::write(path.join("a.txt"), "unstaged\n").unwrap();
+
+        squash_commits(path, &base, "Squashed").unwrap();
+        assert_eq!(git(path, &["log", "--format=%s"]), "Squashed\nBase");
+        assert_eq!(
+            git(path, &["show", "--name-only", "--format="]),
+            "a.txt\nb.txt\nstaged.txt"
+        );
+        assert_eq!(git(path, &["show", "HEAD:a.txt"]), "a");
+        // Only the unstaged edit is left over
+        assert_eq!(git(path, &["status", "--porcelain"]), "M a.txt");
+
+        assert!(squash_commits(path, "HEAD", "Again").is_err());
+    }
 }
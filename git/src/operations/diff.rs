@@ -1,7 +1,278 @@
 use crate::command::run;
+use crate::operations::hunk::{Hunk, HunkLine, HunkLineType};
+use crate::operations::status::ChangeKind;
 use anyhow::{Context, Result};
 use std::path::Path;
 
+/// The changes to one file in a diff
+#[derive(Debug, Clone)]
+pub struct FileDiff {
+    /// Path before the change; `None` for an added file
+    pub old_path: Option<String>,
+    /// Path after the change; `None` for a deleted file
+    pub new_path: Option<String>,
+    /// `Added`, `Deleted`, `Modified`, `Renamed`, `Copied` or `TypeChanged`
+    pub change_kind: ChangeKind,
+    /// File modes such as `100644`, when the diff names them
+    pub old_mode: Option<String>,
+    pub new_mode: Option<String>,
+    pub is_binary: bool,
+    /// Similarity of a rename or copy, in percent
+    pub similarity: Option<u8>,
+    pub hunks: Vec<Hunk>,
+}
+
+impl FileDiff {
+    fn new() -> Self {
+        FileDiff {
+            old_path: None,
+            new_path: None,
+            change_kind: ChangeKind::Modified,
+            old_mode: None,
+            new_mode: None,
+            is_binary: false,
+            similarity: None,
+            hunks: Vec::new(),
+        }
+    }
+
+    /// The path after the change, or before it for a deleted file
+    pub fn path(&self) -> &str {
+        self.new_path
+            .as_deref()
+            .or(self.old_path.as_deref())
+            .unwrap_or_default()
+    }
+
+    /// Whether the file mode changes, e.g. it becomes executable
+    pub fn mode_changed(&self) -> bool {
+        self.old_mode.is_some() && self.new_mode.is_some() && self.old_mode != self.new_mode
+    }
+
+    pub fn additions(&self) -> usize {
+        self.count_lines(HunkLineType::Addition)
+    }
+
+    pub fn deletions(&self) -> usize {
+        self.count_lines(HunkLineType::Deletion)
+    }
+
+    fn count_lines(&self, line_type: HunkLineType) -> usize {
+        self.hunks
+            .iter()
+            .flat_map(|hunk| &hunk.lines)
+            .filter(|line| line.line_type == line_type)
+            .count()
+    }
+}
+
+/// Undo git's quoting of unusual paths (`"a/t\303\244b"`)
+fn unquote(path: &str) -> String {
+    let Some(quoted) = path.strip_prefix('"').and_then(|p| p.strip_suffix('"')) else {
+        return path.to_string();
+    };
+    let mut bytes = Vec::new();
+    let mut chars = quoted.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            let mut buf = [0; 4];
+            bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            continue;
+        }
+        match chars.next() {
+            Some('n') => bytes.push(b'\n'),
+            Some('t') => bytes.push(b'\t'),
+            Some(digit @ '0'..='7') => {
+                let octal: String = std::iter::once(digit)
+                    .chain(chars.by_ref().take(2))
+                    .collect();
+                bytes.push(u8::from_str_radix(&octal, 8).unwrap_or(b'?'));
+            }
+            Some(other) => bytes.push(other as u8),
+            None => {}
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// A path from a `---`/`+++` line, without its `a/` or `b/` prefix; `None`
+/// for `/dev/null`
+fn header_path(value: &str) -> Option<String> {
+    let path = unquote(value.trim_end_matches('\t'));
+    match path.as_str() {
+        "/dev/null" => None,
+        _ => Some(
+            path.strip_prefix("a/")
+                .or_else(|| path.strip_prefix("b/"))
+                .unwrap_or(&path)
+                .to_string(),
+        ),
+    }
+}
+
+/// Paths of a `diff --git a/x b/y` line. Unquoted paths with spaces are
+/// ambiguous; they are split where both halves name the same file.
+fn git_header_paths(value: &str) -> (Option<String>, Option<String>) {
+    if let Some(quoted) = value.strip_prefix('"') {
+        if let Some(end) = quoted.find("\" ").map(|i| i + 2) {
+            let (old, new) = value.split_at(end);
+            return (header_path(old), header_path(new.trim_start()));
+        }
+    }
+    let half = value.len() / 2;
+    if value.len() % 2 == 1 && value.is_char_boundary(half) {
+        let (old, new) = (&value[..half], &value[half + 1..]);
+        if old.strip_prefix("a/") == new.strip_prefix("b/") {
+            return (header_path(old), header_path(new));
+        }
+    }
+    match value.split_once(" b/") {
+        Some((old, new)) => (header_path(old), Some(new.to_string())),
+        None => (None, None),
+    }
+}
+
+/// Start line and count of one side of a hunk header (`-3,4` or `+7`)
+fn parse_range(range: &str) -> (usize, usize) {
+    let range = range.trim_start_matches(['-', '+']);
+    match range.split_once(',') {
+        Some((start, count)) => (start.parse().unwrap_or(0), count.parse().unwrap_or(0)),
+        None => (range.parse().unwrap_or(0), 1),
+    }
+}
+
+/// A hunk from its `@@ -a,b +c,d @@` header; `None` for other lines,
+/// including the `@@@` headers of combined diffs
+fn parse_hunk_header(line: &str) -> Option<Hunk> {
+    let ranges = line.strip_prefix("@@ ")?;
+    let (ranges, _) = ranges.split_once(" @@")?;
+    let (old, new) = ranges.split_once(' ')?;
+    let (old_start, old_lines) = parse_range(old);
+    let (new_start, new_lines) = parse_range(new);
+    Some(Hunk {
+        header: line.to_string(),
+        lines: Vec::new(),
+        is_staged: false,
+        old_start,
+        old_lines,
+        new_start,
+        new_lines,
+        is_selected: false,
+    })
+}
+
+/// Parse `git diff`-style output into one `FileDiff` per file. Text before
+/// the first file header is skipped.
+pub fn parse_diff(output: &str) -> Vec<FileDiff> {
+    let mut files: Vec<FileDiff> = Vec::new();
+    // Lines left on the old and new side of the current hunk
+    let mut remaining: (usize, usize) = (0, 0);
+
+    for line in output.lines() {
+        let file = files.last_mut();
+        if remaining != (0, 0) {
+            if let Some(hunk) = file.and_then(|f| f.hunks.last_mut()) {
+                let line_type = match line.chars().next() {
+                    Some('+') => HunkLineType::Addition,
+                    Some('-') => HunkLineType::Deletion,
+                    Some('\\') => {
+                        if let Some(last) = hunk.lines.last_mut() {
+                            last.no_newline = true;
+                        }
+                        continue;
+                    }
+                    // Some tools strip the space of empty context lines
+                    _ => HunkLineType::Context,
+                };
+                let (old, new) = remaining;
+                remaining = match line_type {
+                    HunkLineType::Addition => (old, new.saturating_sub(1)),
+                    HunkLineType::Deletion => (old.saturating_sub(1), new),
+                    HunkLineType::Context => (old.saturating_sub(1), new.saturating_sub(1)),
+                };
+                hunk.lines.push(HunkLine {
+                    content: line.to_string(),
+                    line_type,
+                    is_selected: false,
+                    no_newline: false,
+                });
+                continue;
+            }
+        }
+
+        if let Some(paths) = line.strip_prefix("diff --git ").map(git_header_paths) {
+            let mut file = FileDiff::new();
+            (file.old_path, file.new_path) = paths;
+            files.push(file);
+            continue;
+        }
+        if let Some(path) = line
+            .strip_prefix("diff --cc ")
+            .or_else(|| line.strip_prefix("diff --combined "))
+        {
+            let mut file = FileDiff::new();
+            file.old_path = Some(unquote(path));
+            file.new_path = file.old_path.clone();
+            files.push(file);
+            continue;
+        }
+        let Some(file) = files.last_mut() else {
+            continue;
+        };
+        if line.starts_with('\\') {
+            // After the last line of a hunk
+            if let Some(last) = file.hunks.last_mut().and_then(|h| h.lines.last_mut()) {
+                last.no_newline = true;
+            }
+        } else if let Some(hunk) = parse_hunk_header(line) {
+            remaining = (hunk.old_lines, hunk.new_lines);
+            file.hunks.push(hunk);
+        } else if let Some(path) = line.strip_prefix("--- ") {
+            file.old_path = header_path(path);
+        } else if let Some(path) = line.strip_prefix("+++ ") {
+            file.new_path = header_path(path);
+        } else if let Some(mode) = line.strip_prefix("new file mode ") {
+            file.change_kind = ChangeKind::Added;
+            file.old_path = None;
+            file.new_mode = Some(mode.to_string());
+        } else if let Some(mode) = line.strip_prefix("deleted file mode ") {
+            file.change_kind = ChangeKind::Deleted;
+            file.new_path = None;
+            file.old_mode = Some(mode.to_string());
+        } else if let Some(mode) = line.strip_prefix("old mode ") {
+            file.old_mode = Some(mode.to_string());
+        } else if let Some(mode) = line.strip_prefix("new mode ") {
+            file.new_mode = Some(mode.to_string());
+            // The leading digits tell a file from a symlink or submodule
+            let old_type = file.old_mode.as_deref().and_then(|m| m.get(..2));
+            if old_type != mode.get(..2) {
+                file.change_kind = ChangeKind::TypeChanged;
+            }
+        } else if let Some(path) = line.strip_prefix("rename from ") {
+            file.change_kind = ChangeKind::Renamed;
+            file.old_path = Some(unquote(path));
+        } else if let Some(path) = line.strip_prefix("rename to ") {
+            file.new_path = Some(unquote(path));
+        } else if let Some(path) = line.strip_prefix("copy from ") {
+            file.change_kind = ChangeKind::Copied;
+            file.old_path = Some(unquote(path));
+        } else if let Some(path) = line.strip_prefix("copy to ") {
+            file.new_path = Some(unquote(path));
+        } else if let Some(percent) = line.strip_prefix("similarity index ") {
+            file.similarity = percent.trim_end_matches('%').parse().ok();
+        } else if let Some(index) = line.strip_prefix("index ") {
+            // `index abc..def 100644` names the mode when it does not change
+            if let Some((_, mode)) = index.split_once(' ') {
+                file.old_mode.get_or_insert_with(|| mode.to_string());
+                file.new_mode.get_or_insert_with(|| mode.to_string());
+            }
+        } else if line.starts_with("Binary files ") || line == "GIT binary patch" {
+            file.is_binary = true;
+        }
+    }
+    files
+}
+
 pub fn get_diff(repo_path: &Path, commit: Option<&str>, staged: bool) -> Result<String> {
     let mut args = vec!["diff"];
 
@@ -22,7 +293,10 @@ pub fn get_commit_diff(repo_path: &Path, commit_hash: &str) -> Result<String> {
         .with_context(|| format!("Failed to get parent of commit: {}", commit_hash))?;
 
     if parent_hash.is_empty() {
-        get_commit_content(commit_hash)
+        // A root commit adds everything it contains
+        let args = vec!["diff-tree", "-p", "--root", "--no-commit-id", commit_hash];
+        run(&args, Some(repo_path))
+            .with_context(|| format!("Failed to get diff of root commit {}", commit_hash))
     } else {
         let args = vec!["diff", &parent_hash, commit_hash];
         run(&args, Some(repo_path)).with_context(|| {
@@ -34,6 +308,20 @@ pub fn get_commit_diff(repo_path: &Path, commit_hash: &str) -> Result<String> {
     }
 }
 
+/// Parse a diff of the working tree, the index or a commit (see `get_diff`)
+pub fn get_file_diffs(
+    repo_path: &Path,
+    commit: Option<&str>,
+    staged: bool,
+) -> Result<Vec<FileDiff>> {
+    Ok(parse_diff(&get_diff(repo_path, commit, staged)?))
+}
+
+/// Parse what a commit changed (see `get_commit_diff`)
+pub fn get_commit_file_diffs(repo_path: &Path, commit_hash: &str) -> Result<Vec<FileDiff>> {
+    Ok(parse_diff(&get_commit_diff(repo_path, commit_hash)?))
+}
+
 fn get_parent_hash(repo_path: &Path, commit_hash: &str) -> Result<String> {
     let args = vec!["rev-list", "--parents", "-n", "1", commit_hash];
     let output = run(&args, Some(repo_path))
@@ -47,17 +335,6 @@ fn get_parent_hash(repo_path: &Path, commit_hash: &str) -> Result<String> {
     }
 }
 
-fn get_commit_content(commit_hash: &str) -> Result<String> {
-    let args = vec!["show", "--no-patch", commit_hash];
-    let output = run(&args, None)
-        .with_context(|| format!("Failed to get commit content for: {}", commit_hash))?;
-
-    Ok(format!(
-        "Initial commit: {}\n\nNo parent diff available (use 'git show' for details)",
-        output.trim()
-    ))
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -77,4 +354,75 @@ mod tests {
         // This will work if in a git repo
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_parse_diff() {
+        let output = "\
+diff --git a/old name.txt b/new name.txt
+similarity index 90%
+rename from old name.txt
+rename to new name.txt
+index 1111111..2222222 100644
+--- a/old name.txt
++++ b/new name.txt
+@@ -1,3 +1,3 @@
+ one
+--- a/not a header
++two
+ three
+\\ No newline at end of file
+diff --git a/run.sh b/run.sh
+old mode 100644
+new mode 100755
+diff --git a/logo.png b/logo.png
+new file mode 100644
+index 0000000..3333333
+Binary files /dev/null and b/logo.png differ
+diff --git a/gone.txt b/gone.txt
+deleted file mode 100644
+index 4444444..0000000
+--- a/gone.txt
++++ /dev/null
+@@ -1 +0,0 @@
+-gone
+\\ No newline at end of file
+";
+        let files = parse_diff(output);
+        assert_eq!(files.len(), 4);
+
+        let renamed = &files[0];
+        assert_eq!(renamed.change_kind, ChangeKind::Renamed);
+        assert_eq!(renamed.old_path.as_deref(), Some("old name.txt"));
+        assert_eq!(renamed.path(), "new name.txt");
+        assert_eq!(renamed.similarity, Some(90));
+        assert_eq!(renamed.new_mode.as_deref(), Some("100644"));
+        assert!(!renamed.mode_changed());
+        // A deleted line that looks like a file header stays in the hunk
+        assert_eq!(renamed.hunks[0].lines.len(), 4);
+        assert_eq!(renamed.hunks[0].lines[1].content, "--- a/not a header");
+        assert_eq!((renamed.additions(), renamed.deletions()), (1, 1));
+        assert!(renamed.hunks[0].lines[3].no_newline);
+
+        let script = &files[1];
+        assert_eq!(script.change_kind, ChangeKind::Modified);
+        assert!(script.mode_changed());
+        assert_eq!(script.new_mode.as_deref(), Some("100755"));
+        assert!(script.hunks.is_empty());
+
+        let image = &files[2];
+        assert_eq!(image.change_kind, ChangeKind::Added);
+        assert!(image.is_binary);
+        assert_eq!(image.old_path, None);
+        assert_eq!(image.path(), "logo.png");
+
+        let gone = &files[3];
+        assert_eq!(gone.change_kind, ChangeKind::Deleted);
+        assert_eq!(gone.new_path, None);
+        assert_eq!(gone.path(), "gone.txt");
+        assert_eq!(gone.deletions(), 1);
+        assert!(gone.hunks[0].lines[0].no_newline);
+
+        let quoted = parse_diff("diff --git \"a/t\\303\\244b\" \"b/t\\303\\244b\"\n");
+        assert_eq!(quoted[0].path(), "täb");
+    }
 }
@@ -1,17 +1,60 @@
 use crate::command::run;
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use std::env;
+use std::fs;
 use std::path::Path;
+use std::process::Command;
 
-pub fn open_in_editor(repo_path: &Path, file_path: &Path) -> Result<()> {
-    let editor = env::var("GIT_EDITOR")
-        .or_else(|_| env::var("EDITOR"))
-        .unwrap_or_else(|_| "vi".to_string()); // Default to vi if no editor is set
+/// The editor git would use: `GIT_EDITOR`, `core.editor`, `VISUAL`, then `EDITOR`
+fn editor_command(repo_path: &Path) -> String {
+    env::var("GIT_EDITOR")
+        .ok()
+        .or_else(|| {
+            run(&["config", "core.editor"], Some(repo_path))
+                .ok()
+                .map(|s| s.trim().to_string())
+        })
+        .or_else(|| env::var("VISUAL").ok())
+        .or_else(|| env::var("EDITOR").ok())
+        .filter(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| "vi".to_string()) // Default to vi if no editor is set
+}
 
-    let file_path_str = file_path.to_str().context("Invalid file path")?;
+/// Open `file_path` in the user's editor and wait for it to exit. The editor
+/// inherits the terminal, and the command goes through the shell so values
+/// like `code --wait` work.
+pub fn open_in_editor(repo_path: &Path, file_path: &Path) -> Result<()> {
+    let editor = editor_command(repo_path);
 
-    run(&[&editor, file_path_str], Some(repo_path))
-        .context(format!("Failed to open file in editor: {}", editor))?;
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(format!("{} \"$@\"", editor))
+        .arg(&editor)
+        .arg(file_path)
+        .current_dir(repo_path)
+        .status()
+        .with_context(|| format!("Failed to open file in editor: {}", editor))?;
+    if !status.success() {
+        bail!("Editor '{}' exited with {}", editor, status);
+    }
 
     Ok(())
 }
+
+/// Let the user edit `text` in their editor, like git does for commit
+/// messages. Lines starting with `#` are dropped from the result.
+pub fn edit_text(repo_path: &Path, file_name: &str, text: &str) -> Result<String> {
+    let path = run(&["rev-parse", "--git-path", file_name], Some(repo_path))?;
+    let path = repo_path.join(path.trim());
+    fs::write(&path, text)?;
+
+    let result = open_in_editor(repo_path, &path).and_then(|_| Ok(fs::read_to_string(&path)?));
+    let _ = fs::remove_file(&path);
+
+    let edited = result?;
+    let edited: Vec<&str> = edited
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .collect();
+    Ok(edited.join("\n").trim().to_string())
+}
@@ -0,0 +1,199 @@
+use crate::command::run;
+use crate::models::Commit;
+use crate::operations::cat::get_file_at_revision;
+use crate::operations::log::{get_commits_matching, LogQuery};
+use anyhow::{bail, Context, Result};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// What a commit did to the followed file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileChange {
+    Added,
+    Modified,
+    Deleted,
+    Renamed { from: String },
+    Copied { from: String },
+}
+
+impl FileChange {
+    /// The file's path before the commit, when it had another one
+    pub fn source(&self) -> Option<&str> {
+        match self {
+            FileChange::Renamed { from } | FileChange::Copied { from } => Some(from),
+            _ => None,
+        }
+    }
+
+    /// One-letter status, as in `git log --name-status`
+    pub fn letter(&self) -> char {
+        match self {
+            FileChange::Added => 'A',
+            FileChange::Modified => 'M',
+            FileChange::Deleted => 'D',
+            FileChange::Renamed { .. } => 'R',
+            FileChange::Copied { .. } => 'C',
+        }
+    }
+}
+
+/// The file as one commit left it
+#[derive(Debug, Clone)]
+pub struct FileVersion {
+    pub commit: Commit,
+    /// Path of the file in this commit
+    pub path: String,
+    pub change: FileChange,
+}
+
+/// `(path, change)` of each commit in `git log --name-status -z` output of
+/// `%x1e%H` records
+fn parse_name_status(output: &str) -> HashMap<String, (String, FileChange)> {
+    let mut changes = HashMap::new();
+    for record in output.split('\x1e') {
+        let mut fields = record
+            .split('\0')
+            .map(|field| field.trim_matches('\n'))
+            .filter(|field| !field.is_empty());
+        let (Some(hash), Some(status), Some(path)) = (fields.next(), fields.next(), fields.next())
+        else {
+            continue;
+        };
+        let (path, change) = match status.chars().next() {
+            Some('A') => (path, FileChange::Added),
+            Some('D') => (path, FileChange::Deleted),
+            Some(letter @ ('R' | 'C')) => {
+                let from = path.to_string();
+                let Some(to) = fields.next() else {
+                    continue;
+                };
+                let change = match letter {
+                    'R' => FileChange::Renamed { from },
+                    _ => FileChange::Copied { from },
+                };
+                (to, change)
+            }
+            _ => (path, FileChange::Modified),
+        };
+        changes.insert(hash.to_string(), (path.to_string(), change));
+    }
+    changes
+}
+
+/// Commits of `query` that touched its single path, newest first, following
+/// the file across renames
+pub fn get_file_history(repo_path: &Path, query: &LogQuery) -> Result<Vec<FileVersion>> {
+    let [ref path] = query.paths[..] else {
+        bail!("File history needs exactly one path");
+    };
+    let mut query = query.clone();
+    query.follow = true;
+    let commits = get_commits_matching(repo_path, &query)?;
+
+    let mut args = vec![
+        "log".to_string(),
+        "--format=%x1e%H".to_string(),
+        "--name-status".to_string(),
+        "-z".to_string(),
+    ];
+    args.extend(query.args());
+    let arg_refs: Vec<&str> = args.iter().map(String::as_str).collect();
+    let output = run(&arg_refs, Some(repo_path))
+        .with_context(|| format!("Failed to get the history of '{}'", path))?;
+    let mut changes = parse_name_status(&output);
+
+    Ok(commits
+        .into_iter()
+        .map(|commit| {
+            // Merges that only take one side's version list no files
+            let (path, change) = changes
+                .remove(&commit.hash)
+                .unwrap_or_else(|| (path.clone(), FileChange::Modified));
+            FileVersion {
+                commit,
+                path,
+                change,
+            }
+        })
+        .collect())
+}
+
+/// What `version`'s commit changed in the file, as a patch (against the
+/// first parent for merges)
+pub fn get_file_version_diff(repo_path: &Path, version: &FileVersion) -> Result<String> {
+    let mut args = vec![
+        "show",
+        "--format=",
+        "-M",
+        "--diff-merges=first-parent",
+        &version.commit.hash,
+        "--",
+        &version.path,
+    ];
+    args.extend(version.change.source());
+    run(&args, Some(repo_path)).with_context(|| {
+        format!(
+            "Failed to diff '{}' in {}",
+            version.path, version.commit.short_hash
+        )
+    })
+}
+
+/// The file's full content at `version`; empty once deleted
+pub fn get_file_version_content(repo_path: &Path, version: &FileVersion) -> Result<String> {
+    if version.change == FileChange::Deleted {
+        return Ok(String::new());
+    }
+    get_file_at_revision(repo_path, &version.commit.hash, &version.path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{create_test_repo, git};
+    use std::fs;
+
+    #[test]
+    fn test_file_history_follows_renames() {
+        let dir = create_test_repo();
+        let path = dir.path();
+        fs::write(path.join("old.txt"), "a\nb\nc\nd\ne\n").unwrap();
+        fs::write(path.join("other.txt"), "other\n").unwrap();
+        git(path, &["add", "."]);
+        git(path, &["commit", "-m", "Add old"]);
+        fs::write(path.join("old.txt"), "a\nb\nc\nd\nE\n").unwrap();
+        git(path, &["commit", "-am", "Edit old"]);
+        git(path, &["mv", "old.txt", "new.txt"]);
+        git(path, &["commit", "-m", "Rename"]);
+        fs::write(path.join("other.txt"), "changed\n").unwrap();
+        git(path, &["commit", "-am", "Unrelated"]);
+        fs::write(path.join("new.txt"), "A\nb\nc\nd\nE\n").unwrap();
+        git(path, &["commit", "-am", "Edit new"]);
+
+        let query = LogQuery::new().revision("HEAD").path("new.txt");
+        let history = get_file_history(path, &query).unwrap();
+        let summaries: Vec<&str> = history.iter().map(|v| v.commit.summary.as_str()).collect();
+        assert_eq!(summaries, ["Edit new", "Rename", "Edit old", "Add old"]);
+        assert_eq!(history[0].change, FileChange::Modified);
+        assert_eq!(
+            history[1].change,
+            FileChange::Renamed {
+                from: "old.txt".to_string()
+            }
+        );
+        assert_eq!(history[2].path, "old.txt");
+        assert_eq!(history[3].change, FileChange::Added);
+
+        let diff = get_file_version_diff(path, &history[1]).unwrap();
+        assert!(diff.contains("rename from old.txt"));
+        let diff = get_file_version_diff(path, &history[2]).unwrap();
+        assert!(diff.contains("-e\n+E"));
+        assert!(!diff.contains("other"));
+
+        assert_eq!(
+            get_file_version_content(path, &history[3]).unwrap(),
+            "a\nb\nc\nd\ne\n"
+        );
+        assert!(get_file_history(path, &LogQuery::new()).is_err());
+    }
+}
@@ -0,0 +1,739 @@
+use crate::command::{run, run_raw, run_with_env};
+use crate::error::GitError;
+use crate::models::abbreviate_hash;
+use crate::operations::{rewrite, stack};
+use anyhow::{bail, Context, Result};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Path of the prepared todo, for the sequence editor to copy
+const TODO_ENV: &str = "OPENISL_HISTORY_EDIT_TODO";
+
+/// What to do with one commit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RebaseAction {
+    /// Keep the commit (with a new message when one is given)
+    Pick,
+    /// Keep the commit with a new message
+    Reword,
+    /// Fold into the previous commit, joining both messages unless a message
+    /// is given
+    Squash,
+    /// Fold into the previous commit, keeping its message unless a message is
+    /// given
+    Fixup,
+    /// Leave the commit out
+    Drop,
+    /// Keep the commit, then stop so it can be amended
+    Edit,
+}
+
+/// One entry of a history edit. Entries fill the positions of the commits
+/// they name in plan order, so listing commits in a different order moves
+/// them; commits in the edited range that no entry names are kept in place.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RebaseStep {
+    pub commit: String,
+    pub action: RebaseAction,
+    pub message: Option<String>,
+}
+
+/// Position of a rebase that is in progress
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RebaseProgress {
+    /// Commits already applied, including the one it stopped at
+    pub done: usize,
+    pub total: usize,
+    /// Commit the rebase stopped at
+    pub current: Option<String>,
+}
+
+/// Why a history edit stopped before finishing
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StopReason {
+    Conflict {
+        paths: Vec<String>,
+    },
+    /// An `Edit` step was reached
+    Edit,
+}
+
+/// Result of `edit_history`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HistoryEditOutcome {
+    /// Every step was applied; `commits` is the number of commits rewritten
+    Completed { commits: usize },
+    /// The rebase is still in progress, waiting for `git rebase --continue`
+    Stopped {
+        progress: RebaseProgress,
+        reason: StopReason,
+    },
+}
+
+impl RebaseAction {
+    fn from_word(word: &str) -> Option<Self> {
+        Some(match word {
+            "p" | "pick" => RebaseAction::Pick,
+            "r" | "reword" => RebaseAction::Reword,
+            "s" | "squash" => RebaseAction::Squash,
+            "f" | "fixup" => RebaseAction::Fixup,
+            "d" | "drop" => RebaseAction::Drop,
+            "e" | "edit" => RebaseAction::Edit,
+            _ => return None,
+        })
+    }
+}
+
+impl RebaseStep {
+    pub fn new(commit: impl Into<String>, action: RebaseAction) -> Self {
+        Self {
+            commit: commit.into(),
+            action,
+            message: None,
+        }
+    }
+
+    pub fn with_message(mut self, message: impl Into<String>) -> Self {
+        self.message = Some(message.into());
+        self
+    }
+}
+
+impl fmt::Display for RebaseProgress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "step {} of {}", self.done, self.total)?;
+        if let Some(ref commit) = self.current {
+            write!(f, " ({})", abbreviate_hash(commit))?;
+        }
+        Ok(())
+    }
+}
+
+impl HistoryEditOutcome {
+    /// `Ok` when the edit finished or stopped at an `Edit` step; an error
+    /// explaining how to carry on when it stopped at a conflict
+    pub fn into_result(self) -> Result<()> {
+        match self {
+            HistoryEditOutcome::Stopped {
+                progress,
+                reason: StopReason::Conflict { paths },
+            } => Err(
+                anyhow::Error::new(GitError::MergeConflict { paths }).context(format!(
+                    "History edit stopped at {}; resolve the conflicts and run \
+                 `git rebase --continue`, or `git rebase --abort`",
+                    progress
+                )),
+            ),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Rewrite history according to `steps`, starting from the parent of the
+/// oldest commit they name and replaying everything up to `HEAD`. With
+/// `onto`, the rewritten commits are moved on top of that revision.
+pub fn edit_history(
+    repo_path: &Path,
+    onto: Option<&str>,
+    steps: &[RebaseStep],
+) -> Result<HistoryEditOutcome> {
+    if steps.is_empty() {
+        bail!("Nothing to do: the history edit has no steps");
+    }
+    if rebase_progress(repo_path)?.is_some() {
+        return Err(GitError::RebaseInProgress.into());
+    }
+
+    let mut resolved = Vec::with_capacity(steps.len());
+    for step in steps {
+        let hash = resolve_commit(repo_path, &step.commit)?;
+        let is_ancestor = run_raw(
+            &["merge-base", "--is-ancestor", &hash, "HEAD"],
+            Some(repo_path),
+        )?;
+        if !is_ancestor.success() {
+            bail!("{} is not an ancestor of HEAD", step.commit);
+        }
+        resolved.push(RebaseStep {
+            commit: hash,
+            ..step.clone()
+        });
+    }
+
+    // The oldest named commit is the one with the most commits above it
+    let mut oldest = &resolved[0].commit;
+    let mut depth = 0;
+    for step in &resolved {
+        let count = run(
+            &["rev-list", "--count", &format!("{}..HEAD", step.commit)],
+            Some(repo_path),
+        )?;
+        let count: usize = count.trim().parse().unwrap_or(0);
+        if count >= depth {
+            depth = count;
+            oldest = &step.commit;
+        }
+    }
+    let base = run_raw(
+        &["rev-parse", "--verify", "--quiet", &format!("{}^", oldest)],
+        Some(repo_path),
+    )?;
+    let base = base
+        .success()
+        .then(|| String::from_utf8_lossy(&base.stdout).trim().to_string());
+
+    let range = match base {
+        Some(ref base) => format!("{}..HEAD", base),
+        None => "HEAD".to_string(),
+    };
+    let output = run(
+        &["rev-list", "--reverse", "--parents", &range],
+        Some(repo_path),
+    )?;
+    let mut commits = Vec::new();
+    for line in output.lines() {
+        let hashes: Vec<&str> = line.split_whitespace().collect();
+        if hashes.len() > 2 {
+            bail!(
+                "Cannot edit history across merge commit {}",
+                abbreviate_hash(hashes[0])
+            );
+        }
+        commits.push(hashes[0].to_string());
+    }
+
+    let todo = plan_todo(&commits, &resolved)?;
+
+    // Without stops, rewrite in the object database first; on a conflict fall
+    // through to a real rebase, which stops there for the user
+    if todo.iter().all(|step| step.action != RebaseAction::Edit) {
+        let new_base = match onto {
+            Some(onto) => Some(resolve_commit(repo_path, onto)?),
+            None => base.clone(),
+        };
+        if let Some(replayed) = rewrite::replay(repo_path, new_base.as_deref(), &todo)? {
+            let Some(tip) = replayed.tip else {
+                bail!("Cannot drop every commit of the branch");
+            };
+            rewrite::move_head(repo_path, &tip, "edit history")?;
+            stack::record_successors(repo_path, &replayed.rewritten)?;
+            return Ok(HistoryEditOutcome::Completed {
+                commits: replayed.commits,
+            });
+        }
+    }
+
+    let state_dir = state_dir(repo_path)?;
+    if state_dir.exists() {
+        fs::remove_dir_all(&state_dir)?;
+    }
+    fs::create_dir_all(&state_dir)?;
+    let (todo_text, rewritten) = render_todo(repo_path, &todo)?;
+    let todo_path = state_dir.join("todo");
+    fs::write(&todo_path, todo_text)?;
+
+    let todo_path = todo_path.to_string_lossy();
+    let env = HashMap::from([
+        // git appends the todo it wrote to the command
+        ("GIT_SEQUENCE_EDITOR", "cp \"$OPENISL_HISTORY_EDIT_TODO\""),
+        (TODO_ENV, todo_path.as_ref()),
+        ("GIT_EDITOR", "true"),
+    ]);
+    let mut args = vec!["rebase", "--interactive"];
+    if let Some(onto) = onto {
+        args.extend(["--onto", onto]);
+    }
+    match base {
+        Some(ref base) => args.push(base),
+        None => args.push("--root"),
+    }
+    let result = run_with_env(&args, Some(repo_path), &env);
+    // git has read the todo by now, even when it stopped
+    let _ = fs::remove_dir_all(&state_dir);
+
+    if let Some(progress) = rebase_progress(repo_path)? {
+        let paths = conflicted_paths(repo_path)?;
+        let reason = if paths.is_empty() {
+            StopReason::Edit
+        } else {
+            StopReason::Conflict { paths }
+        };
+        return Ok(HistoryEditOutcome::Stopped { progress, reason });
+    }
+
+    result.context("Failed to edit history")?;
+    Ok(HistoryEditOutcome::Completed { commits: rewritten })
+}
+
+/// Progress of the interactive rebase in progress, if any
+pub fn rebase_progress(repo_path: &Path) -> Result<Option<RebaseProgress>> {
+    let dir = git_path(repo_path, "rebase-merge")?;
+    if !dir.is_dir() {
+        return Ok(None);
+    }
+
+    let count = |name: &str| -> (usize, Option<String>) {
+        let text = fs::read_to_string(dir.join(name)).unwrap_or_default();
+        let mut last = None;
+        let mut count = 0;
+        for line in text.lines() {
+            let mut words = line.split_whitespace();
+            let command = words.next().and_then(RebaseAction::from_word);
+            // `fixup -C <commit>` carries a flag before the hash
+            let commit = words.find(|w| !w.starts_with('-'));
+            if command.is_some() {
+                count += 1;
+                last = commit.map(str::to_string);
+            }
+        }
+        (count, last)
+    };
+    let (done, last_done) = count("done");
+    let (remaining, _) = count("git-rebase-todo");
+
+    let current = fs::read_to_string(dir.join("stopped-sha"))
+        .ok()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .or(last_done);
+
+    Ok(Some(RebaseProgress {
+        done,
+        total: done + remaining,
+        current,
+    }))
+}
+
+/// A todo template for `commits` (oldest first), in the format `parse_todo`
+/// reads back
+pub fn format_todo(commits: &[(String, String)]) -> String {
+    let mut todo = String::new();
+    for (hash, summary) in commits {
+        todo.push_str(&format!("pick {} {}\n", abbreviate_hash(hash), summary));
+    }
+    todo.push_str(
+        "\n# Commands:\n\
+         # p, pick <commit> = use commit\n\
+         # r, reword <commit> = use commit, but edit the commit message\n\
+         # e, edit <commit> = use commit, but stop for amending\n\
+         # s, squash <commit> = meld into previous commit, joining messages\n\
+         # f, fixup <commit> = meld into previous commit, keeping its message\n\
+         # d, drop <commit> = remove commit\n\
+         #\n\
+         # Lines can be reordered; they run from top to bottom.\n\
+         # Removing a line drops the commit. Removing everything aborts.\n",
+    );
+    todo
+}
+
+/// Parse an edited todo back into steps. Commits missing from the todo are
+/// returned as `Drop` steps, like `git rebase -i` does.
+pub fn parse_todo(todo: &str, commits: &[String]) -> Result<Vec<RebaseStep>> {
+    let mut steps: Vec<RebaseStep> = Vec::new();
+    for line in todo.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut words = line.split_whitespace();
+        let word = words.next().unwrap_or_default();
+        let action = RebaseAction::from_word(word)
+            .ok_or_else(|| GitError::ParseError(format!("todo command '{}'", word)))?;
+        let commit = words
+            .next()
+            .ok_or_else(|| GitError::ParseError(format!("todo line '{}'", line)))?;
+        steps.push(RebaseStep::new(commit, action));
+    }
+    if steps.is_empty() {
+        bail!("Nothing to do");
+    }
+
+    for hash in commits {
+        if !steps.iter().any(|step| hash.starts_with(&step.commit)) {
+            steps.push(RebaseStep::new(hash.clone(), RebaseAction::Drop));
+        }
+    }
+    Ok(steps)
+}
+
+pub(crate) fn resolve_commit(repo_path: &Path, commit: &str) -> Result<String> {
+    let spec = format!("{}^{{commit}}", commit);
+    let hash = run(
+        &["rev-parse", "--verify", "--quiet", &spec],
+        Some(repo_path),
+    )
+    .map_err(|_| GitError::UnknownRevision(commit.to_string()))?;
+    Ok(hash.trim().to_string())
+}
+
+/// Fill the positions of the named commits with the steps, in step order
+fn plan_todo(commits: &[String], steps: &[RebaseStep]) -> Result<Vec<RebaseStep>> {
+    let mut planned = steps.iter();
+    let todo: Vec<RebaseStep> = commits
+        .iter()
+        .map(|hash| {
+            if steps.iter().any(|step| &step.commit == hash) {
+                planned.next().cloned().unwrap()
+            } else {
+                RebaseStep::new(hash.clone(), RebaseAction::Pick)
+            }
+        })
+        .collect();
+
+    for (i, step) in steps.iter().enumerate() {
+        if steps[..i].iter().any(|other| other.commit == step.commit) {
+            bail!("{} appears more than once in the plan", step.commit);
+        }
+    }
+    let first_kept = todo.iter().find(|s| s.action != RebaseAction::Drop);
+    if let Some(step) = first_kept {
+        if matches!(step.action, RebaseAction::Squash | RebaseAction::Fixup) {
+            bail!(
+                "Cannot fold {} into an earlier commit: it would be the first one",
+                abbreviate_hash(&step.commit)
+            );
+        }
+    }
+    if let Some(step) = todo
+        .iter()
+        .find(|s| s.action == RebaseAction::Reword && s.message.is_none())
+    {
+        bail!("No new message to reword {}", step.commit);
+    }
+    Ok(todo)
+}
+
+/// The git todo for `todo`, and the number of commits it keeps. A commit
+/// that gets a new message is picked as a copy carrying that message, so git
+/// never has to ask for one.
+fn render_todo(repo_path: &Path, todo: &[RebaseStep]) -> Result<(String, usize)> {
+    let message_of = |hash: &str| -> Result<String> {
+        Ok(run(&["log", "-1", "--format=%B", hash], Some(repo_path))?
+            .trim_end()
+            .to_string())
+    };
+
+    let mut lines: Vec<(&str, String)> = Vec::new();
+    let mut kept = 0;
+    let mut group: Option<Group> = None;
+    let mut stop_after = false;
+
+    let flush = |lines: &mut Vec<(&str, String)>,
+                 group: &mut Option<Group>,
+                 stop_after: &mut bool|
+     -> Result<()> {
+        if let Some(group) = group.take().filter(|g| g.changed) {
+            let commit = &lines[group.pick].1;
+            let parent = run_raw(
+                &["rev-parse", "--verify", "--quiet", &format!("{}^", commit)],
+                Some(repo_path),
+            )?;
+            let parent = parent
+                .success()
+                .then(|| String::from_utf8_lossy(&parent.stdout).trim().to_string());
+            let tree = run(
+                &["rev-parse", &format!("{}^{{tree}}", commit)],
+                Some(repo_path),
+            )?;
+            // Same parent and tree, so the pick applies the same change
+            lines[group.pick].1 = rewrite::recommit(
+                repo_path,
+                commit,
+                parent.as_deref(),
+                tree.trim(),
+                Some(&group.message),
+            )?;
+        }
+        if std::mem::take(stop_after) {
+            lines.push(("break", String::new()));
+        }
+        Ok(())
+    };
+
+    for step in todo {
+        match step.action {
+            RebaseAction::Drop => lines.push(("drop", step.commit.clone())),
+            RebaseAction::Pick | RebaseAction::Reword | RebaseAction::Edit => {
+                flush(&mut lines, &mut group, &mut stop_after)?;
+                kept += 1;
+                group = Some(Group {
+                    pick: lines.len(),
+                    changed: step.message.is_some(),
+                    message: step.message.clone().unwrap_or_default(),
+                });
+                lines.push(("pick", step.commit.clone()));
+                stop_after = step.action == RebaseAction::Edit;
+            }
+            RebaseAction::Squash | RebaseAction::Fixup => {
+                let Some(ref mut group) = group else {
+                    bail!("Nothing to fold {} into", step.commit);
+                };
+                lines.push(("fixup", step.commit.clone()));
+                if !group.changed {
+                    // Only needed once messages are combined
+                    group.message = message_of(&lines[group.pick].1)?;
+                }
+                match (&step.message, step.action) {
+                    (Some(new), _) => {
+                        group.message = new.clone();
+                        group.changed = true;
+                    }
+                    (None, RebaseAction::Squash) => {
+                        group.message.push_str("\n\n");
+                        group.message.push_str(&message_of(&step.commit)?);
+                        group.changed = true;
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+    flush(&mut lines, &mut group, &mut stop_after)?;
+
+    let mut text = String::new();
+    for (command, commit) in lines {
+        text.push_str(command);
+        if !commit.is_empty() {
+            text.push(' ');
+            text.push_str(&commit);
+        }
+        text.push('\n');
+    }
+    Ok((text, kept))
+}
+
+/// A commit of the todo and the commits folded into it
+struct Group {
+    /// Index of its `pick` line
+    pick: usize,
+    message: String,
+    /// Whether `message` differs from the one git would produce
+    changed: bool,
+}
+
+fn git_path(repo_path: &Path, name: &str) -> Result<PathBuf> {
+    let path = run(&["rev-parse", "--git-path", name], Some(repo_path))?;
+    Ok(repo_path.join(path.trim()))
+}
+
+/// Where the todo lives until git has read it
+fn state_dir(repo_path: &Path) -> Result<PathBuf> {
+    let git_dir = run(&["rev-parse", "--absolute-git-dir"], Some(repo_path))?;
+    Ok(PathBuf::from(git_dir.trim()).join("openisl-history-edit"))
+}
+
+fn conflicted_paths(repo_path: &Path) -> Result<Vec<String>> {
+    let output = run(
+        &["diff", "--name-only", "--diff-filter=U", "-z"],
+        Some(repo_path),
+    )?;
+    Ok(output
+        .split('\0')
+        .filter(|p| !p.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{commit_file, create_test_repo, git};
+
+    fn messages(path: &Path) -> Vec<String> {
+        run(&["log", "--format=%B%x00"], Some(path))
+            .unwrap()
+            .split('\0')
+            .map(|m| m.trim().to_string())
+            .filter(|m| !m.is_empty())
+            .collect()
+    }
+
+    #[test]
+    fn test_reword_squash_drop_and_reorder() {
+        let dir = create_test_repo();
+        let path = dir.path();
+        let a = commit_file(path, "a.txt", "a\n", "A");
+        let b = commit_file(path, "b.txt", "b\n", "B");
+        let c = commit_file(path, "c.txt", "c\n", "C");
+        let d = commit_file(path, "d.txt", "d\n", "D");
+
+        let steps = [
+            RebaseStep::new(&c, RebaseAction::Reword).with_message("C'\n\nwith body"),
+            RebaseStep::new(&a, RebaseAction::Pick),
+            RebaseStep::new(&d, RebaseAction::Squash),
+            RebaseStep::new(&b, RebaseAction::Drop),
+        ];
+        let outcome = edit_history(path, None, &steps).unwrap();
+        assert_eq!(outcome, HistoryEditOutcome::Completed { commits: 2 });
+
+        // C' takes A's slot, A takes C's slot and absorbs D
+        assert_eq!(messages(path), vec!["A\n\nD", "C'\n\nwith body"]);
+        assert!(!path.join("b.txt").exists());
+        assert!(path.join("d.txt").exists());
+        assert!(!state_dir(path).unwrap().exists());
+    }
+
+    #[test]
+    fn test_stops_on_conflict_and_edit() {
+        let dir = create_test_repo();
+        let path = dir.path();
+        commit_file(path, "a.txt", "base\n", "Base");
+        let one = commit_file(path, "a.txt", "one\n", "One");
+        let two = commit_file(path, "a.txt", "two\n", "Two");
+
+        // Dropping One makes Two conflict with Base
+        let outcome =
+            edit_history(path, None, &[RebaseStep::new(&one, RebaseAction::Drop)]).unwrap();
+        match outcome {
+            HistoryEditOutcome::Stopped {
+                ref progress,
+                reason: StopReason::Conflict { ref paths },
+            } => {
+                assert_eq!(paths, &vec!["a.txt".to_string()]);
+                assert_eq!((progress.done, progress.total), (2, 2));
+                assert_eq!(progress.current.as_deref(), Some(two.as_str()));
+            }
+            other => panic!("expected a conflict, got {:?}", other),
+        }
+        let err = outcome.into_result().unwrap_err();
+        assert!(format!("{:#}", err).contains("step 2 of 2"));
+        assert!(matches!(
+            edit_history(path, None, &[RebaseStep::new(&two, RebaseAction::Drop)])
+                .unwrap_err()
+                .downcast_ref::<GitError>(),
+            Some(GitError::RebaseInProgress)
+        ));
+        git(path, &["rebase", "--abort"]);
+
+        let outcome = edit_history(
+            path,
+            None,
+            &[RebaseStep::new(&one, RebaseAction::Edit).with_message("One, edited")],
+        )
+        .unwrap();
+        assert!(matches!(
+            outcome,
+            HistoryEditOutcome::Stopped {
+                reason: StopReason::Edit,
+                ..
+            }
+        ));
+        let head = run(&["log", "-1", "--format=%s"], Some(path)).unwrap();
+        assert_eq!(head.trim(), "One, edited");
+        git(path, &["rebase", "--continue"]);
+        assert_eq!(messages(path), vec!["Two", "One, edited", "Base"]);
+    }
+
+    #[test]
+    fn test_reword_survives_a_resolved_conflict() {
+        let dir = create_test_repo();
+        let path = dir.path();
+        commit_file(path, "a.txt", "base\n", "Base");
+        let one = commit_file(path, "a.txt", "one\n", "One");
+        let two = commit_file(path, "a.txt", "two\n", "Two");
+
+        let steps = [
+            RebaseStep::new(&one, RebaseAction::Drop),
+            RebaseStep::new(&two, RebaseAction::Reword).with_message("Two, reworded\n\nwith body"),
+        ];
+        let outcome = edit_history(path, None, &steps).unwrap();
+        assert!(matches!(
+            outcome,
+            HistoryEditOutcome::Stopped {
+                reason: StopReason::Conflict { .. },
+                ..
+            }
+        ));
+
+        // The user resolves the conflict differently from either side
+        fs::write(path.join("a.txt"), "resolved\n").unwrap();
+        git(path, &["add", "a.txt"]);
+        crate::operations::continue_operation(path).unwrap();
+
+        assert_eq!(messages(path), vec!["Two, reworded\n\nwith body", "Base"]);
+        assert_eq!(git(path, &["show", "HEAD:a.txt"]), "resolved");
+        assert!(!state_dir(path).unwrap().exists());
+    }
+
+    #[test]
+    fn test_fallback_squashes_and_rewords_without_an_editor() {
+        let dir = create_test_repo();
+        let path = dir.path();
+        let a = commit_file(path, "a.txt", "a\n", "A");
+        let b = commit_file(path, "b.txt", "b\n", "B");
+        let c = commit_file(path, "c.txt", "c\n", "C");
+
+        let steps = [
+            RebaseStep::new(&a, RebaseAction::Edit),
+            RebaseStep::new(&b, RebaseAction::Squash),
+            RebaseStep::new(&c, RebaseAction::Pick).with_message("C'"),
+        ];
+        let (todo, kept) = render_todo(path, &steps).unwrap();
+        assert_eq!(kept, 2);
+        let lines: Vec<(&str, &str)> = todo
+            .lines()
+            .map(|line| line.split_once(' ').unwrap_or((line, "")))
+            .collect();
+        let commands: Vec<&str> = lines.iter().map(|(command, _)| *command).collect();
+        assert_eq!(commands, ["pick", "fixup", "break", "pick"]);
+        assert_eq!(lines[1].1, b);
+        // Commits with new messages are picked as copies carrying them
+        let (new_a, new_c) = (lines[0].1, lines[3].1);
+        assert_ne!(new_a, a);
+        assert_eq!(git(path, &["log", "-1", "--format=%B", new_a]), "A\n\nB");
+        assert_eq!(git(path, &["log", "-1", "--format=%B", new_c]), "C'");
+        assert_eq!(
+            git(
+                path,
+                &[
+                    "rev-parse",
+                    &format!("{}^", new_c),
+                    &format!("{}^{{tree}}", new_c)
+                ]
+            ),
+            git(
+                path,
+                &["rev-parse", &format!("{}^", c), &format!("{}^{{tree}}", c)]
+            )
+        );
+
+        let outcome = edit_history(path, None, &steps).unwrap();
+        assert!(matches!(
+            outcome,
+            HistoryEditOutcome::Stopped {
+                reason: StopReason::Edit,
+                ..
+            }
+        ));
+        assert_eq!(messages(path), vec!["A\n\nB"]);
+        crate::operations::continue_operation(path).unwrap();
+        assert_eq!(messages(path), vec!["C'", "A\n\nB"]);
+    }
+
+    #[test]
+    fn test_todo_round_trip_and_invalid_plans() {
+        let commits = vec![
+            (format!("1111111{}", "a".repeat(33)), "First".to_string()),
+            (format!("2222222{}", "b".repeat(33)), "Second".to_string()),
+        ];
+        let todo = format_todo(&commits);
+        assert!(todo.starts_with("pick 1111111 First\npick 2222222 Second\n"));
+
+        let edited = "f 2222222 Second\npick 1111111 First\n# comment\n";
+        let hashes: Vec<String> = commits.iter().map(|(h, _)| h.clone()).collect();
+        let steps = parse_todo(edited, &hashes).unwrap();
+        assert_eq!(steps[0], RebaseStep::new("2222222", RebaseAction::Fixup));
+        assert_eq!(steps[1].action, RebaseAction::Pick);
+
+        let steps = parse_todo("pick 1111111\n", &hashes).unwrap();
+        assert_eq!(steps[1], RebaseStep::new(&hashes[1], RebaseAction::Drop));
+        assert!(parse_todo("# nothing\n", &hashes).is_err());
+        assert!(parse_todo("frobnicate 1111111\n", &hashes).is_err());
+
+        let plan = [RebaseStep::new("x", RebaseAction::Fixup)];
+        assert!(plan_todo(&["x".to_string()], &plan).is_err());
+        let plan = [RebaseStep::new("x", RebaseAction::Reword)];
+        assert!(plan_todo(&["x".to_string()], &plan).is_err());
+    }
+}
@@ -1,4 +1,5 @@
 use crate::command::{run, run_with_stdin};
+use crate::operations::diff::parse_diff;
 use anyhow::{Context, Result};
 use std::path::Path;
 
@@ -14,6 +15,8 @@ pub struct HunkLine {
     pub content: String,
     pub line_type: HunkLineType,
     pub is_selected: bool, // For TUI interaction (visual staging)
+    /// The line ends its side of the file without a newline
+    pub no_newline: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -41,95 +44,73 @@ pub fn get_file_diff_hunks(repo_path: &Path, file_path: &Path, staged: bool) ->
     args.push(file_path.to_str().context("Invalid file path")?);
 
     let output = run(&args, Some(repo_path)).context("Failed to get file diff hunks")?;
+    Ok(parse_hunks(&output, staged))
+}
 
-    let mut hunks = Vec::new();
-    let mut current_hunk: Option<Hunk> = None;
-
-    for line in output.lines() {
-        if line.starts_with("diff --git")
-            || line.starts_with("--- a/")
-            || line.starts_with("+++ b/")
-        {
-            // New file diff or file headers, finalize current hunk if exists
-            if let Some(h) = current_hunk.take() {
-                hunks.push(h);
-            }
-            continue;
-        }
-
-        if line.starts_with("@@") {
-            // Hunk header
-            if let Some(h) = current_hunk.take() {
-                hunks.push(h);
-            }
+/// Hunks of a file's diff between two trees (or commits)
+pub fn get_tree_diff_hunks(
+    repo_path: &Path,
+    from: &str,
+    to: &str,
+    file_path: &Path,
+) -> Result<Vec<Hunk>> {
+    tree_diff_hunks(repo_path, from, to, file_path, 3)
+}
 
-            let mut old_start = 0;
-            let mut old_lines = 0;
-            let mut new_start = 0;
-            let mut new_lines = 0;
-
-            if let Some(parts) = line.split("@@").nth(1) {
-                let range_parts: Vec<&str> = parts.trim().split(' ').collect();
-                if range_parts.len() >= 2 {
-                    let old_range = range_parts[0];
-                    let new_range = range_parts[1];
-
-                    if let Some(start_str) = old_range.split(',').next() {
-                        old_start = start_str.trim_start_matches('-').parse().unwrap_or(0);
-                    }
-                    if let Some(count_str) = old_range.split(',').nth(1) {
-                        old_lines = count_str.parse().unwrap_or(0);
-                    } else {
-                        old_lines = 1;
-                    }
-
-                    if let Some(start_str) = new_range.split(',').next() {
-                        new_start = start_str.trim_start_matches('+').parse().unwrap_or(0);
-                    }
-                    if let Some(count_str) = new_range.split(',').nth(1) {
-                        new_lines = count_str.parse().unwrap_or(0);
-                    } else {
-                        new_lines = 1;
-                    }
-                }
-            }
+/// `get_tree_diff_hunks` with `context` lines around each change
+pub(crate) fn tree_diff_hunks(
+    repo_path: &Path,
+    from: &str,
+    to: &str,
+    file_path: &Path,
+    context: usize,
+) -> Result<Vec<Hunk>> {
+    let file_path = file_path.to_str().context("Invalid file path")?;
+    let unified = format!("--unified={}", context);
+    let output = run(
+        &[
+            "diff",
+            "--no-color",
+            "--no-ext-diff",
+            "--no-textconv",
+            "--no-renames",
+            &unified,
+            from,
+            to,
+            "--",
+            file_path,
+        ],
+        Some(repo_path),
+    )
+    .context("Failed to get file diff hunks")?;
+    Ok(parse_hunks(&output, true))
+}
 
-            current_hunk = Some(Hunk {
-                header: line.to_string(),
-                lines: Vec::new(),
-                is_staged: staged,
-                old_start,
-                old_lines,
-                new_start,
-                new_lines,
-                is_selected: false,
-            });
-        } else if let Some(hunk) = &mut current_hunk {
-            let line_type = if line.starts_with('+') {
-                HunkLineType::Addition
-            } else if line.starts_with('-') {
-                HunkLineType::Deletion
-            } else {
-                HunkLineType::Context
-            };
-            hunk.lines.push(HunkLine {
-                content: line.to_string(),
-                line_type,
-                is_selected: false,
-            });
-        }
-    }
+/// Hunks of every file in `git diff` output
+fn parse_hunks(output: &str, staged: bool) -> Vec<Hunk> {
+    parse_diff(output)
+        .into_iter()
+        .flat_map(|file| file.hunks)
+        .map(|hunk| Hunk {
+            is_staged: staged,
+            ..hunk
+        })
+        .collect()
+}
 
-    if let Some(h) = current_hunk.take() {
-        hunks.push(h);
+/// Append a hunk line to a patch, with git's marker when the line ends the
+/// file without a newline
+fn push_line(patch: &mut String, line: &HunkLine) {
+    patch.push_str(&line.content);
+    patch.push('\n');
+    if line.no_newline {
+        patch.push_str("\\ No newline at end of file\n");
     }
-
-    Ok(hunks)
 }
 
 /// Build a patch containing the full hunk, prefixed with the file headers that
 /// `git apply` needs to identify the target file.
-fn build_hunk_patch(file_path: &Path, hunk: &Hunk) -> String {
+pub(crate) fn build_hunk_patch(file_path: &Path, hunk: &Hunk) -> String {
     let path = file_path.to_string_lossy();
     let mut patch = String::new();
     patch.push_str(&format!("diff --git a/{path} b/{path}\n"));
@@ -138,8 +119,7 @@ fn build_hunk_patch(file_path: &Path, hunk: &Hunk) -> String {
     patch.push_str(&hunk.header);
     patch.push('\n');
     for line in &hunk.lines {
-        patch.push_str(&line.content);
-        patch.push('\n');
+        push_line(&mut patch, line);
     }
     patch
 }
@@ -165,8 +145,7 @@ fn build_partial_hunk_patch(file_path: &Path, hunk: &Hunk) -> Option<String> {
                     old_start.get_or_insert(old_line);
                     new_start.get_or_insert(new_line);
                     old_count += 1;
-                    body.push_str(&line.content);
-                    body.push('\n');
+                    push_line(&mut body, line);
                 }
                 old_line += 1;
             }
@@ -175,8 +154,7 @@ fn build_partial_hunk_patch(file_path: &Path, hunk: &Hunk) -> Option<String> {
                     old_start.get_or_insert(old_line);
                     new_start.get_or_insert(new_line);
                     new_count += 1;
-                    body.push_str(&line.content);
-                    body.push('\n');
+                    push_line(&mut body, line);
                 }
                 new_line += 1;
             }
@@ -267,50 +245,27 @@ pub fn unstage_hunk_lines(repo_path: &Path, file_path: &Path, hunk: &Hunk) -> Re
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_support::{commit_file, create_test_repo, git};
     use std::fs;
-    use std::process::Command;
     use tempfile::TempDir;
 
-    fn git(repo: &Path, args: &[&str]) {
-        let status = Command::new("git")
-            .args(args)
-            .current_dir(repo)
-            .status()
-            .unwrap();
-        assert!(status.success(), "git {:?} failed", args);
-    }
-
-    fn git_output(repo: &Path, args: &[&str]) -> String {
-        let output = Command::new("git")
-            .args(args)
-            .current_dir(repo)
-            .output()
-            .unwrap();
-        assert!(output.status.success(), "git {:?} failed", args);
-        String::from_utf8_lossy(&output.stdout).to_string()
-    }
-
     /// Create a temp repo with a committed file:
     ///   line1, line2, line3, line4, line5
-    fn create_test_repo() -> (TempDir, std::path::PathBuf) {
-        let dir = tempfile::tempdir().unwrap();
-        git(dir.path(), &["init", "-q"]);
-        git(dir.path(), &["config", "user.email", "test@example.com"]);
-        git(dir.path(), &["config", "user.name", "Test"]);
-        fs::write(
-            dir.path().join("test.txt"),
+    fn create_repo_with_file() -> (TempDir, std::path::PathBuf) {
+        let dir = create_test_repo();
+        commit_file(
+            dir.path(),
+            "test.txt",
             "line1\nline2\nline3\nline4\nline5\n",
-        )
-        .unwrap();
-        git(dir.path(), &["add", "test.txt"]);
-        git(dir.path(), &["commit", "-q", "-m", "initial"]);
+            "initial",
+        );
         let file = dir.path().join("test.txt");
         (dir, file)
     }
 
     #[test]
     fn test_get_unstaged_hunks() {
-        let (dir, file) = create_test_repo();
+        let (dir, file) = create_repo_with_file();
         let repo = dir.path();
         fs::write(&file, "line1\nCHANGED\nline3\nline4\nline5\n").unwrap();
 
@@ -330,7 +285,7 @@ mod tests {
 
     #[test]
     fn test_get_staged_hunks() {
-        let (dir, file) = create_test_repo();
+        let (dir, file) = create_repo_with_file();
         let repo = dir.path();
         fs::write(&file, "line1\nCHANGED\nline3\nline4\nline5\n").unwrap();
         git(repo, &["add", "test.txt"]);
@@ -342,21 +297,38 @@ mod tests {
 
     #[test]
     fn test_stage_hunk() {
-        let (dir, file) = create_test_repo();
+        let (dir, file) = create_repo_with_file();
         let repo = dir.path();
         fs::write(&file, "line1\nCHANGED\nline3\nline4\nline5\n").unwrap();
 
         let hunks = get_file_diff_hunks(repo, Path::new("test.txt"), false).unwrap();
         stage_hunk(repo, Path::new("test.txt"), &hunks[0]).unwrap();
 
-        let staged = git_output(repo, &["diff", "--cached", "--", "test.txt"]);
+        let staged = git(repo, &["diff", "--cached", "--", "test.txt"]);
         assert!(staged.contains("+CHANGED"));
         assert!(staged.contains("-line2"));
     }
 
+    #[test]
+    fn test_stage_hunk_without_trailing_newline() {
+        let (dir, file) = create_repo_with_file();
+        let repo = dir.path();
+        fs::write(&file, "line1\nline2\nline3\nline4\nLAST").unwrap();
+
+        let hunks = get_file_diff_hunks(repo, Path::new("test.txt"), false).unwrap();
+        assert_eq!(hunks.len(), 1);
+        let last = hunks[0].lines.last().unwrap();
+        assert_eq!(last.content, "+LAST");
+        assert!(last.no_newline);
+        stage_hunk(repo, Path::new("test.txt"), &hunks[0]).unwrap();
+
+        let staged = git(repo, &["show", ":test.txt"]);
+        assert_eq!(staged, "line1\nline2\nline3\nline4\nLAST");
+    }
+
     #[test]
     fn test_stage_hunk_lines_partial() {
-        let (dir, file) = create_test_repo();
+        let (dir, file) = create_repo_with_file();
         let repo = dir.path();
         // Commit a 7-line file, then edit lines 3 and 5.
         fs::write(&file, "line1\nline2\nline3\nline4\nline5\nline6\nline7\n").unwrap();
@@ -386,12 +358,12 @@ mod tests {
 
         stage_hunk_lines(repo, Path::new("test.txt"), &selected).unwrap();
 
-        let staged = git_output(repo, &["diff", "--cached", "--", "test.txt"]);
+        let staged = git(repo, &["diff", "--cached", "--", "test.txt"]);
         assert!(staged.contains("+CHANGED5"), "staged diff was:\n{}", staged);
         assert!(!staged.contains("CHANGED3"), "staged diff was:\n{}", staged);
 
         // The unselected change must remain unstaged.
-        let unstaged = git_output(repo, &["diff", "--", "test.txt"]);
+        let unstaged = git(repo, &["diff", "--", "test.txt"]);
         assert!(
             unstaged.contains("CHANGED3"),
             "unstaged diff was:\n{}",
@@ -406,7 +378,7 @@ mod tests {
 
     #[test]
     fn test_unstage_hunk() {
-        let (dir, file) = create_test_repo();
+        let (dir, file) = create_repo_with_file();
         let repo = dir.path();
         fs::write(&file, "line1\nCHANGED\nline3\nline4\nline5\n").unwrap();
         git(repo, &["add", "test.txt"]);
@@ -414,13 +386,13 @@ mod tests {
         let staged_hunks = get_file_diff_hunks(repo, Path::new("test.txt"), true).unwrap();
         unstage_hunk(repo, Path::new("test.txt"), &staged_hunks[0]).unwrap();
 
-        let staged = git_output(repo, &["diff", "--cached", "--", "test.txt"]);
+        let staged = git(repo, &["diff", "--cached", "--", "test.txt"]);
         assert!(!staged.contains("CHANGED"));
     }
 
     #[test]
     fn test_stage_hunk_with_no_changes_returns_empty() {
-        let (dir, _file) = create_test_repo();
+        let (dir, _file) = create_repo_with_file();
         let repo = dir.path();
         let hunks = get_file_diff_hunks(repo, Path::new("test.txt"), false).unwrap();
         assert!(hunks.is_empty());
@@ -1,21 +1,217 @@
-use crate::command::run;
-use crate::models::Commit;
+use crate::command::{run, run_with_stdin};
+use crate::models::{Commit, GitRef, RefType, Trailer};
+use crate::operations::oplog::EXCLUDE_PRIVATE_REFS;
 use anyhow::{Context, Result};
-use chrono::DateTime;
-use regex::Regex;
+use chrono::{DateTime, Utc};
+use std::collections::HashSet;
 use std::path::Path;
-use std::sync::LazyLock;
 
-/// Regex to match timezone offsets in git date format (e.g., " +0530" or " -0700")
-static TIMEZONE_REGEX: LazyLock<Regex> =
-    LazyLock::new(|| Regex::new(r" ([+-])(\d{2})(\d{2})$").unwrap());
+/// Separates commit records in `git log` output
+const RECORD_SEPARATOR: char = '\x1e';
+/// Separates the fields of a record
+const FIELD_SEPARATOR: char = '\0';
 
-const GIT_LOG_FORMAT: &str = "%H|%P|%an|%ae|%ad|%s";
+/// hash, parents, author name/email/date, committer name/email/date,
+/// trailers, decorations, subject, raw message. The raw message comes last because it is
+/// the only free-form field that may span lines.
+const GIT_LOG_FORMAT: &str =
+    "%x1e%H%x00%P%x00%an%x00%ae%x00%aI%x00%cn%x00%ce%x00%cI%x00%(trailers:only,unfold)%x00%D%x00%s%x00%B";
 
 pub fn get_commits(repo_path: &Path, max_count: Option<usize>) -> Result<Vec<Commit>> {
     get_commits_filtered(repo_path, max_count, None, false)
 }
 
+/// Commit ordering for a `LogQuery`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogOrder {
+    /// git's default: reverse chronological
+    #[default]
+    Default,
+    /// `--date-order`: by commit date, never showing a parent before its children
+    Date,
+    /// `--topo-order`: keep lines of history together
+    Topo,
+}
+
+/// A `git log` query built up from optional filters.
+///
+/// With no revisions the query covers every ref (`--all`), or only the
+/// remote-tracking branches after `remotes()`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LogQuery {
+    pub revisions: Vec<String>,
+    pub remotes: bool,
+    pub paths: Vec<String>,
+    /// Follow the single path across renames (`--follow`)
+    pub follow: bool,
+    pub since: Option<String>,
+    pub until: Option<String>,
+    pub author: Option<String>,
+    pub grep: Option<String>,
+    /// Match `author`/`grep` literally instead of as regular expressions
+    pub fixed_strings: bool,
+    /// Match `author`/`grep` regardless of case
+    pub ignore_case: bool,
+    pub first_parent: bool,
+    pub no_merges: bool,
+    pub order: LogOrder,
+    pub max_count: Option<usize>,
+    pub skip: Option<usize>,
+}
+
+impl LogQuery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a revision or range such as `main`, `v1.0..HEAD` or `main...feature`
+    pub fn revision(mut self, revision: impl Into<String>) -> Self {
+        self.revisions.push(revision.into());
+        self
+    }
+
+    /// Restrict the default scope to remote-tracking branches
+    pub fn remotes(mut self) -> Self {
+        self.remotes = true;
+        self
+    }
+
+    /// Only commits touching `path`
+    pub fn path(mut self, path: impl Into<String>) -> Self {
+        self.paths.push(path.into());
+        self
+    }
+
+    /// Keep following the path (there must be only one) across renames
+    pub fn follow(mut self) -> Self {
+        self.follow = true;
+        self
+    }
+
+    /// Commits newer than `date` (any date git understands, e.g. `2 weeks ago`)
+    pub fn since(mut self, date: impl Into<String>) -> Self {
+        self.since = Some(date.into());
+        self
+    }
+
+    /// Commits older than `date`
+    pub fn until(mut self, date: impl Into<String>) -> Self {
+        self.until = Some(date.into());
+        self
+    }
+
+    /// Commits whose author name or email matches `pattern`
+    pub fn author(mut self, pattern: impl Into<String>) -> Self {
+        self.author = Some(pattern.into());
+        self
+    }
+
+    /// Commits whose message matches `pattern`
+    pub fn grep(mut self, pattern: impl Into<String>) -> Self {
+        self.grep = Some(pattern.into());
+        self
+    }
+
+    /// Treat `author`/`grep` patterns as literal strings (`--fixed-strings`)
+    pub fn fixed_strings(mut self) -> Self {
+        self.fixed_strings = true;
+        self
+    }
+
+    /// Match `author`/`grep` patterns case-insensitively (`--regexp-ignore-case`)
+    pub fn ignore_case(mut self) -> Self {
+        self.ignore_case = true;
+        self
+    }
+
+    /// Follow only the first parent of merges
+    pub fn first_parent(mut self) -> Self {
+        self.first_parent = true;
+        self
+    }
+
+    pub fn no_merges(mut self) -> Self {
+        self.no_merges = true;
+        self
+    }
+
+    pub fn order(mut self, order: LogOrder) -> Self {
+        self.order = order;
+        self
+    }
+
+    pub fn max_count(mut self, max_count: usize) -> Self {
+        self.max_count = Some(max_count);
+        self
+    }
+
+    /// Skip the first `skip` matching commits, for paging with `max_count`
+    pub fn skip(mut self, skip: usize) -> Self {
+        self.skip = Some(skip);
+        self
+    }
+
+    /// The `git log` filter arguments for this query (without the format)
+    pub fn args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+
+        if let Some(n) = self.max_count {
+            args.push(format!("--max-count={}", n));
+        }
+        if let Some(n) = self.skip {
+            args.push(format!("--skip={}", n));
+        }
+        if let Some(ref date) = self.since {
+            args.push(format!("--since={}", date));
+        }
+        if let Some(ref date) = self.until {
+            args.push(format!("--until={}", date));
+        }
+        if let Some(ref pattern) = self.author {
+            args.push(format!("--author={}", pattern));
+        }
+        if let Some(ref pattern) = self.grep {
+            args.push(format!("--grep={}", pattern));
+        }
+        if self.fixed_strings {
+            args.push("--fixed-strings".to_string());
+        }
+        if self.ignore_case {
+            args.push("--regexp-ignore-case".to_string());
+        }
+        if self.follow {
+            args.push("--follow".to_string());
+        }
+        if self.first_parent {
+            args.push("--first-parent".to_string());
+        }
+        if self.no_merges {
+            args.push("--no-merges".to_string());
+        }
+        match self.order {
+            LogOrder::Default => {}
+            LogOrder::Date => args.push("--date-order".to_string()),
+            LogOrder::Topo => args.push("--topo-order".to_string()),
+        }
+
+        if !self.revisions.is_empty() {
+            args.extend(self.revisions.iter().cloned());
+        } else if self.remotes {
+            args.push("--remotes".to_string());
+        } else {
+            args.push(EXCLUDE_PRIVATE_REFS.to_string());
+            args.push("--all".to_string());
+        }
+
+        // Always terminate revisions so a branch and a file of the same name
+        // are never confused
+        args.push("--".to_string());
+        args.extend(self.paths.iter().cloned());
+
+        args
+    }
+}
+
 /// Fetches commits, optionally scoped to a single branch or to
 /// remote-tracking branches only.
 ///
@@ -27,23 +223,26 @@ pub fn get_commits_filtered(
     branch: Option<&str>,
     remote: bool,
 ) -> Result<Vec<Commit>> {
-    let format_arg = format!("--format={}", GIT_LOG_FORMAT);
-    let n_arg = max_count.map(|n| format!("-n{}", n));
-
-    let scope: String = match branch {
-        Some(name) => name.to_string(),
-        None if remote => "--remotes".to_string(),
-        None => "--all".to_string(),
-    };
-
-    let mut args: Vec<String> = Vec::with_capacity(5);
-    args.push("log".to_string());
-    args.push("--date=iso".to_string());
-    args.push(format_arg);
-    args.push(scope);
-    if let Some(n) = n_arg {
-        args.push(n);
+    let mut query = LogQuery::new();
+    query.max_count = max_count;
+    match branch {
+        Some(name) => query = query.revision(name),
+        None if remote => query = query.remotes(),
+        None => {}
     }
+    get_commits_matching(repo_path, &query)
+}
+
+/// Fetches the commits selected by `query`
+pub fn get_commits_matching(repo_path: &Path, query: &LogQuery) -> Result<Vec<Commit>> {
+    let mut args = vec![
+        "log".to_string(),
+        // Full ref names keep local branches like `feature/x` apart from
+        // remote-tracking branches like `origin/x`
+        "--decorate=full".to_string(),
+        format!("--format={}", GIT_LOG_FORMAT),
+    ];
+    args.extend(query.args());
 
     let arg_refs: Vec<&str> = args.iter().map(String::as_str).collect();
     let output = run(&arg_refs, Some(repo_path))
@@ -52,58 +251,80 @@ pub fn get_commits_filtered(
     parse_commits(&output)
 }
 
-fn parse_commits(output: &str) -> Result<Vec<Commit>> {
-    let mut commits = Vec::new();
-    let records: Vec<&str> = output.trim_end().split("\n").collect();
-
-    for record in records {
-        if record.trim().is_empty() {
-            continue;
-        }
+/// Fetches the given commits, newest first, up to `max_count` of them
+pub(crate) fn get_commits_by_hash(
+    repo_path: &Path,
+    hashes: &HashSet<String>,
+    max_count: Option<usize>,
+) -> Result<Vec<Commit>> {
+    if hashes.is_empty() {
+        return Ok(Vec::new());
+    }
 
-        if let Some(commit) = parse_commit(record) {
-            commits.push(commit);
-        }
+    let stdin: Vec<&str> = hashes.iter().map(String::as_str).collect();
+    let mut stdin = stdin.join("\n");
+    // `--max-count` turns `--no-walk` back into a walk, so the newest are
+    // picked from the hashes alone before any commit is read
+    if let Some(n) = max_count.filter(|n| *n < hashes.len()) {
+        let sorted = run_with_stdin(
+            &["rev-list", "--no-walk=sorted", "--stdin"],
+            Some(repo_path),
+            &stdin,
+        )?;
+        stdin = sorted.lines().take(n).collect::<Vec<_>>().join("\n");
     }
 
-    Ok(commits)
+    let format = format!("--format={}", GIT_LOG_FORMAT);
+    let output = run_with_stdin(
+        &[
+            "log",
+            "--no-walk=sorted",
+            "--stdin",
+            "--decorate=full",
+            &format,
+        ],
+        Some(repo_path),
+        &stdin,
+    )
+    .with_context(|| format!("Failed to get git log from {}", repo_path.display()))?;
+
+    parse_commits(&output)
 }
 
-fn parse_commit(record: &str) -> Option<Commit> {
-    let parts: Vec<&str> = record.splitn(7, '|').collect();
+fn parse_commits(output: &str) -> Result<Vec<Commit>> {
+    Ok(output
+        .split(RECORD_SEPARATOR)
+        .filter(|record| !record.trim().is_empty())
+        .filter_map(parse_commit)
+        .collect())
+}
 
-    if parts.len() < 6 {
+fn parse_date(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|d| d.with_timezone(&Utc))
+        .ok()
+}
+
+fn parse_commit(record: &str) -> Option<Commit> {
+    let fields: Vec<&str> = record.splitn(12, FIELD_SEPARATOR).collect();
+    if fields.len() < 12 {
         return None;
     }
 
-    let hash = parts[0].to_string();
-    let parent_hashes: Vec<String> = if !parts[1].is_empty() {
-        parts[1].split(' ').map(|s| s.to_string()).collect()
-    } else {
-        vec![]
-    };
+    let hash = fields[0].trim_start_matches('\n').to_string();
+    let parent_hashes: Vec<String> = fields[1].split_whitespace().map(String::from).collect();
+
+    let date = parse_date(fields[4])?;
+    let committer_date = parse_date(fields[7]).unwrap_or(date);
 
-    let author = parts[2].to_string();
-    let email = parts[3].to_string();
-
-    // Convert git date format to RFC3339
-    // e.g., "2024-01-10 12:00:00 +0530" -> "2024-01-10T12:00:00+05:30"
-    let date_str = TIMEZONE_REGEX
-        .replace(parts[4], |caps: &regex::Captures| {
-            // Convert " +0530" to "+05:30"
-            format!("{}{}:{}", &caps[1], &caps[2], &caps[3])
-        })
-        .replace(' ', "T");
-
-    let date = DateTime::parse_from_rfc3339(&date_str)
-        .map(|d| d.with_timezone(&chrono::Utc))
-        .ok()?;
-
-    let summary = parts[5].to_string();
-    let message = if parts.len() > 6 && !parts[6].is_empty() {
-        format!("{}\n\n{}", summary, parts[6])
-    } else {
-        summary.clone()
+    let trailers = fields[8].lines().filter_map(Trailer::parse_line).collect();
+
+    let refs = parse_decorations(fields[9]);
+
+    let summary = fields[10].to_string();
+    let message = match fields[11].trim_end() {
+        "" => summary.clone(),
+        body => body.to_string(),
     };
 
     let short_hash = hash.chars().take(7).collect();
@@ -113,67 +334,308 @@ fn parse_commit(record: &str) -> Option<Commit> {
         short_hash,
         message,
         summary,
-        author,
-        email,
+        author: fields[2].to_string(),
+        email: fields[3].to_string(),
         date,
         parent_hashes,
-        refs: Vec::new(),
+        refs,
+        committer: fields[5].to_string(),
+        committer_email: fields[6].to_string(),
+        committer_date,
+        trailers,
     })
 }
 
+/// Parse `%D` output produced with `--decorate=full`, e.g.
+/// `HEAD -> refs/heads/main, tag: refs/tags/v1.0, refs/remotes/origin/main`.
+/// A detached HEAD appears as a bare `HEAD`. Other namespaces (stash, notes)
+/// are skipped.
+fn parse_decorations(decorations: &str) -> Vec<GitRef> {
+    let mut refs = Vec::new();
+
+    for decoration in decorations.split(", ").map(str::trim) {
+        let name = match decoration.strip_prefix("HEAD -> ") {
+            Some(branch) => {
+                refs.push(GitRef {
+                    name: "HEAD".to_string(),
+                    ref_type: RefType::Head,
+                });
+                branch
+            }
+            None => decoration,
+        };
+
+        let (name, ref_type) = if name == "HEAD" {
+            ("HEAD", RefType::Head)
+        } else if let Some(tag) = name.strip_prefix("tag: ") {
+            (tag.strip_prefix("refs/tags/").unwrap_or(tag), RefType::Tag)
+        } else if let Some(branch) = name.strip_prefix("refs/heads/") {
+            (branch, RefType::Branch)
+        } else if let Some(remote) = name.strip_prefix("refs/remotes/") {
+            (remote, RefType::Remote)
+        } else {
+            continue;
+        };
+
+        refs.push(GitRef {
+            name: name.to_string(),
+            ref_type,
+        });
+    }
+
+    refs
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_support::{create_test_repo, git};
+
+    /// Build one record the way `GIT_LOG_FORMAT` lays it out
+    fn record(hash: &str, parents: &str, summary: &str, message: &str, trailers: &str) -> String {
+        [
+            "",
+            hash,
+            parents,
+            "John Doe",
+            "john@example.com",
+            "2024-01-10T12:00:00+05:30",
+            "Jane Roe",
+            "jane@example.com",
+            "2024-01-11T08:00:00Z",
+            trailers,
+            "",
+            summary,
+            message,
+        ]
+        .join("\0")
+        .replacen('\0', "\x1e", 1)
+    }
 
     #[test]
     fn test_parse_single_commit() {
-        let input = "abc123def456789|def456ghi789abc|john@example.com|john@example.com|2024-01-10T12:00:00+00:00|Initial commit|This is the body";
-        let commits = parse_commits(input).unwrap();
+        let input = record(
+            "abc123def456789",
+            "def456ghi789abc",
+            "Initial commit",
+            "Initial commit\n\nThis is the body\n",
+            "",
+        );
+        let commits = parse_commits(&input).unwrap();
         assert_eq!(commits.len(), 1);
 
         let commit = &commits[0];
         assert_eq!(commit.hash, "abc123def456789");
         assert_eq!(commit.short_hash, "abc123d");
-        assert_eq!(commit.author, "john@example.com");
+        assert_eq!(commit.author, "John Doe");
         assert_eq!(commit.email, "john@example.com");
         assert_eq!(commit.summary, "Initial commit");
-        assert!(commit.message.contains("This is the body"));
+        assert_eq!(commit.message, "Initial commit\n\nThis is the body");
+        assert_eq!(commit.date.to_rfc3339(), "2024-01-10T06:30:00+00:00");
+    }
+
+    #[test]
+    fn test_parse_committer_and_trailers() {
+        let input = record(
+            "abc123",
+            "",
+            "Fix parser",
+            "Fix parser\n\nSigned-off-by: John Doe <john@example.com>\nChange-Id: I1234\n",
+            "Signed-off-by: John Doe <john@example.com>\nChange-Id: I1234\n",
+        );
+        let commit = &parse_commits(&input).unwrap()[0];
+
+        assert_eq!(commit.committer, "Jane Roe");
+        assert_eq!(commit.committer_email, "jane@example.com");
+        assert_eq!(
+            commit.committer_date.to_rfc3339(),
+            "2024-01-11T08:00:00+00:00"
+        );
+        assert_eq!(
+            commit.trailers,
+            vec![
+                Trailer {
+                    key: "Signed-off-by".to_string(),
+                    value: "John Doe <john@example.com>".to_string(),
+                },
+                Trailer {
+                    key: "Change-Id".to_string(),
+                    value: "I1234".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_separators_in_message() {
+        let input = record(
+            "abc123",
+            "",
+            "Use a|b in summary",
+            "Use a|b in summary\n\nbody | with pipes\n\nand paragraphs\n",
+            "",
+        );
+        let commit = &parse_commits(&input).unwrap()[0];
+        assert_eq!(commit.summary, "Use a|b in summary");
+        assert!(commit
+            .message
+            .ends_with("body | with pipes\n\nand paragraphs"));
     }
 
     #[test]
     fn test_parse_multiple_commits() {
-        let input = "abc123|def456|jane@example.com|jane@example.com|2024-01-10T12:00:00+00:00|Second commit|\ndef456|abc123|john@example.com|john@example.com|2024-01-09T12:00:00+00:00|First commit|";
-        let commits = parse_commits(input).unwrap();
+        let input = format!(
+            "{}\n{}\n",
+            record("abc123", "def456", "Second commit", "Second commit\n", ""),
+            record("def456", "", "First commit", "First commit\n", "")
+        );
+        let commits = parse_commits(&input).unwrap();
         assert_eq!(commits.len(), 2);
         assert_eq!(commits[0].summary, "Second commit");
+        assert_eq!(commits[0].message, "Second commit");
         assert_eq!(commits[1].summary, "First commit");
     }
 
     #[test]
     fn test_parse_commit_with_merge_parents() {
-        let input = "abc123|def456 ghi789|jane@example.com|jane@example.com|2024-01-10T12:00:00+00:00|Merge branch|";
-        let commits = parse_commits(input).unwrap();
+        let input = record(
+            "abc123",
+            "def456 ghi789",
+            "Merge branch",
+            "Merge branch\n",
+            "",
+        );
+        let commits = parse_commits(&input).unwrap();
         assert_eq!(commits.len(), 1);
         assert_eq!(commits[0].parent_hashes.len(), 2);
     }
 
     #[test]
-    fn test_parse_commit_with_no_body() {
-        let input =
-            "abc123||john@example.com|john@example.com|2024-01-10T12:00:00+00:00|Simple commit|";
-        let commits = parse_commits(input).unwrap();
+    fn test_parse_commit_with_empty_parents() {
+        let input = record("abc123", "", "Root commit", "Root commit\n", "");
+        let commits = parse_commits(&input).unwrap();
         assert_eq!(commits.len(), 1);
-        assert_eq!(commits[0].summary, "Simple commit");
-        assert_eq!(commits[0].message, "Simple commit");
+        assert!(commits[0].parent_hashes.is_empty());
     }
 
     #[test]
-    fn test_parse_commit_with_empty_parents() {
-        let input =
-            "abc123||john@example.com|john@example.com|2024-01-10T12:00:00+00:00|Root commit|";
-        let commits = parse_commits(input).unwrap();
-        assert_eq!(commits.len(), 1);
-        assert!(commits[0].parent_hashes.is_empty());
+    fn test_parse_decorations() {
+        let refs = parse_decorations(
+            "HEAD -> refs/heads/main, tag: refs/tags/v1.0, refs/remotes/origin/main, \
+             refs/heads/feature/x, refs/stash",
+        );
+        let summary: Vec<(&str, RefType)> = refs
+            .iter()
+            .map(|r| (r.name.as_str(), r.ref_type.clone()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("HEAD", RefType::Head),
+                ("main", RefType::Branch),
+                ("v1.0", RefType::Tag),
+                ("origin/main", RefType::Remote),
+                ("feature/x", RefType::Branch),
+            ]
+        );
+
+        let detached = parse_decorations("HEAD, refs/heads/main");
+        assert_eq!(detached[0].ref_type, RefType::Head);
+        assert_eq!(detached[1].name, "main");
+
+        assert!(parse_decorations("").is_empty());
+    }
+
+    #[test]
+    fn test_log_query_args() {
+        assert_eq!(
+            LogQuery::new().args(),
+            vec!["--exclude=refs/openisl/*", "--all", "--"]
+        );
+        assert_eq!(LogQuery::new().remotes().args(), vec!["--remotes", "--"]);
+
+        let args = LogQuery::new()
+            .revision("main..feature")
+            .path("src/lib.rs")
+            .since("2024-01-01")
+            .author("alice")
+            .grep("fix")
+            .first_parent()
+            .no_merges()
+            .order(LogOrder::Topo)
+            .max_count(20)
+            .skip(40)
+            .args();
+        assert_eq!(
+            args,
+            vec![
+                "--max-count=20",
+                "--skip=40",
+                "--since=2024-01-01",
+                "--author=alice",
+                "--grep=fix",
+                "--first-parent",
+                "--no-merges",
+                "--topo-order",
+                "main..feature",
+                "--",
+                "src/lib.rs",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_get_commits_matching() {
+        let dir = create_test_repo();
+        let path = dir.path();
+        let commit = |file: &str, message: &str, author: &str| {
+            std::fs::write(path.join(file), message).unwrap();
+            git(path, &["add", file]);
+            git(path, &["commit", "-m", message, "--author", author]);
+        };
+        commit("a.txt", "Base", "Alice <alice@example.com>");
+        git(path, &["checkout", "-b", "feature"]);
+        commit("b.txt", "Add b", "Bob <bob@example.com>");
+        commit("a.txt", "Fix (a)", "Alice <alice@example.com>");
+        commit("b.txt", "Update b", "Alice <alice@example.com>");
+
+        // Commits share a timestamp, so only topological order is stable
+        let summaries = |query: LogQuery| -> Vec<String> {
+            get_commits_matching(path, &query.order(LogOrder::Topo))
+                .unwrap()
+                .into_iter()
+                .map(|c| c.summary)
+                .collect()
+        };
+
+        assert_eq!(
+            summaries(LogQuery::new().revision("main..feature")),
+            vec!["Update b", "Fix (a)", "Add b"]
+        );
+        assert_eq!(
+            summaries(LogQuery::new().path("a.txt")),
+            vec!["Fix (a)", "Base"]
+        );
+        assert_eq!(
+            summaries(LogQuery::new().author("bob").fixed_strings()),
+            vec!["Add b"]
+        );
+        assert_eq!(
+            summaries(LogQuery::new().grep("FIX (").fixed_strings()),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            summaries(LogQuery::new().grep("FIX (").fixed_strings().ignore_case()),
+            vec!["Fix (a)"]
+        );
+        assert_eq!(
+            summaries(LogQuery::new().grep("^fix").ignore_case()),
+            vec!["Fix (a)"]
+        );
+        assert_eq!(
+            summaries(LogQuery::new().max_count(2).skip(1)),
+            vec!["Fix (a)", "Add b"]
+        );
     }
 
     #[test]
@@ -187,4 +649,44 @@ mod tests {
         let commits = parse_commits("   \n\n   ").unwrap();
         assert!(commits.is_empty());
     }
+
+    #[test]
+    fn test_get_commits_reads_full_message() {
+        let dir = create_test_repo();
+        let path = dir.path();
+        std::fs::write(path.join("a.txt"), "a").unwrap();
+        git(path, &["add", "a.txt"]);
+        git(
+            path,
+            &[
+                "commit",
+                "-m",
+                "Summary | with pipe",
+                "-m",
+                "Body line",
+                "-m",
+                "Co-authored-by: Other <other@example.com>",
+            ],
+        );
+
+        let commits = get_commits(path, None).unwrap();
+        assert_eq!(commits.len(), 1);
+        assert_eq!(commits[0].summary, "Summary | with pipe");
+        assert_eq!(
+            commits[0].message,
+            "Summary | with pipe\n\nBody line\n\nCo-authored-by: Other <other@example.com>"
+        );
+        assert_eq!(commits[0].committer, "Test User");
+        assert_eq!(commits[0].trailers[0].key, "Co-authored-by");
+
+        git(path, &["tag", "v1"]);
+        let refs = &get_commits(path, None).unwrap()[0].refs;
+        assert!(refs.iter().any(|r| r.ref_type == RefType::Head));
+        assert!(refs
+            .iter()
+            .any(|r| r.name == "main" && r.ref_type == RefType::Branch));
+        assert!(refs
+            .iter()
+            .any(|r| r.name == "v1" && r.ref_type == RefType::Tag));
+    }
 }
@@ -0,0 +1,637 @@
+use crate::command::{run, run_raw, run_with_env, run_with_stdin};
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+/// The ref holding the newest operation
+pub const OPLOG_REF: &str = "refs/openisl/oplog";
+
+/// Keeps openISL's private refs out of `--all` history walks
+pub(crate) const EXCLUDE_PRIVATE_REFS: &str = "--exclude=refs/openisl/*";
+
+const PRIVATE_NAMESPACE: &str = "refs/openisl/";
+const STASH_REF: &str = "refs/stash";
+const KIND_TRAILER: &str = "Openisl-Operation: ";
+
+/// What an operation log entry records
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OperationKind {
+    /// A regular openISL command
+    Operation,
+    /// Undo of the operation with this id
+    Undo(String),
+    /// Redo of the undo entry with this id
+    Redo(String),
+    /// Restore of the state from before the operation with this id
+    Restore(String),
+}
+
+/// One entry of the operation log. The snapshot it holds is the state of the
+/// repository just before the operation ran.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Operation {
+    pub id: String,
+    pub timestamp: DateTime<Utc>,
+    pub description: String,
+    pub kind: OperationKind,
+    /// Branch `HEAD` was on, or the commit when detached
+    pub head: Option<String>,
+}
+
+impl OperationKind {
+    fn encode(&self) -> String {
+        match self {
+            OperationKind::Operation => "op".to_string(),
+            OperationKind::Undo(id) => format!("undo {}", id),
+            OperationKind::Redo(id) => format!("redo {}", id),
+            OperationKind::Restore(id) => format!("restore {}", id),
+        }
+    }
+
+    fn decode(value: &str) -> Self {
+        match value.split_once(' ') {
+            Some(("undo", id)) => OperationKind::Undo(id.to_string()),
+            Some(("redo", id)) => OperationKind::Redo(id.to_string()),
+            Some(("restore", id)) => OperationKind::Restore(id.to_string()),
+            _ => OperationKind::Operation,
+        }
+    }
+}
+
+/// Snapshot the repository and record `description` as the next operation.
+/// Returns the id of the new entry.
+pub fn record_operation(repo_path: &Path, description: &str) -> Result<String> {
+    record(repo_path, description, OperationKind::Operation)
+}
+
+/// All recorded operations, newest first
+pub fn get_operations(repo_path: &Path) -> Result<Vec<Operation>> {
+    let output = run_raw(
+        &[
+            "log",
+            "--first-parent",
+            "--format=%x1e%H%x00%ct%x00%B",
+            OPLOG_REF,
+            "--",
+        ],
+        Some(repo_path),
+    )?;
+    // No operations recorded yet
+    if !output.success() {
+        return Ok(Vec::new());
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut operations = Vec::new();
+    for record in stdout.split('\x1e').filter(|r| !r.trim().is_empty()) {
+        let mut fields = record.splitn(3, '\0');
+        let (Some(id), Some(seconds), Some(message)) =
+            (fields.next(), fields.next(), fields.next())
+        else {
+            continue;
+        };
+
+        let mut description = String::new();
+        let mut kind = None;
+        let mut head = None;
+        for line in message.lines() {
+            if let Some(value) = line.strip_prefix(KIND_TRAILER) {
+                kind = Some(OperationKind::decode(value.trim()));
+            } else if let Some(value) = line.strip_prefix("Openisl-Head: ") {
+                head = Some(value.trim().to_string());
+            } else if description.is_empty() {
+                description = line.to_string();
+            }
+        }
+
+        // The root commit that starts the log
+        let Some(kind) = kind else {
+            break;
+        };
+
+        operations.push(Operation {
+            id: id.trim().to_string(),
+            timestamp: DateTime::from_timestamp(seconds.trim().parse().unwrap_or(0), 0)
+                .unwrap_or_default(),
+            description,
+            kind,
+            head,
+        });
+    }
+    Ok(operations)
+}
+
+/// Undo the latest operation that has not been undone yet, or, given an id,
+/// restore the state from before that operation. The current state is
+/// recorded first, so the undo can itself be redone or undone.
+pub fn undo_operation(repo_path: &Path, id: Option<&str>) -> Result<Operation> {
+    let operations = get_operations(repo_path)?;
+    let stacks = Stacks::replay(&operations);
+
+    let latest = stacks.done.last().copied();
+    let target = match id {
+        None => latest.context("Nothing to undo: no operations recorded")?,
+        Some(prefix) => {
+            let mut matches = operations
+                .iter()
+                .filter(|op| op.id.starts_with(prefix))
+                .map(|op| op.id.as_str());
+            let found = matches
+                .next()
+                .with_context(|| format!("No operation {} in the operation log", prefix))?;
+            if matches.next().is_some() {
+                bail!("Operation id {} is ambiguous", prefix);
+            }
+            found
+        }
+    };
+    let operation = find(&operations, target)?.clone();
+
+    let kind = if Some(target) == latest {
+        OperationKind::Undo(operation.id.clone())
+    } else {
+        OperationKind::Restore(operation.id.clone())
+    };
+    let verb = match kind {
+        OperationKind::Undo(_) => "undo",
+        _ => "restore to before",
+    };
+    let description = format!("{} {}", verb, operation.description);
+    record(repo_path, &description, kind)?;
+    restore_snapshot(repo_path, &operation.id, &description)?;
+    Ok(operation)
+}
+
+/// Redo the most recently undone operation
+pub fn redo_operation(repo_path: &Path) -> Result<Operation> {
+    let operations = get_operations(repo_path)?;
+    let stacks = Stacks::replay(&operations);
+
+    let (undo_id, target_id) = stacks.undone.last().copied().context("Nothing to redo")?;
+    let operation = find(&operations, target_id)?.clone();
+
+    let description = format!("redo {}", operation.description);
+    record(
+        repo_path,
+        &description,
+        OperationKind::Redo(undo_id.to_string()),
+    )?;
+    // The undo entry snapshotted the state just before the undo ran
+    restore_snapshot(repo_path, undo_id, &description)?;
+    Ok(operation)
+}
+
+fn find<'a>(operations: &'a [Operation], id: &str) -> Result<&'a Operation> {
+    operations
+        .iter()
+        .find(|op| op.id == id)
+        .with_context(|| format!("Operation {} is missing from the operation log", id))
+}
+
+/// Which operations are in effect and which undos can be redone, derived by
+/// replaying the log oldest first
+struct Stacks<'a> {
+    done: Vec<&'a str>,
+    /// `(undo entry, undone operation)`
+    undone: Vec<(&'a str, &'a str)>,
+}
+
+impl<'a> Stacks<'a> {
+    fn replay(operations: &'a [Operation]) -> Self {
+        let mut stacks = Stacks {
+            done: Vec::new(),
+            undone: Vec::new(),
+        };
+        for op in operations.iter().rev() {
+            match op.kind {
+                OperationKind::Operation | OperationKind::Restore(_) => {
+                    stacks.done.push(&op.id);
+                    stacks.undone.clear();
+                }
+                OperationKind::Undo(ref target) => {
+                    if stacks.done.last() == Some(&target.as_str()) {
+                        stacks.done.pop();
+                        stacks.undone.push((&op.id, target));
+                    }
+                }
+                OperationKind::Redo(ref undo) => {
+                    if stacks.undone.last().map(|(u, _)| *u) == Some(undo.as_str()) {
+                        let (_, target) = stacks.undone.pop().unwrap();
+                        stacks.done.push(target);
+                    }
+                }
+            }
+        }
+        stacks
+    }
+}
+
+/// Fixed identity for snapshot commits, so recording works without
+/// `user.name`/`user.email` configured
+fn snapshot_identity() -> HashMap<&'static str, &'static str> {
+    HashMap::from([
+        ("GIT_AUTHOR_NAME", "openisl"),
+        ("GIT_AUTHOR_EMAIL", "openisl@localhost"),
+        ("GIT_COMMITTER_NAME", "openisl"),
+        ("GIT_COMMITTER_EMAIL", "openisl@localhost"),
+    ])
+}
+
+/// Refs captured by snapshots, as `(refname, oid)`
+fn snapshot_refs(repo_path: &Path) -> Result<Vec<(String, String)>> {
+    let output = run(
+        &["for-each-ref", "--format=%(objectname) %(refname)"],
+        Some(repo_path),
+    )?;
+    Ok(output
+        .lines()
+        .filter_map(|line| line.split_once(' '))
+        .filter(|(_, name)| !name.starts_with(PRIVATE_NAMESPACE) && *name != STASH_REF)
+        .map(|(oid, name)| (name.to_string(), oid.to_string()))
+        .collect())
+}
+
+/// The stash stack, newest first, as `oid message` lines. Stash entries live
+/// in the reflog of `refs/stash`, so they are captured apart from the refs.
+fn stash_entries(repo_path: &Path) -> Result<String> {
+    if rev_parse(repo_path, STASH_REF).is_none() {
+        return Ok(String::new());
+    }
+    run(
+        &["log", "-g", "--format=%H %gs", STASH_REF],
+        Some(repo_path),
+    )
+}
+
+fn rev_parse(repo_path: &Path, spec: &str) -> Option<String> {
+    let output = run_raw(&["rev-parse", "--verify", "--quiet", spec], Some(repo_path)).ok()?;
+    output
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Commit a snapshot (`refs` and `stash` blobs, `index` and `worktree` trees)
+/// to the log
+fn record(repo_path: &Path, description: &str, kind: OperationKind) -> Result<String> {
+    let refs = snapshot_refs(repo_path)?;
+    let stash = stash_entries(repo_path)?;
+    let head_commit = rev_parse(repo_path, "HEAD");
+    let head_ref = run_raw(&["symbolic-ref", "--quiet", "HEAD"], Some(repo_path))?;
+    let head_ref = head_ref
+        .success()
+        .then(|| String::from_utf8_lossy(&head_ref.stdout).trim().to_string());
+
+    let mut refs_blob = match (&head_ref, &head_commit) {
+        (Some(name), _) => format!("HEAD ref: {}\n", name),
+        (None, Some(oid)) => format!("HEAD {}\n", oid),
+        (None, None) => bail!("Cannot snapshot a repository without HEAD"),
+    };
+    for (name, oid) in &refs {
+        refs_blob.push_str(&format!("{} {}\n", oid, name));
+    }
+    let refs_blob = run_with_stdin(
+        &["hash-object", "-w", "--stdin"],
+        Some(repo_path),
+        &refs_blob,
+    )?;
+
+    let stash_blob = run_with_stdin(&["hash-object", "-w", "--stdin"], Some(repo_path), &stash)?;
+
+    let mut tree = format!("100644 blob {}\trefs\n", refs_blob.trim());
+    tree.push_str(&format!("100644 blob {}\tstash\n", stash_blob.trim()));
+    // Unmerged entries cannot be written as a tree
+    if let Ok(index) = run(&["write-tree"], Some(repo_path)) {
+        tree.push_str(&format!("040000 tree {}\tindex\n", index.trim()));
+    }
+    tree.push_str(&format!(
+        "040000 tree {}\tworktree\n",
+        worktree_tree(repo_path)?
+    ));
+    let tree = run_with_stdin(&["mktree"], Some(repo_path), &tree)?;
+
+    let mut message = format!("{}\n\n{}{}\n", description, KIND_TRAILER, kind.encode());
+    if let Some(head) = head_ref
+        .as_deref()
+        .map(|r| r.strip_prefix("refs/heads/").unwrap_or(r).to_string())
+        .or(head_commit.clone())
+    {
+        message.push_str(&format!("Openisl-Head: {}\n", head));
+    }
+
+    let previous = rev_parse(repo_path, OPLOG_REF);
+    // First parents chain the operations. The chain starts at an empty root
+    // so walking it never wanders into the repository's own history.
+    let first_parent = match previous {
+        Some(ref previous) => previous.clone(),
+        None => {
+            let empty_tree = run_with_stdin(&["mktree"], Some(repo_path), "")?;
+            run_with_env(
+                &[
+                    "commit-tree",
+                    empty_tree.trim(),
+                    "-m",
+                    "openisl operation log",
+                ],
+                Some(repo_path),
+                &snapshot_identity(),
+            )?
+            .trim()
+            .to_string()
+        }
+    };
+    let mut parents = vec![first_parent];
+    let mut kept = HashSet::new();
+    for oid in head_commit
+        .iter()
+        .map(String::as_str)
+        .chain(refs.iter().map(|(_, oid)| oid.as_str()))
+        .chain(stash.lines().filter_map(|line| line.split(' ').next()))
+        .filter_map(|oid| rev_parse(repo_path, &format!("{}^{{commit}}", oid)))
+    {
+        if kept.insert(oid.clone()) {
+            parents.push(oid);
+        }
+    }
+
+    let tree = tree.trim().to_string();
+    let mut args = vec!["commit-tree", tree.as_str(), "-m", message.as_str()];
+    for parent in &parents {
+        args.push("-p");
+        args.push(parent);
+    }
+    let commit = run_with_env(&args, Some(repo_path), &snapshot_identity())
+        .context("Failed to record operation")?;
+    let commit = commit.trim().to_string();
+
+    let update_message = format!("openisl: {}", description);
+    let mut update = vec![
+        "update-ref",
+        "-m",
+        update_message.as_str(),
+        OPLOG_REF,
+        commit.as_str(),
+    ];
+    // Guard against a concurrent openISL process recording at the same time
+    let zero = "0".repeat(40);
+    update.push(previous.as_deref().unwrap_or(&zero));
+    run(&update, Some(repo_path)).context("Failed to update the operation log")?;
+
+    Ok(commit)
+}
+
+/// Write the working tree, untracked files included, through a scratch index
+fn worktree_tree(repo_path: &Path) -> Result<String> {
+    let scratch = tempfile::tempdir()?;
+    let scratch_index = scratch.path().join("index");
+    let index = run(&["rev-parse", "--git-path", "index"], Some(repo_path))?;
+    let index = repo_path.join(index.trim());
+    if index.exists() {
+        std::fs::copy(&index, &scratch_index)?;
+    }
+
+    let scratch_index = scratch_index.to_string_lossy().to_string();
+    let env = HashMap::from([("GIT_INDEX_FILE", scratch_index.as_str())]);
+    run_with_env(&["add", "--all"], Some(repo_path), &env)
+        .context("Failed to snapshot the working tree")?;
+    Ok(run_with_env(&["write-tree"], Some(repo_path), &env)?
+        .trim()
+        .to_string())
+}
+
+/// Put refs, `HEAD`, the working tree and the index back the way operation
+/// `id` found them. Untracked files the snapshot doesn't know about are left
+/// alone. Ref updates are logged in the reflog as `openisl: <description>`.
+fn restore_snapshot(repo_path: &Path, id: &str, description: &str) -> Result<()> {
+    let reflog_message = format!("openisl: {}", description);
+    let refs_blob = run(
+        &["cat-file", "blob", &format!("{}:refs", id)],
+        Some(repo_path),
+    )
+    .with_context(|| format!("Operation {} has no ref snapshot", id))?;
+
+    let mut lines = refs_blob.lines();
+    let head = lines
+        .next()
+        .and_then(|line| line.strip_prefix("HEAD "))
+        .with_context(|| format!("Operation {} has no HEAD snapshot", id))?;
+    let saved: HashMap<&str, &str> = lines
+        .filter_map(|line| line.split_once(' '))
+        .map(|(oid, name)| (name, oid))
+        .collect();
+
+    let mut transaction = String::new();
+    for (name, _) in snapshot_refs(repo_path)? {
+        if !saved.contains_key(name.as_str()) {
+            transaction.push_str(&format!("delete {}\n", name));
+        }
+    }
+    for (name, oid) in &saved {
+        transaction.push_str(&format!("update {} {}\n", name, oid));
+    }
+    if !transaction.is_empty() {
+        run_with_stdin(
+            &["update-ref", "-m", &reflog_message, "--stdin"],
+            Some(repo_path),
+            &transaction,
+        )
+        .context("Failed to restore refs")?;
+    }
+
+    match head.strip_prefix("ref: ") {
+        Some(name) => run(
+            &["symbolic-ref", "-m", &reflog_message, "HEAD", name],
+            Some(repo_path),
+        )?,
+        None => run(
+            &[
+                "update-ref",
+                "-m",
+                &reflog_message,
+                "--no-deref",
+                "HEAD",
+                head,
+            ],
+            Some(repo_path),
+        )?,
+    };
+
+    run(
+        &["read-tree", "--reset", "-u", &format!("{}:worktree", id)],
+        Some(repo_path),
+    )
+    .context("Failed to restore the working tree")?;
+
+    let index = format!("{}:index", id);
+    if rev_parse(repo_path, &index).is_some() {
+        run(&["read-tree", &index], Some(repo_path))?;
+    } else if rev_parse(repo_path, "HEAD").is_some() {
+        run(&["read-tree", "HEAD"], Some(repo_path))?;
+    } else {
+        run(&["read-tree", "--empty"], Some(repo_path))?;
+    }
+
+    // Older snapshots did not capture the stash
+    let stash = format!("{}:stash", id);
+    if rev_parse(repo_path, &stash).is_some() {
+        let saved = run(&["cat-file", "blob", &stash], Some(repo_path))?;
+        restore_stash(repo_path, &saved)?;
+    }
+    Ok(())
+}
+
+/// Rebuild the stash stack from entries saved by `stash_entries`
+fn restore_stash(repo_path: &Path, saved: &str) -> Result<()> {
+    if stash_entries(repo_path)? == saved {
+        return Ok(());
+    }
+    if rev_parse(repo_path, STASH_REF).is_some() {
+        run(&["update-ref", "-d", STASH_REF], Some(repo_path))?;
+    }
+    // Oldest first, so the newest entry ends up as `stash@{0}`
+    for (oid, message) in saved.lines().rev().filter_map(|line| line.split_once(' ')) {
+        run(
+            &[
+                "update-ref",
+                "--create-reflog",
+                "-m",
+                message,
+                STASH_REF,
+                oid,
+            ],
+            Some(repo_path),
+        )
+        .context("Failed to restore the stash")?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{commit_file, create_test_repo, git};
+    use std::fs;
+
+    fn head_summary(path: &Path) -> String {
+        run(&["log", "-1", "--format=%s"], Some(path))
+            .unwrap()
+            .trim()
+            .to_string()
+    }
+
+    #[test]
+    fn test_undo_and_redo_commit_keep_uncommitted_work() {
+        let dir = create_test_repo();
+        let path = dir.path();
+        commit_file(path, "a.txt", "one\n", "First");
+
+        // Work in progress that a plain `reset --hard` would throw away
+        fs::write(path.join("a.txt"), "two\n").unwrap();
+        git(path, &["add", "a.txt"]);
+        fs::write(path.join("a.txt"), "three\n").unwrap();
+        fs::write(path.join("untracked.txt"), "scratch\n").unwrap();
+
+        record_operation(path, "commit Second").unwrap();
+        git(path, &["commit", "-am", "Second"]);
+        assert_eq!(head_summary(path), "Second");
+
+        let undone = undo_operation(path, None).unwrap();
+        assert_eq!(undone.description, "commit Second");
+        assert_eq!(head_summary(path), "First");
+        assert_eq!(fs::read_to_string(path.join("a.txt")).unwrap(), "three\n");
+        assert_eq!(
+            fs::read_to_string(path.join("untracked.txt")).unwrap(),
+            "scratch\n"
+        );
+        // The staged half is staged again
+        let staged = run(&["diff", "--cached"], Some(path)).unwrap();
+        assert!(staged.contains("+two"));
+
+        redo_operation(path).unwrap();
+        assert_eq!(head_summary(path), "Second");
+        assert!(redo_operation(path).is_err());
+
+        let operations = get_operations(path).unwrap();
+        assert_eq!(operations.len(), 3);
+        assert_eq!(operations[0].description, "redo commit Second");
+        assert_eq!(operations[2].head.as_deref(), Some("main"));
+
+        // Private refs stay out of `--all`
+        let all = run(&["rev-list", EXCLUDE_PRIVATE_REFS, "--all"], Some(path)).unwrap();
+        assert_eq!(all.lines().count(), 2);
+    }
+
+    #[test]
+    fn test_undo_restores_branches_and_head() {
+        let dir = create_test_repo();
+        let path = dir.path();
+        commit_file(path, "a.txt", "one\n", "First");
+
+        record_operation(path, "branch feature").unwrap();
+        git(path, &["branch", "feature"]);
+        record_operation(path, "checkout feature").unwrap();
+        git(path, &["checkout", "feature"]);
+        record_operation(path, "commit On feature").unwrap();
+        commit_file(path, "b.txt", "b\n", "On feature");
+
+        // Undo the commit, then jump back past the checkout and the branch
+        undo_operation(path, None).unwrap();
+        assert!(!path.join("b.txt").exists());
+        assert_eq!(head_summary(path), "First");
+
+        let branch_op = get_operations(path)
+            .unwrap()
+            .into_iter()
+            .find(|op| op.description == "branch feature")
+            .unwrap();
+        undo_operation(path, Some(&branch_op.id[..12])).unwrap();
+        let head = run(&["symbolic-ref", "--short", "HEAD"], Some(path)).unwrap();
+        assert_eq!(head.trim(), "main");
+        assert!(rev_parse(path, "refs/heads/feature").is_none());
+        assert!(matches!(
+            get_operations(path).unwrap()[0].kind,
+            OperationKind::Restore(_)
+        ));
+        // A restore is a new operation, which clears the redo stack
+        assert!(redo_operation(path).is_err());
+    }
+
+    #[test]
+    fn test_undo_restores_the_stash() {
+        let dir = create_test_repo();
+        let path = dir.path();
+        commit_file(path, "a.txt", "one\n", "First");
+        let stashes = || {
+            let list = run(&["stash", "list", "--format=%gs"], Some(path)).unwrap();
+            list.trim().to_string()
+        };
+
+        fs::write(path.join("a.txt"), "two\n").unwrap();
+        git(path, &["stash", "push", "-m", "older"]);
+        fs::write(path.join("a.txt"), "three\n").unwrap();
+        record_operation(path, "stash push").unwrap();
+        git(path, &["stash", "push", "-m", "newer"]);
+        record_operation(path, "stash drop").unwrap();
+        git(path, &["stash", "drop", "stash@{1}"]);
+        assert_eq!(stashes().lines().count(), 1);
+
+        undo_operation(path, None).unwrap();
+        assert_eq!(stashes(), "On main: newer\nOn main: older");
+
+        // Undoing the push takes its entry off again and restores the change
+        undo_operation(path, None).unwrap();
+        assert_eq!(stashes(), "On main: older");
+        assert_eq!(fs::read_to_string(path.join("a.txt")).unwrap(), "three\n");
+        git(path, &["checkout", "--", "a.txt"]);
+        git(path, &["stash", "pop"]);
+        assert_eq!(fs::read_to_string(path.join("a.txt")).unwrap(), "two\n");
+    }
+
+    #[test]
+    fn test_empty_log() {
+        let dir = create_test_repo();
+        assert!(get_operations(dir.path()).unwrap().is_empty());
+        assert!(undo_operation(dir.path(), None).is_err());
+        assert!(redo_operation(dir.path()).is_err());
+    }
+}
@@ -0,0 +1,133 @@
+use crate::command::run;
+use crate::error::GitError;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use std::path::Path;
+
+/// One reflog entry: where a ref pointed after an update, and why it moved
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReflogEntry {
+    /// Commit the ref pointed to after the update
+    pub hash: String,
+    /// Selector that names this entry, e.g. `HEAD@{2}` or `main@{0}`
+    pub selector: String,
+    pub timestamp: DateTime<Utc>,
+    /// What moved the ref: `commit`, `commit (amend)`, `checkout`, `reset`, ...
+    pub action: String,
+    /// The rest of the reflog message, e.g. `moving from main to feature`
+    pub description: String,
+}
+
+/// Reflog of `reference` (`HEAD` when `None`), newest first
+pub fn get_reflog(
+    repo_path: &Path,
+    reference: Option<&str>,
+    max_count: Option<usize>,
+) -> Result<Vec<ReflogEntry>> {
+    let reference = reference.unwrap_or("HEAD");
+    let max_count = max_count.map(|n| format!("--max-count={}", n));
+
+    // With --date=unix the selector carries the entry's own timestamp rather
+    // than the commit's; the entry index is its position in the walk
+    let mut args = vec![
+        "log",
+        "--walk-reflogs",
+        "--date=unix",
+        "--format=%x1e%H%x00%gd%x00%gs",
+    ];
+    args.extend(max_count.as_deref());
+    args.extend([reference, "--"]);
+    let output = run(&args, Some(repo_path))
+        .with_context(|| format!("Failed to read the reflog of {}", reference))?;
+
+    parse_reflog(&output)
+}
+
+fn parse_reflog(output: &str) -> Result<Vec<ReflogEntry>> {
+    output
+        .split('\x1e')
+        .filter(|record| !record.trim().is_empty())
+        .enumerate()
+        .map(|(index, record)| {
+            let malformed = || GitError::ParseError(format!("reflog entry '{}'", record.trim()));
+            let mut fields = record.trim_end_matches('\n').splitn(3, '\0');
+            let (Some(hash), Some(selector), Some(message)) =
+                (fields.next(), fields.next(), fields.next())
+            else {
+                return Err(malformed().into());
+            };
+            let (name, seconds) = selector
+                .strip_suffix('}')
+                .and_then(|s| s.rsplit_once("@{"))
+                .ok_or_else(malformed)?;
+
+            let (action, description) = match message.split_once(": ") {
+                Some((action, description)) => (action, description),
+                None => (message, ""),
+            };
+
+            Ok(ReflogEntry {
+                hash: hash.to_string(),
+                selector: format!("{}@{{{}}}", name, index),
+                timestamp: DateTime::from_timestamp(seconds.parse().unwrap_or(0), 0)
+                    .unwrap_or_default(),
+                action: action.to_string(),
+                description: description.to_string(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{create_test_repo, git};
+    use std::fs;
+
+    #[test]
+    fn test_parse_reflog() {
+        let output = [
+            "\x1ebbbb\0HEAD@{1700000100}\0checkout: moving from main to feature\n",
+            "\x1eaaaa\0HEAD@{1700000000}\0commit (initial): First: with colon\n",
+            "\x1eaaaa\0HEAD@{1699999999}\0\n",
+        ]
+        .concat();
+        let entries = parse_reflog(&output).unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].selector, "HEAD@{0}");
+        assert_eq!(entries[0].action, "checkout");
+        assert_eq!(entries[0].description, "moving from main to feature");
+        assert_eq!(entries[0].timestamp.timestamp(), 1_700_000_100);
+        assert_eq!(entries[1].action, "commit (initial)");
+        assert_eq!(entries[1].description, "First: with colon");
+        assert_eq!(entries[2].selector, "HEAD@{2}");
+        assert_eq!(entries[2].action, "");
+
+        assert!(parse_reflog("\x1eaaaa\0HEAD\0commit: x").is_err());
+    }
+
+    #[test]
+    fn test_get_reflog_for_head_and_branch() {
+        let dir = create_test_repo();
+        let path = dir.path();
+        fs::write(path.join("a.txt"), "a\n").unwrap();
+        git(path, &["add", "a.txt"]);
+        git(path, &["commit", "-m", "First"]);
+        git(path, &["checkout", "-b", "feature"]);
+        git(path, &["commit", "--allow-empty", "-m", "Second"]);
+
+        let head = get_reflog(path, None, None).unwrap();
+        assert_eq!(head.len(), 3);
+        assert_eq!(head[0].action, "commit");
+        assert_eq!(head[0].description, "Second");
+        assert_eq!(head[1].action, "checkout");
+        assert_eq!(head[2].action, "commit (initial)");
+
+        let main = get_reflog(path, Some("main"), Some(1)).unwrap();
+        assert_eq!(main.len(), 1);
+        assert_eq!(main[0].selector, "main@{0}");
+        assert_eq!(main[0].hash, head[1].hash);
+
+        assert!(get_reflog(path, Some("missing"), None).is_err());
+    }
+}
@@ -0,0 +1,279 @@
+use crate::command::{run, run_with_env};
+use crate::models::abbreviate_hash;
+use anyhow::{bail, Context, Result};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// What the repository is in the middle of
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RepoState {
+    Clean,
+    /// `step` of `total` (1-based) onto `onto`, rebasing `branch` (`None`
+    /// when detached)
+    Rebasing {
+        step: usize,
+        total: usize,
+        onto: String,
+        branch: Option<String>,
+    },
+    /// Applying mailbox patches with `git am`
+    ApplyingPatches {
+        step: usize,
+        total: usize,
+    },
+    /// Merging `head` into the current branch
+    Merging {
+        head: String,
+    },
+    CherryPicking {
+        commit: String,
+    },
+    Reverting {
+        commit: String,
+    },
+    /// Bisecting, started from `original` (a branch or commit)
+    Bisecting {
+        original: String,
+    },
+}
+
+impl RepoState {
+    /// The git command of the operation, which also continues, aborts or
+    /// skips it
+    pub fn operation(&self) -> Option<&'static str> {
+        Some(match self {
+            RepoState::Clean => return None,
+            RepoState::Rebasing { .. } => "rebase",
+            RepoState::ApplyingPatches { .. } => "am",
+            RepoState::Merging { .. } => "merge",
+            RepoState::CherryPicking { .. } => "cherry-pick",
+            RepoState::Reverting { .. } => "revert",
+            RepoState::Bisecting { .. } => "bisect",
+        })
+    }
+
+    pub fn is_clean(&self) -> bool {
+        *self == RepoState::Clean
+    }
+
+    /// Whether `continue_operation` applies (everything but bisect)
+    pub fn can_continue(&self) -> bool {
+        !matches!(self, RepoState::Clean | RepoState::Bisecting { .. })
+    }
+
+    /// Whether `skip_operation` applies (a merge has nothing to skip)
+    pub fn can_skip(&self) -> bool {
+        !matches!(self, RepoState::Clean | RepoState::Merging { .. })
+    }
+}
+
+impl fmt::Display for RepoState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RepoState::Clean => write!(f, "No operation in progress"),
+            RepoState::Rebasing {
+                step,
+                total,
+                onto,
+                branch,
+            } => {
+                write!(f, "Rebasing ")?;
+                if let Some(branch) = branch {
+                    write!(f, "{} ", branch)?;
+                }
+                write!(
+                    f,
+                    "onto {} (step {}/{})",
+                    abbreviate_hash(onto),
+                    step,
+                    total
+                )
+            }
+            RepoState::ApplyingPatches { step, total } => {
+                write!(f, "Applying patches (patch {}/{})", step, total)
+            }
+            RepoState::Merging { head } => write!(f, "Merging {}", abbreviate_hash(head)),
+            RepoState::CherryPicking { commit } => {
+                write!(f, "Cherry-picking {}", abbreviate_hash(commit))
+            }
+            RepoState::Reverting { commit } => write!(f, "Reverting {}", abbreviate_hash(commit)),
+            RepoState::Bisecting { original } => {
+                write!(f, "Bisecting (started from {})", abbreviate_hash(original))
+            }
+        }
+    }
+}
+
+fn read(dir: &Path, name: &str) -> Option<String> {
+    let text = fs::read_to_string(dir.join(name)).ok()?;
+    Some(text.trim().to_string()).filter(|text| !text.is_empty())
+}
+
+fn read_number(dir: &Path, name: &str) -> usize {
+    read(dir, name).and_then(|n| n.parse().ok()).unwrap_or(0)
+}
+
+fn git_dir(repo_path: &Path) -> Result<PathBuf> {
+    let dir = run(&["rev-parse", "--absolute-git-dir"], Some(repo_path))?;
+    Ok(PathBuf::from(dir.trim()))
+}
+
+/// The operation in progress, read from the state files in the git directory
+pub fn repo_state(repo_path: &Path) -> Result<RepoState> {
+    let git_dir = git_dir(repo_path)?;
+
+    let merge_dir = git_dir.join("rebase-merge");
+    if merge_dir.is_dir() {
+        return Ok(RepoState::Rebasing {
+            step: read_number(&merge_dir, "msgnum"),
+            total: read_number(&merge_dir, "end"),
+            onto: read(&merge_dir, "onto").unwrap_or_default(),
+            branch: read(&merge_dir, "head-name")
+                .and_then(|name| name.strip_prefix("refs/heads/").map(str::to_string)),
+        });
+    }
+    let apply_dir = git_dir.join("rebase-apply");
+    if apply_dir.is_dir() {
+        let (step, total) = (
+            read_number(&apply_dir, "next"),
+            read_number(&apply_dir, "last"),
+        );
+        // `git am` marks its directory; the apply backend of rebase does not
+        if apply_dir.join("applying").exists() {
+            return Ok(RepoState::ApplyingPatches { step, total });
+        }
+        return Ok(RepoState::Rebasing {
+            step,
+            total,
+            onto: read(&apply_dir, "onto").unwrap_or_default(),
+            branch: read(&apply_dir, "head-name")
+                .and_then(|name| name.strip_prefix("refs/heads/").map(str::to_string)),
+        });
+    }
+    if let Some(head) = read(&git_dir, "MERGE_HEAD") {
+        let head = head.lines().next().unwrap_or_default().to_string();
+        return Ok(RepoState::Merging { head });
+    }
+    if let Some(commit) = read(&git_dir, "CHERRY_PICK_HEAD") {
+        return Ok(RepoState::CherryPicking { commit });
+    }
+    if let Some(commit) = read(&git_dir, "REVERT_HEAD") {
+        return Ok(RepoState::Reverting { commit });
+    }
+    if let Some(original) = read(&git_dir, "BISECT_START") {
+        return Ok(RepoState::Bisecting { original });
+    }
+    Ok(RepoState::Clean)
+}
+
+/// Run `git <command> <flag>` for the operation in progress. Editors are
+/// skipped, so commits keep the message git prepared.
+fn dispatch(repo_path: &Path, state: &RepoState, flag: &str) -> Result<String> {
+    let Some(command) = state.operation() else {
+        bail!("No operation in progress");
+    };
+    let env = HashMap::from([("GIT_EDITOR", "true")]);
+    run_with_env(&[command, flag], Some(repo_path), &env)
+        .with_context(|| format!("Failed to {} the {}", &flag[2..], command))
+}
+
+/// Continue the operation in progress once its conflicts are resolved
+pub fn continue_operation(repo_path: &Path) -> Result<String> {
+    let state = repo_state(repo_path)?;
+    if let RepoState::Bisecting { .. } = state {
+        bail!("A bisect has nothing to continue; mark the revision good or bad");
+    }
+    dispatch(repo_path, &state, "--continue")
+}
+
+/// Abort the operation in progress, returning to where it started. A bisect
+/// is reset.
+pub fn abort_operation(repo_path: &Path) -> Result<String> {
+    let state = repo_state(repo_path)?;
+    if let RepoState::Bisecting { .. } = state {
+        return run(&["bisect", "reset"], Some(repo_path)).context("Failed to reset the bisect");
+    }
+    dispatch(repo_path, &state, "--abort")
+}
+
+/// Skip the commit (or bisect revision) the operation stopped at
+pub fn skip_operation(repo_path: &Path) -> Result<String> {
+    let state = repo_state(repo_path)?;
+    match state {
+        RepoState::Merging { .. } => bail!("A merge cannot be skipped; abort it instead"),
+        RepoState::Bisecting { .. } => {
+            run(&["bisect", "skip"], Some(repo_path)).context("Failed to skip the revision")
+        }
+        _ => dispatch(repo_path, &state, "--skip"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{create_test_repo, git};
+
+    #[test]
+    fn test_repo_state_and_dispatch() {
+        let dir = create_test_repo();
+        let path = dir.path();
+        fs::write(path.join("f.txt"), "base\n").unwrap();
+        git(path, &["add", "f.txt"]);
+        git(path, &["commit", "-m", "Base"]);
+        git(path, &["checkout", "-b", "topic"]);
+        fs::write(path.join("f.txt"), "topic\n").unwrap();
+        git(path, &["commit", "-am", "Topic"]);
+        git(path, &["checkout", "main"]);
+        fs::write(path.join("f.txt"), "main\n").unwrap();
+        git(path, &["commit", "-am", "Main"]);
+        let main = git(path, &["rev-parse", "HEAD"]);
+        let topic = git(path, &["rev-parse", "topic"]);
+
+        assert_eq!(repo_state(path).unwrap(), RepoState::Clean);
+        assert!(continue_operation(path).is_err());
+
+        assert!(run(&["merge", "topic"], Some(path)).is_err());
+        assert_eq!(
+            repo_state(path).unwrap(),
+            RepoState::Merging {
+                head: topic.clone()
+            }
+        );
+        assert!(skip_operation(path).is_err());
+        abort_operation(path).unwrap();
+        assert_eq!(repo_state(path).unwrap(), RepoState::Clean);
+
+        assert!(run(&["cherry-pick", "topic"], Some(path)).is_err());
+        assert_eq!(
+            repo_state(path).unwrap(),
+            RepoState::CherryPicking {
+                commit: topic.clone()
+            }
+        );
+        // Unresolved conflicts stop the continue
+        assert!(continue_operation(path).is_err());
+        skip_operation(path).unwrap();
+        assert_eq!(repo_state(path).unwrap(), RepoState::Clean);
+
+        git(path, &["checkout", "topic"]);
+        assert!(run(&["rebase", "main"], Some(path)).is_err());
+        let state = repo_state(path).unwrap();
+        assert_eq!(
+            state,
+            RepoState::Rebasing {
+                step: 1,
+                total: 1,
+                onto: main,
+                branch: Some("topic".to_string()),
+            }
+        );
+        assert!(state.to_string().starts_with("Rebasing topic onto"));
+        fs::write(path.join("f.txt"), "both\n").unwrap();
+        git(path, &["add", "f.txt"]);
+        continue_operation(path).unwrap();
+        assert_eq!(repo_state(path).unwrap(), RepoState::Clean);
+        assert_eq!(git(path, &["log", "-1", "--format=%s"]), "Topic");
+    }
+}
@@ -1,5 +1,6 @@
 use crate::command::{run, run_success};
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
+use std::fs;
 use std::path::Path;
 
 /// List files that currently have merge conflicts.
@@ -21,3 +22,305 @@ pub fn mark_resolved(repo_path: &Path, paths: &[&str]) -> Result<()> {
     run_success(&args, Some(repo_path)).with_context(|| "Failed to mark files as resolved")?;
     Ok(())
 }
+
+/// One side of a conflict region
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConflictSide {
+    /// Text after the marker, e.g. `HEAD` or `abc1234 (Fix parser)`
+    pub label: String,
+    /// Lines with their line endings
+    pub lines: Vec<String>,
+}
+
+impl ConflictSide {
+    fn text(&self) -> String {
+        self.lines.concat()
+    }
+}
+
+/// How a conflict region is resolved
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Resolution {
+    Ours,
+    Theirs,
+    /// Ours followed by theirs
+    Both,
+    /// The common ancestor's text; needs a base section
+    Base,
+    /// Text given by the user
+    Manual(String),
+}
+
+/// Lines between `<<<<<<<` and `>>>>>>>` markers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConflictRegion {
+    pub ours: ConflictSide,
+    /// Present with the `diff3` and `zdiff3` conflict styles
+    pub base: Option<ConflictSide>,
+    pub theirs: ConflictSide,
+    pub resolution: Option<Resolution>,
+}
+
+impl ConflictRegion {
+    /// The text `resolution` gives, or `None` for `Base` without a base section
+    pub fn text_for(&self, resolution: &Resolution) -> Option<String> {
+        Some(match resolution {
+            Resolution::Ours => self.ours.text(),
+            Resolution::Theirs => self.theirs.text(),
+            Resolution::Both => self.ours.text() + &self.theirs.text(),
+            Resolution::Base => self.base.as_ref()?.text(),
+            Resolution::Manual(text) => text.clone(),
+        })
+    }
+
+    /// The resolved text, once a resolution is chosen
+    pub fn resolved_text(&self) -> Option<String> {
+        self.text_for(self.resolution.as_ref()?)
+    }
+}
+
+/// Part of a conflicted file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConflictChunk {
+    /// Lines outside any conflict, with their line endings
+    Common(Vec<String>),
+    Conflict(ConflictRegion),
+}
+
+/// A file with conflict markers, split into chunks
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConflictFile {
+    pub path: String,
+    pub chunks: Vec<ConflictChunk>,
+}
+
+/// Marker character and length when `line` is a conflict marker
+fn marker(line: &str) -> Option<(char, usize)> {
+    let line = line.trim_end_matches(['\n', '\r']);
+    let first = line.chars().next().filter(|c| "<|=>".contains(*c))?;
+    let size = line.chars().take_while(|c| *c == first).count();
+    let rest = &line[size..];
+    (size >= 7 && (rest.is_empty() || rest.starts_with(' '))).then_some((first, size))
+}
+
+fn marker_label(line: &str, size: usize) -> String {
+    line.trim_end_matches(['\n', '\r'])[size..]
+        .trim()
+        .to_string()
+}
+
+impl ConflictFile {
+    /// Parse `content` of `path` into chunks. Marker lines of any length from
+    /// 7 on are recognised (see the `conflict-marker-size` attribute), as long
+    /// as the markers of a region match.
+    pub fn parse(path: &str, content: &str) -> Result<Self> {
+        enum Section {
+            Ours,
+            Base,
+            Theirs,
+        }
+        let mut chunks = Vec::new();
+        let mut common: Vec<String> = Vec::new();
+        // Region being read: marker size, section, and the sides so far
+        let mut open: Option<(usize, Section, ConflictRegion)> = None;
+
+        for (number, line) in content.split_inclusive('\n').enumerate() {
+            let Some((region_size, section, region)) = open.as_mut() else {
+                match marker(line) {
+                    Some(('<', size)) => {
+                        if !common.is_empty() {
+                            chunks.push(ConflictChunk::Common(std::mem::take(&mut common)));
+                        }
+                        let side = |label: String| ConflictSide {
+                            label,
+                            lines: Vec::new(),
+                        };
+                        let region = ConflictRegion {
+                            ours: side(marker_label(line, size)),
+                            base: None,
+                            theirs: side(String::new()),
+                            resolution: None,
+                        };
+                        open = Some((size, Section::Ours, region));
+                    }
+                    _ => common.push(line.to_string()),
+                }
+                continue;
+            };
+            match (marker(line), &*section) {
+                (Some(('|', size)), Section::Ours) if size == *region_size => {
+                    region.base = Some(ConflictSide {
+                        label: marker_label(line, size),
+                        lines: Vec::new(),
+                    });
+                    *section = Section::Base;
+                }
+                (Some(('=', size)), Section::Ours | Section::Base) if size == *region_size => {
+                    *section = Section::Theirs;
+                }
+                (Some(('>', size)), Section::Theirs) if size == *region_size => {
+                    region.theirs.label = marker_label(line, size);
+                    let (_, _, region) = open.take().expect("region is open");
+                    chunks.push(ConflictChunk::Conflict(region));
+                }
+                (Some(('<' | '|' | '=' | '>', size)), _) if size == *region_size => {
+                    bail!(
+                        "{}: unexpected conflict marker on line {}",
+                        path,
+                        number + 1
+                    );
+                }
+                _ => {
+                    let side = match section {
+                        Section::Ours => &mut region.ours,
+                        Section::Base => region.base.as_mut().expect("base section is open"),
+                        Section::Theirs => &mut region.theirs,
+                    };
+                    side.lines.push(line.to_string());
+                }
+            }
+        }
+        if open.is_some() {
+            bail!("{}: conflict region is not closed", path);
+        }
+        if !common.is_empty() {
+            chunks.push(ConflictChunk::Common(common));
+        }
+        Ok(Self {
+            path: path.to_string(),
+            chunks,
+        })
+    }
+
+    pub fn regions(&self) -> impl Iterator<Item = &ConflictRegion> {
+        self.chunks.iter().filter_map(|chunk| match chunk {
+            ConflictChunk::Conflict(region) => Some(region),
+            ConflictChunk::Common(_) => None,
+        })
+    }
+
+    pub fn region_mut(&mut self, index: usize) -> Option<&mut ConflictRegion> {
+        self.chunks
+            .iter_mut()
+            .filter_map(|chunk| match chunk {
+                ConflictChunk::Conflict(region) => Some(region),
+                ConflictChunk::Common(_) => None,
+            })
+            .nth(index)
+    }
+
+    pub fn region_count(&self) -> usize {
+        self.regions().count()
+    }
+
+    pub fn unresolved_count(&self) -> usize {
+        self.regions()
+            .filter(|r| r.resolved_text().is_none())
+            .count()
+    }
+
+    /// Resolve region `index`; a `Base` resolution needs a base section
+    pub fn resolve(&mut self, index: usize, resolution: Resolution) -> Result<()> {
+        let path = self.path.clone();
+        let Some(region) = self.region_mut(index) else {
+            bail!("{} has no conflict region {}", path, index + 1);
+        };
+        if region.text_for(&resolution).is_none() {
+            bail!(
+                "{} has no base version; set merge.conflictStyle to diff3 or zdiff3",
+                path
+            );
+        }
+        region.resolution = Some(resolution);
+        Ok(())
+    }
+
+    /// The file with every region resolved, or `None` while some are not
+    pub fn resolved_content(&self) -> Option<String> {
+        let mut content = String::new();
+        for chunk in &self.chunks {
+            match chunk {
+                ConflictChunk::Common(lines) => content.extend(lines.iter().map(String::as_str)),
+                ConflictChunk::Conflict(region) => content.push_str(&region.resolved_text()?),
+            }
+        }
+        Some(content)
+    }
+}
+
+/// Read and parse the conflicted file at `path` in the working tree
+pub fn read_conflict_file(repo_path: &Path, path: &str) -> Result<ConflictFile> {
+    let bytes =
+        fs::read(repo_path.join(path)).with_context(|| format!("Failed to read '{}'", path))?;
+    let content = String::from_utf8(bytes)
+        .map_err(|_| anyhow::anyhow!("'{}' is not a text file; resolve it by hand", path))?;
+    ConflictFile::parse(path, &content)
+}
+
+/// Write `file` with its regions resolved to the working tree and mark it
+/// resolved
+pub fn write_resolution(repo_path: &Path, file: &ConflictFile) -> Result<()> {
+    let Some(content) = file.resolved_content() else {
+        bail!(
+            "{} has {} unresolved conflict(s)",
+            file.path,
+            file.unresolved_count()
+        );
+    };
+    fs::write(repo_path.join(&file.path), content)
+        .with_context(|| format!("Failed to write '{}'", file.path))?;
+    mark_resolved(repo_path, &[&file.path])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_conflict_styles() {
+        let merge = "a\n<<<<<<< HEAD\nours\n=======\ntheirs\n>>>>>>> topic\nz\n";
+        let file = ConflictFile::parse("f.txt", merge).unwrap();
+        assert_eq!(file.chunks.len(), 3);
+        let region = file.regions().next().unwrap();
+        assert_eq!(region.ours.label, "HEAD");
+        assert_eq!(region.theirs.label, "topic");
+        assert_eq!(region.ours.lines, ["ours\n"]);
+        assert!(region.base.is_none());
+
+        // diff3 and zdiff3 add a base section; an empty side is fine too
+        let diff3 = "<<<<<<< HEAD\n||||||| base\nold\r\n=======\nnew\n>>>>>>> abc1234 (Fix)\n";
+        let mut file = ConflictFile::parse("f.txt", diff3).unwrap();
+        let region = file.regions().next().unwrap();
+        assert!(region.ours.lines.is_empty());
+        assert_eq!(region.base.as_ref().unwrap().lines, ["old\r\n"]);
+        assert_eq!(region.theirs.label, "abc1234 (Fix)");
+        assert_eq!(file.resolved_content(), None);
+        file.resolve(0, Resolution::Base).unwrap();
+        assert_eq!(file.resolved_content().unwrap(), "old\r\n");
+
+        // Markers of another size are content
+        let nested = "<<<<<<<< ours\n<<<<<<< inner\n========\n>>>>>>>> theirs\n";
+        let file = ConflictFile::parse("f.txt", nested).unwrap();
+        assert_eq!(
+            file.regions().next().unwrap().ours.lines,
+            ["<<<<<<< inner\n"]
+        );
+
+        assert!(ConflictFile::parse("f.txt", "<<<<<<< HEAD\nours\n").is_err());
+    }
+
+    #[test]
+    fn test_resolve_regions() {
+        let content =
+            "<<<<<<< HEAD\n1\n=======\n2\n>>>>>>> b\nmid\n<<<<<<< HEAD\n3\n=======\n4\n>>>>>>> b\n";
+        let mut file = ConflictFile::parse("f.txt", content).unwrap();
+        assert_eq!(file.region_count(), 2);
+        assert!(file.resolve(0, Resolution::Base).is_err());
+        file.resolve(0, Resolution::Both).unwrap();
+        assert_eq!(file.unresolved_count(), 1);
+        file.resolve(1, Resolution::Manual("34\n".to_string()))
+            .unwrap();
+        assert_eq!(file.resolved_content().unwrap(), "1\n2\nmid\n34\n");
+        assert!(file.resolve(2, Resolution::Ours).is_err());
+    }
+}
@@ -0,0 +1,711 @@
+use crate::command::{run, run_with_stdin};
+use crate::error::GitError;
+use crate::models::Commit;
+use crate::operations::log::{get_commits_by_hash, LogQuery};
+use crate::operations::oplog::EXCLUDE_PRIVATE_REFS;
+use anyhow::{bail, Result};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+/// A parsed revset expression
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Revset {
+    /// A revision git can resolve: branch, tag, hash, `HEAD~2`, ...
+    Symbol(String),
+    /// `all()`: every commit reachable from any ref
+    All,
+    /// `draft()`: commits not yet on any remote-tracking branch
+    Draft,
+    /// `heads(x)`: members of `x` with no children in `x`; `heads()` is `heads(all())`
+    Heads(Box<Revset>),
+    /// `ancestors(x)` or `::x`, including `x` itself
+    Ancestors(Box<Revset>),
+    /// `descendants(x)` or `x::`, including `x` itself
+    Descendants(Box<Revset>),
+    /// `author(pattern)`: case-insensitive substring of author name or email
+    Author(String),
+    /// `desc(pattern)`: case-insensitive substring of the commit message
+    Description(String),
+    /// `file(glob)`: commits touching a path matching the glob
+    File(String),
+    /// `date(spec)`: `>DATE`, `<DATE`, `DATE to DATE`, or a single day
+    Date(String),
+    Union(Box<Revset>, Box<Revset>),
+    Intersection(Box<Revset>, Box<Revset>),
+    Difference(Box<Revset>, Box<Revset>),
+    /// `not x`: everything in `all()` except `x`
+    Not(Box<Revset>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    LParen,
+    RParen,
+    Comma,
+    And,
+    Or,
+    Tilde,
+    DoubleColon,
+    Word(String),
+    Quoted(String),
+}
+
+fn is_symbol_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | '/' | '@' | '^' | '{' | '}' | '*')
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let chars: Vec<char> = input.chars().collect();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            c if c.is_whitespace() => i += 1,
+            '(' => {
+                tokens.push(Token::LParen);
+                i += 1;
+            }
+            ')' => {
+                tokens.push(Token::RParen);
+                i += 1;
+            }
+            ',' => {
+                tokens.push(Token::Comma);
+                i += 1;
+            }
+            '&' => {
+                tokens.push(Token::And);
+                i += 1;
+            }
+            '|' => {
+                tokens.push(Token::Or);
+                i += 1;
+            }
+            '~' => {
+                tokens.push(Token::Tilde);
+                i += 1;
+            }
+            ':' if chars.get(i + 1) == Some(&':') => {
+                tokens.push(Token::DoubleColon);
+                i += 2;
+            }
+            '\'' | '"' => {
+                let end = chars[i + 1..]
+                    .iter()
+                    .position(|&ch| ch == c)
+                    .map(|p| i + 1 + p)
+                    .ok_or_else(|| anyhow::anyhow!("unterminated string in revset"))?;
+                tokens.push(Token::Quoted(chars[i + 1..end].iter().collect()));
+                i = end + 1;
+            }
+            c if is_symbol_char(c) => {
+                let start = i;
+                while i < chars.len() {
+                    if is_symbol_char(chars[i]) {
+                        i += 1;
+                    } else if chars[i] == '~'
+                        && chars.get(i + 1).is_some_and(|n| n.is_ascii_digit())
+                    {
+                        // `HEAD~2` is git's nth-ancestor syntax, not a difference
+                        i += 1;
+                    } else {
+                        break;
+                    }
+                }
+                let word: String = chars[start..i].iter().collect();
+                let is_date = word == "date";
+                tokens.push(Token::Word(word));
+                if is_date {
+                    i = date_argument(&chars, i, &mut tokens)?;
+                }
+            }
+            other => bail!("unexpected character '{}' in revset", other),
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// `date(>2024-01-01)` reads naturally unquoted, so an unquoted `date`
+/// argument is taken literally up to its closing paren. Returns the index to
+/// continue lexing from.
+fn date_argument(chars: &[char], mut i: usize, tokens: &mut Vec<Token>) -> Result<usize> {
+    while chars.get(i).is_some_and(|c| c.is_whitespace()) {
+        i += 1;
+    }
+    if chars.get(i) != Some(&'(') {
+        return Ok(i);
+    }
+    tokens.push(Token::LParen);
+    i += 1;
+    let start = i;
+    while chars.get(i).is_some_and(|c| c.is_whitespace()) {
+        i += 1;
+    }
+    if matches!(chars.get(i), Some('\'' | '"')) {
+        // Quoted arguments go through the regular lexer
+        return Ok(start);
+    }
+    let end = chars[i..]
+        .iter()
+        .position(|&c| c == ')')
+        .map(|p| i + p)
+        .ok_or_else(|| anyhow::anyhow!("unterminated date() in revset"))?;
+    let argument: String = chars[i..end].iter().collect();
+    let argument = argument.trim_end();
+    if !argument.is_empty() {
+        tokens.push(Token::Word(argument.to_string()));
+    }
+    Ok(end)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<()> {
+        match self.next() {
+            Some(ref token) if *token == expected => Ok(()),
+            Some(token) => bail!("expected {:?} in revset, found {:?}", expected, token),
+            None => bail!("expected {:?} at end of revset", expected),
+        }
+    }
+
+    // union := difference ('|' difference)*
+    fn union(&mut self) -> Result<Revset> {
+        let mut left = self.difference()?;
+        while self.peek() == Some(&Token::Or) {
+            self.next();
+            left = Revset::Union(Box::new(left), Box::new(self.difference()?));
+        }
+        Ok(left)
+    }
+
+    // difference := intersection ('~' intersection)*
+    fn difference(&mut self) -> Result<Revset> {
+        let mut left = self.intersection()?;
+        while self.peek() == Some(&Token::Tilde) {
+            self.next();
+            left = Revset::Difference(Box::new(left), Box::new(self.intersection()?));
+        }
+        Ok(left)
+    }
+
+    // intersection := prefix ('&' prefix)*
+    fn intersection(&mut self) -> Result<Revset> {
+        let mut left = self.prefix()?;
+        while self.peek() == Some(&Token::And) {
+            self.next();
+            left = Revset::Intersection(Box::new(left), Box::new(self.prefix()?));
+        }
+        Ok(left)
+    }
+
+    // prefix := 'not' prefix | '::' primary | primary ('::' primary?)?
+    fn prefix(&mut self) -> Result<Revset> {
+        match self.peek() {
+            Some(Token::Word(word)) if word == "not" => {
+                self.next();
+                Ok(Revset::Not(Box::new(self.prefix()?)))
+            }
+            Some(Token::DoubleColon) => {
+                self.next();
+                Ok(Revset::Ancestors(Box::new(self.primary()?)))
+            }
+            _ => {
+                let base = self.primary()?;
+                if self.peek() != Some(&Token::DoubleColon) {
+                    return Ok(base);
+                }
+                self.next();
+                let descendants = Revset::Descendants(Box::new(base));
+                match self.peek() {
+                    Some(Token::Word(_) | Token::Quoted(_) | Token::LParen) => {
+                        let tip = Revset::Ancestors(Box::new(self.primary()?));
+                        Ok(Revset::Intersection(Box::new(descendants), Box::new(tip)))
+                    }
+                    _ => Ok(descendants),
+                }
+            }
+        }
+    }
+
+    fn primary(&mut self) -> Result<Revset> {
+        match self.next() {
+            Some(Token::LParen) => {
+                let inner = self.union()?;
+                self.expect(Token::RParen)?;
+                Ok(inner)
+            }
+            Some(Token::Quoted(symbol)) => Ok(Revset::Symbol(symbol)),
+            Some(Token::Word(word)) => {
+                if self.peek() == Some(&Token::LParen) {
+                    self.next();
+                    self.function(&word)
+                } else {
+                    Ok(Revset::Symbol(word))
+                }
+            }
+            Some(token) => bail!("unexpected {:?} in revset", token),
+            None => bail!("unexpected end of revset"),
+        }
+    }
+
+    /// Parse the arguments of `name(` up to and including the closing paren
+    fn function(&mut self, name: &str) -> Result<Revset> {
+        let revset = match name {
+            "all" => Revset::All,
+            "draft" => Revset::Draft,
+            "heads" => {
+                if self.peek() == Some(&Token::RParen) {
+                    Revset::Heads(Box::new(Revset::All))
+                } else {
+                    Revset::Heads(Box::new(self.union()?))
+                }
+            }
+            "ancestors" => Revset::Ancestors(Box::new(self.union()?)),
+            "descendants" => Revset::Descendants(Box::new(self.union()?)),
+            "author" => Revset::Author(self.pattern(name)?),
+            "desc" => Revset::Description(self.pattern(name)?),
+            "file" => Revset::File(self.pattern(name)?),
+            "date" => Revset::Date(self.pattern(name)?),
+            _ => bail!(
+                "unknown revset function '{}' (expected all, draft, heads, ancestors, \
+                 descendants, author, desc, file, or date)",
+                name
+            ),
+        };
+        self.expect(Token::RParen)?;
+        Ok(revset)
+    }
+
+    /// A string argument: a quoted string or a single word
+    fn pattern(&mut self, function: &str) -> Result<String> {
+        match self.peek() {
+            Some(Token::Quoted(_)) => match self.next() {
+                Some(Token::Quoted(s)) => Ok(s),
+                _ => unreachable!(),
+            },
+            Some(Token::Word(_)) => match self.next() {
+                Some(Token::Word(s)) => Ok(s),
+                _ => unreachable!(),
+            },
+            _ => bail!("{}() needs an argument", function),
+        }
+    }
+}
+
+impl Revset {
+    pub fn parse(input: &str) -> Result<Self> {
+        let mut parser = Parser {
+            tokens: tokenize(input)?,
+            pos: 0,
+        };
+        if parser.tokens.is_empty() {
+            bail!("empty revset");
+        }
+        let revset = parser.union()?;
+        if let Some(token) = parser.next() {
+            bail!("unexpected {:?} after revset", token);
+        }
+        Ok(revset)
+    }
+}
+
+/// The rev-list query for a `date(spec)` leaf
+fn date_query(spec: &str) -> LogQuery {
+    let spec = spec.trim();
+    if let Some(date) = spec.strip_prefix('>') {
+        LogQuery::new().since(date.trim())
+    } else if let Some(date) = spec.strip_prefix('<') {
+        LogQuery::new().until(date.trim())
+    } else if let Some((since, until)) = spec.split_once(" to ") {
+        LogQuery::new().since(since.trim()).until(until.trim())
+    } else {
+        LogQuery::new()
+            .since(format!("{} 00:00:00", spec))
+            .until(format!("{} 23:59:59", spec))
+    }
+}
+
+/// Parent links of every commit reachable from a ref
+struct Graph {
+    children: HashMap<String, Vec<String>>,
+}
+
+struct Evaluator<'a> {
+    repo_path: &'a Path,
+    graph: Option<Graph>,
+}
+
+impl Evaluator<'_> {
+    fn rev_list(&self, query: &LogQuery) -> Result<HashSet<String>> {
+        let mut args = vec!["rev-list".to_string()];
+        args.extend(query.args());
+        let arg_refs: Vec<&str> = args.iter().map(String::as_str).collect();
+        let output = run(&arg_refs, Some(self.repo_path))?;
+        Ok(output.lines().map(str::to_string).collect())
+    }
+
+    fn graph(&mut self) -> Result<&Graph> {
+        if self.graph.is_none() {
+            let output = run(
+                &["rev-list", EXCLUDE_PRIVATE_REFS, "--all", "--parents"],
+                Some(self.repo_path),
+            )?;
+            let mut children: HashMap<String, Vec<String>> = HashMap::new();
+            for line in output.lines() {
+                let mut hashes = line.split_whitespace();
+                let Some(commit) = hashes.next() else {
+                    continue;
+                };
+                for parent in hashes {
+                    children
+                        .entry(parent.to_string())
+                        .or_default()
+                        .push(commit.to_string());
+                }
+            }
+            self.graph = Some(Graph { children });
+        }
+        Ok(self.graph.as_ref().unwrap())
+    }
+
+    fn eval(&mut self, revset: &Revset) -> Result<HashSet<String>> {
+        Ok(match revset {
+            Revset::Symbol(name) => {
+                let spec = format!("{}^{{commit}}", name);
+                let hash = run(
+                    &["rev-parse", "--verify", "--quiet", &spec],
+                    Some(self.repo_path),
+                )
+                .map_err(|_| GitError::UnknownRevision(name.clone()))?;
+                HashSet::from([hash.trim().to_string()])
+            }
+            Revset::All => self.rev_list(&LogQuery::new())?,
+            Revset::Draft => {
+                let output = run(
+                    &[
+                        "rev-list",
+                        EXCLUDE_PRIVATE_REFS,
+                        "--all",
+                        "--not",
+                        "--remotes",
+                    ],
+                    Some(self.repo_path),
+                )?;
+                output.lines().map(str::to_string).collect()
+            }
+            Revset::Ancestors(inner) => match inner.as_ref() {
+                Revset::Symbol(name) => self.rev_list(&LogQuery::new().revision(name.clone()))?,
+                other => {
+                    let tips = self.eval(other)?;
+                    if tips.is_empty() {
+                        return Ok(tips);
+                    }
+                    let stdin: Vec<&str> = tips.iter().map(String::as_str).collect();
+                    let output = run_with_stdin(
+                        &["rev-list", "--stdin"],
+                        Some(self.repo_path),
+                        &stdin.join("\n"),
+                    )?;
+                    output.lines().map(str::to_string).collect()
+                }
+            },
+            Revset::Descendants(inner) => {
+                let roots = self.eval(inner)?;
+                let graph = self.graph()?;
+                let mut result = HashSet::new();
+                let mut pending: Vec<String> = roots.into_iter().collect();
+                while let Some(commit) = pending.pop() {
+                    // Merges reach their descendants along every parent
+                    if !result.insert(commit.clone()) {
+                        continue;
+                    }
+                    if let Some(children) = graph.children.get(&commit) {
+                        pending.extend(children.iter().cloned());
+                    }
+                }
+                result
+            }
+            Revset::Heads(inner) => {
+                let members = self.eval(inner)?;
+                let graph = self.graph()?;
+                members
+                    .iter()
+                    .filter(|commit| {
+                        graph.children.get(*commit).is_none_or(|children| {
+                            !children.iter().any(|child| members.contains(child))
+                        })
+                    })
+                    .cloned()
+                    .collect()
+            }
+            Revset::Author(pattern) => self.rev_list(
+                &LogQuery::new()
+                    .author(pattern.clone())
+                    .fixed_strings()
+                    .ignore_case(),
+            )?,
+            Revset::Description(pattern) => self.rev_list(
+                &LogQuery::new()
+                    .grep(pattern.clone())
+                    .fixed_strings()
+                    .ignore_case(),
+            )?,
+            Revset::File(glob) => {
+                self.rev_list(&LogQuery::new().path(format!(":(glob){}", glob)))?
+            }
+            Revset::Date(spec) => self.rev_list(&date_query(spec))?,
+            Revset::Union(a, b) => {
+                let mut left = self.eval(a)?;
+                left.extend(self.eval(b)?);
+                left
+            }
+            Revset::Intersection(a, b) => {
+                let left = self.eval(a)?;
+                let right = self.eval(b)?;
+                left.intersection(&right).cloned().collect()
+            }
+            Revset::Difference(a, b) => {
+                let left = self.eval(a)?;
+                let right = self.eval(b)?;
+                left.difference(&right).cloned().collect()
+            }
+            Revset::Not(inner) => {
+                let excluded = self.eval(inner)?;
+                let all = self.eval(&Revset::All)?;
+                all.difference(&excluded).cloned().collect()
+            }
+        })
+    }
+}
+
+/// Resolve `revset` to the set of matching commit hashes
+pub fn resolve_revset(repo_path: &Path, revset: &Revset) -> Result<HashSet<String>> {
+    Evaluator {
+        repo_path,
+        graph: None,
+    }
+    .eval(revset)
+}
+
+/// Commits matching `revset`, newest first
+pub fn get_commits_in_revset(
+    repo_path: &Path,
+    revset: &Revset,
+    max_count: Option<usize>,
+) -> Result<Vec<Commit>> {
+    let hashes = resolve_revset(repo_path, revset)?;
+    get_commits_by_hash(repo_path, &hashes, max_count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{create_test_repo, git};
+    use std::fs;
+
+    fn symbol(name: &str) -> Box<Revset> {
+        Box::new(Revset::Symbol(name.to_string()))
+    }
+
+    #[test]
+    fn test_parse_operators_and_precedence() {
+        assert_eq!(
+            Revset::parse("a | b & c ~ d").unwrap(),
+            Revset::Union(
+                symbol("a"),
+                Box::new(Revset::Difference(
+                    Box::new(Revset::Intersection(symbol("b"), symbol("c"))),
+                    symbol("d"),
+                )),
+            )
+        );
+        assert_eq!(
+            Revset::parse("HEAD~2").unwrap(),
+            Revset::Symbol("HEAD~2".to_string())
+        );
+        assert_eq!(
+            Revset::parse("HEAD ~ main").unwrap(),
+            Revset::Difference(symbol("HEAD"), symbol("main"))
+        );
+        assert_eq!(
+            Revset::parse("::main").unwrap(),
+            Revset::Ancestors(symbol("main"))
+        );
+        assert_eq!(
+            Revset::parse("v1::").unwrap(),
+            Revset::Descendants(symbol("v1"))
+        );
+    }
+
+    #[test]
+    fn test_parse_functions() {
+        assert_eq!(
+            Revset::parse("draft() & author(\"Jane Doe\")").unwrap(),
+            Revset::Intersection(
+                Box::new(Revset::Draft),
+                Box::new(Revset::Author("Jane Doe".to_string())),
+            )
+        );
+        assert_eq!(
+            Revset::parse("heads()").unwrap(),
+            Revset::Heads(Box::new(Revset::All))
+        );
+        assert_eq!(
+            Revset::parse("date(>2024-01-01) | file(src/*.rs)").unwrap(),
+            Revset::Union(
+                Box::new(Revset::Date(">2024-01-01".to_string())),
+                Box::new(Revset::File("src/*.rs".to_string())),
+            )
+        );
+        assert_eq!(
+            Revset::parse("date(2024-01-01 to 2024-02-01) & draft()").unwrap(),
+            Revset::Intersection(
+                Box::new(Revset::Date("2024-01-01 to 2024-02-01".to_string())),
+                Box::new(Revset::Draft),
+            )
+        );
+        assert_eq!(
+            Revset::parse("not ancestors(main)").unwrap(),
+            Revset::Not(Box::new(Revset::Ancestors(symbol("main"))))
+        );
+    }
+
+    #[test]
+    fn test_parse_date_inside_quoted_pattern() {
+        assert_eq!(
+            Revset::parse("desc(\"fix update(cache)\")").unwrap(),
+            Revset::Description("fix update(cache)".to_string())
+        );
+        assert_eq!(
+            Revset::parse("desc('date(x)') | date('<2024-01-01')").unwrap(),
+            Revset::Union(
+                Box::new(Revset::Description("date(x)".to_string())),
+                Box::new(Revset::Date("<2024-01-01".to_string())),
+            )
+        );
+        assert!(Revset::parse("update(x)").is_err());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(Revset::parse("").is_err());
+        assert!(Revset::parse("bogus(x)").is_err());
+        assert!(Revset::parse("ancestors(main").is_err());
+        assert!(Revset::parse("a &").is_err());
+        assert!(Revset::parse("author('open").is_err());
+        assert!(Revset::parse("date(>2024-01-01").is_err());
+        assert!(Revset::parse("date()").is_err());
+    }
+
+    #[test]
+    fn test_date_query() {
+        let query = date_query("2024-01-10");
+        assert_eq!(query.since.as_deref(), Some("2024-01-10 00:00:00"));
+        assert_eq!(query.until.as_deref(), Some("2024-01-10 23:59:59"));
+
+        let query = date_query("2024-01-01 to 2024-02-01");
+        assert_eq!(query.since.as_deref(), Some("2024-01-01"));
+        assert_eq!(query.until.as_deref(), Some("2024-02-01"));
+
+        assert_eq!(
+            date_query("<2024-01-01").until.as_deref(),
+            Some("2024-01-01")
+        );
+    }
+
+    #[test]
+    fn test_evaluate_against_repository() {
+        let dir = create_test_repo();
+        let path = dir.path();
+        let commit = |file: &str, message: &str, author: &str| {
+            fs::write(path.join(file), message).unwrap();
+            git(path, &["add", file]);
+            git(path, &["commit", "-m", message, "--author", author]);
+        };
+        commit("base.txt", "Base", "Alice <alice@example.com>");
+        git(path, &["tag", "v1"]);
+        commit("src.rs", "Add source", "Bob <bob@example.com>");
+        git(path, &["update-ref", "refs/remotes/origin/main", "HEAD"]);
+        git(path, &["checkout", "-b", "feature"]);
+        commit("notes.txt", "Write notes", "Alice <alice@example.com>");
+
+        let summaries = |expr: &str| -> Vec<String> {
+            let mut summaries: Vec<String> =
+                get_commits_in_revset(path, &Revset::parse(expr).unwrap(), None)
+                    .unwrap()
+                    .into_iter()
+                    .map(|c| c.summary)
+                    .collect();
+            summaries.sort();
+            summaries
+        };
+
+        assert_eq!(summaries("draft()"), vec!["Write notes"]);
+        assert_eq!(summaries("author(ALICE)"), vec!["Base", "Write notes"]);
+        assert_eq!(summaries("file('*.rs')"), vec!["Add source"]);
+        assert_eq!(
+            summaries("descendants(v1) ~ v1"),
+            vec!["Add source", "Write notes"]
+        );
+        assert_eq!(summaries("heads()"), vec!["Write notes"]);
+        assert_eq!(summaries("::main & author(bob)"), vec!["Add source"]);
+        assert_eq!(summaries("v1::main"), vec!["Add source", "Base"]);
+        assert_eq!(summaries("not ::main"), vec!["Write notes"]);
+        assert!(summaries("date(<2000-01-01)").is_empty());
+
+        let err = resolve_revset(path, &Revset::parse("nope").unwrap()).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<GitError>(),
+            Some(GitError::UnknownRevision(r)) if r == "nope"
+        ));
+    }
+
+    #[test]
+    fn test_descendants_through_merges() {
+        let dir = create_test_repo();
+        let path = dir.path();
+        git(path, &["commit", "--allow-empty", "-m", "Root"]);
+        let root = git(path, &["rev-parse", "HEAD"]);
+        // A chain of diamonds: each merge joins two children of the last one
+        let tree = git(path, &["rev-parse", "HEAD^{tree}"]);
+        let mut tip = root.clone();
+        for i in 0..20 {
+            let commit = |parents: &[&str], message: String| {
+                let mut args = vec!["commit-tree", tree.as_str(), "-m", &message];
+                for parent in parents {
+                    args.extend(["-p", parent]);
+                }
+                git(path, &args)
+            };
+            let left = commit(&[&tip], format!("Left {}", i));
+            let right = commit(&[&tip], format!("Right {}", i));
+            tip = commit(&[&left, &right], format!("Merge {}", i));
+        }
+        git(path, &["reset", "--hard", &tip]);
+
+        let revset = Revset::Descendants(Box::new(Revset::Symbol(root.clone())));
+        assert_eq!(resolve_revset(path, &revset).unwrap().len(), 61);
+
+        assert_eq!(
+            get_commits_in_revset(path, &revset, Some(2)).unwrap().len(),
+            2
+        );
+    }
+}
@@ -0,0 +1,558 @@
+use crate::command::{run, run_raw, run_with_env};
+use crate::operations::history_edit::{RebaseAction, RebaseStep};
+use anyhow::{bail, Context, Result};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+
+/// Outcome of a three-way tree merge
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TreeMerge {
+    Clean(String),
+    Conflicted(Vec<String>),
+}
+
+/// Merge `theirs` into `ours` relative to `base` without touching the index
+/// or working tree. `ours` and `base` may be trees when `ours` has no commit
+/// yet; otherwise all three are commits.
+pub fn merge_trees(repo_path: &Path, base: &str, ours: &str, theirs: &str) -> Result<TreeMerge> {
+    let merge_base = format!("--merge-base={}", base);
+    let output = run_raw(
+        &[
+            "merge-tree",
+            "--write-tree",
+            "--name-only",
+            "-z",
+            &merge_base,
+            ours,
+            theirs,
+        ],
+        Some(repo_path),
+    )?;
+    match output.code {
+        Some(0) | Some(1) => {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            let mut fields = stdout.split('\0');
+            let tree = fields.next().unwrap_or_default().trim().to_string();
+            if output.code == Some(0) {
+                return Ok(TreeMerge::Clean(tree));
+            }
+            let mut paths: Vec<String> = fields
+                .take_while(|path| !path.is_empty())
+                .map(str::to_string)
+                .collect();
+            paths.dedup();
+            Ok(TreeMerge::Conflicted(paths))
+        }
+        // git before 2.40 has no --merge-base, and merge-tree only takes commits
+        _ => merge_in_scratch_index(repo_path, base, ours, theirs),
+    }
+}
+
+/// Mode and object id of one side of an unmerged path
+type Stage = (String, String);
+
+/// `merge_trees` for older git: a three-way `read-tree` into a scratch index,
+/// with `merge-file` for paths both sides changed
+fn merge_in_scratch_index(
+    repo_path: &Path,
+    base: &str,
+    ours: &str,
+    theirs: &str,
+) -> Result<TreeMerge> {
+    let scratch = tempdir()?;
+    let index = scratch.path().join("index");
+    let index = index.to_string_lossy().to_string();
+    let env = HashMap::from([("GIT_INDEX_FILE", index.as_str())]);
+
+    run_with_env(
+        &["read-tree", "-i", "-m", "--aggressive", base, ours, theirs],
+        Some(repo_path),
+        &env,
+    )
+    .context("Failed to merge trees")?;
+
+    // Stages 1 (base), 2 (ours) and 3 (theirs) of each unmerged path
+    let unmerged = run_with_env(&["ls-files", "-u", "-z"], Some(repo_path), &env)?;
+    let mut stages: Vec<(String, [Option<Stage>; 3])> = Vec::new();
+    for entry in unmerged.split('\0').filter(|e| !e.is_empty()) {
+        let Some((info, path)) = entry.split_once('\t') else {
+            continue;
+        };
+        let info: Vec<&str> = info.split(' ').collect();
+        let [mode, oid, stage] = info[..] else {
+            continue;
+        };
+        let stage: usize = stage.parse().unwrap_or(0);
+        if !(1..=3).contains(&stage) {
+            continue;
+        }
+        if stages.last().map(|(p, _)| p.as_str()) != Some(path) {
+            stages.push((path.to_string(), Default::default()));
+        }
+        stages.last_mut().unwrap().1[stage - 1] = Some((mode.to_string(), oid.to_string()));
+    }
+
+    let mut conflicts = Vec::new();
+    for (path, [base, ours, theirs]) in stages {
+        let (Some(base), Some(ours), Some(theirs)) = (base, ours, theirs) else {
+            conflicts.push(path);
+            continue;
+        };
+        let regular = |mode: &str| mode == "100644" || mode == "100755";
+        let mode = if ours.0 == base.0 {
+            &theirs.0
+        } else if theirs.0 == base.0 || theirs.0 == ours.0 {
+            &ours.0
+        } else {
+            conflicts.push(path);
+            continue;
+        };
+        if ![&base.0, &ours.0, &theirs.0].iter().all(|m| regular(m)) {
+            conflicts.push(path);
+            continue;
+        }
+
+        let mut files = Vec::new();
+        for (name, (_, oid)) in [("ours", &ours), ("base", &base), ("theirs", &theirs)] {
+            let blob = run_raw(&["cat-file", "blob", oid], Some(repo_path))?;
+            let file = scratch.path().join(name);
+            fs::write(&file, &blob.stdout)?;
+            files.push(file.to_string_lossy().to_string());
+        }
+        let merged = run_raw(
+            &["merge-file", "-p", &files[0], &files[1], &files[2]],
+            Some(repo_path),
+        )?;
+        if merged.code != Some(0) {
+            conflicts.push(path);
+            continue;
+        }
+        let merged_file = scratch.path().join("merged");
+        fs::write(&merged_file, &merged.stdout)?;
+        let oid = run(
+            &[
+                "hash-object",
+                "-w",
+                "--no-filters",
+                &merged_file.to_string_lossy(),
+            ],
+            Some(repo_path),
+        )?;
+        let cacheinfo = format!("{},{},{}", mode, oid.trim(), path);
+        run_with_env(
+            &["update-index", "--add", "--cacheinfo", &cacheinfo],
+            Some(repo_path),
+            &env,
+        )?;
+    }
+
+    if !conflicts.is_empty() {
+        return Ok(TreeMerge::Conflicted(conflicts));
+    }
+    let tree = run_with_env(&["write-tree"], Some(repo_path), &env)?;
+    Ok(TreeMerge::Clean(tree.trim().to_string()))
+}
+
+/// A commit as read for replaying
+struct Original {
+    tree: String,
+    parent: Option<String>,
+    parent_tree: String,
+    author: [String; 3],
+    message: String,
+}
+
+/// The commit being built; squashes and fixups keep folding into it
+struct Pending {
+    parent: Option<String>,
+    tree: String,
+    message: String,
+    author: [String; 3],
+    /// The original commit, while nothing about it has changed
+    unchanged: Option<String>,
+    /// Original commits folded into this one
+    sources: Vec<String>,
+}
+
+/// Result of `replay`
+pub(crate) struct Replayed {
+    /// New tip, `None` when every commit down to the root was dropped
+    pub tip: Option<String>,
+    /// Commits on the tip above the base
+    pub commits: usize,
+    /// Original commit and the commit that replaced it, for each one changed
+    pub rewritten: Vec<(String, String)>,
+}
+
+fn read_commit(repo_path: &Path, hash: &str, empty_tree: &str) -> Result<Original> {
+    let output = run(
+        &[
+            "log",
+            "-1",
+            "--date=raw",
+            "--format=%T%x00%P%x00%an%x00%ae%x00%ad%x00%B",
+            hash,
+        ],
+        Some(repo_path),
+    )?;
+    let fields: Vec<&str> = output.splitn(6, '\0').collect();
+    let [tree, parents, name, email, date, message] = fields[..] else {
+        bail!("Failed to read commit {}", hash);
+    };
+    let parent = parents.split_whitespace().next().map(str::to_string);
+    let parent_tree = match parent {
+        Some(ref parent) => tree_of(repo_path, parent)?,
+        None => empty_tree.to_string(),
+    };
+    Ok(Original {
+        tree: tree.to_string(),
+        parent,
+        parent_tree,
+        author: [name.to_string(), email.to_string(), date.to_string()],
+        message: message.trim_end().to_string(),
+    })
+}
+
+fn tree_of(repo_path: &Path, commit: &str) -> Result<String> {
+    Ok(run(
+        &["rev-parse", "--verify", &format!("{}^{{tree}}", commit)],
+        Some(repo_path),
+    )?
+    .trim()
+    .to_string())
+}
+
+impl Pending {
+    fn commit(self, repo_path: &Path, rewritten: &mut Vec<(String, String)>) -> Result<String> {
+        if let Some(original) = self.unchanged {
+            return Ok(original);
+        }
+        let [name, email, date] = &self.author;
+        let env = HashMap::from([
+            ("GIT_AUTHOR_NAME", name.as_str()),
+            ("GIT_AUTHOR_EMAIL", email.as_str()),
+            ("GIT_AUTHOR_DATE", date.as_str()),
+        ]);
+        let mut args = vec!["commit-tree", self.tree.as_str()];
+        if let Some(ref parent) = self.parent {
+            args.extend(["-p", parent.as_str()]);
+        }
+        args.extend(["-m", self.message.as_str()]);
+        let hash = run_with_env(&args, Some(repo_path), &env).context("Failed to write commit")?;
+        let hash = hash.trim().to_string();
+        rewritten.extend(self.sources.into_iter().map(|s| (s, hash.clone())));
+        Ok(hash)
+    }
+}
+
+/// Copy `commit` onto `parent` in the object database, keeping its author and
+/// message. Returns `None` when its change conflicts there.
+pub(crate) fn rebase_commit(
+    repo_path: &Path,
+    commit: &str,
+    parent: &str,
+) -> Result<Option<String>> {
+    let empty_tree = run(&["hash-object", "-t", "tree", "/dev/null"], Some(repo_path))?;
+    let original = read_commit(repo_path, commit, empty_tree.trim())?;
+    let parent_tree = tree_of(repo_path, parent)?;
+    let tree = if original.parent_tree == parent_tree {
+        original.tree
+    } else {
+        let merged = match original.parent {
+            Some(ref base) => merge_trees(repo_path, base, parent, commit)?,
+            None => merge_in_scratch_index(repo_path, empty_tree.trim(), parent, commit)?,
+        };
+        match merged {
+            TreeMerge::Clean(tree) => tree,
+            TreeMerge::Conflicted(_) => return Ok(None),
+        }
+    };
+    let pending = Pending {
+        parent: Some(parent.to_string()),
+        tree,
+        message: original.message,
+        author: original.author,
+        unchanged: None,
+        sources: Vec::new(),
+    };
+    pending.commit(repo_path, &mut Vec::new()).map(Some)
+}
+
+/// Write a commit with `commit`'s author and date, on `parent` with `tree`.
+/// The message defaults to `commit`'s own.
+pub(crate) fn recommit(
+    repo_path: &Path,
+    commit: &str,
+    parent: Option<&str>,
+    tree: &str,
+    message: Option<&str>,
+) -> Result<String> {
+    let empty_tree = run(&["hash-object", "-t", "tree", "/dev/null"], Some(repo_path))?;
+    let original = read_commit(repo_path, commit, empty_tree.trim())?;
+    let pending = Pending {
+        parent: parent.map(str::to_string),
+        tree: tree.to_string(),
+        message: message.map_or(original.message, str::to_string),
+        author: original.author,
+        unchanged: None,
+        sources: Vec::new(),
+    };
+    pending.commit(repo_path, &mut Vec::new())
+}
+
+/// Replay `todo` (oldest first, no `Edit` steps) on top of `base` in the
+/// object database. Returns `None` when a step conflicts and needs a real
+/// rebase.
+pub(crate) fn replay(
+    repo_path: &Path,
+    base: Option<&str>,
+    todo: &[RebaseStep],
+) -> Result<Option<Replayed>> {
+    let empty_tree = run(&["hash-object", "-t", "tree", "/dev/null"], Some(repo_path))?;
+    let empty_tree = empty_tree.trim();
+
+    let mut tip = base.map(str::to_string);
+    let mut tip_tree = match tip {
+        Some(ref tip) => tree_of(repo_path, tip)?,
+        None => empty_tree.to_string(),
+    };
+    let mut pending: Option<Pending> = None;
+    let mut count = 0;
+    let mut rewritten = Vec::new();
+
+    for step in todo {
+        let (folding, message) = match step.action {
+            RebaseAction::Drop => continue,
+            RebaseAction::Edit => bail!("Cannot replay an edit step in memory"),
+            RebaseAction::Pick | RebaseAction::Reword => (false, step.message.clone()),
+            RebaseAction::Squash | RebaseAction::Fixup => (true, step.message.clone()),
+        };
+        let original = read_commit(repo_path, &step.commit, empty_tree)?;
+
+        if !folding {
+            if let Some(done) = pending.take() {
+                tip_tree = done.tree.clone();
+                tip = Some(done.commit(repo_path, &mut rewritten)?);
+                count += 1;
+            }
+        }
+        let ours = match pending {
+            Some(ref pending) => pending.tree.clone(),
+            None => tip_tree.clone(),
+        };
+
+        let tree = if original.parent_tree == ours {
+            original.tree.clone()
+        } else {
+            // merge-tree wants commits, which only exist once there is a tip
+            let merged = match (tip.as_deref(), &original.parent, &pending) {
+                (Some(tip), Some(parent), None) => {
+                    merge_trees(repo_path, parent, tip, &step.commit)?
+                }
+                _ => {
+                    merge_in_scratch_index(repo_path, &original.parent_tree, &ours, &original.tree)?
+                }
+            };
+            match merged {
+                TreeMerge::Clean(tree) => tree,
+                TreeMerge::Conflicted(_) => return Ok(None),
+            }
+        };
+
+        match pending {
+            Some(ref mut open) if folding => {
+                open.tree = tree;
+                open.unchanged = None;
+                open.sources.push(step.commit.clone());
+                match message {
+                    Some(message) => open.message = message,
+                    None if step.action == RebaseAction::Squash => {
+                        open.message = format!("{}\n\n{}", open.message, original.message);
+                    }
+                    None => {}
+                }
+            }
+            _ if folding => bail!("Cannot fold {} into an earlier commit", step.commit),
+            _ => {
+                // Like rebase, leave out commits whose change is already there
+                if tree == ours && original.tree != original.parent_tree {
+                    continue;
+                }
+                let unchanged =
+                    (original.parent == tip && message.is_none()).then(|| step.commit.clone());
+                pending = Some(Pending {
+                    parent: tip.clone(),
+                    tree,
+                    message: message.unwrap_or(original.message),
+                    author: original.author,
+                    unchanged,
+                    sources: vec![step.commit.clone()],
+                });
+            }
+        }
+    }
+    if let Some(done) = pending.take() {
+        tip = Some(done.commit(repo_path, &mut rewritten)?);
+        count += 1;
+    }
+
+    Ok(Some(Replayed {
+        tip,
+        commits: count,
+        rewritten,
+    }))
+}
+
+/// Point the current branch (or detached `HEAD`) at `new_head`, updating the
+/// index and working tree only when the tree changes. Local changes are kept
+/// unless they touch files that differ, in which case nothing is moved.
+pub(crate) fn move_head(repo_path: &Path, new_head: &str, reason: &str) -> Result<()> {
+    let old_head = run(&["rev-parse", "--verify", "HEAD"], Some(repo_path))?;
+    let old_head = old_head.trim();
+    if old_head == new_head {
+        return Ok(());
+    }
+
+    if tree_of(repo_path, old_head)? != tree_of(repo_path, new_head)? {
+        run(
+            &["read-tree", "-m", "-u", old_head, new_head],
+            Some(repo_path),
+        )
+        .context("Failed to update the working tree")?;
+    }
+
+    update_head_ref(repo_path, new_head, old_head, reason)
+}
+
+/// Point the current branch (or detached `HEAD`) from `old_head` to
+/// `new_head` without touching the index or working tree
+pub(crate) fn update_head_ref(
+    repo_path: &Path,
+    new_head: &str,
+    old_head: &str,
+    reason: &str,
+) -> Result<()> {
+    let message = format!("openisl: {}", reason);
+    let branch = run_raw(&["symbolic-ref", "--quiet", "HEAD"], Some(repo_path))?;
+    if branch.success() {
+        let branch = String::from_utf8_lossy(&branch.stdout).trim().to_string();
+        run(
+            &["update-ref", "-m", &message, &branch, new_head, old_head],
+            Some(repo_path),
+        )?;
+    } else {
+        run(
+            &[
+                "update-ref",
+                "--no-deref",
+                "-m",
+                &message,
+                "HEAD",
+                new_head,
+                old_head,
+            ],
+            Some(repo_path),
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{create_test_repo, git};
+
+    #[test]
+    fn test_merge_in_scratch_index() {
+        let dir = create_test_repo();
+        let path = dir.path();
+        fs::write(path.join("f.txt"), "1\n2\n3\n4\n5\n").unwrap();
+        git(path, &["add", "f.txt"]);
+        git(path, &["commit", "-m", "Base"]);
+        let base = git(path, &["rev-parse", "HEAD^{tree}"]);
+        fs::write(path.join("f.txt"), "one\n2\n3\n4\n5\n").unwrap();
+        git(path, &["commit", "-am", "Ours"]);
+        let ours = git(path, &["rev-parse", "HEAD^{tree}"]);
+        fs::write(path.join("f.txt"), "1\n2\n3\n4\nfive\n").unwrap();
+        git(path, &["commit", "-am", "Theirs"]);
+        let theirs = git(path, &["rev-parse", "HEAD^{tree}"]);
+
+        // Theirs relative to Ours applies cleanly onto Base
+        let TreeMerge::Clean(tree) = merge_in_scratch_index(path, &ours, &base, &theirs).unwrap()
+        else {
+            panic!("expected a clean merge");
+        };
+        let merged = git(path, &["cat-file", "blob", &format!("{}:f.txt", tree)]);
+        assert_eq!(merged, "1\n2\n3\n4\nfive");
+
+        // Clean filters and line-ending conversion are not applied to the
+        // merged blob a second time
+        git(path, &["config", "filter.shout.clean", "tr a-z A-Z"]);
+        git(path, &["config", "core.autocrlf", "true"]);
+        fs::write(path.join(".git/info/attributes"), "* filter=shout\n").unwrap();
+        assert_eq!(
+            merge_in_scratch_index(path, &ours, &base, &theirs).unwrap(),
+            TreeMerge::Clean(tree)
+        );
+        fs::remove_file(path.join(".git/info/attributes")).unwrap();
+        git(path, &["config", "--unset", "core.autocrlf"]);
+
+        fs::write(path.join("f.txt"), "uno\n2\n3\n4\n5\n").unwrap();
+        git(path, &["commit", "-am", "Clash"]);
+        let clash = git(path, &["rev-parse", "HEAD^{tree}"]);
+        assert_eq!(
+            merge_in_scratch_index(path, &base, &ours, &clash).unwrap(),
+            TreeMerge::Conflicted(vec!["f.txt".to_string()])
+        );
+        // The checkout is left alone
+        assert_eq!(git(path, &["status", "--porcelain"]), "");
+    }
+
+    #[test]
+    fn test_rewrite_keeps_the_checkout() {
+        use crate::operations::history_edit::{edit_history, HistoryEditOutcome};
+
+        let dir = create_test_repo();
+        let path = dir.path();
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            fs::write(path.join(name), format!("{}\n", name)).unwrap();
+            git(path, &["add", name]);
+            git(path, &["commit", "-m", &format!("Add {}", name)]);
+        }
+        let middle = git(path, &["rev-parse", "HEAD~1"]);
+        let author_date = git(path, &["log", "-1", "--format=%ad", &middle]);
+        fs::write(path.join("a.txt"), "local edit\n").unwrap();
+        let mtime = || {
+            fs::metadata(path.join("c.txt"))
+                .unwrap()
+                .modified()
+                .unwrap()
+        };
+        let before = mtime();
+
+        // Rewording leaves HEAD's tree alone, so nothing is checked out
+        let reword = RebaseStep::new(&middle, RebaseAction::Reword).with_message("Reworded");
+        let outcome = edit_history(path, None, &[reword]).unwrap();
+        assert_eq!(outcome, HistoryEditOutcome::Completed { commits: 2 });
+        assert_eq!(
+            git(path, &["log", "-1", "--format=%s", "HEAD~1"]),
+            "Reworded"
+        );
+        assert_eq!(
+            git(path, &["log", "-1", "--format=%ad", "HEAD~1"]),
+            author_date
+        );
+        assert_eq!(mtime(), before);
+        assert_eq!(git(path, &["status", "--porcelain"]), "M a.txt");
+
+        // Dropping updates the checkout but keeps unrelated local changes
+        let middle = git(path, &["rev-parse", "HEAD~1"]);
+        edit_history(path, None, &[RebaseStep::new(&middle, RebaseAction::Drop)]).unwrap();
+        assert!(!path.join("b.txt").exists());
+        assert_eq!(mtime(), before);
+        assert_eq!(git(path, &["status", "--porcelain"]), "M a.txt");
+        assert_eq!(git(path, &["log", "--format=%s"]), "Add c.txt\nAdd a.txt");
+    }
+}
@@ -58,15 +58,21 @@ impl SmartLogFormatter {
             .collect()
     }
 
+    /// The branch HEAD points to, falling back to `main`
     fn find_main_branch(&self) -> String {
-        for commit in &self.commits {
-            for r in &commit.refs {
-                if r.ref_type == crate::models::RefType::Head {
-                    return r.name.clone();
-                }
-            }
-        }
-        "main".to_string()
+        use crate::models::RefType;
+
+        self.commits
+            .iter()
+            .find(|commit| commit.refs.iter().any(|r| r.ref_type == RefType::Head))
+            .and_then(|commit| {
+                commit
+                    .refs
+                    .iter()
+                    .find(|r| r.ref_type == RefType::Branch)
+                    .map(|r| r.name.clone())
+            })
+            .unwrap_or_else(|| "main".to_string())
     }
 
     fn format_graph_node(&self, node: &GraphNode, index: usize, total: usize) -> String {
@@ -100,7 +106,10 @@ impl SmartLogFormatter {
                 .commit
                 .refs
                 .iter()
-                .filter(|r| r.ref_type != crate::models::RefType::Remote)
+                .filter(|r| {
+                    r.ref_type != crate::models::RefType::Remote
+                        && r.ref_type != crate::models::RefType::Head
+                })
                 .map(|r| {
                     let name = if r.name.starts_with("refs/heads/") {
                         &r.name[11..]
@@ -154,6 +163,10 @@ mod tests {
             date: Utc::now(),
             parent_hashes: vec![],
             refs: vec![],
+            committer: "Test".to_string(),
+            committer_email: "test@example.com".to_string(),
+            committer_date: Utc::now(),
+            trailers: vec![],
         }
     }
 
@@ -202,6 +215,10 @@ mod tests {
                 name: "main".to_string(),
                 ref_type: crate::models::RefType::Branch,
             }],
+            committer: "Test".to_string(),
+            committer_email: "test@example.com".to_string(),
+            committer_date: Utc::now(),
+            trailers: vec![],
         }];
         let formatter = SmartLogFormatter::new(commits, 80);
         let output = formatter.format();
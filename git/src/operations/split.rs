@@ -0,0 +1,590 @@
+use crate::command::{run, run_raw, run_with_env};
+use crate::models::abbreviate_hash;
+use crate::operations::history_edit::{resolve_commit, RebaseAction, RebaseStep};
+use crate::operations::hunk::{get_tree_diff_hunks, Hunk, HunkLineType};
+use crate::operations::{rewrite, stack};
+use crate::vcs::{ChangePatch, ChangeSegment, SplitPart};
+use anyhow::{bail, Context, Result};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+
+/// A file with changes left to split
+#[derive(Debug, Clone)]
+pub struct SplitFile {
+    pub path: String,
+    /// Hunks of the remaining change; empty for binary and mode-only changes
+    pub hunks: Vec<Hunk>,
+    /// For files without hunks: take the whole change (visual selection)
+    pub is_selected: bool,
+}
+
+impl SplitFile {
+    /// Number of `ChangeSegment`s a patch for this file takes
+    pub fn segment_count(&self) -> usize {
+        let lines = self
+            .hunks
+            .iter()
+            .flat_map(|hunk| &hunk.lines)
+            .filter(|line| is_change(&line.line_type))
+            .count();
+        lines.max(1)
+    }
+
+    /// A patch including the lines marked `is_selected` in `hunks`, or the
+    /// whole file when it has no hunks and is selected itself
+    pub fn selected_patch(&self) -> ChangePatch {
+        let include = |selected: bool| match selected {
+            true => ChangeSegment::Include,
+            false => ChangeSegment::Exclude,
+        };
+        if self.hunks.is_empty() {
+            return ChangePatch {
+                file_path: self.path.clone(),
+                segments: vec![include(self.is_selected)],
+            };
+        }
+        let segments = self
+            .hunks
+            .iter()
+            .flat_map(|hunk| &hunk.lines)
+            .filter(|line| is_change(&line.line_type))
+            .map(|line| include(line.is_selected))
+            .collect();
+        ChangePatch {
+            file_path: self.path.clone(),
+            segments,
+        }
+    }
+}
+
+fn is_change(line_type: &HunkLineType) -> bool {
+    *line_type != HunkLineType::Context
+}
+
+/// The commit being split
+struct Target {
+    hash: String,
+    parent: Option<String>,
+    parent_tree: String,
+    tree: String,
+}
+
+fn resolve_target(repo_path: &Path, commit: &str) -> Result<Target> {
+    let hash = resolve_commit(repo_path, commit)?;
+    let is_ancestor = run_raw(
+        &["merge-base", "--is-ancestor", &hash, "HEAD"],
+        Some(repo_path),
+    )?;
+    if !is_ancestor.success() {
+        bail!("{} is not an ancestor of HEAD", commit);
+    }
+    let parents = run(
+        &["rev-list", "--parents", "-n", "1", &hash],
+        Some(repo_path),
+    )?;
+    let parents: Vec<&str> = parents.split_whitespace().skip(1).collect();
+    if parents.len() > 1 {
+        bail!("Cannot split merge commit {}", abbreviate_hash(&hash));
+    }
+    let parent = parents.first().map(|p| p.to_string());
+    let parent_tree = match parent {
+        Some(ref parent) => tree_of(repo_path, parent)?,
+        None => run(&["hash-object", "-t", "tree", "/dev/null"], Some(repo_path))?
+            .trim()
+            .to_string(),
+    };
+    let tree = tree_of(repo_path, &hash)?;
+    Ok(Target {
+        hash,
+        parent,
+        parent_tree,
+        tree,
+    })
+}
+
+fn tree_of(repo_path: &Path, commit: &str) -> Result<String> {
+    Ok(run(
+        &["rev-parse", "--verify", &format!("{}^{{tree}}", commit)],
+        Some(repo_path),
+    )?
+    .trim()
+    .to_string())
+}
+
+/// Files that differ between two trees, with their hunks
+fn changed_files(repo_path: &Path, from: &str, to: &str) -> Result<Vec<SplitFile>> {
+    let names = run(
+        &["diff", "--no-renames", "--name-only", "-z", from, to],
+        Some(repo_path),
+    )?;
+    names
+        .split('\0')
+        .filter(|name| !name.is_empty())
+        .map(|name| {
+            Ok(SplitFile {
+                path: name.to_string(),
+                hunks: get_tree_diff_hunks(repo_path, from, to, Path::new(name))?,
+                is_selected: false,
+            })
+        })
+        .collect()
+}
+
+/// Contents of `path` in `tree`, empty when it does not exist there
+fn blob(repo_path: &Path, tree: &str, path: &str) -> Result<Vec<u8>> {
+    let output = run_raw(
+        &["cat-file", "blob", &format!("{}:{}", tree, path)],
+        Some(repo_path),
+    )?;
+    Ok(if output.success() {
+        output.stdout
+    } else {
+        Vec::new()
+    })
+}
+
+/// Rebuild a file from its old and new contents, taking only the changed
+/// lines whose segment is `Include`
+fn select_lines(
+    old: &[u8],
+    new: &[u8],
+    hunks: &[Hunk],
+    segments: &[ChangeSegment],
+) -> Result<Vec<u8>> {
+    let old: Vec<&[u8]> = old.split_inclusive(|b| *b == b'\n').collect();
+    let new: Vec<&[u8]> = new.split_inclusive(|b| *b == b'\n').collect();
+    let mut segments = segments.iter();
+    let mut out: Vec<u8> = Vec::new();
+    let (mut old_index, mut new_index) = (0, 0);
+
+    let push = |out: &mut Vec<u8>, line: Option<&&[u8]>| -> Result<()> {
+        let line = line.context("The diff does not match the file")?;
+        // A line that lost its place at the end of the file needs its newline
+        if out.last().is_some_and(|b| *b != b'\n') {
+            out.push(b'\n');
+        }
+        out.extend_from_slice(line);
+        Ok(())
+    };
+
+    for hunk in hunks {
+        // A hunk that only adds lines starts after `old_start`
+        let start = if hunk.old_lines == 0 {
+            hunk.old_start
+        } else {
+            hunk.old_start.saturating_sub(1)
+        };
+        while old_index < start {
+            push(&mut out, old.get(old_index))?;
+            old_index += 1;
+            new_index += 1;
+        }
+        for line in &hunk.lines {
+            // "\ No newline at end of file"
+            if line.content.starts_with('\\') {
+                continue;
+            }
+            match line.line_type {
+                HunkLineType::Context => {
+                    push(&mut out, old.get(old_index))?;
+                    old_index += 1;
+                    new_index += 1;
+                }
+                HunkLineType::Deletion => {
+                    if segments.next() != Some(&ChangeSegment::Include) {
+                        push(&mut out, old.get(old_index))?;
+                    }
+                    old_index += 1;
+                }
+                HunkLineType::Addition => {
+                    if segments.next() == Some(&ChangeSegment::Include) {
+                        push(&mut out, new.get(new_index))?;
+                    }
+                    new_index += 1;
+                }
+            }
+        }
+    }
+    while old_index < old.len() {
+        push(&mut out, old.get(old_index))?;
+        old_index += 1;
+    }
+    Ok(out)
+}
+
+/// Tree of `from` plus the lines `patches` include of its diff to `to`
+pub(crate) fn apply_patches(
+    repo_path: &Path,
+    from: &str,
+    to: &str,
+    patches: &[ChangePatch],
+) -> Result<String> {
+    let scratch = tempdir()?;
+    let index = scratch.path().join("index");
+    let index = index.to_string_lossy().to_string();
+    let env = HashMap::from([("GIT_INDEX_FILE", index.as_str())]);
+    run_with_env(&["read-tree", from], Some(repo_path), &env)?;
+
+    let files = changed_files(repo_path, from, to)?;
+    for patch in patches {
+        let Some(file) = files.iter().find(|f| f.path == patch.file_path) else {
+            bail!("{} has no changes left to split", patch.file_path);
+        };
+        let path = file.path.as_str();
+        let whole = if file.hunks.is_empty() {
+            patch.segments.first() == Some(&ChangeSegment::Include)
+        } else {
+            let old = blob(repo_path, from, path)?;
+            let new = blob(repo_path, to, path)?;
+            let selected = select_lines(&old, &new, &file.hunks, &patch.segments)?;
+            if selected == old {
+                continue;
+            }
+            if selected != new {
+                let content = scratch.path().join("content");
+                fs::write(&content, &selected)?;
+                let oid = run(
+                    &[
+                        "hash-object",
+                        "-w",
+                        "--no-filters",
+                        &content.to_string_lossy(),
+                    ],
+                    Some(repo_path),
+                )?;
+                let mode = entry_mode(repo_path, to, path)?
+                    .or(entry_mode(repo_path, from, path)?)
+                    .unwrap_or_else(|| "100644".to_string());
+                let cacheinfo = format!("{},{},{}", mode, oid.trim(), path);
+                run_with_env(
+                    &["update-index", "--add", "--cacheinfo", &cacheinfo],
+                    Some(repo_path),
+                    &env,
+                )?;
+                continue;
+            }
+            true
+        };
+        if !whole {
+            continue;
+        }
+        // Take the file as it is in `to`, or delete it
+        let entry = run(&["ls-tree", "-z", to, "--", path], Some(repo_path))?;
+        match entry.trim_end_matches('\0').split_once('\t') {
+            Some((info, _)) => {
+                let info: Vec<&str> = info.split(' ').collect();
+                let cacheinfo = format!("{},{},{}", info[0], info[2], path);
+                run_with_env(
+                    &["update-index", "--add", "--cacheinfo", &cacheinfo],
+                    Some(repo_path),
+                    &env,
+                )?;
+            }
+            None => {
+                run_with_env(
+                    &["update-index", "--force-remove", "--", path],
+                    Some(repo_path),
+                    &env,
+                )?;
+            }
+        }
+    }
+
+    Ok(run_with_env(&["write-tree"], Some(repo_path), &env)?
+        .trim()
+        .to_string())
+}
+
+fn entry_mode(repo_path: &Path, tree: &str, path: &str) -> Result<Option<String>> {
+    let entry = run(&["ls-tree", tree, "--", path], Some(repo_path))?;
+    Ok(entry.split_whitespace().next().map(str::to_string))
+}
+
+/// Trees of the parts, in order
+fn part_trees(repo_path: &Path, target: &Target, parts: &[SplitPart]) -> Result<Vec<String>> {
+    let mut trees = Vec::with_capacity(parts.len());
+    let mut base = target.parent_tree.clone();
+    for (i, part) in parts.iter().enumerate() {
+        let tree = apply_patches(repo_path, &base, &target.tree, &part.patches)?;
+        if tree == base {
+            bail!("Part {} of the split selects no changes", i + 1);
+        }
+        trees.push(tree.clone());
+        base = tree;
+    }
+    if base == target.tree {
+        bail!("The selected lines leave nothing for the last commit");
+    }
+    Ok(trees)
+}
+
+/// The changes of `commit`, by file, that `parts` leave over
+pub fn split_files(repo_path: &Path, commit: &str, parts: &[SplitPart]) -> Result<Vec<SplitFile>> {
+    let target = resolve_target(repo_path, commit)?;
+    let mut base = target.parent_tree.clone();
+    for part in parts {
+        base = apply_patches(repo_path, &base, &target.tree, &part.patches)?;
+    }
+    changed_files(repo_path, &base, &target.tree)
+}
+
+/// Split `commit` into one commit per part followed by the rest of its change
+/// under its original message, all with its author and date. The commits
+/// above it, and any stacks on top of it, are carried along. Returns the new
+/// commits, oldest first.
+pub fn split_commit(repo_path: &Path, commit: &str, parts: &[SplitPart]) -> Result<Vec<String>> {
+    if parts.is_empty() {
+        bail!("Nothing to do: the split has no parts");
+    }
+    let target = resolve_target(repo_path, commit)?;
+    let merges = run(
+        &[
+            "rev-list",
+            "--min-parents=2",
+            &format!("{}..HEAD", target.hash),
+        ],
+        Some(repo_path),
+    )?;
+    if !merges.trim().is_empty() {
+        bail!("Cannot split a commit below a merge");
+    }
+
+    let trees = part_trees(repo_path, &target, parts)?;
+    let mut parent = target.parent.clone();
+    let mut commits = Vec::with_capacity(parts.len() + 1);
+    for (part, tree) in parts.iter().zip(&trees) {
+        let hash = rewrite::recommit(
+            repo_path,
+            &target.hash,
+            parent.as_deref(),
+            tree,
+            Some(&part.message),
+        )?;
+        parent = Some(hash.clone());
+        commits.push(hash);
+    }
+    let last = rewrite::recommit(
+        repo_path,
+        &target.hash,
+        parent.as_deref(),
+        &target.tree,
+        None,
+    )?;
+    commits.push(last.clone());
+
+    // The last commit has the original tree, so nothing above can conflict
+    let above = run(
+        &["rev-list", "--reverse", &format!("{}..HEAD", target.hash)],
+        Some(repo_path),
+    )?;
+    let steps: Vec<RebaseStep> = above
+        .lines()
+        .map(|hash| RebaseStep::new(hash, RebaseAction::Pick))
+        .collect();
+    let mut rewritten = vec![(target.hash.clone(), last.clone())];
+    let tip = if steps.is_empty() {
+        last
+    } else {
+        let replayed = rewrite::replay(repo_path, Some(&last), &steps)?
+            .context("Failed to carry the commits above the split along")?;
+        rewritten.extend(replayed.rewritten);
+        replayed.tip.unwrap_or(last)
+    };
+    rewrite::move_head(repo_path, &tip, "split")?;
+    stack::record_successors(repo_path, &rewritten)?;
+
+    // Branches stacked on the old commit (not just those below HEAD)
+    if stack::needs_restack(repo_path).unwrap_or(false) {
+        stack::restack(repo_path)?;
+    }
+    Ok(commits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{create_test_repo, git};
+
+    #[test]
+    fn test_split_commit_by_lines() {
+        let dir = create_test_repo();
+        let path = dir.path();
+        fs::write(path.join("f.txt"), "1\n2\n3\n4\n5\n6\n7\n8\n9\n").unwrap();
+        git(path, &["add", "f.txt"]);
+        git(path, &["commit", "-m", "Base"]);
+        fs::write(path.join("f.txt"), "one\n2\n3\n4\n5\n6\n7\n8\nnine").unwrap();
+        fs::write(path.join("new.txt"), "new\n").unwrap();
+        git(path, &["add", "f.txt", "new.txt"]);
+        git(
+            path,
+            &[
+                "commit",
+                "-m",
+                "Mixed change",
+                "--date=2020-01-01T00:00:00Z",
+            ],
+        );
+        git(path, &["commit", "--allow-empty", "-m", "Above"]);
+        let date = git(path, &["log", "-1", "--format=%ad", "HEAD~1"]);
+
+        let files = split_files(path, "HEAD~1", &[]).unwrap();
+        let names: Vec<&str> = files.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(names, ["f.txt", "new.txt"]);
+        // -1 +one in the first hunk, -9 +nine (no newline) in the second
+        assert_eq!(files[0].segment_count(), 4);
+        assert_eq!(files[1].segment_count(), 1);
+
+        // Take the last line's change, then the new file
+        use ChangeSegment::{Exclude, Include};
+        let parts = [
+            SplitPart {
+                patches: vec![ChangePatch {
+                    file_path: "f.txt".to_string(),
+                    segments: vec![Exclude, Exclude, Include, Include],
+                }],
+                message: "Change nine".to_string(),
+            },
+            SplitPart {
+                patches: vec![ChangePatch {
+                    file_path: "new.txt".to_string(),
+                    segments: vec![Include],
+                }],
+                message: "Add new.txt".to_string(),
+            },
+        ];
+        let left = split_files(path, "HEAD~1", &parts).unwrap();
+        assert_eq!(left.len(), 1);
+        assert_eq!(left[0].segment_count(), 2);
+
+        let commits = split_commit(path, "HEAD~1", &parts).unwrap();
+        assert_eq!(commits.len(), 3);
+        assert_eq!(
+            git(path, &["log", "--format=%s"]),
+            "Above\nMixed change\nAdd new.txt\nChange nine\nBase"
+        );
+        assert_eq!(
+            git(path, &["show", "HEAD~3:f.txt"]),
+            "1\n2\n3\n4\n5\n6\n7\n8\nnine"
+        );
+        assert_eq!(git(path, &["ls-tree", "--name-only", "HEAD~3"]), "f.txt");
+        assert_eq!(git(path, &["log", "-1", "--format=%ad", "HEAD~3"]), date);
+        assert_eq!(git(path, &["log", "-1", "--format=%ad", "HEAD~1"]), date);
+        assert_eq!(git(path, &["status", "--porcelain"]), "");
+        assert_eq!(
+            git(path, &["diff", "HEAD~1", "HEAD~2", "--stat"])
+                .lines()
+                .count(),
+            2
+        );
+
+        // Selecting everything leaves nothing for the original message
+        let everything = SplitPart {
+            patches: vec![ChangePatch {
+                file_path: "f.txt".to_string(),
+                segments: vec![Include, Include],
+            }],
+            message: "All".to_string(),
+        };
+        assert!(split_commit(path, "HEAD~1", &[everything]).is_err());
+    }
+
+    #[test]
+    fn test_split_lines_inside_one_hunk() {
+        let dir = create_test_repo();
+        let path = dir.path();
+        fs::write(path.join("f.txt"), "1\n2\n3\n").unwrap();
+        git(path, &["add", "f.txt"]);
+        git(path, &["commit", "-m", "Base"]);
+        fs::write(path.join("f.txt"), "1\ntwo\nextra\n3\n").unwrap();
+        git(path, &["commit", "-am", "Rework two"]);
+
+        let files = split_files(path, "HEAD", &[]).unwrap();
+        assert_eq!(files[0].hunks.len(), 1);
+        // -2 +two +extra
+        assert_eq!(files[0].segment_count(), 3);
+
+        use ChangeSegment::{Exclude, Include};
+        let parts = [SplitPart {
+            patches: vec![ChangePatch {
+                file_path: "f.txt".to_string(),
+                segments: vec![Include, Include, Exclude],
+            }],
+            message: "Rename two".to_string(),
+        }];
+        split_commit(path, "HEAD", &parts).unwrap();
+        assert_eq!(git(path, &["show", "HEAD~1:f.txt"]), "1\ntwo\n3");
+        assert_eq!(git(path, &["show", "HEAD:f.txt"]), "1\ntwo\nextra\n3");
+        // The second commit only adds the line left out
+        assert_eq!(
+            git(path, &["diff", "--numstat", "HEAD~1", "HEAD"]),
+            "1\t0\tf.txt"
+        );
+    }
+
+    #[test]
+    fn test_split_three_ways_and_restack() {
+        let dir = create_test_repo();
+        let path = dir.path();
+        git(path, &["commit", "--allow-empty", "-m", "Base"]);
+        git(path, &["checkout", "-b", "feature"]);
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            fs::write(path.join(name), format!("{}\n", name)).unwrap();
+        }
+        git(path, &["add", "."]);
+        git(
+            path,
+            &[
+                "commit",
+                "-m",
+                "Add three files",
+                "--author=Other Person <other@example.com>",
+                "--date=2020-01-01T00:00:00Z",
+            ],
+        );
+        let old = git(path, &["rev-parse", "HEAD"]);
+        // A stack on top of the commit, not below HEAD
+        git(path, &["checkout", "-b", "stacked"]);
+        fs::write(path.join("d.txt"), "d\n").unwrap();
+        git(path, &["add", "d.txt"]);
+        git(path, &["commit", "-m", "Add d.txt"]);
+        git(path, &["checkout", "feature"]);
+
+        let part = |file: &str| SplitPart {
+            patches: vec![ChangePatch {
+                file_path: file.to_string(),
+                segments: vec![ChangeSegment::Include],
+            }],
+            message: format!("Add {}", file),
+        };
+        let commits = split_commit(path, "HEAD", &[part("a.txt"), part("b.txt")]).unwrap();
+        assert_eq!(commits.len(), 3);
+        assert_eq!(git(path, &["rev-parse", "HEAD"]), commits[2]);
+        assert_eq!(
+            git(path, &["log", "--format=%s", "main..HEAD"]),
+            "Add three files\nAdd b.txt\nAdd a.txt"
+        );
+        for (commit, file) in commits.iter().zip(["a.txt", "b.txt", "c.txt"]) {
+            let files = git(
+                path,
+                &["diff-tree", "--no-commit-id", "--name-only", "-r", commit],
+            );
+            assert_eq!(files, file);
+            assert_eq!(
+                git(path, &["log", "-1", "--format=%an <%ae> %aI", commit]),
+                "Other Person <other@example.com> 2020-01-01T00:00:00+00:00"
+            );
+        }
+
+        // The stacked branch now sits on the last part
+        assert_eq!(git(path, &["rev-parse", "stacked~1"]), commits[2]);
+        assert_eq!(git(path, &["show", "stacked:d.txt"]), "d");
+        assert!(run(
+            &["merge-base", "--is-ancestor", &old, "stacked"],
+            Some(path)
+        )
+        .is_err());
+        assert!(!stack::needs_restack(path).unwrap());
+    }
+}
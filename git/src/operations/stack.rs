@@ -0,0 +1,666 @@
+use crate::command::{run, run_raw};
+use crate::models::abbreviate_hash;
+use crate::operations::{reflog, rewrite};
+use anyhow::{bail, Result};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// One draft commit of the current stack
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StackEntry {
+    pub hash: String,
+    pub parents: Vec<String>,
+    pub summary: String,
+    /// Local branches pointing at the commit
+    pub branches: Vec<String>,
+    pub is_head: bool,
+    /// Newest version of the commit, if it has been amended or rewritten
+    pub successor: Option<String>,
+    /// The commit's parent has been rewritten; `restack` moves it onto the
+    /// parent's successor
+    pub obsolete: bool,
+}
+
+/// Direction for `stack_target`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StackMove {
+    /// Towards the top of the stack (children)
+    Next,
+    /// Towards trunk (parents)
+    Prev,
+}
+
+/// A draft commit as listed by `drafts`
+struct Draft {
+    hash: String,
+    parents: Vec<String>,
+    summary: String,
+}
+
+/// The branch commits are measured against: `openisl.trunk` if configured,
+/// then `origin/HEAD`, then the first of `main`, `master` and `trunk` that
+/// exists locally or on `origin`
+pub fn find_trunk(repo_path: &Path) -> Result<String> {
+    let exists = |name: &str| -> Result<bool> {
+        let spec = format!("{}^{{commit}}", name);
+        Ok(run_raw(
+            &["rev-parse", "--verify", "--quiet", &spec],
+            Some(repo_path),
+        )?
+        .success())
+    };
+
+    let configured = run_raw(&["config", "openisl.trunk"], Some(repo_path))?;
+    if configured.success() {
+        let name = String::from_utf8_lossy(&configured.stdout)
+            .trim()
+            .to_string();
+        if !exists(&name)? {
+            bail!("openisl.trunk is set to '{}', which does not exist", name);
+        }
+        return Ok(name);
+    }
+
+    let origin_head = run_raw(
+        &[
+            "symbolic-ref",
+            "--quiet",
+            "--short",
+            "refs/remotes/origin/HEAD",
+        ],
+        Some(repo_path),
+    )?;
+    if origin_head.success() {
+        return Ok(String::from_utf8_lossy(&origin_head.stdout)
+            .trim()
+            .to_string());
+    }
+
+    for name in ["main", "master", "trunk"] {
+        for candidate in [name.to_string(), format!("origin/{}", name)] {
+            if exists(&candidate)? {
+                return Ok(candidate);
+            }
+        }
+    }
+    bail!("No trunk branch found; set one with `git config openisl.trunk <branch>`")
+}
+
+/// Draft commits reachable from `HEAD` or a local branch but not from trunk,
+/// oldest first
+fn drafts(repo_path: &Path, trunk: &str) -> Result<Vec<Draft>> {
+    let output = run(
+        &[
+            "log",
+            "--topo-order",
+            "--reverse",
+            "--format=%x1e%H%x00%P%x00%s",
+            "HEAD",
+            "--branches",
+            "--not",
+            trunk,
+            "--",
+        ],
+        Some(repo_path),
+    )?;
+    Ok(output
+        .split('\x1e')
+        .filter(|record| !record.trim().is_empty())
+        .filter_map(|record| {
+            let mut fields = record.trim_end_matches('\n').splitn(3, '\0');
+            Some(Draft {
+                hash: fields.next()?.to_string(),
+                parents: fields
+                    .next()?
+                    .split_whitespace()
+                    .map(str::to_string)
+                    .collect(),
+                summary: fields.next().unwrap_or_default().to_string(),
+            })
+        })
+        .collect())
+}
+
+/// Local branch names by the commit they point at
+fn local_branches(repo_path: &Path) -> Result<HashMap<String, Vec<String>>> {
+    let output = run(
+        &[
+            "for-each-ref",
+            "--format=%(objectname)%00%(refname:short)",
+            "refs/heads",
+        ],
+        Some(repo_path),
+    )?;
+    let mut branches: HashMap<String, Vec<String>> = HashMap::new();
+    for line in output.lines() {
+        if let Some((hash, name)) = line.split_once('\0') {
+            branches
+                .entry(hash.to_string())
+                .or_default()
+                .push(name.to_string());
+        }
+    }
+    Ok(branches)
+}
+
+fn head(repo_path: &Path) -> Result<String> {
+    Ok(run(&["rev-parse", "--verify", "HEAD"], Some(repo_path))?
+        .trim()
+        .to_string())
+}
+
+fn successors_file(repo_path: &Path) -> Result<PathBuf> {
+    let path = run(
+        &["rev-parse", "--git-path", "openisl-successors"],
+        Some(repo_path),
+    )?;
+    Ok(repo_path.join(path.trim()))
+}
+
+/// Remember that each `(old, new)` pair's `new` commit replaces `old`
+pub fn record_successors(repo_path: &Path, pairs: &[(String, String)]) -> Result<()> {
+    let pairs: Vec<_> = pairs.iter().filter(|(old, new)| old != new).collect();
+    if pairs.is_empty() {
+        return Ok(());
+    }
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(successors_file(repo_path)?)?;
+    for (old, new) in pairs {
+        writeln!(file, "{} {}", old, new)?;
+    }
+    Ok(())
+}
+
+/// Direct successors, from amends in the reflog and the pairs openISL records:
+/// each rewritten commit and the commit that replaced it
+pub fn get_successors(repo_path: &Path) -> Result<HashMap<String, String>> {
+    let mut successors = HashMap::new();
+
+    // Newest first, so each amend's previous entry is the commit it replaced
+    let entries = reflog::get_reflog(repo_path, None, None).unwrap_or_default();
+    for (entry, previous) in entries.iter().zip(entries.iter().skip(1)) {
+        if entry.action.starts_with("commit (amend)") && entry.hash != previous.hash {
+            successors.insert(previous.hash.clone(), entry.hash.clone());
+        }
+    }
+
+    let recorded = fs::read_to_string(successors_file(repo_path)?).unwrap_or_default();
+    for line in recorded.lines() {
+        if let Some((old, new)) = line.split_once(' ') {
+            successors.insert(old.to_string(), new.to_string());
+        }
+    }
+    Ok(successors)
+}
+
+/// Follow `hash`'s successors to the newest one that is still a draft
+fn newest(
+    successors: &HashMap<String, String>,
+    drafts: &HashSet<&str>,
+    hash: &str,
+) -> Option<String> {
+    let mut current = hash;
+    let mut newest = None;
+    for _ in 0..successors.len() {
+        let Some(next) = successors.get(current) else {
+            break;
+        };
+        if drafts.contains(next.as_str()) {
+            newest = Some(next.clone());
+        }
+        current = next;
+    }
+    newest.filter(|newest| newest != hash)
+}
+
+/// The stack `HEAD` is on, newest first: its draft ancestors, the commits
+/// above it, and commits still sitting on older versions of any of them
+pub fn get_stack(repo_path: &Path) -> Result<Vec<StackEntry>> {
+    let (drafts, newest_of) = rewritten_drafts(repo_path)?;
+    let head = head(repo_path)?;
+    let branches = local_branches(repo_path)?;
+
+    let draft_set: HashSet<&str> = drafts.iter().map(|d| d.hash.as_str()).collect();
+    let by_hash: HashMap<&str, &Draft> = drafts.iter().map(|d| (d.hash.as_str(), d)).collect();
+
+    // HEAD and its draft ancestors
+    let mut in_stack: HashSet<String> = HashSet::new();
+    let mut current = Some(head.as_str());
+    while let Some(hash) = current {
+        in_stack.insert(hash.to_string());
+        current = by_hash
+            .get(hash)
+            .and_then(|d| d.parents.first())
+            .map(String::as_str)
+            .filter(|parent| draft_set.contains(parent));
+    }
+
+    // Older versions of those, then everything above any of them
+    let mut roots: HashSet<String> = newest_of
+        .iter()
+        .filter(|(_, newest)| in_stack.contains(newest.as_str()))
+        .map(|(old, _)| old.to_string())
+        .collect();
+    roots.insert(head.clone());
+    for draft in &drafts {
+        if roots.contains(&draft.hash) || draft.parents.iter().any(|p| roots.contains(p)) {
+            roots.insert(draft.hash.clone());
+        }
+    }
+    in_stack.extend(roots);
+
+    Ok(drafts
+        .iter()
+        .rev()
+        .filter(|d| in_stack.contains(&d.hash))
+        .map(|d| StackEntry {
+            hash: d.hash.clone(),
+            parents: d.parents.clone(),
+            summary: d.summary.clone(),
+            branches: branches.get(&d.hash).cloned().unwrap_or_default(),
+            is_head: d.hash == head,
+            successor: newest_of.get(d.hash.as_str()).cloned(),
+            obsolete: d.parents.iter().any(|p| newest_of.contains_key(p.as_str())),
+        })
+        .collect())
+}
+
+/// What to check out to move `count` commits up or down the stack: the
+/// commit's branch when exactly one local branch points at it, else its hash
+pub fn stack_target(repo_path: &Path, direction: StackMove, count: usize) -> Result<String> {
+    let (drafts, newest_of) = rewritten_drafts(repo_path)?;
+
+    let mut current = head(repo_path)?;
+    for _ in 0..count {
+        current = match direction {
+            StackMove::Prev => {
+                let draft = drafts.iter().find(|d| d.hash == current);
+                match draft.and_then(|d| d.parents.first()) {
+                    Some(parent) => parent.clone(),
+                    None => bail!("Already at the bottom of the stack"),
+                }
+            }
+            StackMove::Next => {
+                let children: Vec<&Draft> = drafts
+                    .iter()
+                    .filter(|d| d.parents.first() == Some(&current))
+                    .filter(|d| !newest_of.contains_key(&d.hash))
+                    .collect();
+                match children[..] {
+                    [] => bail!("Already at the top of the stack"),
+                    [child] => child.hash.clone(),
+                    _ => {
+                        let names: Vec<String> = children
+                            .iter()
+                            .map(|c| format!("{} {}", abbreviate_hash(&c.hash), c.summary))
+                            .collect();
+                        bail!(
+                            "{} has more than one child; check one out directly:\n  {}",
+                            abbreviate_hash(&current),
+                            names.join("\n  ")
+                        );
+                    }
+                }
+            }
+        };
+    }
+
+    let branches = local_branches(repo_path)?;
+    Ok(match branches.get(&current).map(Vec::as_slice) {
+        Some([branch]) => branch.clone(),
+        _ => current,
+    })
+}
+
+/// All draft commits, and the newest successor of each rewritten one
+fn rewritten_drafts(repo_path: &Path) -> Result<(Vec<Draft>, HashMap<String, String>)> {
+    let trunk = find_trunk(repo_path)?;
+    let drafts = drafts(repo_path, &trunk)?;
+    let successors = get_successors(repo_path)?;
+    let draft_set: HashSet<&str> = drafts.iter().map(|d| d.hash.as_str()).collect();
+    let newest_of = drafts
+        .iter()
+        .filter_map(|d| Some((d.hash.clone(), newest(&successors, &draft_set, &d.hash)?)))
+        .collect();
+    Ok((drafts, newest_of))
+}
+
+/// Whether `restack` has anything to do: a draft commit or a local branch
+/// still points at or sits on a rewritten commit
+pub fn needs_restack(repo_path: &Path) -> Result<bool> {
+    let (drafts, newest_of) = rewritten_drafts(repo_path)?;
+    if newest_of.is_empty() {
+        return Ok(false);
+    }
+    let on_rewritten = drafts
+        .iter()
+        .filter(|d| !newest_of.contains_key(&d.hash))
+        .any(|d| d.parents.iter().any(|p| newest_of.contains_key(p)));
+    let branch_on_rewritten = local_branches(repo_path)?
+        .keys()
+        .any(|hash| newest_of.contains_key(hash));
+    Ok(on_rewritten || branch_on_rewritten)
+}
+
+/// Copy every draft commit that sits on a rewritten commit onto that commit's
+/// newest successor, then move local branches (and `HEAD`) along. Branches
+/// pointing at a rewritten commit move to its successor. Returns the number of
+/// commits copied; nothing moves if any of them conflicts.
+pub fn restack(repo_path: &Path) -> Result<usize> {
+    let (drafts, newest_of) = rewritten_drafts(repo_path)?;
+    let newest_of: HashMap<&str, String> = newest_of
+        .iter()
+        .map(|(old, new)| (old.as_str(), new.clone()))
+        .collect();
+
+    // Commits to copy: not rewritten themselves, but above a rewritten one
+    let mut to_move: Vec<&Draft> = Vec::new();
+    let mut moving: HashSet<&str> = HashSet::new();
+    for draft in &drafts {
+        if newest_of.contains_key(draft.hash.as_str()) {
+            continue;
+        }
+        if draft.parents.len() > 1 {
+            continue;
+        }
+        let above_rewritten = draft
+            .parents
+            .iter()
+            .any(|p| newest_of.contains_key(p.as_str()) || moving.contains(p.as_str()));
+        if above_rewritten {
+            moving.insert(&draft.hash);
+            to_move.push(draft);
+        }
+    }
+
+    // A successor may itself be moving, so copy each commit once its new
+    // parent is final
+    let mut moved: HashMap<String, String> = HashMap::new();
+    let target_of = |hash: &str, moved: &HashMap<String, String>| -> Option<String> {
+        let hash = newest_of.get(hash).map(String::as_str).unwrap_or(hash);
+        if moving.contains(hash) {
+            moved.get(hash).cloned()
+        } else {
+            Some(hash.to_string())
+        }
+    };
+    while moved.len() < to_move.len() {
+        let before = moved.len();
+        for draft in &to_move {
+            if moved.contains_key(&draft.hash) {
+                continue;
+            }
+            let Some(parent) = target_of(&draft.parents[0], &moved) else {
+                continue;
+            };
+            let Some(copy) = rewrite::rebase_commit(repo_path, &draft.hash, &parent)? else {
+                bail!(
+                    "Restacking {} ({}) conflicts; rebase it by hand with \
+                     `git rebase --onto {} {}`",
+                    abbreviate_hash(&draft.hash),
+                    draft.summary,
+                    abbreviate_hash(&parent),
+                    abbreviate_hash(&draft.parents[0])
+                );
+            };
+            moved.insert(draft.hash.clone(), copy);
+        }
+        if moved.len() == before {
+            bail!("Cannot restack: the rewritten commits form a cycle");
+        }
+    }
+
+    let new_position = |hash: &str| -> Option<String> {
+        moved
+            .get(hash)
+            .cloned()
+            .or_else(|| target_of(hash, &moved).filter(|target| target != hash))
+    };
+
+    // HEAD first, since updating the checkout is the step that can fail
+    let head = head(repo_path)?;
+    if let Some(new_head) = new_position(&head) {
+        rewrite::move_head(repo_path, &new_head, "restack")?;
+    }
+    let current = run_raw(&["symbolic-ref", "--quiet", "HEAD"], Some(repo_path))?;
+    let current = String::from_utf8_lossy(&current.stdout).trim().to_string();
+    for (hash, names) in local_branches(repo_path)? {
+        let Some(new) = new_position(&hash) else {
+            continue;
+        };
+        for name in names {
+            let refname = format!("refs/heads/{}", name);
+            if refname == current {
+                continue;
+            }
+            run(
+                &[
+                    "update-ref",
+                    "-m",
+                    "openisl: restack",
+                    &refname,
+                    &new,
+                    &hash,
+                ],
+                Some(repo_path),
+            )?;
+        }
+    }
+
+    let pairs: Vec<(String, String)> = moved.into_iter().collect();
+    record_successors(repo_path, &pairs)?;
+    Ok(pairs.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{commit_file, create_test_repo, git};
+    use tempfile::TempDir;
+
+    fn setup() -> TempDir {
+        let dir = create_test_repo();
+        git(dir.path(), &["commit", "--allow-empty", "-m", "Trunk"]);
+        dir
+    }
+
+    fn commit(path: &Path, name: &str) -> String {
+        commit_file(path, name, &format!("{}\n", name), &format!("Add {}", name))
+    }
+
+    #[test]
+    fn test_stack_amend_and_restack() {
+        let dir = setup();
+        let path = dir.path();
+        git(path, &["checkout", "-b", "part1"]);
+        let a = commit(path, "a.txt");
+        git(path, &["checkout", "-b", "part2"]);
+        commit(path, "b.txt");
+        commit(path, "c.txt");
+
+        let stack = get_stack(path).unwrap();
+        let summaries: Vec<&str> = stack.iter().map(|e| e.summary.as_str()).collect();
+        assert_eq!(summaries, vec!["Add c.txt", "Add b.txt", "Add a.txt"]);
+        assert!(stack[0].is_head);
+        assert_eq!(stack[2].branches, vec!["part1".to_string()]);
+
+        assert_eq!(stack_target(path, StackMove::Prev, 2).unwrap(), "part1");
+        assert!(stack_target(path, StackMove::Next, 1).is_err());
+        assert!(stack_target(path, StackMove::Prev, 4).is_err());
+
+        // Amend the bottom commit; the rest of the stack is left behind
+        git(path, &["checkout", "part1"]);
+        fs::write(path.join("a.txt"), "a, amended\n").unwrap();
+        git(path, &["commit", "-a", "--amend", "-m", "Add a.txt (v2)"]);
+        let stack = get_stack(path).unwrap();
+        let old = stack.iter().find(|e| e.hash == a).unwrap();
+        assert_eq!(old.successor, Some(git(path, &["rev-parse", "HEAD"])));
+        let obsolete: Vec<&str> = stack
+            .iter()
+            .filter(|e| e.obsolete)
+            .map(|e| e.summary.as_str())
+            .collect();
+        assert_eq!(obsolete, vec!["Add b.txt"]);
+        assert!(needs_restack(path).unwrap());
+
+        assert_eq!(restack(path).unwrap(), 2);
+        assert_eq!(
+            git(path, &["log", "--format=%s", "part2"]),
+            "Add c.txt\nAdd b.txt\nAdd a.txt (v2)\nTrunk"
+        );
+        assert_eq!(git(path, &["show", "part2:a.txt"]), "a, amended");
+        let stack = get_stack(path).unwrap();
+        assert_eq!(stack.len(), 3);
+        assert!(stack.iter().all(|e| !e.obsolete && e.successor.is_none()));
+        assert_eq!(stack_target(path, StackMove::Next, 2).unwrap(), "part2");
+        assert!(!needs_restack(path).unwrap());
+        assert_eq!(restack(path).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_find_trunk() {
+        let dir = setup();
+        let path = dir.path();
+        assert_eq!(find_trunk(path).unwrap(), "main");
+
+        git(path, &["branch", "develop"]);
+        git(path, &["config", "openisl.trunk", "develop"]);
+        assert_eq!(find_trunk(path).unwrap(), "develop");
+        git(path, &["config", "openisl.trunk", "missing"]);
+        assert!(find_trunk(path).is_err());
+    }
+
+    #[test]
+    fn test_successors_from_amend_reflog() {
+        let dir = setup();
+        let path = dir.path();
+        git(path, &["checkout", "-b", "feature"]);
+        let first = commit(path, "a.txt");
+        git(path, &["commit", "--amend", "-m", "Add a.txt (v2)"]);
+        let second = git(path, &["rev-parse", "HEAD"]);
+        git(path, &["commit", "--amend", "-m", "Add a.txt (v3)"]);
+        let third = git(path, &["rev-parse", "HEAD"]);
+        let later = commit(path, "b.txt");
+
+        let successors = get_successors(path).unwrap();
+        assert_eq!(successors.get(&first), Some(&second));
+        assert_eq!(successors.get(&second), Some(&third));
+        // Plain commits replace nothing
+        assert!(!successors.contains_key(&third));
+        assert!(!successors.values().any(|new| new == &later));
+    }
+
+    #[test]
+    fn test_recorded_successors() {
+        let dir = setup();
+        let path = dir.path();
+        let pairs = vec![
+            ("a".repeat(40), "b".repeat(40)),
+            ("c".repeat(40), "c".repeat(40)),
+        ];
+        record_successors(path, &pairs).unwrap();
+        record_successors(path, &[]).unwrap();
+
+        let successors = get_successors(path).unwrap();
+        assert_eq!(successors.get(&"a".repeat(40)), Some(&"b".repeat(40)));
+        // A commit is never its own successor
+        assert!(!successors.contains_key(&"c".repeat(40)));
+    }
+
+    #[test]
+    fn test_obsolete_marks_commits_on_rewritten_parents() {
+        let dir = setup();
+        let path = dir.path();
+        git(path, &["checkout", "-b", "feature"]);
+        let a = commit(path, "a.txt");
+        let b = commit(path, "b.txt");
+        let c = commit(path, "c.txt");
+        assert!(get_stack(path).unwrap().iter().all(|e| !e.obsolete));
+
+        // Rewrite the middle commit outside of a checkout, as openISL does
+        let tree = git(path, &["rev-parse", &format!("{}^{{tree}}", b)]);
+        let new_b = git(
+            path,
+            &["commit-tree", &tree, "-p", &a, "-m", "Add b.txt (v2)"],
+        );
+        git(path, &["branch", "rewritten", &new_b]);
+        record_successors(path, &[(b.clone(), new_b.clone())]).unwrap();
+
+        let stack = get_stack(path).unwrap();
+        let entry = |hash: &str| stack.iter().find(|e| e.hash == hash).unwrap();
+        assert!(entry(&c).obsolete);
+        assert!(!entry(&b).obsolete);
+        assert_eq!(entry(&b).successor.as_deref(), Some(new_b.as_str()));
+        assert!(!entry(&a).obsolete && entry(&a).successor.is_none());
+    }
+
+    #[test]
+    fn test_restack_moves_branch_on_rewritten_commit() {
+        let dir = setup();
+        let path = dir.path();
+        git(path, &["checkout", "-b", "feature"]);
+        let a = commit(path, "a.txt");
+        git(path, &["branch", "old-feature"]);
+        git(path, &["commit", "--amend", "-m", "Add a.txt (v2)"]);
+        let amended = git(path, &["rev-parse", "HEAD"]);
+
+        // Nothing sits on the old commit, but a branch still points at it
+        assert_eq!(git(path, &["rev-parse", "old-feature"]), a);
+        assert!(needs_restack(path).unwrap());
+        assert_eq!(restack(path).unwrap(), 0);
+        assert_eq!(git(path, &["rev-parse", "old-feature"]), amended);
+        assert!(!needs_restack(path).unwrap());
+    }
+
+    #[test]
+    fn test_restack_conflict_moves_nothing() {
+        let dir = setup();
+        let path = dir.path();
+        git(path, &["checkout", "-b", "part1"]);
+        commit_file(path, "a.txt", "one\n", "One");
+        git(path, &["checkout", "-b", "part2"]);
+        let two = commit_file(path, "a.txt", "two\n", "Two");
+        git(path, &["checkout", "part1"]);
+        fs::write(path.join("a.txt"), "amended\n").unwrap();
+        git(path, &["commit", "-a", "--amend", "-m", "One (v2)"]);
+
+        let err = restack(path).unwrap_err();
+        assert!(format!("{}", err).contains("conflicts"));
+        assert_eq!(git(path, &["rev-parse", "part2"]), two);
+        assert_eq!(git(path, &["status", "--porcelain"]), "");
+    }
+
+    #[test]
+    fn test_stack_target_next_and_prev() {
+        let dir = setup();
+        let path = dir.path();
+        git(path, &["checkout", "-b", "feature"]);
+        let a = commit(path, "a.txt");
+        let b = commit(path, "b.txt");
+        git(path, &["branch", "also-b"]);
+
+        // Two branches on a commit: its hash is the target
+        git(path, &["checkout", "--detach", &a]);
+        assert_eq!(stack_target(path, StackMove::Next, 1).unwrap(), b);
+        // The bottom draft steps down onto trunk, and no further
+        assert_eq!(stack_target(path, StackMove::Prev, 1).unwrap(), "main");
+        assert!(stack_target(path, StackMove::Prev, 2).is_err());
+
+        // A second child makes `next` ambiguous
+        git(path, &["checkout", "-b", "sibling", &a]);
+        let sibling = commit(path, "c.txt");
+        git(path, &["checkout", "--detach", &a]);
+        let err = stack_target(path, StackMove::Next, 1).unwrap_err();
+        assert!(format!("{}", err).contains("more than one child"));
+
+        // Rewritten children are skipped
+        record_successors(path, &[(sibling.clone(), b.clone())]).unwrap();
+        assert_eq!(stack_target(path, StackMove::Next, 1).unwrap(), b);
+        git(path, &["checkout", "feature"]);
+        assert_eq!(stack_target(path, StackMove::Prev, 1).unwrap(), a);
+    }
+}
@@ -1,13 +1,27 @@
 use crate::command::run;
+use crate::error::GitError;
 use anyhow::{Context, Result};
 use std::path::Path;
 
+/// Status of one path in the working copy
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct FileStatus {
     pub path: String,
+    /// Single-value summary of `index` and `worktree`, for display
     pub status: StatusType,
+    /// Change staged in the index, relative to `HEAD`
+    pub index: ChangeKind,
+    /// Change in the working tree, relative to the index
+    pub worktree: ChangeKind,
+    /// Source path of a rename or copy
+    pub original_path: Option<String>,
+    /// Which sides added, modified or deleted an unmerged path
+    pub conflict: Option<ConflictKind>,
+    /// Set when the path is a submodule
+    pub submodule: Option<SubmoduleStatus>,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum StatusType {
     Modified,
     Added,
@@ -20,48 +34,365 @@ pub enum StatusType {
     Conflicted,
 }
 
-pub fn get_status(repo_path: &Path) -> Result<Vec<FileStatus>> {
-    let output = run(&["status", "--porcelain"], Some(repo_path))
-        .with_context(|| "Failed to get git status")?;
+/// The change on one side (index or working tree) of a status entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Unmodified,
+    Modified,
+    TypeChanged,
+    Added,
+    Deleted,
+    Renamed,
+    Copied,
+    Untracked,
+    Unmerged,
+}
+
+/// The unmerged states git reports, named after `git status`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictKind {
+    /// `DD`
+    BothDeleted,
+    /// `AU`
+    AddedByUs,
+    /// `UD`
+    DeletedByThem,
+    /// `UA`
+    AddedByThem,
+    /// `DU`
+    DeletedByUs,
+    /// `AA`
+    BothAdded,
+    /// `UU`
+    BothModified,
+}
 
-    let mut files = Vec::new();
-    for line in output.lines() {
-        if line.trim().is_empty() {
-            continue;
+/// Submodule state flags from `git status --porcelain=v2`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SubmoduleStatus {
+    /// The checked-out commit differs from the recorded one
+    pub commit_changed: bool,
+    pub has_tracked_changes: bool,
+    pub has_untracked_changes: bool,
+}
+
+/// The `# branch.*` header of `git status --porcelain=v2 --branch`
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct BranchStatus {
+    /// `None` before the first commit
+    pub oid: Option<String>,
+    /// `None` when `HEAD` is detached
+    pub head: Option<String>,
+    pub upstream: Option<String>,
+    pub ahead: usize,
+    pub behind: usize,
+}
+
+/// Branch header plus per-file status
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct RepoStatus {
+    pub branch: BranchStatus,
+    pub files: Vec<FileStatus>,
+}
+
+impl ChangeKind {
+    fn from_code(code: char) -> Self {
+        match code {
+            'M' => ChangeKind::Modified,
+            'T' => ChangeKind::TypeChanged,
+            'A' => ChangeKind::Added,
+            'D' => ChangeKind::Deleted,
+            'R' => ChangeKind::Renamed,
+            'C' => ChangeKind::Copied,
+            'U' => ChangeKind::Unmerged,
+            '?' => ChangeKind::Untracked,
+            _ => ChangeKind::Unmodified,
         }
+    }
 
-        if line.len() < 4 {
-            continue;
+    /// The letter `git status --short` uses for this change
+    pub fn code(self) -> char {
+        match self {
+            ChangeKind::Unmodified => ' ',
+            ChangeKind::Modified => 'M',
+            ChangeKind::TypeChanged => 'T',
+            ChangeKind::Added => 'A',
+            ChangeKind::Deleted => 'D',
+            ChangeKind::Renamed => 'R',
+            ChangeKind::Copied => 'C',
+            ChangeKind::Untracked => '?',
+            ChangeKind::Unmerged => 'U',
         }
+    }
 
-        let status_code = &line[0..2];
-        let path = line[3..].trim().to_string();
-
-        let status_type = match status_code {
-            " M" => StatusType::Modified,
-            "M " => StatusType::ModifiedStaged,
-            "A " => StatusType::AddedStaged,
-            "AM" => StatusType::Added,
-            " D" => StatusType::Deleted,
-            "D " => StatusType::DeletedStaged,
-            "??" => StatusType::Untracked,
-            "R " => StatusType::Renamed,
-            "UU" => StatusType::Conflicted,
-            _ => StatusType::Modified,
+    pub fn label(self) -> &'static str {
+        match self {
+            ChangeKind::Unmodified => "Unmodified",
+            ChangeKind::Modified => "Modified",
+            ChangeKind::TypeChanged => "Type changed",
+            ChangeKind::Added => "Added",
+            ChangeKind::Deleted => "Deleted",
+            ChangeKind::Renamed => "Renamed",
+            ChangeKind::Copied => "Copied",
+            ChangeKind::Untracked => "Untracked",
+            ChangeKind::Unmerged => "Conflicted",
+        }
+    }
+}
+
+impl ConflictKind {
+    fn from_code(code: &str) -> Option<Self> {
+        Some(match code {
+            "DD" => ConflictKind::BothDeleted,
+            "AU" => ConflictKind::AddedByUs,
+            "UD" => ConflictKind::DeletedByThem,
+            "UA" => ConflictKind::AddedByThem,
+            "DU" => ConflictKind::DeletedByUs,
+            "AA" => ConflictKind::BothAdded,
+            "UU" => ConflictKind::BothModified,
+            _ => return None,
+        })
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            ConflictKind::BothDeleted => "both deleted",
+            ConflictKind::AddedByUs => "added by us",
+            ConflictKind::DeletedByThem => "deleted by them",
+            ConflictKind::AddedByThem => "added by them",
+            ConflictKind::DeletedByUs => "deleted by us",
+            ConflictKind::BothAdded => "both added",
+            ConflictKind::BothModified => "both modified",
+        }
+    }
+}
+
+impl FileStatus {
+    /// An entry known only by its summary, as reported by backends without
+    /// an index. Staged variants map to the index side, the rest to the
+    /// working tree.
+    pub fn new(path: impl Into<String>, status: StatusType) -> Self {
+        let (index, worktree) = match status {
+            StatusType::Modified => (ChangeKind::Unmodified, ChangeKind::Modified),
+            StatusType::Added => (ChangeKind::Unmodified, ChangeKind::Added),
+            StatusType::Deleted => (ChangeKind::Unmodified, ChangeKind::Deleted),
+            StatusType::Untracked => (ChangeKind::Unmodified, ChangeKind::Untracked),
+            StatusType::ModifiedStaged => (ChangeKind::Modified, ChangeKind::Unmodified),
+            StatusType::AddedStaged => (ChangeKind::Added, ChangeKind::Unmodified),
+            StatusType::DeletedStaged => (ChangeKind::Deleted, ChangeKind::Unmodified),
+            StatusType::Renamed => (ChangeKind::Renamed, ChangeKind::Unmodified),
+            StatusType::Conflicted => (ChangeKind::Unmerged, ChangeKind::Unmerged),
         };
+        Self {
+            path: path.into(),
+            status,
+            index,
+            worktree,
+            original_path: None,
+            conflict: None,
+            submodule: None,
+        }
+    }
 
-        files.push(FileStatus {
+    fn from_sides(path: String, index: ChangeKind, worktree: ChangeKind) -> Self {
+        let status = match (index, worktree) {
+            (_, ChangeKind::Untracked) => StatusType::Untracked,
+            (ChangeKind::Renamed | ChangeKind::Copied, _) => StatusType::Renamed,
+            (ChangeKind::Added, ChangeKind::Unmodified) => StatusType::AddedStaged,
+            (ChangeKind::Added, _) => StatusType::Added,
+            (ChangeKind::Deleted, _) => StatusType::DeletedStaged,
+            (ChangeKind::Modified | ChangeKind::TypeChanged, ChangeKind::Unmodified) => {
+                StatusType::ModifiedStaged
+            }
+            (_, ChangeKind::Deleted) => StatusType::Deleted,
+            (_, ChangeKind::Added) => StatusType::Added,
+            _ => StatusType::Modified,
+        };
+        Self {
             path,
-            status: status_type,
-        });
+            status,
+            index,
+            worktree,
+            original_path: None,
+            conflict: None,
+            submodule: None,
+        }
+    }
+
+    /// Two-letter code in the style of `git status --short` (`MM`, `R `, `??`)
+    pub fn short_code(&self) -> String {
+        match self.conflict {
+            Some(kind) => match kind {
+                ConflictKind::BothDeleted => "DD",
+                ConflictKind::AddedByUs => "AU",
+                ConflictKind::DeletedByThem => "UD",
+                ConflictKind::AddedByThem => "UA",
+                ConflictKind::DeletedByUs => "DU",
+                ConflictKind::BothAdded => "AA",
+                ConflictKind::BothModified => "UU",
+            }
+            .to_string(),
+            None if self.worktree == ChangeKind::Untracked => "??".to_string(),
+            None => format!("{}{}", self.index.code(), self.worktree.code()),
+        }
+    }
+
+    /// Human-readable summary such as `Modified (staged), Modified` or
+    /// `Conflicted (deleted by us)`
+    pub fn description(&self) -> String {
+        if let Some(kind) = self.conflict {
+            return format!("Conflicted ({})", kind.description());
+        }
+        if self.index == ChangeKind::Unmerged || self.worktree == ChangeKind::Untracked {
+            return self.worktree.label().to_string();
+        }
+
+        let mut parts = Vec::new();
+        if self.index != ChangeKind::Unmodified {
+            parts.push(format!("{} (staged)", self.index.label()));
+        }
+        if self.worktree != ChangeKind::Unmodified {
+            parts.push(self.worktree.label().to_string());
+        }
+        parts.join(", ")
+    }
+
+    /// Whether any part of the change is staged
+    pub fn is_staged(&self) -> bool {
+        self.conflict.is_none()
+            && !matches!(self.index, ChangeKind::Unmodified | ChangeKind::Unmerged)
     }
 
-    Ok(files)
+    /// Whether the working tree differs from the index
+    pub fn has_unstaged_changes(&self) -> bool {
+        self.conflict.is_some() || self.worktree != ChangeKind::Unmodified
+    }
+}
+
+pub fn get_status(repo_path: &Path) -> Result<Vec<FileStatus>> {
+    Ok(get_repo_status(repo_path)?.files)
+}
+
+/// Branch header and file status from `git status --porcelain=v2 -z --branch`
+pub fn get_repo_status(repo_path: &Path) -> Result<RepoStatus> {
+    let output = run(
+        &["status", "--porcelain=v2", "-z", "--branch"],
+        Some(repo_path),
+    )
+    .with_context(|| "Failed to get git status")?;
+
+    parse_porcelain_v2(&output)
+}
+
+/// Parse NUL-separated `--porcelain=v2` output. Paths are never quoted in
+/// this format, and a rename entry is followed by its original path.
+pub fn parse_porcelain_v2(output: &str) -> Result<RepoStatus> {
+    let mut status = RepoStatus::default();
+    let mut entries = output.split('\0').filter(|entry| !entry.is_empty());
+    let malformed = |entry: &str| GitError::ParseError(format!("status entry '{}'", entry));
+
+    while let Some(entry) = entries.next() {
+        let (kind, rest) = entry.split_at_checked(2).ok_or_else(|| malformed(entry))?;
+        match kind {
+            "# " => parse_branch_header(rest, &mut status.branch),
+            "1 " => {
+                let fields: Vec<&str> = rest.splitn(8, ' ').collect();
+                let [xy, sub, _, _, _, _, _, path] = fields[..] else {
+                    return Err(malformed(entry).into());
+                };
+                status.files.push(tracked_entry(xy, sub, path, None)?);
+            }
+            "2 " => {
+                let fields: Vec<&str> = rest.splitn(9, ' ').collect();
+                let [xy, sub, _, _, _, _, _, _, path] = fields[..] else {
+                    return Err(malformed(entry).into());
+                };
+                let original = entries.next().ok_or_else(|| malformed(entry))?;
+                status
+                    .files
+                    .push(tracked_entry(xy, sub, path, Some(original))?);
+            }
+            "u " => {
+                let fields: Vec<&str> = rest.splitn(10, ' ').collect();
+                let [xy, sub, _, _, _, _, _, _, _, path] = fields[..] else {
+                    return Err(malformed(entry).into());
+                };
+                let mut file = tracked_entry(xy, sub, path, None)?;
+                file.conflict = ConflictKind::from_code(xy);
+                file.status = StatusType::Conflicted;
+                status.files.push(file);
+            }
+            "? " => status.files.push(FileStatus::from_sides(
+                rest.to_string(),
+                ChangeKind::Unmodified,
+                ChangeKind::Untracked,
+            )),
+            // Ignored files are only listed with --ignored
+            "! " => {}
+            _ => return Err(malformed(entry).into()),
+        }
+    }
+
+    Ok(status)
+}
+
+fn parse_branch_header(header: &str, branch: &mut BranchStatus) {
+    let Some((key, value)) = header.split_once(' ') else {
+        return;
+    };
+    match key {
+        "branch.oid" if value != "(initial)" => branch.oid = Some(value.to_string()),
+        "branch.head" if value != "(detached)" => branch.head = Some(value.to_string()),
+        "branch.upstream" => branch.upstream = Some(value.to_string()),
+        "branch.ab" => {
+            for count in value.split_whitespace() {
+                if let Some(n) = count.strip_prefix('+') {
+                    branch.ahead = n.parse().unwrap_or(0);
+                } else if let Some(n) = count.strip_prefix('-') {
+                    branch.behind = n.parse().unwrap_or(0);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn tracked_entry(
+    xy: &str,
+    submodule: &str,
+    path: &str,
+    original_path: Option<&str>,
+) -> Result<FileStatus> {
+    let mut codes = xy.chars();
+    let (Some(x), Some(y)) = (codes.next(), codes.next()) else {
+        return Err(GitError::ParseError(format!("status code '{}'", xy)).into());
+    };
+    let mut file = FileStatus::from_sides(
+        path.to_string(),
+        ChangeKind::from_code(x),
+        ChangeKind::from_code(y),
+    );
+    file.original_path = original_path.map(str::to_string);
+
+    // `N...` for plain files, `S<c><m><u>` for submodules
+    let flags: Vec<char> = submodule.chars().collect();
+    if let ['S', commit, tracked, untracked] = flags[..] {
+        file.submodule = Some(SubmoduleStatus {
+            commit_changed: commit == 'C',
+            has_tracked_changes: tracked == 'M',
+            has_untracked_changes: untracked == 'U',
+        });
+    }
+    Ok(file)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_support::{create_test_repo, git};
+    use std::fs;
+
+    const ZEROS: &str = "0000000000000000000000000000000000000000";
 
     #[test]
     fn test_get_status() {
@@ -78,4 +409,124 @@ mod tests {
         assert_eq!(StatusType::Modified, StatusType::Modified);
         assert_ne!(StatusType::Modified, StatusType::Added);
     }
+
+    #[test]
+    fn test_parse_porcelain_v2() {
+        let output = [
+            "# branch.oid 1234567890abcdef1234567890abcdef12345678".to_string(),
+            "# branch.head feature/x".to_string(),
+            "# branch.upstream origin/feature/x".to_string(),
+            "# branch.ab +2 -1".to_string(),
+            format!(
+                "1 MM N... 100644 100644 100644 {z} {z} src/a file.rs",
+                z = ZEROS
+            ),
+            format!("1 A. N... 000000 100644 100644 {z} {z} new.rs", z = ZEROS),
+            format!(
+                "2 R. N... 100644 100644 100644 {z} {z} R100 lib/new name.rs",
+                z = ZEROS
+            ),
+            "lib/old name.rs".to_string(),
+            format!(
+                "u DU N... 100644 000000 100644 100644 {z} {z} {z} gone.rs",
+                z = ZEROS
+            ),
+            format!(
+                "1 .M SC.U 160000 160000 160000 {z} {z} vendor/lib",
+                z = ZEROS
+            ),
+            "? notes \"draft\".txt".to_string(),
+            String::new(),
+        ]
+        .join("\0");
+
+        let status = parse_porcelain_v2(&output).unwrap();
+        assert_eq!(status.branch.head.as_deref(), Some("feature/x"));
+        assert_eq!(status.branch.upstream.as_deref(), Some("origin/feature/x"));
+        assert_eq!((status.branch.ahead, status.branch.behind), (2, 1));
+
+        let files = &status.files;
+        assert_eq!(files.len(), 6);
+
+        assert_eq!(files[0].path, "src/a file.rs");
+        assert_eq!(files[0].index, ChangeKind::Modified);
+        assert_eq!(files[0].worktree, ChangeKind::Modified);
+        assert_eq!(files[0].short_code(), "MM");
+        assert!(files[0].is_staged() && files[0].has_unstaged_changes());
+        assert_eq!(files[0].description(), "Modified (staged), Modified");
+
+        assert_eq!(files[1].status, StatusType::AddedStaged);
+
+        assert_eq!(files[2].path, "lib/new name.rs");
+        assert_eq!(files[2].original_path.as_deref(), Some("lib/old name.rs"));
+        assert_eq!(files[2].status, StatusType::Renamed);
+
+        assert_eq!(files[3].status, StatusType::Conflicted);
+        assert_eq!(files[3].conflict, Some(ConflictKind::DeletedByUs));
+        assert_eq!(files[3].short_code(), "DU");
+        assert_eq!(files[3].description(), "Conflicted (deleted by us)");
+        assert!(!files[3].is_staged());
+
+        assert_eq!(
+            files[4].submodule,
+            Some(SubmoduleStatus {
+                commit_changed: true,
+                has_tracked_changes: false,
+                has_untracked_changes: true,
+            })
+        );
+
+        assert_eq!(files[5].path, "notes \"draft\".txt");
+        assert_eq!(files[5].short_code(), "??");
+    }
+
+    #[test]
+    fn test_parse_porcelain_v2_initial_and_detached() {
+        let status =
+            parse_porcelain_v2("# branch.oid (initial)\0# branch.head (detached)\0").unwrap();
+        assert_eq!(status.branch, BranchStatus::default());
+        assert!(parse_porcelain_v2("1 M\0").is_err());
+    }
+
+    #[test]
+    fn test_get_repo_status_reports_every_conflict_kind() {
+        let dir = create_test_repo();
+        let path = dir.path();
+
+        fs::write(path.join("both.txt"), "base\n").unwrap();
+        fs::write(path.join("theirs-delete.txt"), "base\n").unwrap();
+        git(path, &["add", "."]);
+        git(path, &["commit", "-m", "Base"]);
+
+        git(path, &["checkout", "-b", "other"]);
+        fs::write(path.join("both.txt"), "other\n").unwrap();
+        fs::remove_file(path.join("theirs-delete.txt")).unwrap();
+        fs::write(path.join("added.txt"), "other\n").unwrap();
+        git(path, &["add", "-A"]);
+        git(path, &["commit", "-m", "Other"]);
+
+        git(path, &["checkout", "main"]);
+        fs::write(path.join("both.txt"), "main\n").unwrap();
+        fs::write(path.join("theirs-delete.txt"), "main\n").unwrap();
+        fs::write(path.join("added.txt"), "main\n").unwrap();
+        git(path, &["add", "-A"]);
+        git(path, &["commit", "-m", "Main"]);
+        assert!(run(&["merge", "other"], Some(path)).is_err());
+
+        let status = get_repo_status(path).unwrap();
+        assert_eq!(status.branch.head.as_deref(), Some("main"));
+        let conflict = |name: &str| {
+            status
+                .files
+                .iter()
+                .find(|f| f.path == name)
+                .and_then(|f| f.conflict)
+        };
+        assert_eq!(conflict("both.txt"), Some(ConflictKind::BothModified));
+        assert_eq!(conflict("added.txt"), Some(ConflictKind::BothAdded));
+        assert_eq!(
+            conflict("theirs-delete.txt"),
+            Some(ConflictKind::DeletedByThem)
+        );
+    }
 }
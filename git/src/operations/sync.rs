@@ -3,9 +3,6 @@ use crate::vcs::SyncState;
 use anyhow::{Context, Result};
 use std::path::Path;
 
-#[cfg(test)]
-use tempfile;
-
 /// Fetch sync state (ahead/behind counts and conflicts)
 pub fn get_sync_state(repo_path: &Path) -> Result<SyncState> {
     let mut sync_state = SyncState::default();
@@ -47,7 +44,7 @@ fn get_tracking_remote(repo_path: &Path, branch: &str) -> Result<(Option<String>
     )
     .context("Failed to get tracking remote")?;
 
-    if output.status.success() {
+    if output.success() {
         let tracking_lossy = String::from_utf8_lossy(&output.stdout);
         let tracking = tracking_lossy.trim();
         if tracking.is_empty() || tracking.contains("@") {
@@ -74,7 +71,7 @@ fn get_ahead_behind(repo_path: &Path, remote_ref: &str, sync_state: &mut SyncSta
     )
     .context("Failed to get ahead count")?;
 
-    if ahead_output.status.success() {
+    if ahead_output.success() {
         let ahead_lossy = String::from_utf8_lossy(&ahead_output.stdout);
         let ahead = ahead_lossy.trim().parse().ok();
         sync_state.local_unpushed = ahead;
@@ -87,7 +84,7 @@ fn get_ahead_behind(repo_path: &Path, remote_ref: &str, sync_state: &mut SyncSta
     )
     .context("Failed to get behind count")?;
 
-    if behind_output.status.success() {
+    if behind_output.success() {
         let behind_lossy = String::from_utf8_lossy(&behind_output.stdout);
         let behind = behind_lossy.trim().parse().ok();
         sync_state.remote_unpulled = behind;
@@ -104,16 +101,13 @@ pub fn get_current_branch(repo_path: &Path) -> Result<Option<String>> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_support::create_test_repo;
 
     #[test]
     fn test_get_sync_state_no_remote() {
-        let temp_dir = tempfile::tempdir().unwrap();
+        let temp_dir = create_test_repo();
         let repo_path = temp_dir.path();
 
-        command::run_raw(&["init"], Some(repo_path)).unwrap();
-        command::run_raw(&["config", "user.email", "test@test.com"], Some(repo_path)).unwrap();
-        command::run_raw(&["config", "user.name", "Test User"], Some(repo_path)).unwrap();
-
         std::fs::write(repo_path.join("test.txt"), "test").unwrap();
         command::run_raw(&["add", "test.txt"], Some(repo_path)).unwrap();
         command::run_raw(&["commit", "-m", "Initial commit"], Some(repo_path)).unwrap();
@@ -127,13 +121,9 @@ mod tests {
 
     #[test]
     fn test_get_sync_state_with_conflicts() {
-        let temp_dir = tempfile::tempdir().unwrap();
+        let temp_dir = create_test_repo();
         let repo_path = temp_dir.path();
 
-        command::run_raw(&["init"], Some(repo_path)).unwrap();
-        command::run_raw(&["config", "user.email", "test@test.com"], Some(repo_path)).unwrap();
-        command::run_raw(&["config", "user.name", "Test User"], Some(repo_path)).unwrap();
-
         std::fs::write(repo_path.join("test.txt"), "test").unwrap();
         command::run_raw(&["add", "test.txt"], Some(repo_path)).unwrap();
         command::run_raw(&["commit", "-m", "First commit"], Some(repo_path)).unwrap();
@@ -1,13 +1,16 @@
 use crate::command::run_success;
+use crate::operations::oplog::record_operation;
 use anyhow::{Context, Result};
 use std::path::Path;
 
 /// Undo the last operation by moving HEAD (and the index/working tree) back to
 /// the previous state recorded in the reflog.
 ///
-/// This is a destructive operation: uncommitted working-tree changes are
-/// discarded, matching `git reset --hard HEAD@{1}`.
+/// Uncommitted working-tree changes are discarded, matching
+/// `git reset --hard HEAD@{1}`, but the state is recorded in the operation
+/// log first so `openisl undo` can bring it back.
 pub fn undo_last(repo_path: &Path) -> Result<()> {
+    record_operation(repo_path, "reset --hard HEAD@{1}")?;
     run_success(&["reset", "--hard", "HEAD@{1}"], Some(repo_path))
         .with_context(|| "Failed to undo last operation")?;
     Ok(())
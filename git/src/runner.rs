@@ -0,0 +1,264 @@
+use anyhow::{bail, Result};
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+
+/// A single git invocation: arguments (without the leading `git`), working
+/// directory, extra environment and optional stdin
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct GitInvocation {
+    pub args: Vec<String>,
+    pub cwd: Option<PathBuf>,
+    pub env: Vec<(String, String)>,
+    pub stdin: Option<String>,
+}
+
+impl GitInvocation {
+    pub fn new(args: &[&str]) -> Self {
+        Self {
+            args: args.iter().map(|a| a.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Exit code and captured output of a git invocation
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct GitOutput {
+    /// Exit code, `None` when the process was killed by a signal
+    pub code: Option<i32>,
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+}
+
+impl GitOutput {
+    /// A successful invocation printing `stdout`
+    pub fn ok(stdout: &str) -> Self {
+        Self {
+            code: Some(0),
+            stdout: stdout.as_bytes().to_vec(),
+            stderr: Vec::new(),
+        }
+    }
+
+    /// A failed invocation exiting with `code` and printing `stderr`
+    pub fn failed(code: i32, stderr: &str) -> Self {
+        Self {
+            code: Some(code),
+            stdout: Vec::new(),
+            stderr: stderr.as_bytes().to_vec(),
+        }
+    }
+
+    pub fn success(&self) -> bool {
+        self.code == Some(0)
+    }
+}
+
+/// Executes git invocations
+pub trait GitRunner: Send + Sync {
+    fn run(&self, invocation: &GitInvocation) -> Result<GitOutput>;
+}
+
+/// Spawns the `git` executable found on `PATH`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ProcessRunner;
+
+impl GitRunner for ProcessRunner {
+    fn run(&self, invocation: &GitInvocation) -> Result<GitOutput> {
+        let mut cmd = Command::new("git");
+        cmd.args(&invocation.args);
+
+        if let Some(ref dir) = invocation.cwd {
+            cmd.current_dir(dir);
+        }
+
+        for (key, value) in &invocation.env {
+            cmd.env(key, value);
+        }
+
+        let output = match invocation.stdin {
+            Some(ref data) => {
+                cmd.stdin(Stdio::piped());
+                cmd.stdout(Stdio::piped());
+                cmd.stderr(Stdio::piped());
+
+                let mut child = cmd.spawn()?;
+                if let Some(ref mut stdin) = child.stdin {
+                    stdin.write_all(data.as_bytes())?;
+                }
+                child.wait_with_output()?
+            }
+            None => cmd.output()?,
+        };
+
+        Ok(GitOutput {
+            code: output.status.code(),
+            stdout: output.stdout,
+            stderr: output.stderr,
+        })
+    }
+}
+
+/// An invocation together with the output it produced
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedCall {
+    pub invocation: GitInvocation,
+    pub output: GitOutput,
+}
+
+/// Forwards to another runner and keeps a log of every call
+pub struct RecordingRunner {
+    inner: Arc<dyn GitRunner>,
+    calls: Mutex<Vec<RecordedCall>>,
+}
+
+impl RecordingRunner {
+    pub fn new(inner: Arc<dyn GitRunner>) -> Self {
+        Self {
+            inner,
+            calls: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Record invocations of the real `git`
+    pub fn process() -> Self {
+        Self::new(Arc::new(ProcessRunner))
+    }
+
+    /// Every call made so far, in order
+    pub fn calls(&self) -> Vec<RecordedCall> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    /// The arguments of every call made so far, in order
+    pub fn invocations(&self) -> Vec<Vec<String>> {
+        self.calls
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|call| call.invocation.args.clone())
+            .collect()
+    }
+}
+
+impl GitRunner for RecordingRunner {
+    fn run(&self, invocation: &GitInvocation) -> Result<GitOutput> {
+        let output = self.inner.run(invocation)?;
+        self.calls.lock().unwrap().push(RecordedCall {
+            invocation: invocation.clone(),
+            output: output.clone(),
+        });
+        Ok(output)
+    }
+}
+
+/// Answers invocations with canned output, matched on their arguments.
+///
+/// When several responses are registered for the same arguments they are
+/// handed out in order and the last one is repeated, which makes a
+/// `RecordingRunner` log directly replayable. Unmatched invocations fail.
+#[derive(Default)]
+pub struct FakeRunner {
+    responses: Mutex<Vec<(Vec<String>, GitOutput)>>,
+}
+
+impl FakeRunner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replay a recorded session
+    pub fn replay(calls: &[RecordedCall]) -> Self {
+        let runner = Self::new();
+        for call in calls {
+            runner.push(call.invocation.args.clone(), call.output.clone());
+        }
+        runner
+    }
+
+    /// Answer `git <args>` with a successful `stdout`
+    pub fn respond(self, args: &[&str], stdout: &str) -> Self {
+        self.respond_with(args, GitOutput::ok(stdout))
+    }
+
+    /// Answer `git <args>` with a failure printing `stderr`
+    pub fn fail(self, args: &[&str], stderr: &str) -> Self {
+        self.respond_with(args, GitOutput::failed(1, stderr))
+    }
+
+    pub fn respond_with(self, args: &[&str], output: GitOutput) -> Self {
+        self.push(args.iter().map(|a| a.to_string()).collect(), output);
+        self
+    }
+
+    fn push(&self, args: Vec<String>, output: GitOutput) {
+        self.responses.lock().unwrap().push((args, output));
+    }
+}
+
+impl GitRunner for FakeRunner {
+    fn run(&self, invocation: &GitInvocation) -> Result<GitOutput> {
+        let mut responses = self.responses.lock().unwrap();
+        let mut matching = responses
+            .iter()
+            .enumerate()
+            .filter(|(_, (args, _))| *args == invocation.args)
+            .map(|(i, _)| i);
+
+        let Some(first) = matching.next() else {
+            bail!(
+                "unexpected git invocation: git {}",
+                invocation.args.join(" ")
+            );
+        };
+
+        if matching.next().is_some() {
+            Ok(responses.remove(first).1)
+        } else {
+            Ok(responses[first].1.clone())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fake_runner_hands_out_responses_in_order() {
+        let runner = FakeRunner::new()
+            .respond(&["rev-parse", "HEAD"], "aaa\n")
+            .respond(&["rev-parse", "HEAD"], "bbb\n")
+            .fail(
+                &["checkout", "nope", "--"],
+                "fatal: invalid reference: nope\n",
+            );
+
+        let head = GitInvocation::new(&["rev-parse", "HEAD"]);
+        assert_eq!(runner.run(&head).unwrap().stdout, b"aaa\n");
+        assert_eq!(runner.run(&head).unwrap().stdout, b"bbb\n");
+        assert_eq!(runner.run(&head).unwrap().stdout, b"bbb\n");
+
+        let checkout = runner
+            .run(&GitInvocation::new(&["checkout", "nope", "--"]))
+            .unwrap();
+        assert!(!checkout.success());
+
+        assert!(runner.run(&GitInvocation::new(&["status"])).is_err());
+    }
+
+    #[test]
+    fn test_recording_can_be_replayed() {
+        let fake: Arc<dyn GitRunner> = Arc::new(FakeRunner::new().respond(&["version"], "1\n"));
+        let recorder = RecordingRunner::new(fake);
+        recorder.run(&GitInvocation::new(&["version"])).unwrap();
+
+        assert_eq!(recorder.invocations(), vec![vec!["version".to_string()]]);
+
+        let replay = FakeRunner::replay(&recorder.calls());
+        let output = replay.run(&GitInvocation::new(&["version"])).unwrap();
+        assert_eq!(output, GitOutput::ok("1\n"));
+    }
+}
@@ -0,0 +1,29 @@
+use crate::command::run;
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+
+/// A temporary repository on `main` with a committer identity configured
+pub(crate) fn create_test_repo() -> TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    git(dir.path(), &["init", "-q", "-b", "main"]);
+    git(dir.path(), &["config", "user.email", "test@example.com"]);
+    git(dir.path(), &["config", "user.name", "Test User"]);
+    dir
+}
+
+/// Run git in `repo`, panicking on failure, and return its trimmed stdout
+pub(crate) fn git(repo: &Path, args: &[&str]) -> String {
+    run(args, Some(repo))
+        .unwrap_or_else(|err| panic!("git {:?} failed: {}", args, err))
+        .trim()
+        .to_string()
+}
+
+/// Write `name`, stage and commit it, and return the new commit hash
+pub(crate) fn commit_file(repo: &Path, name: &str, content: &str, message: &str) -> String {
+    fs::write(repo.join(name), content).unwrap();
+    git(repo, &["add", name]);
+    git(repo, &["commit", "-q", "-m", message]);
+    git(repo, &["rev-parse", "HEAD"])
+}
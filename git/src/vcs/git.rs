@@ -0,0 +1,685 @@
+use super::{
+    Change, ChangeCount, HistoryEditAction, HistoryEditPlan, HistoryPoint, Ref, RefType, SavedWork,
+    SplitPart, SyncState, Vcs,
+};
+use crate::command::{run, with_runner};
+use crate::models::abbreviate_hash;
+use crate::operations::absorb::{self, AbsorbedHunk};
+use crate::operations::bisect::{self, BisectState, BisectStep, BisectVerdict};
+use crate::operations::blame::{self, Blame, BlameOptions};
+use crate::operations::file_history::{self, FileVersion};
+use crate::operations::history_edit::{self, HistoryEditOutcome, RebaseAction, RebaseStep};
+use crate::operations::hunk::{self, Hunk};
+use crate::operations::log::LogQuery;
+use crate::operations::repo_state::{self, RepoState};
+use crate::operations::reset::ResetMode;
+use crate::operations::resolve::{self, ConflictFile};
+use crate::operations::revset::{self, Revset};
+use crate::operations::split::{self, SplitFile};
+use crate::operations::stack::{StackEntry, StackMove};
+use crate::operations::status::{FileStatus, RepoStatus};
+use crate::operations::{
+    branch, checkout, commit, diff, log, merge, oplog, rebase, reflog, remote, reset, stack, stage,
+    stash, status, sync,
+};
+use crate::runner::GitRunner;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// `Vcs` implementation backed by the git command line
+#[derive(Clone)]
+pub struct GitBackend {
+    repo_path: PathBuf,
+    runner: Option<Arc<dyn GitRunner>>,
+}
+
+impl fmt::Debug for GitBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GitBackend")
+            .field("repo_path", &self.repo_path)
+            .field("custom_runner", &self.runner.is_some())
+            .finish()
+    }
+}
+
+impl GitBackend {
+    pub fn new(repo_path: impl Into<PathBuf>) -> Self {
+        Self {
+            repo_path: repo_path.into(),
+            runner: None,
+        }
+    }
+
+    /// A backend whose git invocations all go through `runner`.
+    ///
+    /// The runner is installed with [`crate::with_runner`] for the length of
+    /// each trait call, so it is thread-local: git run from another thread
+    /// during the call goes to the `git` executable, while callbacks the call
+    /// invokes (such as `bisect_run`'s `on_step`) run with the runner still
+    /// installed. Whatever runner the calling thread had is restored when the
+    /// call returns, even if it panics.
+    pub fn with_runner(repo_path: impl Into<PathBuf>, runner: Arc<dyn GitRunner>) -> Self {
+        Self {
+            repo_path: repo_path.into(),
+            runner: Some(runner),
+        }
+    }
+
+    fn scoped<T>(&self, f: impl FnOnce() -> T) -> T {
+        match self.runner {
+            Some(ref runner) => with_runner(runner.clone(), f),
+            None => f(),
+        }
+    }
+
+    /// Run a mutating operation after snapshotting the repository into the
+    /// operation log, so `undo_operation` can restore the state before it
+    fn recorded<T>(&self, description: &str, f: impl FnOnce() -> Result<T>) -> Result<T> {
+        self.scoped(|| {
+            oplog::record_operation(&self.repo_path, description)?;
+            f()
+        })
+    }
+
+    fn stash_to_saved_work(&self, entry: stash::StashEntry) -> Result<SavedWork> {
+        let timestamp = DateTime::parse_from_str(&entry.date, "%Y-%m-%d %H:%M:%S %z")
+            .map(|d| d.with_timezone(&Utc))
+            .unwrap_or_else(|_| Utc::now());
+
+        let numstat = run(
+            &["stash", "show", "--numstat", &entry.name],
+            Some(&self.repo_path),
+        )
+        .with_context(|| format!("Failed to get stats for {}", entry.name))?;
+
+        let mut files_affected = Vec::new();
+        let mut change_count = ChangeCount::default();
+        for line in numstat.lines() {
+            let parts: Vec<&str> = line.splitn(3, '\t').collect();
+            if parts.len() < 3 {
+                continue;
+            }
+            // Binary files report "-" for both counts
+            change_count.additions += parts[0].parse::<usize>().unwrap_or(0);
+            change_count.deletions += parts[1].parse::<usize>().unwrap_or(0);
+            files_affected.push(parts[2].to_string());
+        }
+
+        Ok(SavedWork {
+            id: entry.name,
+            message: Some(entry.message).filter(|m| !m.is_empty()),
+            timestamp,
+            files_affected,
+            change_count,
+        })
+    }
+}
+
+impl Vcs for GitBackend {
+    fn name(&self) -> &'static str {
+        "git"
+    }
+
+    fn repo_path(&self) -> &Path {
+        &self.repo_path
+    }
+
+    fn log(
+        &self,
+        max_count: Option<usize>,
+        branch: Option<&str>,
+        remote: bool,
+    ) -> Result<Vec<Change>> {
+        self.scoped(|| {
+            let commits = log::get_commits_filtered(&self.repo_path, max_count, branch, remote)?;
+            Ok(commits.into_iter().map(Change::from).collect())
+        })
+    }
+
+    fn query_log(&self, query: &LogQuery) -> Result<Vec<Change>> {
+        self.scoped(|| {
+            let commits = log::get_commits_matching(&self.repo_path, query)?;
+            Ok(commits.into_iter().map(Change::from).collect())
+        })
+    }
+
+    fn revset_log(&self, revset: &str, max_count: Option<usize>) -> Result<Vec<Change>> {
+        let revset = Revset::parse(revset)?;
+        self.scoped(|| {
+            let commits = revset::get_commits_in_revset(&self.repo_path, &revset, max_count)?;
+            Ok(commits.into_iter().map(Change::from).collect())
+        })
+    }
+
+    fn status(&self) -> Result<Vec<FileStatus>> {
+        self.scoped(|| status::get_status(&self.repo_path))
+    }
+
+    fn repo_status(&self) -> Result<RepoStatus> {
+        self.scoped(|| status::get_repo_status(&self.repo_path))
+    }
+
+    fn diff(&self, target: Option<&str>, staged: bool) -> Result<String> {
+        self.scoped(|| diff::get_diff(&self.repo_path, target, staged))
+    }
+
+    fn change_diff(&self, id: &str) -> Result<String> {
+        self.scoped(|| diff::get_commit_diff(&self.repo_path, id))
+    }
+
+    fn stage(&self, path: &str) -> Result<()> {
+        self.scoped(|| stage::stage_file(&self.repo_path, path))
+    }
+
+    fn unstage(&self, path: &str) -> Result<()> {
+        self.scoped(|| stage::unstage_file(&self.repo_path, path))
+    }
+
+    fn stage_all(&self) -> Result<()> {
+        self.scoped(|| stage::stage_all(&self.repo_path))
+    }
+
+    fn unstage_all(&self) -> Result<()> {
+        self.scoped(|| stage::unstage_all(&self.repo_path))
+    }
+
+    fn hunks(&self, path: &str, staged: bool) -> Result<Vec<Hunk>> {
+        self.scoped(|| hunk::get_file_diff_hunks(&self.repo_path, Path::new(path), staged))
+    }
+
+    fn stage_hunk(&self, path: &str, hunk: &Hunk) -> Result<()> {
+        self.scoped(|| hunk::stage_hunk(&self.repo_path, Path::new(path), hunk))
+    }
+
+    fn unstage_hunk(&self, path: &str, hunk: &Hunk) -> Result<()> {
+        self.scoped(|| hunk::unstage_hunk(&self.repo_path, Path::new(path), hunk))
+    }
+
+    fn stage_hunk_lines(&self, path: &str, hunk: &Hunk) -> Result<()> {
+        self.scoped(|| hunk::stage_hunk_lines(&self.repo_path, Path::new(path), hunk))
+    }
+
+    fn unstage_hunk_lines(&self, path: &str, hunk: &Hunk) -> Result<()> {
+        self.scoped(|| hunk::unstage_hunk_lines(&self.repo_path, Path::new(path), hunk))
+    }
+
+    fn commit(&self, message: &str) -> Result<()> {
+        self.recorded(&format!("commit {}", summary(message)), || {
+            commit::commit(&self.repo_path, message)
+        })
+    }
+
+    fn amend(&self, message: Option<&str>) -> Result<()> {
+        self.recorded("amend", || commit::amend_commit(&self.repo_path, message))
+    }
+
+    fn current_branch(&self) -> Result<Option<String>> {
+        self.scoped(|| branch::get_current_branch(&self.repo_path))
+    }
+
+    fn branches(&self) -> Result<Vec<Ref>> {
+        self.scoped(|| {
+            let refs = branch::get_branches(&self.repo_path, false, true)?;
+            Ok(refs.into_iter().map(Ref::from).collect())
+        })
+    }
+
+    fn create_branch(&self, name: &str, at: Option<&str>) -> Result<()> {
+        self.recorded(&format!("create branch {}", name), || match at {
+            Some(commit_hash) => {
+                branch::create_branch_from_commit(&self.repo_path, name, commit_hash)
+            }
+            None => branch::create_branch(&self.repo_path, name),
+        })
+    }
+
+    fn checkout(&self, target: &str) -> Result<()> {
+        self.recorded(&format!("checkout {}", target), || {
+            checkout::checkout(&self.repo_path, target)
+        })
+    }
+
+    fn saved_work(&self) -> Result<Vec<SavedWork>> {
+        self.scoped(|| {
+            stash::get_stash_list(&self.repo_path)?
+                .into_iter()
+                .map(|entry| self.stash_to_saved_work(entry))
+                .collect()
+        })
+    }
+
+    fn save_work(&self, message: Option<&str>) -> Result<()> {
+        self.recorded("stash push", || stash::stash_push(&self.repo_path, message))
+    }
+
+    fn apply_saved_work(&self, id: Option<&str>) -> Result<()> {
+        self.recorded("stash apply", || stash::stash_apply(&self.repo_path, id))
+    }
+
+    fn pop_saved_work(&self, id: Option<&str>) -> Result<()> {
+        self.recorded("stash pop", || stash::stash_pop(&self.repo_path, id))
+    }
+
+    fn drop_saved_work(&self, id: Option<&str>) -> Result<()> {
+        self.recorded("stash drop", || stash::stash_drop(&self.repo_path, id))
+    }
+
+    fn show_saved_work(&self, id: &str) -> Result<String> {
+        self.scoped(|| stash::stash_show(&self.repo_path, id))
+    }
+
+    fn sync_state(&self) -> Result<SyncState> {
+        self.scoped(|| sync::get_sync_state(&self.repo_path))
+    }
+
+    fn edit_history(&self, plan: &HistoryEditPlan) -> Result<()> {
+        self.recorded(&describe_plan(plan), || {
+            let steps: Vec<RebaseStep> = plan
+                .changes
+                .iter()
+                .map(|entry| {
+                    let action = match entry.action {
+                        HistoryEditAction::Keep if entry.message.is_some() => RebaseAction::Reword,
+                        HistoryEditAction::Keep => RebaseAction::Pick,
+                        HistoryEditAction::Revise => RebaseAction::Reword,
+                        HistoryEditAction::Combine => RebaseAction::Squash,
+                        HistoryEditAction::Remove => RebaseAction::Drop,
+                        HistoryEditAction::Edit => RebaseAction::Edit,
+                    };
+                    RebaseStep {
+                        commit: entry.change_id.clone(),
+                        action,
+                        message: entry.message.clone(),
+                    }
+                })
+                .collect();
+            history_edit::edit_history(&self.repo_path, None, &steps)?.into_result()
+        })
+    }
+
+    fn squash(&self, base: &str, message: &str) -> Result<()> {
+        self.recorded(&format!("squash onto {}", abbreviate_hash(base)), || {
+            commit::squash_commits(&self.repo_path, base, message)
+        })
+    }
+
+    fn cherry_pick(&self, id: &str) -> Result<()> {
+        self.recorded(&format!("cherry-pick {}", abbreviate_hash(id)), || {
+            commit::cherry_pick_commit(&self.repo_path, id)
+        })
+    }
+
+    fn revert(&self, id: &str) -> Result<()> {
+        self.recorded(&format!("revert {}", abbreviate_hash(id)), || {
+            commit::revert_commit(&self.repo_path, id)
+        })
+    }
+
+    fn merge(&self, target: &str) -> Result<String> {
+        self.recorded(&format!("merge {}", target), || {
+            merge::merge(&self.repo_path, target, false)
+        })
+    }
+
+    fn rebase(&self, upstream: Option<&str>) -> Result<String> {
+        let description = format!("rebase onto {}", upstream.unwrap_or("upstream"));
+        self.recorded(&description, || {
+            rebase::rebase(&self.repo_path, upstream, false)
+        })
+    }
+
+    fn rebase_interactive(
+        &self,
+        upstream: &str,
+        steps: &[RebaseStep],
+    ) -> Result<HistoryEditOutcome> {
+        self.recorded(&format!("rebase -i onto {}", upstream), || {
+            history_edit::edit_history(&self.repo_path, Some(upstream), steps)
+        })
+    }
+
+    fn pull(&self, rebase: bool) -> Result<String> {
+        self.recorded("pull", || remote::pull(&self.repo_path, rebase))
+    }
+
+    fn operation_log(&self) -> Result<Vec<HistoryPoint>> {
+        self.scoped(|| {
+            let operations = oplog::get_operations(&self.repo_path)?;
+            Ok(operations.into_iter().map(HistoryPoint::from).collect())
+        })
+    }
+
+    fn undo_operation(&self, id: Option<&str>) -> Result<()> {
+        self.scoped(|| oplog::undo_operation(&self.repo_path, id).map(|_| ()))
+    }
+
+    fn reflog(
+        &self,
+        reference: Option<&str>,
+        max_count: Option<usize>,
+    ) -> Result<Vec<HistoryPoint>> {
+        self.scoped(|| {
+            let entries = reflog::get_reflog(&self.repo_path, reference, max_count)?;
+            Ok(entries.into_iter().map(HistoryPoint::from).collect())
+        })
+    }
+
+    fn reset(&self, target: &str, mode: ResetMode) -> Result<()> {
+        self.recorded(&format!("reset {}", abbreviate_hash(target)), || {
+            reset::reset(&self.repo_path, mode, Some(target)).map(|_| ())
+        })
+    }
+
+    fn redo_operation(&self) -> Result<()> {
+        self.scoped(|| oplog::redo_operation(&self.repo_path).map(|_| ()))
+    }
+
+    fn stack(&self) -> Result<Vec<StackEntry>> {
+        self.scoped(|| stack::get_stack(&self.repo_path))
+    }
+
+    fn stack_target(&self, direction: StackMove, count: usize) -> Result<String> {
+        self.scoped(|| stack::stack_target(&self.repo_path, direction, count))
+    }
+
+    fn restack(&self) -> Result<usize> {
+        self.scoped(|| {
+            // Skip the oplog entry when there is nothing to move
+            if !stack::needs_restack(&self.repo_path)? {
+                return Ok(0);
+            }
+            self.recorded("restack", || stack::restack(&self.repo_path))
+        })
+    }
+
+    fn split_files(&self, id: &str, parts: &[SplitPart]) -> Result<Vec<SplitFile>> {
+        self.scoped(|| split::split_files(&self.repo_path, id, parts))
+    }
+
+    fn absorb(&self, dry_run: bool) -> Result<Vec<AbsorbedHunk>> {
+        if dry_run {
+            return self.scoped(|| absorb::absorb(&self.repo_path, true));
+        }
+        self.recorded("absorb", || absorb::absorb(&self.repo_path, false))
+    }
+
+    fn split_change(&self, id: &str, parts: &[SplitPart]) -> Result<()> {
+        self.recorded(&format!("split {}", abbreviate_hash(id)), || {
+            split::split_commit(&self.repo_path, id, parts).map(|_| ())
+        })
+    }
+
+    fn repo_state(&self) -> Result<RepoState> {
+        self.scoped(|| repo_state::repo_state(&self.repo_path))
+    }
+
+    fn continue_operation(&self) -> Result<()> {
+        self.scoped(|| repo_state::continue_operation(&self.repo_path).map(|_| ()))
+    }
+
+    fn abort_operation(&self) -> Result<()> {
+        self.scoped(|| repo_state::abort_operation(&self.repo_path).map(|_| ()))
+    }
+
+    fn skip_operation(&self) -> Result<()> {
+        self.scoped(|| repo_state::skip_operation(&self.repo_path).map(|_| ()))
+    }
+
+    fn conflict_file(&self, path: &str) -> Result<ConflictFile> {
+        self.scoped(|| resolve::read_conflict_file(&self.repo_path, path))
+    }
+
+    fn resolve_conflict(&self, file: &ConflictFile) -> Result<()> {
+        self.scoped(|| resolve::write_resolution(&self.repo_path, file))
+    }
+
+    fn blame(&self, path: &str, options: &BlameOptions) -> Result<Blame> {
+        self.scoped(|| blame::get_blame(&self.repo_path, path, options))
+    }
+
+    fn file_history(&self, query: &LogQuery) -> Result<Vec<FileVersion>> {
+        self.scoped(|| file_history::get_file_history(&self.repo_path, query))
+    }
+
+    fn file_version_diff(&self, version: &FileVersion) -> Result<String> {
+        self.scoped(|| file_history::get_file_version_diff(&self.repo_path, version))
+    }
+
+    fn file_version_content(&self, version: &FileVersion) -> Result<String> {
+        self.scoped(|| file_history::get_file_version_content(&self.repo_path, version))
+    }
+
+    fn bisect_state(&self) -> Result<Option<BisectState>> {
+        self.scoped(|| bisect::bisect_state(&self.repo_path))
+    }
+
+    fn bisect_mark(&self, verdict: BisectVerdict, id: Option<&str>) -> Result<BisectState> {
+        self.scoped(|| bisect::bisect_mark(&self.repo_path, verdict, id))
+    }
+
+    fn bisect_run(
+        &self,
+        command: &str,
+        on_step: &mut dyn FnMut(&BisectStep),
+    ) -> Result<BisectState> {
+        self.scoped(|| bisect::bisect_run(&self.repo_path, command, on_step))
+    }
+
+    fn bisect_reset(&self) -> Result<()> {
+        self.scoped(|| bisect::bisect_reset(&self.repo_path))
+    }
+}
+
+impl From<oplog::Operation> for HistoryPoint {
+    fn from(op: oplog::Operation) -> Self {
+        let action = match op.kind {
+            oplog::OperationKind::Operation => op
+                .description
+                .split_whitespace()
+                .next()
+                .unwrap_or_default()
+                .to_string(),
+            oplog::OperationKind::Undo(_) => "undo".to_string(),
+            oplog::OperationKind::Redo(_) => "redo".to_string(),
+            oplog::OperationKind::Restore(_) => "restore".to_string(),
+        };
+        HistoryPoint {
+            id: abbreviate_hash(&op.id).to_string(),
+            timestamp: op.timestamp,
+            action,
+            description: op.description,
+            refs: op
+                .head
+                .map(|name| {
+                    // A detached HEAD is recorded as its commit
+                    let detached = name.len() == 40 && name.chars().all(|c| c.is_ascii_hexdigit());
+                    Ref {
+                        ref_type: if detached {
+                            RefType::Head
+                        } else {
+                            RefType::Branch
+                        },
+                        name,
+                    }
+                })
+                .into_iter()
+                .collect(),
+        }
+    }
+}
+
+impl From<reflog::ReflogEntry> for HistoryPoint {
+    fn from(entry: reflog::ReflogEntry) -> Self {
+        let ref_type = if entry.selector.starts_with("HEAD@") {
+            RefType::Head
+        } else {
+            RefType::Branch
+        };
+        HistoryPoint {
+            id: entry.hash,
+            timestamp: entry.timestamp,
+            action: entry.action,
+            description: entry.description,
+            refs: vec![Ref {
+                name: entry.selector,
+                ref_type,
+            }],
+        }
+    }
+}
+
+/// First line of a commit message
+fn summary(message: &str) -> &str {
+    message.lines().next().unwrap_or_default()
+}
+
+/// Operation log description for a history edit, e.g. `reword abc123, drop def456`
+fn describe_plan(plan: &HistoryEditPlan) -> String {
+    let steps: Vec<String> = plan
+        .changes
+        .iter()
+        .filter_map(|entry| {
+            let verb = match entry.action {
+                HistoryEditAction::Keep => return None,
+                HistoryEditAction::Revise => "reword",
+                HistoryEditAction::Remove => "drop",
+                HistoryEditAction::Combine => "squash",
+                HistoryEditAction::Edit => "edit",
+            };
+            Some(format!("{} {}", verb, abbreviate_hash(&entry.change_id)))
+        })
+        .collect();
+    if steps.is_empty() {
+        "edit history".to_string()
+    } else {
+        steps.join(", ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{commit_file, create_test_repo};
+    use crate::vcs::HistoryEditPlanEntry;
+    use std::fs;
+
+    #[test]
+    fn test_log_returns_changes() {
+        let dir = create_test_repo();
+        commit_file(dir.path(), "a.txt", "a", "First");
+        commit_file(dir.path(), "b.txt", "b", "Second");
+
+        let backend = GitBackend::new(dir.path());
+        let changes = backend.log(None, None, false).unwrap();
+
+        assert_eq!(backend.name(), "git");
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].summary, "Second");
+        assert_eq!(changes[0].parent_ids, vec![changes[1].id.clone()]);
+    }
+
+    #[test]
+    fn test_saved_work_counts_changes() {
+        let dir = create_test_repo();
+        commit_file(dir.path(), "a.txt", "one\n", "First");
+        fs::write(dir.path().join("a.txt"), "two\nthree\n").unwrap();
+
+        let backend = GitBackend::new(dir.path());
+        backend.save_work(Some("wip")).unwrap();
+
+        let saved = backend.saved_work().unwrap();
+        assert_eq!(saved.len(), 1);
+        assert_eq!(saved[0].id, "stash@{0}");
+        assert!(saved[0].message.as_deref().unwrap().contains("wip"));
+        assert_eq!(saved[0].files_affected, vec!["a.txt".to_string()]);
+        assert_eq!(saved[0].change_count.additions, 2);
+        assert_eq!(saved[0].change_count.deletions, 1);
+    }
+
+    #[test]
+    fn test_edit_history_removes_and_revises() {
+        let dir = create_test_repo();
+        commit_file(dir.path(), "a.txt", "a", "First");
+        commit_file(dir.path(), "b.txt", "b", "Second");
+        commit_file(dir.path(), "c.txt", "c", "Third");
+
+        let backend = GitBackend::new(dir.path());
+        let changes = backend.log(None, None, false).unwrap();
+        let plan = HistoryEditPlan {
+            changes: vec![
+                HistoryEditPlanEntry {
+                    change_id: changes[1].id.clone(),
+                    action: HistoryEditAction::Revise,
+                    message: Some("Second, reworded".to_string()),
+                },
+                HistoryEditPlanEntry {
+                    change_id: changes[0].id.clone(),
+                    action: HistoryEditAction::Remove,
+                    message: None,
+                },
+            ],
+        };
+        backend.edit_history(&plan).unwrap();
+
+        let summaries: Vec<String> = backend
+            .log(None, None, false)
+            .unwrap()
+            .into_iter()
+            .map(|c| c.summary)
+            .collect();
+        assert_eq!(summaries, vec!["Second, reworded", "First"]);
+    }
+
+    #[test]
+    fn test_merge_is_recorded_and_undone() {
+        let dir = create_test_repo();
+        commit_file(dir.path(), "a.txt", "a", "First");
+        let backend = GitBackend::new(dir.path());
+        let trunk = backend.current_branch().unwrap().unwrap();
+        backend.create_branch("feature", None).unwrap();
+        backend.checkout("feature").unwrap();
+        commit_file(dir.path(), "b.txt", "b", "Second");
+        backend.checkout(&trunk).unwrap();
+        let before = backend.log(None, None, false).unwrap()[0].id.clone();
+
+        backend.merge("feature").unwrap();
+        let operations = backend.operation_log().unwrap();
+        assert_eq!(operations[0].description, "merge feature");
+
+        backend.undo_operation(None).unwrap();
+        assert_eq!(backend.log(None, None, false).unwrap()[0].id, before);
+    }
+
+    #[test]
+    fn test_runner_is_used_for_every_invocation() {
+        use crate::error::GitError;
+        use crate::runner::{FakeRunner, RecordingRunner};
+
+        // Mutating calls also snapshot the operation log, so a read-only
+        // call keeps the script short
+        let fake = FakeRunner::new().respond(&["diff", "main"], "").fail(
+            &["diff", "gone"],
+            "fatal: ambiguous argument 'gone': unknown revision or path not in the working tree.\n",
+        );
+        let recorder = Arc::new(RecordingRunner::new(Arc::new(fake)));
+        let backend = GitBackend::with_runner("/nonexistent", recorder.clone());
+
+        backend.diff(Some("main"), false).unwrap();
+        let err = backend.diff(Some("gone"), false).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<GitError>(),
+            Some(GitError::UnknownRevision(r)) if r == "gone"
+        ));
+        assert_eq!(
+            recorder.invocations(),
+            vec![vec!["diff", "main"], vec!["diff", "gone"]]
+        );
+        assert_eq!(
+            recorder.calls()[0].invocation.cwd.as_deref(),
+            Some(Path::new("/nonexistent"))
+        );
+    }
+}
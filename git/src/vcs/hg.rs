@@ -0,0 +1,750 @@
+use super::{
+    Change, ChangeCount, HistoryEditAction, HistoryEditPlan, Ref, RefType, SavedWork, SyncState,
+    Vcs,
+};
+use crate::command::{run_program, run_program_raw};
+use crate::models::Trailer;
+use crate::operations::status::{FileStatus, StatusType};
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use std::collections::VecDeque;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Fields are separated by US (0x1f) and records by RS (0x1e) so that
+/// multi-line descriptions survive parsing. The escapes are expanded by hg.
+const LOG_TEMPLATE: &str = r"{node}\x1f{node|short}\x1f{p1node}\x1f{p2node}\x1f{author|person}\x1f{author|email}\x1f{date|hgdate}\x1f{bookmarks}\x1f{tags}\x1f{desc}\x1e";
+
+const NULL_NODE: &str = "0000000000000000000000000000000000000000";
+
+/// `Vcs` implementation for Mercurial and Sapling repositories
+#[derive(Debug, Clone)]
+pub struct HgBackend {
+    repo_path: PathBuf,
+    program: &'static str,
+}
+
+impl HgBackend {
+    /// Backend driving `hg` in a `.hg` repository
+    pub fn mercurial(repo_path: impl Into<PathBuf>) -> Self {
+        Self {
+            repo_path: repo_path.into(),
+            program: "hg",
+        }
+    }
+
+    /// Backend driving `sl` in a `.sl` repository
+    pub fn sapling(repo_path: impl Into<PathBuf>) -> Self {
+        Self {
+            repo_path: repo_path.into(),
+            program: "sl",
+        }
+    }
+
+    fn hg(&self, args: &[&str]) -> Result<String> {
+        // HGPLAIN disables aliases, localisation and other output tweaks
+        run_program(
+            self.program,
+            args,
+            Some(&self.repo_path),
+            &[("HGPLAIN", "1")],
+        )
+    }
+
+    /// Files `resolve --list` reports as unresolved. The command fails
+    /// outside a merge, which simply means there are none.
+    fn unresolved_files(&self) -> Result<Vec<String>> {
+        let output = run_program_raw(
+            self.program,
+            &["resolve", "--list"],
+            Some(&self.repo_path),
+            &[("HGPLAIN", "1")],
+        )?;
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|l| l.strip_prefix("U "))
+            .map(str::to_string)
+            .collect())
+    }
+
+    fn histedit_in_progress(&self) -> Result<bool> {
+        let root = self
+            .hg(&["root"])
+            .context("Failed to find repository root")?;
+        let root = Path::new(root.trim());
+        Ok([".hg", ".sl"]
+            .iter()
+            .any(|dir| root.join(dir).join("histedit-state").exists()))
+    }
+}
+
+impl Vcs for HgBackend {
+    fn name(&self) -> &'static str {
+        self.program
+    }
+
+    fn repo_path(&self) -> &Path {
+        &self.repo_path
+    }
+
+    fn log(
+        &self,
+        max_count: Option<usize>,
+        branch: Option<&str>,
+        _remote: bool,
+    ) -> Result<Vec<Change>> {
+        let mut args = vec!["log", "-T", LOG_TEMPLATE];
+
+        let limit = max_count.map(|n| n.to_string());
+        if let Some(ref n) = limit {
+            args.push("-l");
+            args.push(n);
+        }
+
+        let revset = branch.map(|b| format!("reverse(::{})", b));
+        if let Some(ref r) = revset {
+            args.push("-r");
+            args.push(r);
+        }
+
+        let output = self
+            .hg(&args)
+            .with_context(|| format!("Failed to get {} log", self.program))?;
+        parse_log(&output)
+    }
+
+    fn status(&self) -> Result<Vec<FileStatus>> {
+        let output = self
+            .hg(&["status"])
+            .with_context(|| format!("Failed to get {} status", self.program))?;
+        let mut files = parse_status(&output);
+
+        for path in self.unresolved_files()? {
+            match files.iter_mut().find(|f| f.path == path) {
+                Some(file) => *file = FileStatus::new(path, StatusType::Conflicted),
+                None => files.push(FileStatus::new(path, StatusType::Conflicted)),
+            }
+        }
+
+        Ok(files)
+    }
+
+    fn diff(&self, target: Option<&str>, _staged: bool) -> Result<String> {
+        // There is no index, so `staged` has nothing to select
+        let mut args = vec!["diff", "--git"];
+        if let Some(t) = target {
+            if !self.repo_path.join(t).exists() {
+                args.push("-r");
+            }
+            args.push(t);
+        }
+
+        self.hg(&args)
+            .with_context(|| format!("Failed to get diff for {:?}", target))
+    }
+
+    fn change_diff(&self, id: &str) -> Result<String> {
+        self.hg(&["diff", "--git", "-c", id])
+            .with_context(|| format!("Failed to get diff for change {}", id))
+    }
+
+    fn stage(&self, path: &str) -> Result<()> {
+        // Tracking a file is the closest thing to staging: commits always
+        // take every modified tracked file.
+        self.hg(&["add", path])
+            .with_context(|| format!("Failed to add {}", path))?;
+        Ok(())
+    }
+
+    fn unstage(&self, _path: &str) -> Result<()> {
+        bail!("{} has no staging area", self.program)
+    }
+
+    fn stage_all(&self) -> Result<()> {
+        self.hg(&["addremove"])
+            .context("Failed to add new and remove missing files")?;
+        Ok(())
+    }
+
+    fn unstage_all(&self) -> Result<()> {
+        bail!("{} has no staging area", self.program)
+    }
+
+    fn commit(&self, message: &str) -> Result<()> {
+        self.hg(&["commit", "-m", message])
+            .context("Failed to commit")?;
+        Ok(())
+    }
+
+    fn amend(&self, message: Option<&str>) -> Result<()> {
+        let mut args = vec!["commit", "--amend"];
+        if let Some(msg) = message {
+            args.push("-m");
+            args.push(msg);
+        }
+        self.hg(&args).context("Failed to amend")?;
+        Ok(())
+    }
+
+    fn current_branch(&self) -> Result<Option<String>> {
+        let output = self
+            .hg(&["log", "-r", ".", "-T", "{activebookmark}"])
+            .context("Failed to get active bookmark")?;
+        let bookmark = output.trim();
+        Ok((!bookmark.is_empty()).then(|| bookmark.to_string()))
+    }
+
+    fn branches(&self) -> Result<Vec<Ref>> {
+        let output = self
+            .hg(&["bookmarks", "-T", r"{bookmark}\n"])
+            .context("Failed to list bookmarks")?;
+        Ok(output
+            .lines()
+            .filter(|l| !l.trim().is_empty())
+            .map(|name| Ref {
+                name: name.to_string(),
+                ref_type: RefType::Branch,
+            })
+            .collect())
+    }
+
+    fn create_branch(&self, name: &str, at: Option<&str>) -> Result<()> {
+        let mut args = vec!["bookmark", name];
+        if let Some(rev) = at {
+            args.push("-r");
+            args.push(rev);
+        }
+        self.hg(&args)
+            .with_context(|| format!("Failed to create bookmark '{}'", name))?;
+
+        if at.is_some() {
+            self.checkout(name)?;
+        }
+        Ok(())
+    }
+
+    fn checkout(&self, target: &str) -> Result<()> {
+        self.hg(&["update", target])
+            .with_context(|| format!("Failed to update to '{}'", target))?;
+        Ok(())
+    }
+
+    fn saved_work(&self) -> Result<Vec<SavedWork>> {
+        let output = self
+            .hg(&["shelve", "--list", "--stat"])
+            .context("Failed to list shelves")?;
+        Ok(parse_shelve_list(&output, Utc::now()))
+    }
+
+    fn save_work(&self, message: Option<&str>) -> Result<()> {
+        let mut args = vec!["shelve"];
+        if let Some(msg) = message {
+            args.push("-m");
+            args.push(msg);
+        }
+        self.hg(&args).context("Failed to shelve changes")?;
+        Ok(())
+    }
+
+    fn apply_saved_work(&self, id: Option<&str>) -> Result<()> {
+        let mut args = vec!["unshelve", "--keep"];
+        args.extend(id);
+        self.hg(&args).context("Failed to unshelve")?;
+        Ok(())
+    }
+
+    fn pop_saved_work(&self, id: Option<&str>) -> Result<()> {
+        let mut args = vec!["unshelve"];
+        args.extend(id);
+        self.hg(&args).context("Failed to unshelve")?;
+        Ok(())
+    }
+
+    fn drop_saved_work(&self, id: Option<&str>) -> Result<()> {
+        let name = match id {
+            Some(name) => name.to_string(),
+            None => match self.saved_work()?.into_iter().next() {
+                Some(latest) => latest.id,
+                None => bail!("No shelves to delete"),
+            },
+        };
+        self.hg(&["shelve", "--delete", &name])
+            .with_context(|| format!("Failed to delete shelve {}", name))?;
+        Ok(())
+    }
+
+    fn show_saved_work(&self, id: &str) -> Result<String> {
+        self.hg(&["--config", "diff.git=true", "shelve", "--patch", id])
+            .with_context(|| format!("Failed to show shelve {}", id))
+    }
+
+    fn sync_state(&self) -> Result<SyncState> {
+        // Draft phase marks changesets that have not been pushed. Counting
+        // incoming changes needs the network, so it is left unknown.
+        let remote = run_program_raw(
+            self.program,
+            &["paths", "default"],
+            Some(&self.repo_path),
+            &[("HGPLAIN", "1")],
+        )?;
+        let remote_name = remote.status.success().then(|| "default".to_string());
+
+        let draft = self
+            .hg(&["log", "-r", "draft() & ::.", "-T", r"{node}\n"])
+            .context("Failed to count draft changes")?;
+
+        Ok(SyncState {
+            local_unpushed: remote_name
+                .as_ref()
+                .map(|_| draft.lines().filter(|l| !l.is_empty()).count()),
+            remote_name,
+            remote_unpulled: None,
+            has_conflicts: !self.unresolved_files()?.is_empty(),
+        })
+    }
+
+    fn edit_history(&self, plan: &HistoryEditPlan) -> Result<()> {
+        if plan.changes.is_empty() {
+            return Ok(());
+        }
+
+        // The plan may list changes in any order, so histedit starts from the
+        // oldest of them. It wants a rule for every changeset between there
+        // and the working copy parent; anything the plan does not mention is
+        // kept.
+        let ids: Vec<&str> = plan.changes.iter().map(|e| e.change_id.as_str()).collect();
+        let roots = self
+            .hg(&[
+                "log",
+                "-r",
+                &format!("roots({})", ids.join(" + ")),
+                "-T",
+                r"{node}\n",
+            ])
+            .context("Failed to find the oldest change of the plan")?;
+        let roots: Vec<&str> = roots.lines().filter(|l| !l.is_empty()).collect();
+        let [base] = roots[..] else {
+            bail!("History edit plan must name changes on a single line of history");
+        };
+
+        let nodes = self
+            .hg(&["log", "-r", &format!("{}::.", base), "-T", r"{node}\n"])
+            .with_context(|| format!("Failed to list changes from {}", base))?;
+        let nodes: Vec<String> = nodes
+            .lines()
+            .filter(|l| !l.is_empty())
+            .map(str::to_string)
+            .collect();
+
+        let (rules, mut messages) = histedit_rules(plan, &nodes)?;
+
+        let mut commands = tempfile::NamedTempFile::new()?;
+        commands.write_all(rules.as_bytes())?;
+        let commands_path = commands.path().to_string_lossy().to_string();
+
+        let mut result = self.hg(&["histedit", "--commands", &commands_path, base]);
+
+        // `mess` would open an editor, so revisions stop with `edit` and are
+        // amended here before continuing.
+        while self.histedit_in_progress()? {
+            if !self.unresolved_files()?.is_empty() {
+                bail!(
+                    "History edit stopped on a conflict; resolve it and run `{} histedit --continue`",
+                    self.program
+                );
+            }
+            let Some(message) = messages.pop_front() else {
+                bail!(
+                    "History edit stopped for editing; run `{} histedit --continue` when done",
+                    self.program
+                );
+            };
+            if let Some(msg) = message {
+                self.hg(&["commit", "--amend", "-m", &msg])
+                    .context("Failed to reword change")?;
+            }
+            result = self.hg(&["histedit", "--continue"]);
+        }
+
+        result.context("Failed to edit history")?;
+        Ok(())
+    }
+
+    fn squash(&self, base: &str, message: &str) -> Result<()> {
+        // Fold everything after `base` into its first child
+        let revset = format!("{}::. - {}", base, base);
+        let nodes = self
+            .hg(&["log", "-r", &revset, "-T", r"{node}\n"])
+            .with_context(|| format!("Failed to list changes after {}", base))?;
+        let nodes: Vec<&str> = nodes.lines().filter(|l| !l.is_empty()).collect();
+        if nodes.is_empty() {
+            bail!("Nothing to squash after {}", base);
+        }
+
+        let mut plan = HistoryEditPlan {
+            changes: nodes
+                .iter()
+                .map(|node| super::HistoryEditPlanEntry {
+                    change_id: node.to_string(),
+                    action: HistoryEditAction::Combine,
+                    message: None,
+                })
+                .collect(),
+        };
+        plan.changes[0].action = HistoryEditAction::Revise;
+        plan.changes[0].message = Some(message.to_string());
+        self.edit_history(&plan)
+    }
+
+    fn cherry_pick(&self, id: &str) -> Result<()> {
+        self.hg(&["graft", "-r", id])
+            .with_context(|| format!("Failed to graft {}", id))?;
+        Ok(())
+    }
+
+    fn revert(&self, id: &str) -> Result<()> {
+        let message = format!("Backed out changeset {}", id);
+        self.hg(&["backout", "-r", id, "-m", &message])
+            .with_context(|| format!("Failed to back out {}", id))?;
+        Ok(())
+    }
+}
+
+/// Histedit rules for `nodes` (oldest first) in the order the plan asks for.
+/// Also returns, for every change that will stop with `edit`, the message to
+/// amend it with (`None` for plain `Edit` entries, which hand control back to
+/// the user).
+fn histedit_rules(
+    plan: &HistoryEditPlan,
+    nodes: &[String],
+) -> Result<(String, VecDeque<Option<String>>)> {
+    let mut rules = String::new();
+    let mut messages = VecDeque::new();
+    for (node, entry) in plan.arrange(nodes)? {
+        let action = match entry.map(|e| (e.action, e.message.clone())) {
+            None | Some((HistoryEditAction::Keep, _)) => "pick",
+            Some((HistoryEditAction::Revise, message)) => {
+                let message = message.context("Revise needs a new message")?;
+                messages.push_back(Some(message));
+                "edit"
+            }
+            Some((HistoryEditAction::Edit, _)) => {
+                messages.push_back(None);
+                "edit"
+            }
+            Some((HistoryEditAction::Combine, _)) => "roll",
+            Some((HistoryEditAction::Remove, _)) => "drop",
+        };
+        rules.push_str(&format!("{} {}\n", action, node));
+    }
+
+    Ok((rules, messages))
+}
+
+fn parse_log(output: &str) -> Result<Vec<Change>> {
+    let mut changes = Vec::new();
+
+    for record in output.split('\x1e') {
+        let record = record.trim_start_matches('\n');
+        if record.is_empty() {
+            continue;
+        }
+
+        let fields: Vec<&str> = record.splitn(10, '\x1f').collect();
+        if fields.len() < 10 {
+            bail!("Unexpected log record: {:?}", record);
+        }
+
+        let seconds: i64 = fields[6]
+            .split_whitespace()
+            .next()
+            .and_then(|s| s.parse().ok())
+            .with_context(|| format!("Invalid date: {}", fields[6]))?;
+        let date = DateTime::from_timestamp(seconds, 0).unwrap_or_default();
+
+        let parent_ids = [fields[2], fields[3]]
+            .iter()
+            .filter(|p| !p.is_empty() && **p != NULL_NODE)
+            .map(|p| p.to_string())
+            .collect();
+
+        let mut refs: Vec<Ref> = fields[7]
+            .split_whitespace()
+            .map(|name| Ref {
+                name: name.to_string(),
+                ref_type: RefType::Branch,
+            })
+            .collect();
+        refs.extend(
+            fields[8]
+                .split_whitespace()
+                .filter(|t| *t != "tip")
+                .map(|name| Ref {
+                    name: name.to_string(),
+                    ref_type: RefType::Tag,
+                }),
+        );
+
+        let message = fields[9].to_string();
+        let trailers = Trailer::parse_message(&message);
+        changes.push(Change {
+            id: fields[0].to_string(),
+            short_id: fields[1].to_string(),
+            summary: message.lines().next().unwrap_or("").to_string(),
+            message,
+            author: fields[4].to_string(),
+            email: fields[5].to_string(),
+            date,
+            parent_ids,
+            refs,
+            committer: fields[4].to_string(),
+            committer_email: fields[5].to_string(),
+            committer_date: date,
+            trailers,
+        });
+    }
+
+    Ok(changes)
+}
+
+fn parse_status(output: &str) -> Vec<FileStatus> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (code, path) = line.split_at_checked(2)?;
+            let status = match code.trim() {
+                "M" => StatusType::Modified,
+                "A" => StatusType::Added,
+                "R" | "!" => StatusType::Deleted,
+                "?" => StatusType::Untracked,
+                _ => return None,
+            };
+            Some(FileStatus::new(path, status))
+        })
+        .collect()
+}
+
+/// Parse `hg shelve --list --stat`: a header line per shelve followed by its
+/// indented diffstat.
+fn parse_shelve_list(output: &str, now: DateTime<Utc>) -> Vec<SavedWork> {
+    let mut shelves: Vec<SavedWork> = Vec::new();
+
+    for line in output.lines() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        if !line.starts_with(' ') {
+            let name = line.split_whitespace().next().unwrap_or_default();
+            let (age, message) = match (line.find('('), line.find(')')) {
+                (Some(open), Some(close)) if open < close => {
+                    (&line[open + 1..close], line[close + 1..].trim())
+                }
+                _ => ("", ""),
+            };
+            shelves.push(SavedWork {
+                id: name.to_string(),
+                message: (!message.is_empty()).then(|| message.to_string()),
+                timestamp: now - parse_age(age).unwrap_or_default(),
+                files_affected: Vec::new(),
+                change_count: ChangeCount::default(),
+            });
+            continue;
+        }
+
+        let Some(shelve) = shelves.last_mut() else {
+            continue;
+        };
+        if let Some((file, _)) = line.split_once('|') {
+            shelve.files_affected.push(file.trim().to_string());
+        } else if line.contains("changed") {
+            for part in line.split(',') {
+                let count = part
+                    .split_whitespace()
+                    .next()
+                    .and_then(|n| n.parse::<usize>().ok())
+                    .unwrap_or(0);
+                if part.contains("insertion") {
+                    shelve.change_count.additions = count;
+                } else if part.contains("deletion") {
+                    shelve.change_count.deletions = count;
+                }
+            }
+        }
+    }
+
+    shelves
+}
+
+/// Parse an abbreviated age such as `5m ago` or `2 hours ago`
+fn parse_age(age: &str) -> Option<Duration> {
+    let age = age.trim().trim_end_matches("ago").trim();
+    let split = age.find(|c: char| !c.is_ascii_digit())?;
+    let amount: i64 = age[..split].parse().ok()?;
+    let unit = age[split..].trim();
+    let duration = match unit.chars().next()? {
+        's' => Duration::seconds(amount),
+        'm' if unit.starts_with("mo") => Duration::days(amount * 30),
+        'm' => Duration::minutes(amount),
+        'h' => Duration::hours(amount),
+        'd' => Duration::days(amount),
+        'w' => Duration::weeks(amount),
+        'y' => Duration::days(amount * 365),
+        _ => return None,
+    };
+    Some(duration)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vcs::HistoryEditPlanEntry;
+
+    #[test]
+    fn test_parse_log() {
+        let output = format!(
+            "{a}\x1f{sa}\x1f{n}\x1f{n}\x1fAlice\x1falice@example.com\x1f1700000000 0\x1f\x1ftip\x1fInitial\x1e\
+             {b}\x1f{sb}\x1f{a}\x1f{n}\x1fBob\x1fbob@example.com\x1f1700003600 -3600\x1ffeature main\x1fv1.0 tip\x1fAdd feature\n\nWith body\x1e",
+            a = "a".repeat(40),
+            sa = "a".repeat(12),
+            b = "b".repeat(40),
+            sb = "b".repeat(12),
+            n = NULL_NODE,
+        );
+
+        let changes = parse_log(&output).unwrap();
+        assert_eq!(changes.len(), 2);
+
+        assert!(changes[0].parent_ids.is_empty());
+        assert!(changes[0].refs.is_empty());
+        assert_eq!(changes[0].date.timestamp(), 1700000000);
+
+        let second = &changes[1];
+        assert_eq!(second.short_id, "b".repeat(12));
+        assert_eq!(second.parent_ids, vec!["a".repeat(40)]);
+        assert_eq!(second.summary, "Add feature");
+        assert_eq!(second.message, "Add feature\n\nWith body");
+        assert_eq!(second.author, "Bob");
+        let refs: Vec<(&str, RefType)> = second
+            .refs
+            .iter()
+            .map(|r| (r.name.as_str(), r.ref_type.clone()))
+            .collect();
+        assert_eq!(
+            refs,
+            vec![
+                ("feature", RefType::Branch),
+                ("main", RefType::Branch),
+                ("v1.0", RefType::Tag),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_status() {
+        let files = parse_status("M src/lib.rs\nA new.rs\nR old.rs\n! gone.rs\n? scratch.txt\n");
+        let statuses: Vec<(&str, &StatusType)> =
+            files.iter().map(|f| (f.path.as_str(), &f.status)).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                ("src/lib.rs", &StatusType::Modified),
+                ("new.rs", &StatusType::Added),
+                ("old.rs", &StatusType::Deleted),
+                ("gone.rs", &StatusType::Deleted),
+                ("scratch.txt", &StatusType::Untracked),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_shelve_list() {
+        let now = Utc::now();
+        let output = "\
+default         (5m ago)    changes to: Add feature
+ a.txt |  2 +-
+ b.txt |  1 +
+ 2 files changed, 2 insertions(+), 1 deletions(-)
+wip             (2h ago)
+ c.txt |  1 -
+ 1 files changed, 0 insertions(+), 1 deletions(-)
+";
+
+        let shelves = parse_shelve_list(output, now);
+        assert_eq!(shelves.len(), 2);
+
+        assert_eq!(shelves[0].id, "default");
+        assert_eq!(
+            shelves[0].message.as_deref(),
+            Some("changes to: Add feature")
+        );
+        assert_eq!(shelves[0].timestamp, now - Duration::minutes(5));
+        assert_eq!(shelves[0].files_affected, vec!["a.txt", "b.txt"]);
+        assert_eq!(shelves[0].change_count.additions, 2);
+        assert_eq!(shelves[0].change_count.deletions, 1);
+
+        assert_eq!(shelves[1].id, "wip");
+        assert_eq!(shelves[1].message, None);
+        assert_eq!(shelves[1].timestamp, now - Duration::hours(2));
+        assert_eq!(shelves[1].change_count.deletions, 1);
+    }
+
+    #[test]
+    fn test_histedit_rules() {
+        let plan = HistoryEditPlan {
+            changes: vec![
+                HistoryEditPlanEntry {
+                    change_id: "aaa".to_string(),
+                    action: HistoryEditAction::Revise,
+                    message: Some("New message".to_string()),
+                },
+                HistoryEditPlanEntry {
+                    change_id: "ccc".to_string(),
+                    action: HistoryEditAction::Combine,
+                    message: None,
+                },
+                HistoryEditPlanEntry {
+                    change_id: "ddd".to_string(),
+                    action: HistoryEditAction::Remove,
+                    message: None,
+                },
+            ],
+        };
+
+        let nodes: Vec<String> = ["aaa", "bbb", "ccc", "ddd"]
+            .iter()
+            .map(|n| n.to_string())
+            .collect();
+        let (rules, messages) = histedit_rules(&plan, &nodes).unwrap();
+        assert_eq!(rules, "edit aaa\npick bbb\nroll ccc\ndrop ddd\n");
+        assert_eq!(messages, vec![Some("New message".to_string())]);
+
+        // Listing a later change first moves it into the earlier position, and
+        // `roll` folds into whatever ends up before it
+        let reordered = HistoryEditPlan {
+            changes: vec![
+                HistoryEditPlanEntry {
+                    change_id: "ccc".to_string(),
+                    action: HistoryEditAction::Keep,
+                    message: None,
+                },
+                HistoryEditPlanEntry {
+                    change_id: "aaa".to_string(),
+                    action: HistoryEditAction::Combine,
+                    message: None,
+                },
+            ],
+        };
+        let (rules, _) = histedit_rules(&reordered, &nodes).unwrap();
+        assert_eq!(rules, "pick ccc\npick bbb\nroll aaa\npick ddd\n");
+
+        let unknown = HistoryEditPlan {
+            changes: vec![HistoryEditPlanEntry {
+                change_id: "eee".to_string(),
+                action: HistoryEditAction::Keep,
+                message: None,
+            }],
+        };
+        assert!(histedit_rules(&unknown, &nodes).is_err());
+    }
+}
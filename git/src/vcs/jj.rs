@@ -0,0 +1,626 @@
+use super::{
+    Change, HistoryEditAction, HistoryEditPlan, HistoryPoint, Ref, RefType, SavedWork, SyncState,
+    Vcs,
+};
+use crate::command::{run_program, run_program_raw};
+use crate::models::Trailer;
+use crate::operations::status::{FileStatus, StatusType};
+use anyhow::{bail, Context, Result};
+use chrono::DateTime;
+use std::path::{Path, PathBuf};
+
+/// Fields are separated by US (0x1f) and records by RS (0x1e) so that
+/// multi-line descriptions survive parsing.
+const LOG_TEMPLATE: &str = r#"commit_id ++ "\x1f" ++ change_id.short() ++ "\x1f" ++ parents.map(|p| p.commit_id()).join(" ") ++ "\x1f" ++ author.name() ++ "\x1f" ++ author.email() ++ "\x1f" ++ author.timestamp().utc().format("%s") ++ "\x1f" ++ local_bookmarks.map(|b| b.name()).join(" ") ++ "\x1f" ++ remote_bookmarks.map(|b| b.name() ++ "@" ++ b.remote()).join(" ") ++ "\x1f" ++ tags.map(|t| t.name()).join(" ") ++ "\x1f" ++ if(current_working_copy, "@") ++ "\x1f" ++ description ++ "\x1e""#;
+
+const OP_LOG_TEMPLATE: &str =
+    r#"id.short() ++ "\x1f" ++ time.start().utc().format("%s") ++ "\x1f" ++ description ++ "\x1e""#;
+
+const CHANGE_ID_TEMPLATE: &str = r#"change_id ++ "\n""#;
+
+/// `Vcs` implementation for Jujutsu repositories, including ones colocated
+/// with git. `Change.short_id` carries the jj change ID, which survives
+/// rewrites; `Change.id` is the commit ID so git tooling can still use it.
+#[derive(Debug, Clone)]
+pub struct JjBackend {
+    repo_path: PathBuf,
+}
+
+impl JjBackend {
+    pub fn new(repo_path: impl Into<PathBuf>) -> Self {
+        Self {
+            repo_path: repo_path.into(),
+        }
+    }
+
+    fn jj(&self, args: &[&str]) -> Result<String> {
+        let mut full_args = vec!["--no-pager", "--color", "never"];
+        full_args.extend_from_slice(args);
+        run_program("jj", &full_args, Some(&self.repo_path), &[])
+    }
+
+    /// Run a template over a revset, one result per line
+    fn query(&self, revset: &str, template: &str) -> Result<Vec<String>> {
+        let output = self
+            .jj(&["log", "--no-graph", "-r", revset, "-T", template])
+            .with_context(|| format!("Failed to evaluate revset {}", revset))?;
+        Ok(output
+            .lines()
+            .filter(|l| !l.is_empty())
+            .map(str::to_string)
+            .collect())
+    }
+
+    fn description(&self, revset: &str) -> Result<String> {
+        self.jj(&["log", "--no-graph", "-r", revset, "-T", "description"])
+            .with_context(|| format!("Failed to get description of {}", revset))
+    }
+
+    fn no_stash(&self) -> Result<()> {
+        bail!("jj has no stash; the working copy is already a change (use `jj new`)")
+    }
+}
+
+impl Vcs for JjBackend {
+    fn name(&self) -> &'static str {
+        "jj"
+    }
+
+    fn repo_path(&self) -> &Path {
+        &self.repo_path
+    }
+
+    fn log(
+        &self,
+        max_count: Option<usize>,
+        branch: Option<&str>,
+        remote: bool,
+    ) -> Result<Vec<Change>> {
+        let revset = match branch {
+            Some(b) => format!("::{}", b),
+            None if remote => "::remote_bookmarks()".to_string(),
+            None => "all()".to_string(),
+        };
+        let mut args = vec!["log", "--no-graph", "-r", &revset, "-T", LOG_TEMPLATE];
+
+        let limit = max_count.map(|n| n.to_string());
+        if let Some(ref n) = limit {
+            args.push("-n");
+            args.push(n);
+        }
+
+        let output = self.jj(&args).context("Failed to get jj log")?;
+        parse_log(&output)
+    }
+
+    fn status(&self) -> Result<Vec<FileStatus>> {
+        let output = self
+            .jj(&["diff", "--summary"])
+            .context("Failed to get jj status")?;
+        let mut files = parse_summary(&output);
+
+        // `resolve --list` exits non-zero when there is nothing to resolve
+        let conflicts = run_program_raw(
+            "jj",
+            &["--no-pager", "resolve", "--list"],
+            Some(&self.repo_path),
+            &[],
+        )?;
+        for line in String::from_utf8_lossy(&conflicts.stdout).lines() {
+            let Some(path) = line.split_whitespace().next() else {
+                continue;
+            };
+            match files.iter_mut().find(|f| f.path == path) {
+                Some(file) => *file = FileStatus::new(path, StatusType::Conflicted),
+                None => files.push(FileStatus::new(path, StatusType::Conflicted)),
+            }
+        }
+
+        Ok(files)
+    }
+
+    fn diff(&self, target: Option<&str>, _staged: bool) -> Result<String> {
+        let mut args = vec!["diff", "--git"];
+        if let Some(t) = target {
+            if !self.repo_path.join(t).exists() {
+                args.push("--from");
+            }
+            args.push(t);
+        }
+
+        self.jj(&args)
+            .with_context(|| format!("Failed to get diff for {:?}", target))
+    }
+
+    fn change_diff(&self, id: &str) -> Result<String> {
+        self.jj(&["diff", "--git", "-r", id])
+            .with_context(|| format!("Failed to get diff for change {}", id))
+    }
+
+    fn stage(&self, _path: &str) -> Result<()> {
+        bail!("jj has no staging area; the working copy is committed automatically")
+    }
+
+    fn unstage(&self, _path: &str) -> Result<()> {
+        bail!("jj has no staging area; the working copy is committed automatically")
+    }
+
+    fn stage_all(&self) -> Result<()> {
+        bail!("jj has no staging area; the working copy is committed automatically")
+    }
+
+    fn unstage_all(&self) -> Result<()> {
+        bail!("jj has no staging area; the working copy is committed automatically")
+    }
+
+    fn commit(&self, message: &str) -> Result<()> {
+        self.jj(&["commit", "-m", message])
+            .context("Failed to commit")?;
+        Ok(())
+    }
+
+    fn amend(&self, message: Option<&str>) -> Result<()> {
+        // Passing a message keeps `squash` from opening an editor to combine
+        // the two descriptions.
+        let message = match message {
+            Some(msg) => msg.to_string(),
+            None => self.description("@-")?,
+        };
+        self.jj(&["squash", "-m", &message])
+            .context("Failed to squash the working copy into its parent")?;
+        Ok(())
+    }
+
+    fn current_branch(&self) -> Result<Option<String>> {
+        let names = self.query(
+            "(@ | @-) & bookmarks()",
+            r#"local_bookmarks.map(|b| b.name() ++ "\n").join("")"#,
+        )?;
+        Ok(names.into_iter().next())
+    }
+
+    fn branches(&self) -> Result<Vec<Ref>> {
+        let names = self.query(
+            "bookmarks()",
+            r#"local_bookmarks.map(|b| b.name() ++ "\n").join("")"#,
+        )?;
+        Ok(names
+            .into_iter()
+            .map(|name| Ref {
+                name,
+                ref_type: RefType::Branch,
+            })
+            .collect())
+    }
+
+    fn create_branch(&self, name: &str, at: Option<&str>) -> Result<()> {
+        self.jj(&["bookmark", "create", name, "-r", at.unwrap_or("@-")])
+            .with_context(|| format!("Failed to create bookmark '{}'", name))?;
+
+        if at.is_some() {
+            self.checkout(name)?;
+        }
+        Ok(())
+    }
+
+    fn checkout(&self, target: &str) -> Result<()> {
+        self.jj(&["new", target])
+            .with_context(|| format!("Failed to start a change on '{}'", target))?;
+        Ok(())
+    }
+
+    fn saved_work(&self) -> Result<Vec<SavedWork>> {
+        self.no_stash().map(|_| Vec::new())
+    }
+
+    fn save_work(&self, _message: Option<&str>) -> Result<()> {
+        self.no_stash()
+    }
+
+    fn apply_saved_work(&self, _id: Option<&str>) -> Result<()> {
+        self.no_stash()
+    }
+
+    fn pop_saved_work(&self, _id: Option<&str>) -> Result<()> {
+        self.no_stash()
+    }
+
+    fn drop_saved_work(&self, _id: Option<&str>) -> Result<()> {
+        self.no_stash()
+    }
+
+    fn show_saved_work(&self, _id: &str) -> Result<String> {
+        self.no_stash().map(|_| String::new())
+    }
+
+    fn sync_state(&self) -> Result<SyncState> {
+        let remotes = self.jj(&["git", "remote", "list"]).unwrap_or_default();
+        let remote_name = remotes
+            .lines()
+            .next()
+            .and_then(|l| l.split_whitespace().next())
+            .map(str::to_string);
+
+        let (local_unpushed, remote_unpulled) = if remote_name.is_some() {
+            (
+                Some(
+                    self.query("remote_bookmarks()..@-", CHANGE_ID_TEMPLATE)?
+                        .len(),
+                ),
+                Some(
+                    self.query("@-..remote_bookmarks()", CHANGE_ID_TEMPLATE)?
+                        .len(),
+                ),
+            )
+        } else {
+            (None, None)
+        };
+
+        let conflicted = self.query("@ & conflicts()", CHANGE_ID_TEMPLATE)?;
+
+        Ok(SyncState {
+            remote_name,
+            local_unpushed,
+            remote_unpulled,
+            has_conflicts: !conflicted.is_empty(),
+        })
+    }
+
+    fn edit_history(&self, plan: &HistoryEditPlan) -> Result<()> {
+        if plan.changes.is_empty() {
+            return Ok(());
+        }
+
+        // Commit IDs change as soon as anything below them is rewritten;
+        // change IDs do not, so resolve every entry up front.
+        let mut resolved = plan.clone();
+        for entry in &mut resolved.changes {
+            entry.change_id = self
+                .query(&entry.change_id, CHANGE_ID_TEMPLATE)?
+                .into_iter()
+                .next()
+                .with_context(|| format!("Unknown change {}", entry.change_id))?;
+            if entry.action == HistoryEditAction::Revise && entry.message.is_none() {
+                bail!("No new message for {}", entry.change_id);
+            }
+        }
+
+        let ids: Vec<&str> = resolved
+            .changes
+            .iter()
+            .map(|e| e.change_id.as_str())
+            .collect();
+        let range = format!("roots({})::@", ids.join(" | "));
+        if !self
+            .query(&format!("({}) & merges()", range), CHANGE_ID_TEMPLATE)?
+            .is_empty()
+        {
+            bail!("Cannot edit history across a merge");
+        }
+        let base = self.query(&format!("roots({})-", range), CHANGE_ID_TEMPLATE)?;
+        let [ref base] = base[..] else {
+            bail!("History edit plan must name changes on a single line of history");
+        };
+        // `jj log` lists the newest change first
+        let mut changes = self.query(&range, CHANGE_ID_TEMPLATE)?;
+        changes.reverse();
+        let arranged = resolved.arrange(&changes)?;
+        let first_kept = arranged
+            .iter()
+            .find(|(_, entry)| entry.is_none_or(|e| e.action != HistoryEditAction::Remove));
+        if let Some((change_id, Some(entry))) = first_kept {
+            if entry.action == HistoryEditAction::Combine {
+                bail!("Cannot combine {}: nothing comes before it", change_id);
+            }
+        }
+
+        // Move changes into plan order by rebuilding the line from the first
+        // one out of place, each on top of the change the plan puts before it
+        if let Some(first_moved) = arranged
+            .iter()
+            .zip(&changes)
+            .position(|((planned, _), current)| planned != current)
+        {
+            let mut parent = match first_moved {
+                0 => base.as_str(),
+                n => arranged[n - 1].0,
+            };
+            for (change, _) in &arranged[first_moved..] {
+                self.jj(&["rebase", "-r", change, "-d", parent])
+                    .with_context(|| format!("Failed to move {}", change))?;
+                parent = change;
+            }
+        }
+
+        // `Combine` folds into the change before it in plan order
+        let mut previous: Option<&str> = None;
+        for (change_id, entry) in arranged {
+            let Some(entry) = entry else {
+                previous = Some(change_id);
+                continue;
+            };
+            match entry.action {
+                HistoryEditAction::Keep => {}
+                HistoryEditAction::Revise => {
+                    let message = entry.message.as_deref().unwrap_or_default();
+                    self.jj(&["describe", change_id, "-m", message])
+                        .with_context(|| format!("Failed to describe {}", change_id))?;
+                }
+                HistoryEditAction::Combine => {
+                    let Some(into) = previous else {
+                        bail!("Cannot combine {}: nothing comes before it", change_id);
+                    };
+                    let message = match entry.message.as_deref() {
+                        Some(msg) => msg.to_string(),
+                        None => self.description(into)?,
+                    };
+                    self.jj(&[
+                        "squash", "--from", change_id, "--into", into, "-m", &message,
+                    ])
+                    .with_context(|| format!("Failed to squash {}", change_id))?;
+                    continue;
+                }
+                HistoryEditAction::Remove => {
+                    self.jj(&["abandon", change_id])
+                        .with_context(|| format!("Failed to abandon {}", change_id))?;
+                    continue;
+                }
+                HistoryEditAction::Edit => {
+                    self.jj(&["edit", change_id])
+                        .with_context(|| format!("Failed to edit {}", change_id))?;
+                }
+            }
+            previous = Some(change_id);
+        }
+        Ok(())
+    }
+
+    fn squash(&self, base: &str, message: &str) -> Result<()> {
+        let revset = format!("{}..@-", base);
+        let changes = self.query(&format!("roots({})", revset), CHANGE_ID_TEMPLATE)?;
+        let Some(first) = changes.first() else {
+            bail!("Nothing to squash after {}", base);
+        };
+
+        let rest = format!("({}) ~ {}", revset, first);
+        if !self.query(&rest, CHANGE_ID_TEMPLATE)?.is_empty() {
+            self.jj(&["squash", "--from", &rest, "--into", first, "-m", message])
+                .context("Failed to squash changes")?;
+        } else {
+            self.jj(&["describe", first, "-m", message])
+                .context("Failed to describe squashed change")?;
+        }
+        Ok(())
+    }
+
+    fn cherry_pick(&self, id: &str) -> Result<()> {
+        self.jj(&["duplicate", id, "-d", "@-"])
+            .with_context(|| format!("Failed to duplicate {}", id))?;
+        Ok(())
+    }
+
+    fn revert(&self, id: &str) -> Result<()> {
+        self.jj(&["revert", "-r", id, "-B", "@"])
+            .with_context(|| format!("Failed to revert {}", id))?;
+        Ok(())
+    }
+
+    fn operation_log(&self) -> Result<Vec<HistoryPoint>> {
+        let output = self
+            .jj(&["op", "log", "--no-graph", "-T", OP_LOG_TEMPLATE])
+            .context("Failed to get operation log")?;
+        parse_op_log(&output)
+    }
+
+    fn undo_operation(&self, id: Option<&str>) -> Result<()> {
+        match id {
+            Some(op) => self.jj(&["op", "undo", op]),
+            None => self.jj(&["undo"]),
+        }
+        .context("Failed to undo operation")?;
+        Ok(())
+    }
+}
+
+fn parse_log(output: &str) -> Result<Vec<Change>> {
+    let mut changes = Vec::new();
+
+    for record in output.split('\x1e') {
+        let record = record.trim_start_matches('\n');
+        if record.is_empty() {
+            continue;
+        }
+
+        let fields: Vec<&str> = record.splitn(11, '\x1f').collect();
+        if fields.len() < 11 {
+            bail!("Unexpected log record: {:?}", record);
+        }
+
+        let seconds: i64 = fields[5]
+            .trim()
+            .parse()
+            .with_context(|| format!("Invalid timestamp: {}", fields[5]))?;
+
+        let mut refs = Vec::new();
+        if fields[9] == "@" {
+            refs.push(Ref {
+                name: "@".to_string(),
+                ref_type: RefType::Head,
+            });
+        }
+        for (names, ref_type) in [
+            (fields[6], RefType::Branch),
+            (fields[7], RefType::Remote),
+            (fields[8], RefType::Tag),
+        ] {
+            refs.extend(names.split_whitespace().map(|name| Ref {
+                name: name.to_string(),
+                ref_type: ref_type.clone(),
+            }));
+        }
+
+        // jj keeps the trailing newline of descriptions
+        let message = fields[10].trim_end().to_string();
+        let date = DateTime::from_timestamp(seconds, 0).unwrap_or_default();
+        let trailers = Trailer::parse_message(&message);
+        changes.push(Change {
+            id: fields[0].to_string(),
+            short_id: fields[1].to_string(),
+            summary: message.lines().next().unwrap_or("").to_string(),
+            message,
+            author: fields[3].to_string(),
+            email: fields[4].to_string(),
+            date,
+            parent_ids: fields[2].split_whitespace().map(str::to_string).collect(),
+            refs,
+            committer: fields[3].to_string(),
+            committer_email: fields[4].to_string(),
+            committer_date: date,
+            trailers,
+        });
+    }
+
+    Ok(changes)
+}
+
+fn parse_summary(output: &str) -> Vec<FileStatus> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (code, path) = line.split_once(' ')?;
+            let status = match code {
+                "M" => StatusType::Modified,
+                "A" | "C" => StatusType::Added,
+                "D" => StatusType::Deleted,
+                "R" => StatusType::Renamed,
+                _ => return None,
+            };
+            // Renames are printed as `{old => new}` or `dir/{old => new}`
+            let path = match (path.find('{'), path.find(" => "), path.find('}')) {
+                (Some(open), Some(arrow), Some(close)) if open < arrow && arrow < close => {
+                    format!(
+                        "{}{}{}",
+                        &path[..open],
+                        &path[arrow + 4..close],
+                        &path[close + 1..]
+                    )
+                }
+                _ => path.to_string(),
+            };
+            Some(FileStatus::new(path, status))
+        })
+        .collect()
+}
+
+fn parse_op_log(output: &str) -> Result<Vec<HistoryPoint>> {
+    let mut points = Vec::new();
+
+    for record in output.split('\x1e') {
+        let record = record.trim_start_matches('\n');
+        if record.is_empty() {
+            continue;
+        }
+
+        let fields: Vec<&str> = record.splitn(3, '\x1f').collect();
+        if fields.len() < 3 {
+            bail!("Unexpected operation record: {:?}", record);
+        }
+
+        let seconds: i64 = fields[1]
+            .trim()
+            .parse()
+            .with_context(|| format!("Invalid timestamp: {}", fields[1]))?;
+        let description = fields[2].trim().to_string();
+
+        points.push(HistoryPoint {
+            id: fields[0].to_string(),
+            timestamp: DateTime::from_timestamp(seconds, 0).unwrap_or_default(),
+            action: description
+                .split_whitespace()
+                .next()
+                .unwrap_or_default()
+                .to_string(),
+            description,
+            refs: Vec::new(),
+        });
+    }
+
+    Ok(points)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_log() {
+        let output = format!(
+            "{c}\x1fkxryzmor\x1f{p}\x1fAlice\x1falice@example.com\x1f1700000000\x1fmain feat\x1fmain@origin\x1fv1\x1f@\x1fAdd feature\n\nBody\n\x1e\
+             {p}\x1fqpvuntsm\x1f\x1fBob\x1fbob@example.com\x1f1699990000\x1f\x1f\x1f\x1f\x1f\x1e",
+            c = "c".repeat(40),
+            p = "p".repeat(40),
+        );
+
+        let changes = parse_log(&output).unwrap();
+        assert_eq!(changes.len(), 2);
+
+        let head = &changes[0];
+        assert_eq!(head.id, "c".repeat(40));
+        assert_eq!(head.short_id, "kxryzmor");
+        assert_eq!(head.parent_ids, vec!["p".repeat(40)]);
+        assert_eq!(head.summary, "Add feature");
+        assert_eq!(head.message, "Add feature\n\nBody");
+        let refs: Vec<(&str, RefType)> = head
+            .refs
+            .iter()
+            .map(|r| (r.name.as_str(), r.ref_type.clone()))
+            .collect();
+        assert_eq!(
+            refs,
+            vec![
+                ("@", RefType::Head),
+                ("main", RefType::Branch),
+                ("feat", RefType::Branch),
+                ("main@origin", RefType::Remote),
+                ("v1", RefType::Tag),
+            ]
+        );
+
+        let root = &changes[1];
+        assert!(root.parent_ids.is_empty());
+        assert!(root.refs.is_empty());
+        assert_eq!(root.summary, "");
+        assert_eq!(root.date.timestamp(), 1699990000);
+    }
+
+    #[test]
+    fn test_parse_summary() {
+        let files = parse_summary("M src/lib.rs\nA new.rs\nD old.rs\nR src/{a.rs => b.rs}\n");
+        let statuses: Vec<(&str, &StatusType)> =
+            files.iter().map(|f| (f.path.as_str(), &f.status)).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                ("src/lib.rs", &StatusType::Modified),
+                ("new.rs", &StatusType::Added),
+                ("old.rs", &StatusType::Deleted),
+                ("src/b.rs", &StatusType::Renamed),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_op_log() {
+        let output = "\
+a1b2c3d4e5f6\x1f1700000100\x1fdescribe commit 0123456789abcdef\n\x1e\
+0f9e8d7c6b5a\x1f1700000000\x1fsnapshot working copy\n\x1e";
+
+        let points = parse_op_log(output).unwrap();
+        assert_eq!(points.len(), 2);
+        assert_eq!(points[0].id, "a1b2c3d4e5f6");
+        assert_eq!(points[0].action, "describe");
+        assert_eq!(points[0].description, "describe commit 0123456789abcdef");
+        assert_eq!(points[1].action, "snapshot");
+        assert_eq!(points[1].timestamp.timestamp(), 1700000000);
+    }
+}
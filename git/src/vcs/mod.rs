@@ -1,6 +1,31 @@
+mod git;
+mod hg;
+mod jj;
+
+pub use git::GitBackend;
+pub use hg::HgBackend;
+pub use jj::JjBackend;
+
+use crate::models::Trailer;
+use crate::operations::absorb::AbsorbedHunk;
+use crate::operations::bisect::{BisectState, BisectStep, BisectVerdict};
+use crate::operations::blame::{Blame, BlameOptions};
+use crate::operations::diff::{parse_diff, FileDiff};
+use crate::operations::file_history::FileVersion;
+use crate::operations::history_edit::{HistoryEditOutcome, RebaseStep};
+use crate::operations::hunk::Hunk;
+use crate::operations::log::LogQuery;
+use crate::operations::repo_state::RepoState;
+use crate::operations::reset::ResetMode;
+use crate::operations::resolve::ConflictFile;
+use crate::operations::split::SplitFile;
+use crate::operations::stack::{StackEntry, StackMove};
+use crate::operations::status::{BranchStatus, FileStatus, RepoStatus};
+use anyhow::{bail, Result};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::fmt;
+use std::path::Path;
 
 /// A version control commit/revision - VCS-agnostic representation
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -23,6 +48,14 @@ pub struct Change {
     pub parent_ids: Vec<String>,
     /// Associated references (branches, tags, etc.)
     pub refs: Vec<Ref>,
+    /// Committer name (the author for backends without a separate committer)
+    pub committer: String,
+    /// Committer email
+    pub committer_email: String,
+    /// Commit timestamp
+    pub committer_date: DateTime<Utc>,
+    /// Message trailers (`Signed-off-by`, `Change-Id`, ...)
+    pub trailers: Vec<Trailer>,
 }
 
 impl fmt::Display for Change {
@@ -43,6 +76,10 @@ impl From<Change> for crate::Commit {
             date: change.date,
             parent_hashes: change.parent_ids,
             refs: change.refs.into_iter().map(Into::into).collect(),
+            committer: change.committer,
+            committer_email: change.committer_email,
+            committer_date: change.committer_date,
+            trailers: change.trailers,
         }
     }
 }
@@ -59,6 +96,10 @@ impl From<crate::Commit> for Change {
             date: commit.date,
             parent_ids: commit.parent_hashes,
             refs: commit.refs.into_iter().map(Into::into).collect(),
+            committer: commit.committer,
+            committer_email: commit.committer_email,
+            committer_date: commit.committer_date,
+            trailers: commit.trailers,
         }
     }
 }
@@ -161,7 +202,7 @@ pub struct ChangeCount {
     pub deletions: usize,
 }
 
-/// History timeline point (git: reflog, hg: journal)
+/// History timeline point (git: reflog or openISL operation log, jj: op log)
 #[derive(Debug, Clone)]
 pub struct HistoryPoint {
     pub id: String,
@@ -181,7 +222,9 @@ pub enum HistoryEditAction {
     Edit,
 }
 
-/// History edit plan
+/// History edit plan. Entries are listed oldest first; listing them in a
+/// different order moves the changes, and `Combine` folds a change into the
+/// one before it.
 #[derive(Debug, Clone)]
 pub struct HistoryEditPlan {
     pub changes: Vec<HistoryEditPlanEntry>,
@@ -195,6 +238,53 @@ pub struct HistoryEditPlanEntry {
     pub message: Option<String>,
 }
 
+impl HistoryEditPlan {
+    /// Lay the plan over `changes`, the changes being edited oldest first.
+    /// The positions of the changes it names are filled with its entries in
+    /// plan order; every other change keeps its place without an entry.
+    /// Entries may name a change by a unique prefix of its ID.
+    pub(crate) fn arrange<'a>(
+        &'a self,
+        changes: &'a [String],
+    ) -> Result<Vec<(&'a str, Option<&'a HistoryEditPlanEntry>)>> {
+        let mut named = Vec::with_capacity(self.changes.len());
+        for entry in &self.changes {
+            if entry.change_id.is_empty() {
+                bail!("A history edit plan entry has no change ID");
+            }
+            let mut matches = changes
+                .iter()
+                .enumerate()
+                .filter(|(_, change)| change.starts_with(&entry.change_id))
+                .map(|(index, _)| index);
+            let Some(index) = matches.next() else {
+                bail!("{} is not an ancestor of the working copy", entry.change_id);
+            };
+            if matches.next().is_some() {
+                bail!("{} names more than one change", entry.change_id);
+            }
+            if named.contains(&index) {
+                bail!("{} appears more than once in the plan", entry.change_id);
+            }
+            named.push(index);
+        }
+
+        let mut entries = named.iter().zip(&self.changes);
+        Ok(changes
+            .iter()
+            .enumerate()
+            .map(|(index, change)| {
+                if named.contains(&index) {
+                    let (planned, entry) = entries.next().unwrap();
+                    (changes[*planned].as_str(), Some(entry))
+                } else {
+                    (change.as_str(), None)
+                }
+            })
+            .collect())
+    }
+}
+
 /// Change segment for selective staging
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ChangeSegment {
@@ -202,9 +292,470 @@ pub enum ChangeSegment {
     Exclude,
 }
 
-/// Change patch for selective staging
+/// Change patch for selective staging. There is one segment per added or
+/// removed line of the file's diff, in diff order; a file whose change has no
+/// lines (binary, mode-only) has a single segment. Missing segments count as
+/// `Exclude`.
 #[derive(Debug, Clone)]
 pub struct ChangePatch {
     pub file_path: String,
     pub segments: Vec<ChangeSegment>,
 }
+
+/// One commit carved out of a change by `Vcs::split_change`: the lines its
+/// patches include from what the earlier parts left over, and its message
+#[derive(Debug, Clone)]
+pub struct SplitPart {
+    pub patches: Vec<ChangePatch>,
+    pub message: String,
+}
+
+/// A version control backend.
+///
+/// The CLI and TUI talk to repositories through this trait so that git is one
+/// backend among several. Operations a backend has no equivalent for (e.g. an
+/// index for hunk staging) keep the default implementation, which returns an
+/// error naming the backend.
+pub trait Vcs {
+    /// Short backend name ("git", "hg", ...)
+    fn name(&self) -> &'static str;
+
+    /// Path the backend was opened at
+    fn repo_path(&self) -> &Path;
+
+    /// List changes, newest first, optionally limited to one branch or the remotes
+    fn log(
+        &self,
+        max_count: Option<usize>,
+        branch: Option<&str>,
+        remote: bool,
+    ) -> Result<Vec<Change>>;
+
+    /// List the changes selected by `query`. Backends that only implement
+    /// `log` can answer queries limited to a count and a single revision.
+    fn query_log(&self, query: &LogQuery) -> Result<Vec<Change>> {
+        let simple = LogQuery {
+            revisions: query.revisions.iter().take(1).cloned().collect(),
+            remotes: query.remotes,
+            max_count: query.max_count,
+            ..LogQuery::default()
+        };
+        if *query != simple {
+            bail!("{} does not support filtered log queries", self.name());
+        }
+        self.log(
+            query.max_count,
+            query.revisions.first().map(String::as_str),
+            query.remotes,
+        )
+    }
+
+    /// List the changes matching a revset expression such as
+    /// `draft() & author(alice)`, newest first
+    fn revset_log(&self, _revset: &str, _max_count: Option<usize>) -> Result<Vec<Change>> {
+        bail!("{} does not support revsets", self.name())
+    }
+
+    /// Working copy status
+    fn status(&self) -> Result<Vec<FileStatus>>;
+
+    /// Working copy status together with the branch and its divergence from
+    /// the tracked remote
+    fn repo_status(&self) -> Result<RepoStatus> {
+        let sync = self.sync_state().unwrap_or_default();
+        Ok(RepoStatus {
+            branch: BranchStatus {
+                head: self.current_branch()?,
+                upstream: sync.remote_name,
+                ahead: sync.local_unpushed.unwrap_or(0),
+                behind: sync.remote_unpulled.unwrap_or(0),
+                ..BranchStatus::default()
+            },
+            files: self.status()?,
+        })
+    }
+
+    /// Diff of the working copy, optionally limited to a revision or path
+    fn diff(&self, target: Option<&str>, staged: bool) -> Result<String>;
+
+    /// Diff introduced by a single change
+    fn change_diff(&self, id: &str) -> Result<String>;
+
+    /// `diff`, parsed into one `FileDiff` per file. Every backend produces
+    /// git-style diffs, so parsing them is shared.
+    fn diff_files(&self, target: Option<&str>, staged: bool) -> Result<Vec<FileDiff>> {
+        Ok(parse_diff(&self.diff(target, staged)?))
+    }
+
+    /// `change_diff`, parsed into one `FileDiff` per file
+    fn change_diff_files(&self, id: &str) -> Result<Vec<FileDiff>> {
+        Ok(parse_diff(&self.change_diff(id)?))
+    }
+
+    /// Mark a file for inclusion in the next commit
+    fn stage(&self, path: &str) -> Result<()>;
+
+    /// Undo `stage` for a file
+    fn unstage(&self, path: &str) -> Result<()>;
+
+    /// Stage every pending change
+    fn stage_all(&self) -> Result<()>;
+
+    /// Unstage everything
+    fn unstage_all(&self) -> Result<()>;
+
+    /// Hunks of a file's diff, from the index when `staged` is set
+    fn hunks(&self, _path: &str, _staged: bool) -> Result<Vec<Hunk>> {
+        bail!("{} does not support hunk staging", self.name())
+    }
+
+    /// Stage a whole hunk
+    fn stage_hunk(&self, _path: &str, _hunk: &Hunk) -> Result<()> {
+        bail!("{} does not support hunk staging", self.name())
+    }
+
+    /// Unstage a whole hunk
+    fn unstage_hunk(&self, _path: &str, _hunk: &Hunk) -> Result<()> {
+        bail!("{} does not support hunk staging", self.name())
+    }
+
+    /// Stage the selected lines of a hunk
+    fn stage_hunk_lines(&self, _path: &str, _hunk: &Hunk) -> Result<()> {
+        bail!("{} does not support hunk staging", self.name())
+    }
+
+    /// Unstage the selected lines of a hunk
+    fn unstage_hunk_lines(&self, _path: &str, _hunk: &Hunk) -> Result<()> {
+        bail!("{} does not support hunk staging", self.name())
+    }
+
+    /// Commit pending changes
+    fn commit(&self, message: &str) -> Result<()>;
+
+    /// Fold pending changes into the current change, optionally rewording it
+    fn amend(&self, message: Option<&str>) -> Result<()>;
+
+    /// Name of the active branch or bookmark, if any
+    fn current_branch(&self) -> Result<Option<String>>;
+
+    /// All branches or bookmarks
+    fn branches(&self) -> Result<Vec<Ref>>;
+
+    /// Create a branch; when `at` is given the branch starts there and is checked out
+    fn create_branch(&self, name: &str, at: Option<&str>) -> Result<()>;
+
+    /// Update the working copy to a branch or change
+    fn checkout(&self, target: &str) -> Result<()>;
+
+    /// Saved work entries (git: stash, hg: shelve)
+    fn saved_work(&self) -> Result<Vec<SavedWork>>;
+
+    /// Save pending changes and clean the working copy
+    fn save_work(&self, message: Option<&str>) -> Result<()>;
+
+    /// Restore saved work, keeping the entry
+    fn apply_saved_work(&self, id: Option<&str>) -> Result<()>;
+
+    /// Restore saved work and delete the entry
+    fn pop_saved_work(&self, id: Option<&str>) -> Result<()>;
+
+    /// Delete saved work without restoring it
+    fn drop_saved_work(&self, id: Option<&str>) -> Result<()>;
+
+    /// Diff of a saved work entry
+    fn show_saved_work(&self, id: &str) -> Result<String>;
+
+    /// Divergence from the tracked remote
+    fn sync_state(&self) -> Result<SyncState>;
+
+    /// Rewrite history according to a plan
+    fn edit_history(&self, plan: &HistoryEditPlan) -> Result<()>;
+
+    /// Collapse every change after `base` into a single change
+    fn squash(&self, base: &str, message: &str) -> Result<()>;
+
+    /// Copy a change onto the working copy parent
+    fn cherry_pick(&self, id: &str) -> Result<()>;
+
+    /// Create a change that undoes `id`
+    fn revert(&self, id: &str) -> Result<()>;
+
+    /// Merge `target` into the working copy, returning the backend's report
+    fn merge(&self, _target: &str) -> Result<String> {
+        bail!("{} does not support merge", self.name())
+    }
+
+    /// Move the current branch onto `upstream` (its upstream by default),
+    /// returning the backend's report
+    fn rebase(&self, _upstream: Option<&str>) -> Result<String> {
+        bail!("{} does not support rebase", self.name())
+    }
+
+    /// Replay the changes since `upstream` onto it as `steps` (an edited
+    /// todo) say
+    fn rebase_interactive(
+        &self,
+        _upstream: &str,
+        _steps: &[RebaseStep],
+    ) -> Result<HistoryEditOutcome> {
+        bail!("{} does not support interactive rebase", self.name())
+    }
+
+    /// Fetch and integrate the remote's changes, rebasing local ones with
+    /// `rebase`, returning the backend's report
+    fn pull(&self, _rebase: bool) -> Result<String> {
+        bail!("{} does not support pull", self.name())
+    }
+
+    /// Repository-level operations, newest first (jj: op log)
+    fn operation_log(&self) -> Result<Vec<HistoryPoint>> {
+        bail!("{} does not record an operation log", self.name())
+    }
+
+    /// Undo an operation from `operation_log`, or the latest when `id` is `None`
+    fn undo_operation(&self, _id: Option<&str>) -> Result<()> {
+        bail!("{} does not record an operation log", self.name())
+    }
+
+    /// Reflog of `reference` (git: `HEAD` when `None`), newest first
+    fn reflog(
+        &self,
+        _reference: Option<&str>,
+        _max_count: Option<usize>,
+    ) -> Result<Vec<HistoryPoint>> {
+        bail!("{} does not keep a reflog", self.name())
+    }
+
+    /// Move the current branch to `target`, e.g. to recover a reflog entry
+    fn reset(&self, _target: &str, _mode: ResetMode) -> Result<()> {
+        bail!("{} does not support reset", self.name())
+    }
+
+    /// Redo the most recently undone operation
+    fn redo_operation(&self) -> Result<()> {
+        bail!("{} does not support redo", self.name())
+    }
+
+    /// Draft changes of the current stack, newest first
+    fn stack(&self) -> Result<Vec<StackEntry>> {
+        bail!("{} does not support stacks", self.name())
+    }
+
+    /// What to check out to move `count` changes up or down the stack
+    fn stack_target(&self, _direction: StackMove, _count: usize) -> Result<String> {
+        bail!("{} does not support stacks", self.name())
+    }
+
+    /// Move changes left on rewritten parents onto the new versions, returning
+    /// how many moved. Backends that carry descendants along when a change is
+    /// rewritten (jj) have nothing to do.
+    fn restack(&self) -> Result<usize> {
+        Ok(0)
+    }
+
+    /// Changes of `id` by file, minus what `parts` already take; the hunks to
+    /// pick the next part's lines from
+    fn split_files(&self, _id: &str, _parts: &[SplitPart]) -> Result<Vec<SplitFile>> {
+        bail!("{} does not support splitting changes", self.name())
+    }
+
+    /// Split a change into one commit per part, oldest first, followed by
+    /// whatever the parts leave over under the original message. Authors and
+    /// dates are kept and the changes above are carried along.
+    fn split_change(&self, _id: &str, _parts: &[SplitPart]) -> Result<()> {
+        bail!("{} does not support splitting changes", self.name())
+    }
+
+    /// Fold staged hunks into the draft changes that last touched their
+    /// lines, leaving ambiguous hunks staged, and restack. With `dry_run`,
+    /// only report where each hunk would go.
+    fn absorb(&self, _dry_run: bool) -> Result<Vec<AbsorbedHunk>> {
+        bail!("{} does not support absorb", self.name())
+    }
+
+    /// The operation (rebase, merge, ...) in progress. Backends that don't
+    /// track one report `Clean`.
+    fn repo_state(&self) -> Result<RepoState> {
+        Ok(RepoState::Clean)
+    }
+
+    /// Continue the operation in progress once its conflicts are resolved
+    fn continue_operation(&self) -> Result<()> {
+        bail!("{} does not support continuing operations", self.name())
+    }
+
+    /// Abort the operation in progress, returning to where it started
+    fn abort_operation(&self) -> Result<()> {
+        bail!("{} does not support aborting operations", self.name())
+    }
+
+    /// Skip the commit the operation in progress stopped at
+    fn skip_operation(&self) -> Result<()> {
+        bail!("{} does not support skipping commits", self.name())
+    }
+
+    /// Parse the conflict markers of a conflicted file in the working copy
+    fn conflict_file(&self, _path: &str) -> Result<ConflictFile> {
+        bail!("{} does not support resolving conflicts", self.name())
+    }
+
+    /// Write a conflicted file with every region resolved and mark it resolved
+    fn resolve_conflict(&self, _file: &ConflictFile) -> Result<()> {
+        bail!("{} does not support resolving conflicts", self.name())
+    }
+
+    /// Annotate each line of `path` with the change that last touched it
+    fn blame(&self, _path: &str, _options: &BlameOptions) -> Result<Blame> {
+        bail!("{} does not support blame", self.name())
+    }
+
+    /// Changes of `query` touching its single path, newest first, following
+    /// the file across renames
+    fn file_history(&self, _query: &LogQuery) -> Result<Vec<FileVersion>> {
+        bail!("{} does not support file history", self.name())
+    }
+
+    /// What the change of `version` did to the file, as a patch
+    fn file_version_diff(&self, _version: &FileVersion) -> Result<String> {
+        bail!("{} does not support file history", self.name())
+    }
+
+    /// The file's full content at `version`
+    fn file_version_content(&self, _version: &FileVersion) -> Result<String> {
+        bail!("{} does not support file history", self.name())
+    }
+
+    /// The bisect session in progress, if any
+    fn bisect_state(&self) -> Result<Option<BisectState>> {
+        Ok(None)
+    }
+
+    /// Mark a revision (the checked-out one by default) good, bad or skipped,
+    /// starting a bisect session if there is none
+    fn bisect_mark(&self, _verdict: BisectVerdict, _id: Option<&str>) -> Result<BisectState> {
+        bail!("{} does not support bisect", self.name())
+    }
+
+    /// Test candidates with the shell `command` until the first bad change is
+    /// found, showing each tested one to `on_step`
+    fn bisect_run(
+        &self,
+        _command: &str,
+        _on_step: &mut dyn FnMut(&BisectStep),
+    ) -> Result<BisectState> {
+        bail!("{} does not support bisect", self.name())
+    }
+
+    /// End the bisect session and return to where it started
+    fn bisect_reset(&self) -> Result<()> {
+        bail!("{} does not support bisect", self.name())
+    }
+}
+
+/// Open the repository containing `path` with the matching backend.
+///
+/// The nearest enclosing repository wins: `.jj` selects Jujutsu (also when
+/// colocated with `.git`), `.git` git, `.sl` Sapling and `.hg` Mercurial.
+pub fn open(path: &Path) -> Result<Box<dyn Vcs>> {
+    let mut current = Some(path);
+    while let Some(dir) = current {
+        if dir.join(".jj").is_dir() {
+            return Ok(Box::new(JjBackend::new(path)));
+        }
+        if dir.join(".git").exists() {
+            return Ok(Box::new(GitBackend::new(path)));
+        }
+        if dir.join(".sl").is_dir() {
+            return Ok(Box::new(HgBackend::sapling(path)));
+        }
+        if dir.join(".hg").is_dir() {
+            return Ok(Box::new(HgBackend::mercurial(path)));
+        }
+        current = dir.parent();
+    }
+
+    Err(crate::GitError::RepositoryNotFound.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_open_detects_backend() {
+        let dir = tempdir().unwrap();
+        let nested = dir.path().join("src");
+        std::fs::create_dir_all(&nested).unwrap();
+
+        std::fs::create_dir(dir.path().join(".hg")).unwrap();
+        assert_eq!(open(&nested).unwrap().name(), "hg");
+
+        std::fs::create_dir(nested.join(".sl")).unwrap();
+        assert_eq!(open(&nested).unwrap().name(), "sl");
+        assert_eq!(open(dir.path()).unwrap().name(), "hg");
+
+        // Colocated jj/git repositories are driven through jj
+        std::fs::create_dir(dir.path().join(".git")).unwrap();
+        assert_eq!(open(dir.path()).unwrap().name(), "git");
+        std::fs::create_dir(dir.path().join(".jj")).unwrap();
+        assert_eq!(open(dir.path()).unwrap().name(), "jj");
+    }
+
+    #[test]
+    fn test_arrange_plan_moves_named_changes() {
+        let entry = |id: &str, action| HistoryEditPlanEntry {
+            change_id: id.to_string(),
+            action,
+            message: None,
+        };
+        let plan = HistoryEditPlan {
+            changes: vec![
+                entry("ddd", HistoryEditAction::Keep),
+                entry("b", HistoryEditAction::Combine),
+            ],
+        };
+        let changes: Vec<String> = ["aaa", "bbb", "ccc", "ddd"]
+            .iter()
+            .map(|c| c.to_string())
+            .collect();
+
+        let arranged: Vec<(&str, Option<HistoryEditAction>)> = plan
+            .arrange(&changes)
+            .unwrap()
+            .into_iter()
+            .map(|(change, entry)| (change, entry.map(|e| e.action)))
+            .collect();
+        assert_eq!(
+            arranged,
+            vec![
+                ("aaa", None),
+                ("ddd", Some(HistoryEditAction::Keep)),
+                ("ccc", None),
+                ("bbb", Some(HistoryEditAction::Combine)),
+            ]
+        );
+
+        let unknown = HistoryEditPlan {
+            changes: vec![entry("eee", HistoryEditAction::Keep)],
+        };
+        assert!(unknown.arrange(&changes).is_err());
+        let twice = HistoryEditPlan {
+            changes: vec![
+                entry("aaa", HistoryEditAction::Keep),
+                entry("a", HistoryEditAction::Remove),
+            ],
+        };
+        assert!(twice.arrange(&changes).is_err());
+        let empty = HistoryEditPlan {
+            changes: vec![entry("", HistoryEditAction::Remove)],
+        };
+        assert!(empty.arrange(&changes).is_err());
+        let changes = vec!["abc1".to_string(), "abc2".to_string()];
+        let ambiguous = HistoryEditPlan {
+            changes: vec![entry("abc", HistoryEditAction::Remove)],
+        };
+        let err = ambiguous.arrange(&changes).unwrap_err();
+        assert!(err.to_string().contains("more than one change"));
+    }
+}
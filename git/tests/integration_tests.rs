@@ -2,8 +2,8 @@ use openisl_git::{
     add_paths, apply_patch, bisect_reset, bisect_start, commit, get_blame, get_branches,
     get_commits, get_commits_filtered, get_conflicted_files, get_current_branch,
     get_file_at_revision, get_stash_list, get_status, init, mark_resolved, merge, move_file,
-    remove_file, reset, stash_pop, stash_push, undo_last, Commit, FileStatus, GitRef, RefType,
-    ResetMode, StatusType,
+    remove_file, reset, stash_pop, stash_push, undo_last, BlameOptions, Commit, FileStatus, GitRef,
+    RefType, ResetMode, StatusType,
 };
 
 use std::process::Command;
@@ -35,6 +35,10 @@ fn create_test_commit(
         date: chrono::Utc::now(),
         parent_hashes: parents.iter().map(|s| s.to_string()).collect(),
         refs: vec![],
+        committer: author.to_string(),
+        committer_email: email.to_string(),
+        committer_date: chrono::Utc::now(),
+        trailers: vec![],
     }
 }
 
@@ -244,10 +248,7 @@ mod commit_tests {
 
     #[test]
     fn test_file_status_creation() {
-        let status = FileStatus {
-            path: "src/main.rs".to_string(),
-            status: StatusType::Modified,
-        };
+        let status = FileStatus::new("src/main.rs", StatusType::Modified);
         assert_eq!(status.path, "src/main.rs");
         assert_eq!(status.status, StatusType::Modified);
     }
@@ -305,6 +306,10 @@ mod edge_case_tests {
                 date: now,
                 parent_hashes: vec![],
                 refs: vec![],
+                committer: "test".to_string(),
+                committer_email: "test@test.com".to_string(),
+                committer_date: now,
+                trailers: vec![],
             },
             Commit {
                 hash: "def456ghi789abc".to_string(),
@@ -316,6 +321,10 @@ mod edge_case_tests {
                 date: now,
                 parent_hashes: vec!["abc123def456789".to_string()],
                 refs: vec![],
+                committer: "test".to_string(),
+                committer_email: "test@test.com".to_string(),
+                committer_date: now,
+                trailers: vec![],
             },
         ];
         assert_eq!(commits.len(), 2);
@@ -333,6 +342,10 @@ mod edge_case_tests {
             date: chrono::Utc::now(),
             parent_hashes: vec![],
             refs: vec![],
+            committer: "Very Long Author Name That Might Be Truncated".to_string(),
+            committer_email: "very.long.email.address@example.com".to_string(),
+            committer_date: chrono::Utc::now(),
+            trailers: vec![],
         };
         assert!(commit.author.len() > 40);
         assert!(commit.email.len() > 30);
@@ -340,10 +353,10 @@ mod edge_case_tests {
 
     #[test]
     fn test_special_characters_in_paths() {
-        let status = FileStatus {
-            path: "src/path/with spaces/and-dashes/file.rs".to_string(),
-            status: StatusType::Modified,
-        };
+        let status = FileStatus::new(
+            "src/path/with spaces/and-dashes/file.rs",
+            StatusType::Modified,
+        );
         assert!(status.path.contains(' '));
         assert!(status.path.contains('-'));
     }
@@ -360,6 +373,10 @@ mod edge_case_tests {
             date: chrono::Utc::now(),
             parent_hashes: vec![],
             refs: vec![],
+            committer: "Test".to_string(),
+            committer_email: "test@test.com".to_string(),
+            committer_date: chrono::Utc::now(),
+            trailers: vec![],
         };
         assert!(commit.message.contains("café"));
         assert!(commit.message.contains("中文"));
@@ -385,6 +402,10 @@ mod serialization_tests {
                 name: "main".to_string(),
                 ref_type: RefType::Branch,
             }],
+            committer: "Test Author".to_string(),
+            committer_email: "test@example.com".to_string(),
+            committer_date: chrono::Utc::now(),
+            trailers: vec![],
         };
 
         let json = serde_json::to_string(&commit).unwrap();
@@ -412,10 +433,7 @@ mod serialization_tests {
 
     #[test]
     fn test_file_status_serialization() {
-        let status = FileStatus {
-            path: "src/main.rs".to_string(),
-            status: StatusType::Modified,
-        };
+        let status = FileStatus::new("src/main.rs", StatusType::Modified);
 
         assert_eq!(status.path, "src/main.rs");
         assert_eq!(status.status, StatusType::Modified);
@@ -549,9 +567,11 @@ mod serialization_tests {
         add_paths(repo, &["a.txt"]).unwrap();
         commit(repo, "initial").unwrap();
 
-        let blame = get_blame(repo, "a.txt").unwrap();
-        assert!(blame.contains("line one"));
-        assert!(blame.contains("Test"));
+        let blame = get_blame(repo, "a.txt", &BlameOptions::default()).unwrap();
+        assert_eq!(blame.lines.len(), 1);
+        assert_eq!(blame.lines[0].content, "line one");
+        assert_eq!(blame.lines[0].author, "Test");
+        assert_eq!(blame.commit_of(&blame.lines[0]).unwrap().summary, "initial");
     }
 
     #[test]
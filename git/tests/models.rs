@@ -1,4 +1,4 @@
-use openisl_git::{Commit, GitRef, RefType};
+use openisl_git::{Commit, GitRef, RefType, Trailer};
 
 #[test]
 fn test_commit_display() {
@@ -12,6 +12,10 @@ fn test_commit_display() {
         date: chrono::Utc::now(),
         parent_hashes: vec![],
         refs: vec![],
+        committer: "John Doe".to_string(),
+        committer_email: "john@example.com".to_string(),
+        committer_date: chrono::Utc::now(),
+        trailers: vec![],
     };
 
     let display = format!("{}", commit);
@@ -77,6 +81,10 @@ fn test_commit_serialization() {
         date: chrono::Utc::now(),
         parent_hashes: vec![],
         refs: vec![],
+        committer: "Test".to_string(),
+        committer_email: "test@test.com".to_string(),
+        committer_date: chrono::Utc::now(),
+        trailers: vec![],
     };
 
     let json = serde_json::to_string(&commit).unwrap();
@@ -85,3 +93,18 @@ fn test_commit_serialization() {
     assert_eq!(commit.hash, deserialized.hash);
     assert_eq!(commit.summary, deserialized.summary);
 }
+
+#[test]
+fn test_trailer_parse_message() {
+    let trailers = Trailer::parse_message(
+        "Add feature\n\nLonger body.\n\nSigned-off-by: A <a@example.com>\nCo-authored-by: B <b@example.com>\n",
+    );
+    assert_eq!(trailers.len(), 2);
+    assert_eq!(trailers[0].key, "Signed-off-by");
+    assert_eq!(trailers[1].value, "B <b@example.com>");
+    assert_eq!(trailers[1].to_string(), "Co-authored-by: B <b@example.com>");
+
+    // A final paragraph that is not entirely trailers is just body text
+    assert!(Trailer::parse_message("Fix\n\nNote: this is prose\nand more").is_empty());
+    assert!(Trailer::parse_message("Key: value").is_empty());
+}
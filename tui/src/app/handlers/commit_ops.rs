@@ -2,23 +2,39 @@
 //!
 //! Contains commit, file, hunk, and stash operations invoked by handlers.
 use super::super::*;
+use openisl_git::{
+    GitError, HistoryEditAction, HistoryEditPlan, HistoryEditPlanEntry, ResetMode, SplitPart,
+    StatusType,
+};
+
+/// Status line text for a failed operation. Recognised git failures are
+/// shown by kind with a recovery hint instead of the outer context message.
+pub(crate) fn error_status(action: &str, e: &anyhow::Error) -> String {
+    match e.downcast_ref::<GitError>() {
+        Some(GitError::CommandFailed(_)) | None => format!("{}: {}", action, e),
+        Some(git_error) => match git_error.suggestion() {
+            Some(hint) => format!("{}: {} ({})", action, git_error, hint),
+            None => format!("{}: {}", action, git_error),
+        },
+    }
+}
 
 impl App {
     pub(crate) fn stage_selected_hunks_or_lines(&mut self) {
-        if let Some(repo_path) = &self.repo_path {
+        if let Some(vcs) = &self.vcs {
             if let Some(file) = self.files.get(self.selected_file_index) {
                 let mut staged_any = false;
                 // Only unstaged hunks can be staged. Staged hunks are skipped.
                 for hunk in self.hunks.iter().filter(|h| !h.is_staged) {
                     if hunk.is_selected {
-                        if let Err(e) = stage_hunk(repo_path, Path::new(&file.path), hunk) {
-                            self.status_message = format!("Error staging hunk: {}", e);
+                        if let Err(e) = vcs.stage_hunk(&file.path, hunk) {
+                            self.status_message = error_status("Error staging hunk", &e);
                             return;
                         }
                         staged_any = true;
                     } else if hunk.lines.iter().any(|l| l.is_selected) {
-                        if let Err(e) = stage_hunk_lines(repo_path, Path::new(&file.path), hunk) {
-                            self.status_message = format!("Error staging selected lines: {}", e);
+                        if let Err(e) = vcs.stage_hunk_lines(&file.path, hunk) {
+                            self.status_message = error_status("Error staging selected lines", &e);
                             return;
                         }
                         staged_any = true;
@@ -40,20 +56,21 @@ impl App {
     }
 
     pub(crate) fn unstage_selected_hunks_or_lines(&mut self) {
-        if let Some(repo_path) = &self.repo_path {
+        if let Some(vcs) = &self.vcs {
             if let Some(file) = self.files.get(self.selected_file_index) {
                 let mut unstaged_any = false;
                 // Only staged hunks can be unstaged.
                 for hunk in self.hunks.iter().filter(|h| h.is_staged) {
                     if hunk.is_selected {
-                        if let Err(e) = unstage_hunk(repo_path, Path::new(&file.path), hunk) {
-                            self.status_message = format!("Error unstaging hunk: {}", e);
+                        if let Err(e) = vcs.unstage_hunk(&file.path, hunk) {
+                            self.status_message = error_status("Error unstaging hunk", &e);
                             return;
                         }
                         unstaged_any = true;
                     } else if hunk.lines.iter().any(|l| l.is_selected) {
-                        if let Err(e) = unstage_hunk_lines(repo_path, Path::new(&file.path), hunk) {
-                            self.status_message = format!("Error unstaging selected lines: {}", e);
+                        if let Err(e) = vcs.unstage_hunk_lines(&file.path, hunk) {
+                            self.status_message =
+                                error_status("Error unstaging selected lines", &e);
                             return;
                         }
                         unstaged_any = true;
@@ -80,12 +97,10 @@ impl App {
         self.current_file_diff_output.clear(); // Clear previous diff output
 
         if let Some(file) = self.files.get(self.selected_file_index) {
-            if let Some(ref repo_path) = self.repo_path {
+            if let Some(ref vcs) = self.vcs {
                 // Get diff for the selected file (either staged or unstaged)
-                let staged_hunks_result =
-                    get_file_diff_hunks(repo_path, Path::new(&file.path), true);
-                let unstaged_hunks_result =
-                    get_file_diff_hunks(repo_path, Path::new(&file.path), false);
+                let staged_hunks_result = vcs.hunks(&file.path, true);
+                let unstaged_hunks_result = vcs.hunks(&file.path, false);
 
                 match (staged_hunks_result, unstaged_hunks_result) {
                     (Ok(staged_hunks), Ok(mut unstaged_hunks)) => {
@@ -107,15 +122,14 @@ impl App {
 
                 // Also update the general diff_content for the Diff View if still used
                 let file_path_str = file.path.clone(); // Clone to own the String and avoid borrow issues
-                match openisl_git::get_diff(repo_path, Some(&file_path_str), false) {
-                    // Corrected: Path::new(&file.path) to &file.path
+                match vcs.diff(Some(&file_path_str), false) {
                     Ok(diff) => {
                         self.diff_content = diff;
                         self.current_file_diff_output = self.diff_content.clone(); // Store raw diff
                         self.parse_diff(); // Parse to get stats
                     }
                     Err(e) => {
-                        self.diff_content = format!("Error fetching full diff: {}", e);
+                        self.diff_content = error_status("Error fetching full diff", &e);
                         self.current_file_diff_output = self.diff_content.clone();
                         self.parse_diff();
                     }
@@ -128,8 +142,8 @@ impl App {
         } else if let Some(commit) = self.selected_commit() {
             let commit_hash = commit.hash.clone(); // Clone to end borrow of self
             let commit_short_hash = commit.short_hash.clone(); // Clone to end borrow of self
-            if let Some(ref repo_path) = self.repo_path {
-                match get_commit_diff(repo_path, &commit_hash) {
+            if let Some(ref vcs) = self.vcs {
+                match vcs.change_diff(&commit_hash) {
                     Ok(diff) => {
                         self.diff_content = diff;
                         self.current_file_diff_output = self.diff_content.clone();
@@ -140,7 +154,7 @@ impl App {
                         // Use cloned short_hash
                     }
                     Err(e) => {
-                        self.diff_content = format!("Error fetching diff: {}", e);
+                        self.diff_content = error_status("Error fetching diff", &e);
                         self.current_file_diff_output = self.diff_content.clone();
                         self.parse_diff();
                     }
@@ -160,14 +174,14 @@ impl App {
 
     pub(crate) fn refresh_files(&mut self) {
         self.is_loading = true;
-        if let Some(ref repo_path) = self.repo_path {
-            match openisl_git::get_status(repo_path) {
+        if let Some(ref vcs) = self.vcs {
+            match vcs.status() {
                 Ok(files) => {
                     self.files = files;
                     self.is_loading = false;
                 }
                 Err(e) => {
-                    self.status_message = format!("Error loading files: {}", e);
+                    self.status_message = error_status("Error loading files", &e);
                     self.is_loading = false;
                 }
             }
@@ -187,14 +201,14 @@ impl App {
         }
 
         if let Some(file) = self.files.get(self.selected_file_index) {
-            if let Some(ref repo_path) = self.repo_path {
-                match openisl_git::stage_file(repo_path, &file.path) {
+            if let Some(ref vcs) = self.vcs {
+                match vcs.stage(&file.path) {
                     Ok(_) => {
                         self.status_message = format!("Staged: {}", file.path);
                         self.refresh_files();
                     }
                     Err(e) => {
-                        self.status_message = format!("Error staging file: {}", e);
+                        self.status_message = error_status("Error staging file", &e);
                     }
                 }
             } else {
@@ -214,14 +228,14 @@ impl App {
         }
 
         if let Some(file) = self.files.get(self.selected_file_index) {
-            if let Some(ref repo_path) = self.repo_path {
-                match openisl_git::unstage_file(repo_path, &file.path) {
+            if let Some(ref vcs) = self.vcs {
+                match vcs.unstage(&file.path) {
                     Ok(_) => {
                         self.status_message = format!("Unstaged: {}", file.path);
                         self.refresh_files();
                     }
                     Err(e) => {
-                        self.status_message = format!("Error unstaging file: {}", e);
+                        self.status_message = error_status("Error unstaging file", &e);
                     }
                 }
             } else {
@@ -241,14 +255,8 @@ impl App {
         }
 
         if let Some(file) = self.files.get(self.selected_file_index) {
-            let is_staged = matches!(
-                file.status,
-                openisl_git::StatusType::ModifiedStaged
-                    | openisl_git::StatusType::AddedStaged
-                    | openisl_git::StatusType::DeletedStaged
-            );
-
-            if is_staged {
+            // Partly staged files (`MM`) get the rest staged first
+            if file.is_staged() && !file.has_unstaged_changes() {
                 self.unstage_selected_file();
             } else {
                 self.stage_selected_file();
@@ -257,14 +265,14 @@ impl App {
     }
 
     pub(crate) fn stage_all_files(&mut self) {
-        if let Some(ref repo_path) = self.repo_path {
-            match openisl_git::stage_all(repo_path) {
+        if let Some(ref vcs) = self.vcs {
+            match vcs.stage_all() {
                 Ok(_) => {
                     self.status_message = "Staged all files".to_string();
                     self.refresh_files();
                 }
                 Err(e) => {
-                    self.status_message = format!("Error staging all files: {}", e);
+                    self.status_message = error_status("Error staging all files", &e);
                 }
             }
         } else {
@@ -273,14 +281,14 @@ impl App {
     }
 
     pub(crate) fn unstage_all_files(&mut self) {
-        if let Some(ref repo_path) = self.repo_path {
-            match openisl_git::unstage_all(repo_path) {
+        if let Some(ref vcs) = self.vcs {
+            match vcs.unstage_all() {
                 Ok(_) => {
                     self.status_message = "Unstaged all files".to_string();
                     self.refresh_files();
                 }
                 Err(e) => {
-                    self.status_message = format!("Error unstaging all files: {}", e);
+                    self.status_message = error_status("Error unstaging all files", &e);
                 }
             }
         } else {
@@ -289,14 +297,19 @@ impl App {
     }
 
     pub(crate) fn amend_commit(&mut self) {
-        if let Some(ref repo_path) = self.repo_path {
-            match openisl_git::amend_commit(repo_path, None) {
+        if let Some(ref vcs) = self.vcs {
+            match vcs.amend(None) {
                 Ok(_) => {
-                    self.status_message = "Commit amended successfully".to_string();
+                    // Bring commits stacked on the old version along
+                    self.status_message = match vcs.restack() {
+                        Ok(0) => "Commit amended successfully".to_string(),
+                        Ok(moved) => format!("Commit amended; restacked {} commit(s)", moved),
+                        Err(e) => error_status("Commit amended, but restacking failed", &e),
+                    };
                     self.refresh_commits();
                 }
                 Err(e) => {
-                    self.status_message = format!("Error amending commit: {}", e);
+                    self.status_message = error_status("Error amending commit", &e);
                 }
             }
         } else {
@@ -306,14 +319,21 @@ impl App {
 
     pub(crate) fn drop_commit(&mut self) {
         if let Some(commit) = self.selected_commit() {
-            if let Some(ref repo_path) = self.repo_path {
-                match openisl_git::drop_commit(repo_path, &commit.hash) {
+            if let Some(ref vcs) = self.vcs {
+                let plan = HistoryEditPlan {
+                    changes: vec![HistoryEditPlanEntry {
+                        change_id: commit.hash.clone(),
+                        action: HistoryEditAction::Remove,
+                        message: None,
+                    }],
+                };
+                match vcs.edit_history(&plan) {
                     Ok(_) => {
                         self.status_message = format!("Dropped commit {}", commit.short_hash);
                         self.refresh_commits();
                     }
                     Err(e) => {
-                        self.status_message = format!("Error dropping commit: {}", e);
+                        self.status_message = error_status("Error dropping commit", &e);
                     }
                 }
             } else {
@@ -326,15 +346,15 @@ impl App {
 
     pub(crate) fn squash_commits(&mut self) {
         if let Some(commit) = self.selected_commit() {
-            if let Some(ref repo_path) = self.repo_path {
-                match openisl_git::squash_commits(repo_path, &commit.hash, "Squashed commit") {
+            if let Some(ref vcs) = self.vcs {
+                match vcs.squash(&commit.hash, "Squashed commit") {
                     Ok(_) => {
                         self.status_message =
                             format!("Squashed commits into {}", commit.short_hash);
                         self.refresh_commits();
                     }
                     Err(e) => {
-                        self.status_message = format!("Error squashing commits: {}", e);
+                        self.status_message = error_status("Error squashing commits", &e);
                     }
                 }
             } else {
@@ -347,14 +367,14 @@ impl App {
 
     pub(crate) fn cherry_pick_commit(&mut self) {
         if let Some(commit) = self.selected_commit() {
-            if let Some(ref repo_path) = self.repo_path {
-                match openisl_git::cherry_pick_commit(repo_path, &commit.hash) {
+            if let Some(ref vcs) = self.vcs {
+                match vcs.cherry_pick(&commit.hash) {
                     Ok(_) => {
                         self.status_message = format!("Cherry-picked {}", commit.short_hash);
                         self.refresh_commits();
                     }
                     Err(e) => {
-                        self.status_message = format!("Error cherry-picking: {}", e);
+                        self.status_message = error_status("Error cherry-picking", &e);
                     }
                 }
             } else {
@@ -367,14 +387,14 @@ impl App {
 
     pub(crate) fn revert_commit(&mut self) {
         if let Some(commit) = self.selected_commit() {
-            if let Some(ref repo_path) = self.repo_path {
-                match openisl_git::revert_commit(repo_path, &commit.hash) {
+            if let Some(ref vcs) = self.vcs {
+                match vcs.revert(&commit.hash) {
                     Ok(_) => {
                         self.status_message = format!("Reverted {}", commit.short_hash);
                         self.refresh_commits();
                     }
                     Err(e) => {
-                        self.status_message = format!("Error reverting: {}", e);
+                        self.status_message = error_status("Error reverting", &e);
                     }
                 }
             } else {
@@ -387,9 +407,12 @@ impl App {
 
     fn refresh_commits(&mut self) {
         self.is_loading = true;
-        if let Some(ref repo_path) = self.repo_path {
-            match openisl_git::get_commits(repo_path, Some(100)) {
-                Ok(commits) => {
+        if let Some(ref vcs) = self.vcs {
+            // While bisecting, HEAD is a candidate; show history from the bad commit
+            let tip = vcs.bisect_state().ok().flatten().and_then(|b| b.bad);
+            match vcs.log(Some(100), tip.as_deref(), false) {
+                Ok(changes) => {
+                    let commits: Vec<Commit> = changes.into_iter().map(Into::into).collect();
                     self.commits = commits.clone();
                     self.filtered_commits = commits.clone();
                     self.tree = crate::tree::CommitTree::new(commits);
@@ -398,7 +421,7 @@ impl App {
                     self.is_loading = false;
                 }
                 Err(e) => {
-                    self.status_message = format!("Error refreshing commits: {}", e);
+                    self.status_message = error_status("Error refreshing commits", &e);
                     self.is_loading = false;
                 }
             }
@@ -406,12 +429,692 @@ impl App {
             self.is_loading = false;
         }
         self.refresh_sync_state();
+        self.refresh_obsolete_commits();
+    }
+
+    /// Mark the current stack's commits that sit on a rewritten parent.
+    /// Backends without stacks simply show no markers.
+    pub(crate) fn refresh_obsolete_commits(&mut self) {
+        self.obsolete_commits = self
+            .vcs
+            .as_ref()
+            .and_then(|vcs| vcs.stack().ok())
+            .map(|stack| {
+                stack
+                    .into_iter()
+                    .filter(|entry| entry.obsolete)
+                    .map(|entry| entry.hash)
+                    .collect()
+            })
+            .unwrap_or_default();
+    }
+
+    pub(crate) fn restack(&mut self) {
+        if let Some(ref vcs) = self.vcs {
+            match vcs.restack() {
+                Ok(0) => {
+                    self.status_message = "Nothing to restack".to_string();
+                }
+                Ok(moved) => {
+                    self.status_message = format!("Restacked {} commit(s)", moved);
+                    self.refresh_commits();
+                }
+                Err(e) => {
+                    self.status_message = error_status("Error restacking", &e);
+                }
+            }
+        } else {
+            self.status_message = "No repository path available".to_string();
+        }
+    }
+
+    /// Load the selected commit's changes and pick lines for a new commit
+    pub(crate) fn start_split(&mut self) {
+        let Some(commit) = self.selected_commit() else {
+            self.status_message = "No commit selected".to_string();
+            return;
+        };
+        let Some(ref vcs) = self.vcs else {
+            self.status_message = "No repository path available".to_string();
+            return;
+        };
+        let (hash, short_hash) = (commit.hash.clone(), commit.short_hash.clone());
+        match vcs.split_files(&hash, &[]) {
+            Ok(files) if files.is_empty() => {
+                self.status_message = format!("Commit {} has no changes to split", short_hash);
+            }
+            Ok(files) => {
+                self.split_commit = Some(hash);
+                self.split_files = files;
+                self.selected_split_file = 0;
+                self.selected_hunk_index = 0;
+                self.selected_hunk_line_index = 0;
+                self.split_message.clear();
+                self.view_mode = ViewMode::Split;
+                self.status_message = format!(
+                    "Splitting {}: select the lines for the first commit, then Enter",
+                    short_hash
+                );
+            }
+            Err(e) => {
+                self.status_message = error_status("Error splitting commit", &e);
+            }
+        }
+    }
+
+    pub(crate) fn split_selection_is_empty(&self) -> bool {
+        !self.split_files.iter().any(|file| {
+            file.is_selected
+                || file
+                    .hunks
+                    .iter()
+                    .flat_map(|hunk| &hunk.lines)
+                    .any(|line| line.is_selected)
+        })
+    }
+
+    pub(crate) fn cancel_split(&mut self) {
+        self.split_commit = None;
+        self.split_files.clear();
+        self.split_message.clear();
+        self.view_mode = ViewMode::List;
+        self.status_message = "Split cancelled".to_string();
+    }
+
+    /// Split the commit into the selected lines and the rest
+    pub(crate) fn finish_split(&mut self) {
+        let (Some(hash), Some(ref vcs)) = (self.split_commit.clone(), &self.vcs) else {
+            self.view_mode = ViewMode::List;
+            return;
+        };
+        let part = SplitPart {
+            patches: self
+                .split_files
+                .iter()
+                .map(SplitFile::selected_patch)
+                .collect(),
+            message: self.split_message.trim().to_string(),
+        };
+        match vcs.split_change(&hash, &[part]) {
+            Ok(()) => {
+                self.split_commit = None;
+                self.split_files.clear();
+                self.split_message.clear();
+                self.view_mode = ViewMode::List;
+                self.status_message = format!("Split {} into 2 commits", abbreviate_hash(&hash));
+                self.refresh_commits();
+            }
+            Err(e) => {
+                self.view_mode = ViewMode::Split;
+                self.status_message = error_status("Error splitting commit", &e);
+            }
+        }
+    }
+
+    pub(crate) fn absorb(&mut self) {
+        let Some(ref vcs) = self.vcs else {
+            self.status_message = "No repository path available".to_string();
+            return;
+        };
+        match vcs.absorb(false) {
+            Ok(hunks) => {
+                let absorbed = hunks.iter().filter(|h| h.commit.is_some()).count();
+                let left = hunks.len() - absorbed;
+                self.status_message = match (absorbed, left) {
+                    (0, _) => "No staged hunk belongs to a single stack commit".to_string(),
+                    (_, 0) => format!("Absorbed {} hunk(s)", absorbed),
+                    _ => format!("Absorbed {} hunk(s); {} left staged", absorbed, left),
+                };
+                if absorbed > 0 {
+                    self.refresh_commits();
+                    self.refresh_files();
+                }
+            }
+            Err(e) => {
+                self.status_message = error_status("Error absorbing changes", &e);
+            }
+        }
+    }
+
+    /// Open the selected file in the resolver if it is conflicted, otherwise
+    /// the first conflicted file
+    pub(crate) fn start_resolve(&mut self) {
+        self.refresh_files();
+        let conflicted = |file: &&FileStatus| file.status == StatusType::Conflicted;
+        let path = match self.files.get(self.selected_file_index) {
+            Some(file) if self.active_panel == PanelType::Files && conflicted(&file) => {
+                Some(file.path.clone())
+            }
+            _ => self.files.iter().find(conflicted).map(|f| f.path.clone()),
+        };
+        let (Some(path), Some(ref vcs)) = (path, &self.vcs) else {
+            self.status_message = "No conflicted files".to_string();
+            return;
+        };
+        match vcs.conflict_file(&path) {
+            Ok(file) if file.region_count() == 0 => {
+                self.status_message = format!(
+                    "{} has no conflict markers; stage it with Space once it is resolved",
+                    path
+                );
+            }
+            Ok(file) => {
+                self.status_message =
+                    format!("Resolving {}: {} conflict(s)", path, file.region_count());
+                self.conflict_file = Some(file);
+                self.selected_conflict_region = 0;
+                self.view_mode = ViewMode::Resolve;
+            }
+            Err(e) => {
+                self.status_message = error_status("Error reading conflicts", &e);
+            }
+        }
+    }
+
+    /// Resolve the region under the cursor (or undo its resolution) and move
+    /// on to the next unresolved one
+    pub(crate) fn resolve_region(&mut self, resolution: Option<Resolution>) {
+        let Some(ref mut file) = self.conflict_file else {
+            return;
+        };
+        let index = self.selected_conflict_region;
+        let Some(resolution) = resolution else {
+            if let Some(region) = file.region_mut(index) {
+                region.resolution = None;
+            }
+            return;
+        };
+        if let Err(e) = file.resolve(index, resolution) {
+            self.status_message = error_status("Cannot resolve", &e);
+            return;
+        }
+        let unresolved = file.unresolved_count();
+        if let Some(next) = file
+            .regions()
+            .enumerate()
+            .skip(index + 1)
+            .find(|(_, region)| region.resolution.is_none())
+        {
+            self.selected_conflict_region = next.0;
+        }
+        self.status_message = match unresolved {
+            0 => "All conflicts resolved; Enter writes the file".to_string(),
+            n => format!("{} conflict(s) left", n),
+        };
+    }
+
+    /// Edit the region under the cursor, starting from its resolution or ours
+    pub(crate) fn start_conflict_edit(&mut self) {
+        let Some(region) = self
+            .conflict_file
+            .as_ref()
+            .and_then(|file| file.regions().nth(self.selected_conflict_region))
+        else {
+            return;
+        };
+        self.conflict_edit = region
+            .resolved_text()
+            .unwrap_or_else(|| region.ours.lines.concat());
+        self.view_mode = ViewMode::ResolveEdit;
+        self.status_message = "Edit the text for this region (Ctrl+S to use it)".to_string();
+    }
+
+    pub(crate) fn finish_conflict_edit(&mut self) {
+        let mut text = std::mem::take(&mut self.conflict_edit);
+        if !text.is_empty() && !text.ends_with('\n') {
+            text.push('\n');
+        }
+        self.view_mode = ViewMode::Resolve;
+        self.resolve_region(Some(Resolution::Manual(text)));
+    }
+
+    pub(crate) fn cancel_resolve(&mut self) {
+        self.conflict_file = None;
+        self.conflict_edit.clear();
+        self.view_mode = ViewMode::List;
+        self.status_message = "Resolution cancelled; the file is unchanged".to_string();
+    }
+
+    /// Write the resolved file, mark it resolved and go on to the next
+    /// conflicted file, if any
+    pub(crate) fn finish_resolve(&mut self) {
+        let (Some(ref file), Some(ref vcs)) = (&self.conflict_file, &self.vcs) else {
+            self.view_mode = ViewMode::List;
+            return;
+        };
+        let unresolved = file.unresolved_count();
+        if unresolved > 0 {
+            self.status_message = format!("{} conflict(s) left to resolve", unresolved);
+            return;
+        }
+        if let Err(e) = vcs.resolve_conflict(file) {
+            self.status_message = error_status("Error resolving conflicts", &e);
+            return;
+        }
+        let path = file.path.clone();
+        self.conflict_file = None;
+        self.view_mode = ViewMode::List;
+        self.refresh_sync_state();
+        self.start_resolve();
+        if self.view_mode == ViewMode::Resolve {
+            self.status_message = format!("Resolved {}; next: {}", path, self.status_message);
+        } else if self.has_conflicts {
+            self.status_message = format!("Resolved {}; {}", path, self.status_message);
+        } else if self.repo_state.can_continue() {
+            self.status_message = format!(
+                "Resolved {}; no conflicts left (N continues the {})",
+                path,
+                self.repo_state.operation().unwrap_or_default()
+            );
+        } else {
+            self.status_message = format!("Resolved {}; no conflicts left", path);
+        }
+    }
+
+    pub(crate) fn continue_operation(&mut self) {
+        self.run_repo_operation("Finished", "Error continuing", |vcs| {
+            vcs.continue_operation()
+        });
+    }
+
+    pub(crate) fn abort_operation(&mut self) {
+        self.run_repo_operation("Aborted", "Error aborting", |vcs| vcs.abort_operation());
+    }
+
+    pub(crate) fn skip_operation(&mut self) {
+        self.run_repo_operation("Skipped a commit of", "Error skipping", |vcs| {
+            vcs.skip_operation()
+        });
+    }
+
+    /// The file selected in the Files panel, when that panel is active
+    fn selected_panel_file(&self) -> Option<String> {
+        match self.files.get(self.selected_file_index) {
+            Some(file) if self.active_panel == PanelType::Files => Some(file.path.clone()),
+            _ => None,
+        }
+    }
+
+    /// Ask for the path of the file to blame or show the history of
+    fn prompt_path(&mut self, action: PathAction) {
+        self.path_input.clear();
+        self.path_action = action;
+        self.status_message.clear();
+        self.view_mode = ViewMode::PathInput;
+    }
+
+    /// Blame the file selected in the Files panel, or ask for a path
+    pub(crate) fn blame_selected_file(&mut self) {
+        match self.selected_panel_file() {
+            Some(path) => self.blame_file(&path),
+            None => self.prompt_path(PathAction::Blame),
+        }
+    }
+
+    /// Blame `path` as it is in the working tree
+    pub(crate) fn blame_file(&mut self, path: &str) {
+        if path.is_empty() {
+            self.status_message = "Enter a path to blame".to_string();
+            return;
+        }
+        self.blame_history.clear();
+        if self.load_blame(path, BlameOptions::new(), 0) {
+            self.status_message.clear();
+            self.view_mode = ViewMode::Blame;
+        }
+    }
+
+    fn load_blame(&mut self, path: &str, options: BlameOptions, line: usize) -> bool {
+        let Some(ref vcs) = self.vcs else {
+            self.status_message = "No repository path available".to_string();
+            return false;
+        };
+        match vcs.blame(path, &options) {
+            Ok(blame) => {
+                self.selected_blame_line = line.min(blame.lines.len().saturating_sub(1));
+                self.blame = Some(blame);
+                true
+            }
+            Err(e) => {
+                self.status_message = error_status("Error blaming file", &e);
+                false
+            }
+        }
+    }
+
+    /// Open the details of the commit that last changed the selected line
+    pub(crate) fn show_blamed_commit(&mut self) {
+        let Some(commit) = self.blame.as_ref().and_then(|blame| {
+            let line = blame.lines.get(self.selected_blame_line)?;
+            blame.commit_of(line)
+        }) else {
+            return;
+        };
+        if commit.is_uncommitted() {
+            self.status_message = "The line is not committed yet".to_string();
+            return;
+        }
+        let hash = commit.hash.clone();
+        if self.select_commit_hash(&hash) {
+            self.view_mode = ViewMode::Details;
+        }
+    }
+
+    /// Show the history of the file selected in the Files panel, or ask for
+    /// a path
+    pub(crate) fn file_history_of_selected_file(&mut self) {
+        match self.selected_panel_file() {
+            Some(path) => self.show_file_history(&path),
+            None => self.prompt_path(PathAction::FileHistory),
+        }
+    }
+
+    /// Show the history of the file selected in the Files panel, or of the
+    /// first file in the diff
+    pub(crate) fn file_history_from_diff(&mut self) {
+        match self
+            .selected_panel_file()
+            .or_else(|| first_diff_path(&self.diff_content))
+        {
+            Some(path) => self.show_file_history(&path),
+            None => self.status_message = "No file in the diff".to_string(),
+        }
+    }
+
+    /// List the commits that changed `path` up to `HEAD`, following renames
+    pub(crate) fn show_file_history(&mut self, path: &str) {
+        if path.is_empty() {
+            self.status_message = "Enter a path to show the history of".to_string();
+            return;
+        }
+        let Some(ref vcs) = self.vcs else {
+            self.status_message = "No repository path available".to_string();
+            return;
+        };
+        let query = LogQuery::new().revision("HEAD").path(path);
+        match vcs.file_history(&query) {
+            Ok(versions) if versions.is_empty() => {
+                self.status_message = format!("No commits changed '{}'", path);
+            }
+            Ok(versions) => {
+                self.file_history = versions;
+                self.file_history_content = false;
+                self.status_message.clear();
+                self.select_file_version(0);
+                self.view_mode = ViewMode::FileHistory;
+            }
+            Err(e) => self.status_message = error_status("Error loading file history", &e),
+        }
+    }
+
+    pub(crate) fn select_file_version(&mut self, index: usize) {
+        self.selected_file_version = index;
+        self.load_file_version();
+    }
+
+    /// Load the selected version's diff, or its full content
+    pub(crate) fn load_file_version(&mut self) {
+        self.file_history_scroll = 0;
+        let (Some(vcs), Some(version)) = (
+            self.vcs.as_ref(),
+            self.file_history.get(self.selected_file_version),
+        ) else {
+            return;
+        };
+        let text = match self.file_history_content {
+            true => vcs.file_version_content(version),
+            false => vcs.file_version_diff(version),
+        };
+        match text {
+            Ok(text) => self.file_history_text = text,
+            Err(e) => {
+                self.file_history_text.clear();
+                self.status_message = error_status("Error loading the file", &e);
+            }
+        }
+    }
+
+    /// Show the full content of the previous (older) or next version
+    pub(crate) fn step_file_version(&mut self, older: bool) {
+        let index = match older {
+            true => self.selected_file_version + 1,
+            false => match self.selected_file_version.checked_sub(1) {
+                Some(index) => index,
+                None => {
+                    self.status_message = "This is the newest version".to_string();
+                    return;
+                }
+            },
+        };
+        if index >= self.file_history.len() {
+            self.status_message = "This is the oldest version".to_string();
+            return;
+        }
+        self.status_message.clear();
+        self.file_history_content = true;
+        self.select_file_version(index);
+    }
+
+    /// Open the details of the selected version's commit
+    pub(crate) fn show_file_version_commit(&mut self) {
+        let Some(version) = self.file_history.get(self.selected_file_version) else {
+            return;
+        };
+        let hash = version.commit.hash.clone();
+        if self.select_commit_hash(&hash) {
+            self.view_mode = ViewMode::Details;
+        }
+    }
+
+    /// Select `hash` in the commit list, loading the history from it when it
+    /// is not among the loaded commits
+    fn select_commit_hash(&mut self, hash: &str) -> bool {
+        if let Some(index) = self.commits.iter().position(|c| c.hash == hash) {
+            self.is_filtering = false;
+            self.selected_index = index;
+            self.scroll_offset = index.saturating_sub(10);
+            return true;
+        }
+        let Some(ref vcs) = self.vcs else {
+            return false;
+        };
+        match vcs.log(Some(100), Some(hash), false) {
+            Ok(changes) => {
+                let commits: Vec<Commit> = changes.into_iter().map(Into::into).collect();
+                self.commits = commits.clone();
+                self.filtered_commits = commits.clone();
+                self.tree = crate::tree::CommitTree::new(commits);
+                self.is_filtering = false;
+                self.selected_index = 0;
+                self.scroll_offset = 0;
+                true
+            }
+            Err(e) => {
+                self.status_message = error_status("Error loading commit", &e);
+                false
+            }
+        }
+    }
+
+    /// Blame the file again at the parent of the selected line's commit, to
+    /// see past that change (e.g. a reformat)
+    pub(crate) fn blame_parent(&mut self) {
+        let Some(ref blame) = self.blame else {
+            return;
+        };
+        let Some(line) = blame.lines.get(self.selected_blame_line) else {
+            return;
+        };
+        let Some(commit) = blame.commit_of(line) else {
+            return;
+        };
+        let Some((parent, parent_path)) = commit.previous.clone() else {
+            self.status_message = format!(
+                "{} added the line; there is no parent to blame",
+                abbreviate_hash(&commit.hash)
+            );
+            return;
+        };
+        let back = (
+            blame.path.clone(),
+            blame.options.clone(),
+            self.selected_blame_line,
+        );
+        let options = BlameOptions {
+            revision: Some(parent.clone()),
+            ..blame.options.clone()
+        };
+        let status = format!(
+            "Blaming the parent of {} ({}); Backspace goes back",
+            abbreviate_hash(&commit.hash),
+            abbreviate_hash(&parent)
+        );
+        let original_line = line.original_line.saturating_sub(1);
+        if self.load_blame(&parent_path, options, original_line) {
+            self.blame_history.push(back);
+            self.status_message = status;
+        }
+    }
+
+    /// Return to the blame before the last `blame_parent`
+    pub(crate) fn blame_back(&mut self) {
+        let Some((path, options, line)) = self.blame_history.pop() else {
+            self.status_message = "No earlier blame".to_string();
+            return;
+        };
+        if self.load_blame(&path, options, line) {
+            self.status_message.clear();
+        }
+    }
+
+    /// Flip one of `-w`, `-M` and `-C` and blame again
+    pub(crate) fn toggle_blame_option(
+        &mut self,
+        flag: impl FnOnce(&mut BlameOptions) -> &mut bool,
+    ) {
+        let Some(ref blame) = self.blame else {
+            return;
+        };
+        let mut options = blame.options.clone();
+        let enabled = flag(&mut options);
+        *enabled = !*enabled;
+        let path = blame.path.clone();
+        let line = self.selected_blame_line;
+        if self.load_blame(&path, options, line) {
+            self.status_message.clear();
+        }
+    }
+
+    /// Colour the commit graph by bisect marks and pick commits with g/b/s
+    pub(crate) fn start_bisect(&mut self) {
+        if self.vcs.is_none() {
+            self.status_message = "No repository path available".to_string();
+            return;
+        }
+        self.refresh_commits();
+        self.select_bisect_candidate();
+        self.status_message = match self.bisect {
+            Some(ref state) => state.summary(),
+            None => "Bisect: mark a bad commit (b) and a good one (g)".to_string(),
+        };
+        self.view_mode = ViewMode::Bisect;
+    }
+
+    /// Mark the selected commit; git checks out the next candidate, which
+    /// becomes the selection
+    pub(crate) fn mark_bisect(&mut self, verdict: BisectVerdict) {
+        let Some(hash) = self.selected_commit().map(|c| c.hash.clone()) else {
+            self.status_message = "No commit selected".to_string();
+            return;
+        };
+        let Some(ref vcs) = self.vcs else {
+            self.status_message = "No repository path available".to_string();
+            return;
+        };
+        match vcs.bisect_mark(verdict, Some(&hash)) {
+            Ok(state) => {
+                self.status_message = state.summary();
+                self.bisect = Some(state);
+                self.refresh_commits();
+                self.select_bisect_candidate();
+            }
+            Err(e) => {
+                self.status_message = error_status("Error marking commit", &e);
+            }
+        }
+    }
+
+    pub(crate) fn reset_bisect(&mut self) {
+        let Some(ref vcs) = self.vcs else {
+            self.status_message = "No repository path available".to_string();
+            return;
+        };
+        match vcs.bisect_reset() {
+            Ok(()) => {
+                self.bisect = None;
+                self.refresh_commits();
+                self.status_message = "Bisect reset".to_string();
+                self.view_mode = ViewMode::List;
+            }
+            Err(e) => {
+                self.status_message = error_status("Error resetting bisect", &e);
+            }
+        }
+    }
+
+    /// Select the first bad commit once found, otherwise the one to test
+    fn select_bisect_candidate(&mut self) {
+        let Some(ref state) = self.bisect else {
+            return;
+        };
+        let Some(target) = state.first_bad.as_ref().or(state.current.as_ref()) else {
+            return;
+        };
+        if let Some(index) = self.commits.iter().position(|c| &c.hash == target) {
+            self.selected_index = index;
+            if index >= self.scroll_offset + 20 {
+                self.scroll_offset = index - 20 + 1;
+            }
+        }
+    }
+
+    /// Continue, abort or skip the operation in progress and report on it
+    fn run_repo_operation(
+        &mut self,
+        done: &str,
+        action: &str,
+        run: impl FnOnce(&dyn Vcs) -> Result<()>,
+    ) {
+        let Some(ref vcs) = self.vcs else {
+            self.status_message = "No repository path available".to_string();
+            return;
+        };
+        let state = vcs.repo_state().unwrap_or(RepoState::Clean);
+        let Some(operation) = state.operation() else {
+            self.status_message = "No operation in progress".to_string();
+            return;
+        };
+        if let Err(e) = run(vcs.as_ref()) {
+            self.status_message = error_status(action, &e);
+            self.refresh_sync_state();
+            return;
+        }
+        self.refresh_sync_state();
+        self.status_message = if self.repo_state.is_clean() {
+            format!("{} the {}", done, operation)
+        } else {
+            self.repo_state.to_string()
+        };
+        self.refresh_commits();
+        self.refresh_files();
     }
 
     pub(crate) fn refresh_sync_state(&mut self) {
         self.is_loading = true;
-        if let Some(ref repo_path) = self.repo_path {
-            match openisl_git::get_sync_state(repo_path) {
+        if let Some(ref vcs) = self.vcs {
+            self.repo_state = vcs.repo_state().unwrap_or(RepoState::Clean);
+            self.bisect = vcs.bisect_state().ok().flatten();
+            match vcs.sync_state() {
                 Ok(sync_state) => {
                     self.repo_ahead = sync_state.local_unpushed;
                     self.repo_behind = sync_state.remote_unpulled;
@@ -419,7 +1122,7 @@ impl App {
                     self.is_loading = false;
                 }
                 Err(e) => {
-                    self.status_message = format!("Error getting sync state: {}", e);
+                    self.status_message = error_status("Error getting sync state", &e);
                     self.is_loading = false;
                 }
             }
@@ -430,8 +1133,8 @@ impl App {
 
     pub(crate) fn refresh_stashes(&mut self) {
         self.is_loading = true;
-        if let Some(ref repo_path) = self.repo_path {
-            match get_stash_list(repo_path) {
+        if let Some(ref vcs) = self.vcs {
+            match vcs.saved_work() {
                 Ok(stashes) => {
                     self.stashes = stashes;
                     self.selected_stash_index = 0;
@@ -439,7 +1142,7 @@ impl App {
                     self.is_loading = false;
                 }
                 Err(e) => {
-                    self.status_message = format!("Error refreshing stashes: {}", e);
+                    self.status_message = error_status("Error refreshing stashes", &e);
                     self.is_loading = false;
                 }
             }
@@ -451,14 +1154,14 @@ impl App {
 
     pub(crate) fn fetch_stash_diff(&mut self, stash_index_str: &str) {
         self.is_loading = true;
-        if let Some(ref repo_path) = self.repo_path {
-            match stash_show(repo_path, stash_index_str) {
+        if let Some(ref vcs) = self.vcs {
+            match vcs.show_saved_work(stash_index_str) {
                 Ok(diff) => {
                     self.stash_diff_content = diff;
                     self.is_loading = false;
                 }
                 Err(e) => {
-                    self.stash_diff_content = format!("Error fetching stash diff: {}", e);
+                    self.stash_diff_content = error_status("Error fetching stash diff", &e);
                     self.is_loading = false;
                 }
             }
@@ -470,8 +1173,8 @@ impl App {
 
     pub(crate) fn apply_stash(&mut self, stash_index_str: Option<&str>) {
         self.is_loading = true;
-        if let Some(ref repo_path) = self.repo_path {
-            match stash_apply(repo_path, stash_index_str) {
+        if let Some(ref vcs) = self.vcs {
+            match vcs.apply_saved_work(stash_index_str) {
                 Ok(_) => {
                     self.status_message =
                         format!("Stash {} applied", stash_index_str.unwrap_or("0"));
@@ -479,7 +1182,7 @@ impl App {
                     self.is_loading = false;
                 }
                 Err(e) => {
-                    self.status_message = format!("Error applying stash: {}", e);
+                    self.status_message = error_status("Error applying stash", &e);
                     self.is_loading = false;
                 }
             }
@@ -491,8 +1194,8 @@ impl App {
 
     pub(crate) fn drop_stash(&mut self, stash_index_str: Option<&str>) {
         self.is_loading = true;
-        if let Some(ref repo_path) = self.repo_path {
-            match stash_drop(repo_path, stash_index_str) {
+        if let Some(ref vcs) = self.vcs {
+            match vcs.drop_saved_work(stash_index_str) {
                 Ok(_) => {
                     self.status_message =
                         format!("Stash {} dropped", stash_index_str.unwrap_or("0"));
@@ -500,7 +1203,7 @@ impl App {
                     self.is_loading = false;
                 }
                 Err(e) => {
-                    self.status_message = format!("Error dropping stash: {}", e);
+                    self.status_message = error_status("Error dropping stash", &e);
                     self.is_loading = false;
                 }
             }
@@ -512,8 +1215,8 @@ impl App {
 
     pub(crate) fn pop_stash(&mut self, stash_index_str: Option<&str>) {
         self.is_loading = true;
-        if let Some(ref repo_path) = self.repo_path {
-            match stash_pop(repo_path, stash_index_str) {
+        if let Some(ref vcs) = self.vcs {
+            match vcs.pop_saved_work(stash_index_str) {
                 Ok(_) => {
                     self.status_message =
                         format!("Stash {} popped", stash_index_str.unwrap_or("0"));
@@ -521,7 +1224,7 @@ impl App {
                     self.is_loading = false;
                 }
                 Err(e) => {
-                    self.status_message = format!("Error popping stash: {}", e);
+                    self.status_message = error_status("Error popping stash", &e);
                     self.is_loading = false;
                 }
             }
@@ -533,15 +1236,15 @@ impl App {
 
     pub(crate) fn checkout_commit(&mut self) {
         if let Some(commit) = self.selected_commit() {
-            if let Some(ref repo_path) = self.repo_path {
-                match openisl_git::checkout_commit(repo_path, &commit.hash) {
+            if let Some(ref vcs) = self.vcs {
+                match vcs.checkout(&commit.hash) {
                     Ok(_) => {
                         self.status_message = format!("Checked out {}", commit.short_hash);
                         self.refresh_commits();
                         self.view_mode = ViewMode::List;
                     }
                     Err(e) => {
-                        self.status_message = format!("Error checking out commit: {}", e);
+                        self.status_message = error_status("Error checking out commit", &e);
                     }
                 }
             } else {
@@ -553,23 +1256,25 @@ impl App {
     }
 
     pub(crate) fn create_branch_at_commit(&mut self) {
-        if let Some(commit) = self.selected_commit() {
+        let target = match self.branch_target.clone() {
+            Some(hash) => Some((abbreviate_hash(&hash).to_string(), hash)),
+            None => self
+                .selected_commit()
+                .map(|commit| (commit.short_hash.clone(), commit.hash.clone())),
+        };
+        if let Some((short_hash, hash)) = target {
             if !self.branch_input.is_empty() {
-                if let Some(ref repo_path) = self.repo_path {
-                    match openisl_git::create_branch_from_commit(
-                        repo_path,
-                        &self.branch_input,
-                        &commit.hash,
-                    ) {
+                if let Some(ref vcs) = self.vcs {
+                    match vcs.create_branch(&self.branch_input, Some(&hash)) {
                         Ok(_) => {
                             self.status_message = format!(
                                 "Created branch '{}' from {}",
-                                self.branch_input, commit.short_hash
+                                self.branch_input, short_hash
                             );
                             self.refresh_commits();
                         }
                         Err(e) => {
-                            self.status_message = format!("Error creating branch: {}", e);
+                            self.status_message = error_status("Error creating branch", &e);
                         }
                     }
                 } else {
@@ -580,4 +1285,163 @@ impl App {
             self.status_message = "No commit selected".to_string();
         }
     }
+
+    pub(crate) fn refresh_operation_log(&mut self) {
+        self.is_loading = true;
+        if let Some(ref vcs) = self.vcs {
+            match vcs.operation_log() {
+                Ok(operations) => {
+                    self.operations = operations;
+                    self.selected_operation_index = 0;
+                    self.operation_scroll_offset = 0;
+                }
+                Err(e) => {
+                    self.operations.clear();
+                    self.status_message = error_status("Error loading operation log", &e);
+                }
+            }
+        } else {
+            self.status_message = "No repository path available".to_string();
+        }
+        self.is_loading = false;
+    }
+
+    pub(crate) fn undo_selected_operation(&mut self) {
+        if let Some(operation) = self.operations.get(self.selected_operation_index) {
+            let operation_id = operation.id.clone();
+            if let Some(ref vcs) = self.vcs {
+                match vcs.undo_operation(Some(&operation_id)) {
+                    Ok(_) => {
+                        self.status_message = format!("Undid operation {}", operation_id);
+                        self.refresh_commits();
+                        self.refresh_operation_log();
+                    }
+                    Err(e) => {
+                        self.status_message = error_status("Error undoing operation", &e);
+                    }
+                }
+            } else {
+                self.status_message = "No repository path available".to_string();
+            }
+        } else {
+            self.status_message = "No operation selected".to_string();
+        }
+    }
+
+    pub(crate) fn redo_operation(&mut self) {
+        if let Some(ref vcs) = self.vcs {
+            match vcs.redo_operation() {
+                Ok(_) => {
+                    self.status_message = "Redid the last undone operation".to_string();
+                    self.refresh_commits();
+                    self.refresh_operation_log();
+                }
+                Err(e) => {
+                    self.status_message = error_status("Error redoing operation", &e);
+                }
+            }
+        } else {
+            self.status_message = "No repository path available".to_string();
+        }
+    }
+
+    pub(crate) fn refresh_reflog(&mut self) {
+        self.is_loading = true;
+        if let Some(ref vcs) = self.vcs {
+            match vcs.reflog(None, None) {
+                Ok(entries) => {
+                    self.reflog = entries;
+                    self.selected_reflog_index = 0;
+                    self.reflog_scroll_offset = 0;
+                }
+                Err(e) => {
+                    self.reflog.clear();
+                    self.status_message = error_status("Error loading reflog", &e);
+                }
+            }
+        } else {
+            self.status_message = "No repository path available".to_string();
+        }
+        self.is_loading = false;
+        self.fetch_reflog_diff();
+    }
+
+    pub(crate) fn fetch_reflog_diff(&mut self) {
+        let Some(entry) = self.reflog.get(self.selected_reflog_index) else {
+            self.reflog_diff_content.clear();
+            return;
+        };
+        if let Some(ref vcs) = self.vcs {
+            self.reflog_diff_content = match vcs.change_diff(&entry.id) {
+                Ok(diff) => diff,
+                Err(e) => error_status("Error fetching diff", &e),
+            };
+        }
+    }
+
+    pub(crate) fn checkout_reflog_entry(&mut self) {
+        let Some(entry) = self.reflog.get(self.selected_reflog_index) else {
+            self.status_message = "No reflog entry selected".to_string();
+            return;
+        };
+        let hash = entry.id.clone();
+        if let Some(ref vcs) = self.vcs {
+            match vcs.checkout(&hash) {
+                Ok(_) => {
+                    self.status_message = format!("Checked out {}", abbreviate_hash(&hash));
+                    self.refresh_commits();
+                    self.refresh_reflog();
+                }
+                Err(e) => {
+                    self.status_message = error_status("Error checking out commit", &e);
+                }
+            }
+        } else {
+            self.status_message = "No repository path available".to_string();
+        }
+    }
+
+    /// Hard-reset the current branch to the selected entry. The operation log
+    /// records the state first, so `O` then `u` brings discarded work back.
+    pub(crate) fn reset_to_reflog_entry(&mut self) {
+        let Some(entry) = self.reflog.get(self.selected_reflog_index) else {
+            self.status_message = "No reflog entry selected".to_string();
+            return;
+        };
+        let hash = entry.id.clone();
+        if let Some(ref vcs) = self.vcs {
+            match vcs.reset(&hash, ResetMode::Hard) {
+                Ok(_) => {
+                    self.status_message = format!(
+                        "Reset to {} (undo from the operation log with O, u)",
+                        abbreviate_hash(&hash)
+                    );
+                    self.refresh_commits();
+                    self.refresh_reflog();
+                }
+                Err(e) => {
+                    self.status_message = error_status("Error resetting", &e);
+                }
+            }
+        } else {
+            self.status_message = "No repository path available".to_string();
+        }
+    }
+}
+
+/// Path of the first file in a diff (its new path, or the old one when the
+/// file is deleted)
+fn first_diff_path(diff: &str) -> Option<String> {
+    let mut old_path = None;
+    for line in diff.lines() {
+        if let Some(path) = line.strip_prefix("+++ b/") {
+            return Some(path.to_string());
+        }
+        if let Some(path) = line.strip_prefix("--- a/") {
+            old_path = Some(path.to_string());
+        } else if line.starts_with("@@") && old_path.is_some() {
+            break;
+        }
+    }
+    old_path
 }
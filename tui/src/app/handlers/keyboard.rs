@@ -8,6 +8,8 @@ impl App {
     pub(crate) fn handle_key(&mut self, key: KeyEvent) -> bool {
         if key.code == KeyCode::Char('?')
             && self.view_mode != ViewMode::CommandPalette
+            && self.view_mode != ViewMode::SplitMessage
+            && self.view_mode != ViewMode::ResolveEdit
             && self.view_mode != ViewMode::Help
         {
             self.view_mode = ViewMode::Help;
@@ -26,6 +28,16 @@ impl App {
             ViewMode::CommandPalette => self.handle_command_palette_key(key),
             ViewMode::Stash => self.handle_stash_key(key),
             ViewMode::HunkStaging => self.handle_hunk_staging_key(key), // Handle hunk staging mode
+            ViewMode::OperationLog => self.handle_operation_log_key(key),
+            ViewMode::Reflog => self.handle_reflog_key(key),
+            ViewMode::Split => self.handle_split_key(key),
+            ViewMode::SplitMessage => self.handle_split_message_key(key),
+            ViewMode::Resolve => self.handle_resolve_key(key),
+            ViewMode::ResolveEdit => self.handle_resolve_edit_key(key),
+            ViewMode::Bisect => self.handle_bisect_key(key),
+            ViewMode::Blame => self.handle_blame_key(key),
+            ViewMode::PathInput => self.handle_path_input_key(key),
+            ViewMode::FileHistory => self.handle_file_history_key(key),
         }
     }
 
@@ -45,16 +57,8 @@ impl App {
             KeyCode::Enter => self.view_mode = ViewMode::Details,
             KeyCode::Tab => self.next_panel(),
             KeyCode::BackTab => self.prev_panel(),
-            KeyCode::Char('h') | KeyCode::Left => {
-                if self.sidebar_visible {
-                    self.prev_panel();
-                }
-            }
-            KeyCode::Char('l') | KeyCode::Right => {
-                if self.sidebar_visible {
-                    self.next_panel();
-                }
-            }
+            KeyCode::Char('h') | KeyCode::Left if self.sidebar_visible => self.prev_panel(),
+            KeyCode::Char('l') | KeyCode::Right if self.sidebar_visible => self.next_panel(),
             KeyCode::Char('b') if key.modifiers.contains(KeyModifiers::CONTROL) => {
                 self.toggle_sidebar();
             }
@@ -107,6 +111,29 @@ impl App {
             KeyCode::Char('R') => {
                 self.revert_commit();
             }
+            KeyCode::Char('T') => {
+                self.restack();
+            }
+            KeyCode::Char('X') => {
+                self.start_split();
+            }
+            KeyCode::Char('M') => {
+                self.start_resolve();
+            }
+            KeyCode::Char('B') => self.start_bisect(),
+            KeyCode::Char('w') => self.blame_selected_file(),
+            KeyCode::Char('H') => self.file_history_of_selected_file(),
+            KeyCode::Char('N') => self.continue_operation(),
+            KeyCode::Char('K') => self.skip_operation(),
+            KeyCode::Char('Z') => self.abort_operation(),
+            KeyCode::Char('O') => {
+                self.refresh_operation_log();
+                self.view_mode = ViewMode::OperationLog;
+            }
+            KeyCode::Char('L') => {
+                self.refresh_reflog();
+                self.view_mode = ViewMode::Reflog;
+            }
             KeyCode::Char('c') => {
                 self.branch_input.clear();
                 self.view_mode = ViewMode::InputBranch;
@@ -194,6 +221,275 @@ impl App {
         false
     }
 
+    pub(crate) fn handle_split_key(&mut self, key: KeyEvent) -> bool {
+        let file_count = self.split_files.len();
+        match key.code {
+            KeyCode::Esc => self.cancel_split(),
+            KeyCode::Char('j') | KeyCode::Down => {
+                let Some(file) = self.split_files.get(self.selected_split_file) else {
+                    return false;
+                };
+                if let Some(hunk) = file.hunks.get(self.selected_hunk_index) {
+                    if self.selected_hunk_line_index < hunk.lines.len().saturating_sub(1) {
+                        self.selected_hunk_line_index += 1;
+                    } else if self.selected_hunk_index < file.hunks.len().saturating_sub(1) {
+                        self.selected_hunk_index += 1;
+                        self.selected_hunk_line_index = 0;
+                    }
+                }
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                if self.selected_hunk_line_index > 0 {
+                    self.selected_hunk_line_index -= 1;
+                } else if self.selected_hunk_index > 0 {
+                    self.selected_hunk_index -= 1;
+                    self.selected_hunk_line_index = self
+                        .split_files
+                        .get(self.selected_split_file)
+                        .and_then(|file| file.hunks.get(self.selected_hunk_index))
+                        .map_or(0, |hunk| hunk.lines.len().saturating_sub(1));
+                }
+            }
+            KeyCode::Tab | KeyCode::BackTab if file_count > 0 => {
+                self.selected_split_file = if key.code == KeyCode::Tab {
+                    (self.selected_split_file + 1) % file_count
+                } else {
+                    (self.selected_split_file + file_count - 1) % file_count
+                };
+                self.selected_hunk_index = 0;
+                self.selected_hunk_line_index = 0;
+            }
+            KeyCode::Char(' ') => {
+                let Some(file) = self.split_files.get_mut(self.selected_split_file) else {
+                    return false;
+                };
+                if file.hunks.is_empty() {
+                    file.is_selected = !file.is_selected;
+                } else if let Some(line) = file
+                    .hunks
+                    .get_mut(self.selected_hunk_index)
+                    .and_then(|hunk| hunk.lines.get_mut(self.selected_hunk_line_index))
+                    .filter(|line| line.line_type != HunkLineType::Context)
+                {
+                    line.is_selected = !line.is_selected;
+                }
+            }
+            KeyCode::Char('a') => {
+                let Some(file) = self.split_files.get_mut(self.selected_split_file) else {
+                    return false;
+                };
+                if let Some(hunk) = file.hunks.get_mut(self.selected_hunk_index) {
+                    let mut changes: Vec<_> = hunk
+                        .lines
+                        .iter_mut()
+                        .filter(|line| line.line_type != HunkLineType::Context)
+                        .collect();
+                    let select = changes.iter().any(|line| !line.is_selected);
+                    changes
+                        .iter_mut()
+                        .for_each(|line| line.is_selected = select);
+                }
+            }
+            KeyCode::Enter => {
+                if self.split_selection_is_empty() {
+                    self.status_message =
+                        "Select the lines for the first commit (Space, or a for a hunk)"
+                            .to_string();
+                } else {
+                    self.view_mode = ViewMode::SplitMessage;
+                    self.status_message =
+                        "Message for the first commit (Enter to split, Esc to go back)".to_string();
+                }
+            }
+            _ => {}
+        }
+        false
+    }
+
+    pub(crate) fn handle_split_message_key(&mut self, key: KeyEvent) -> bool {
+        match key.code {
+            KeyCode::Esc => {
+                self.view_mode = ViewMode::Split;
+                self.status_message.clear();
+            }
+            KeyCode::Enter => {
+                if self.split_message.trim().is_empty() {
+                    self.status_message = "The new commit needs a message".to_string();
+                } else {
+                    self.finish_split();
+                }
+            }
+            KeyCode::Backspace => {
+                self.split_message.pop();
+            }
+            KeyCode::Char(c) if !c.is_control() => {
+                self.split_message.push(c);
+            }
+            _ => {}
+        }
+        false
+    }
+
+    pub(crate) fn handle_resolve_key(&mut self, key: KeyEvent) -> bool {
+        let region_count = self.conflict_file.as_ref().map_or(0, |f| f.region_count());
+        match key.code {
+            KeyCode::Esc => self.cancel_resolve(),
+            KeyCode::Char('j') | KeyCode::Char('n') | KeyCode::Down
+                if self.selected_conflict_region + 1 < region_count =>
+            {
+                self.selected_conflict_region += 1;
+            }
+            KeyCode::Char('k') | KeyCode::Char('p') | KeyCode::Up => {
+                self.selected_conflict_region = self.selected_conflict_region.saturating_sub(1);
+            }
+            KeyCode::Char('o') => self.resolve_region(Some(Resolution::Ours)),
+            KeyCode::Char('t') => self.resolve_region(Some(Resolution::Theirs)),
+            KeyCode::Char('b') => self.resolve_region(Some(Resolution::Both)),
+            KeyCode::Char('a') => self.resolve_region(Some(Resolution::Base)),
+            KeyCode::Char('u') => self.resolve_region(None),
+            KeyCode::Char('e') => self.start_conflict_edit(),
+            KeyCode::Enter => self.finish_resolve(),
+            _ => {}
+        }
+        false
+    }
+
+    pub(crate) fn handle_bisect_key(&mut self, key: KeyEvent) -> bool {
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => {
+                self.view_mode = ViewMode::List;
+                self.status_message.clear();
+            }
+            KeyCode::Char('j') | KeyCode::Down => self.move_down(),
+            KeyCode::Char('k') | KeyCode::Up => self.move_up(),
+            KeyCode::PageDown => self.page_down(),
+            KeyCode::PageUp => self.page_up(),
+            KeyCode::Char('g') => self.mark_bisect(BisectVerdict::Good),
+            KeyCode::Char('b') => self.mark_bisect(BisectVerdict::Bad),
+            KeyCode::Char('s') => self.mark_bisect(BisectVerdict::Skip),
+            KeyCode::Char('r') => self.reset_bisect(),
+            _ => {}
+        }
+        false
+    }
+
+    pub(crate) fn handle_blame_key(&mut self, key: KeyEvent) -> bool {
+        let line_count = self.blame.as_ref().map_or(0, |b| b.lines.len());
+        let last = line_count.saturating_sub(1);
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => {
+                self.blame = None;
+                self.blame_history.clear();
+                self.view_mode = ViewMode::List;
+                self.status_message.clear();
+            }
+            KeyCode::Char('j') | KeyCode::Down => {
+                self.selected_blame_line = (self.selected_blame_line + 1).min(last);
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                self.selected_blame_line = self.selected_blame_line.saturating_sub(1);
+            }
+            KeyCode::PageDown => {
+                self.selected_blame_line = (self.selected_blame_line + 20).min(last)
+            }
+            KeyCode::PageUp => {
+                self.selected_blame_line = self.selected_blame_line.saturating_sub(20)
+            }
+            KeyCode::Home => self.selected_blame_line = 0,
+            KeyCode::End => self.selected_blame_line = last,
+            KeyCode::Enter => self.show_blamed_commit(),
+            KeyCode::Char('p') => self.blame_parent(),
+            KeyCode::Backspace => self.blame_back(),
+            KeyCode::Char('w') => self.toggle_blame_option(|o| &mut o.ignore_whitespace),
+            KeyCode::Char('m') => self.toggle_blame_option(|o| &mut o.detect_moves),
+            KeyCode::Char('c') => self.toggle_blame_option(|o| &mut o.detect_copies),
+            _ => {}
+        }
+        false
+    }
+
+    pub(crate) fn handle_file_history_key(&mut self, key: KeyEvent) -> bool {
+        let last = self.file_history.len().saturating_sub(1);
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => {
+                self.file_history.clear();
+                self.file_history_text.clear();
+                self.view_mode = ViewMode::List;
+                self.status_message.clear();
+            }
+            KeyCode::Char('j') | KeyCode::Down => {
+                self.select_file_version((self.selected_file_version + 1).min(last))
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                self.select_file_version(self.selected_file_version.saturating_sub(1))
+            }
+            KeyCode::Home => self.select_file_version(0),
+            KeyCode::End => self.select_file_version(last),
+            KeyCode::Char('[') => self.step_file_version(true),
+            KeyCode::Char(']') => self.step_file_version(false),
+            KeyCode::Char('v') | KeyCode::Tab => {
+                self.file_history_content = !self.file_history_content;
+                self.load_file_version();
+            }
+            KeyCode::PageDown => {
+                self.file_history_scroll = self.file_history_scroll.saturating_add(20)
+            }
+            KeyCode::PageUp => {
+                self.file_history_scroll = self.file_history_scroll.saturating_sub(20)
+            }
+            KeyCode::Enter => self.show_file_version_commit(),
+            _ => {}
+        }
+        false
+    }
+
+    pub(crate) fn handle_path_input_key(&mut self, key: KeyEvent) -> bool {
+        match key.code {
+            KeyCode::Esc => {
+                self.view_mode = ViewMode::List;
+                self.status_message.clear();
+            }
+            KeyCode::Enter => {
+                let path = self.path_input.trim().to_string();
+                match self.path_action {
+                    PathAction::Blame => self.blame_file(&path),
+                    PathAction::FileHistory => self.show_file_history(&path),
+                }
+            }
+            KeyCode::Backspace => {
+                self.path_input.pop();
+            }
+            KeyCode::Char(c) if !c.is_control() => {
+                self.path_input.push(c);
+            }
+            _ => {}
+        }
+        false
+    }
+
+    pub(crate) fn handle_resolve_edit_key(&mut self, key: KeyEvent) -> bool {
+        match key.code {
+            KeyCode::Esc => {
+                self.conflict_edit.clear();
+                self.view_mode = ViewMode::Resolve;
+                self.status_message.clear();
+            }
+            KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.finish_conflict_edit();
+            }
+            KeyCode::Enter => self.conflict_edit.push('\n'),
+            KeyCode::Tab => self.conflict_edit.push('\t'),
+            KeyCode::Backspace => {
+                self.conflict_edit.pop();
+            }
+            KeyCode::Char(c) if !c.is_control() => {
+                self.conflict_edit.push(c);
+            }
+            _ => {}
+        }
+        false
+    }
+
     pub(crate) fn handle_search_key(&mut self, key: KeyEvent) -> bool {
         match key.code {
             KeyCode::Esc => {
@@ -289,6 +585,7 @@ impl App {
             KeyCode::Esc => {
                 self.view_mode = ViewMode::List;
                 self.branch_input.clear();
+                self.branch_target = None;
                 self.status_message.clear();
                 return false;
             }
@@ -297,6 +594,7 @@ impl App {
                     self.create_branch_at_commit();
                 }
                 self.branch_input.clear();
+                self.branch_target = None;
                 self.view_mode = ViewMode::List;
                 return false;
             }
@@ -325,11 +623,49 @@ impl App {
                         "Select a file with diffs to enter hunk staging mode".to_string();
                 }
             }
+            KeyCode::Char('H') => self.file_history_from_diff(),
+            KeyCode::Char('j') | KeyCode::Down => self.scroll_diff(1),
+            KeyCode::Char('k') | KeyCode::Up => self.scroll_diff(-1),
+            KeyCode::PageDown => self.scroll_diff(20),
+            KeyCode::PageUp => self.scroll_diff(-20),
+            KeyCode::Home => self.diff_scroll = 0,
+            KeyCode::End => self.scroll_diff(i32::MAX),
+            KeyCode::Char('s') => {
+                self.side_by_side = !self.side_by_side;
+                self.status_message = match self.side_by_side {
+                    true => "Side-by-side diff".to_string(),
+                    false => "Unified diff".to_string(),
+                };
+            }
+            KeyCode::Char('w') => {
+                self.word_diff = !self.word_diff;
+                self.status_message = match self.word_diff {
+                    true => "Highlighting changed words".to_string(),
+                    false => "Highlighting whole lines".to_string(),
+                };
+            }
             _ => {}
         }
         false
     }
 
+    /// Move the diff view `lines` down (up when negative), staying within
+    /// the diff
+    pub(crate) fn scroll_diff(&mut self, lines: i32) {
+        // The diff pane spans the terminal, inside its borders
+        let line_count = if shows_side_by_side(self, self.terminal_width.saturating_sub(2)) {
+            DiffParser::split_rows(&self.diff_files).len()
+        } else if self.diff_files.is_empty() {
+            self.diff_content.lines().count()
+        } else {
+            DiffParser::from_file_diffs(&self.diff_files).len()
+        };
+        let last = line_count.saturating_sub(1).min(u16::MAX as usize) as i32;
+        self.diff_scroll = (self.diff_scroll as i32)
+            .saturating_add(lines)
+            .clamp(0, last) as u16;
+    }
+
     pub(crate) fn handle_help_key(&mut self, key: KeyEvent) -> bool {
         match key.code {
             KeyCode::Char('q') | KeyCode::Esc | KeyCode::Char('?') => {
@@ -391,33 +727,34 @@ impl App {
             }
             KeyCode::Enter => {
                 self.apply_filter();
-                self.status_message = format!("Filter: {} commits", self.filtered_commits.len());
+                // A rejected revset has already explained itself
+                if self.is_filtering || self.filter_input.is_empty() {
+                    self.status_message =
+                        format!("Filter: {} commits", self.filtered_commits.len());
+                }
                 self.view_mode = ViewMode::List;
                 return false;
             }
             KeyCode::Backspace => {
                 self.filter_input.pop();
             }
-            KeyCode::Char('a') => {
-                self.filter_mode = FilterMode::Author;
-                self.status_message = "Filtering by author...".to_string();
-            }
-            KeyCode::Char('m') => {
-                self.filter_mode = FilterMode::Message;
-                self.status_message = "Filtering by message...".to_string();
-            }
-            KeyCode::Char('d') => {
-                self.filter_mode = FilterMode::Date;
-                self.status_message = "Filtering by date (YYYY-MM-DD)...".to_string();
+            // Letters are filter text, so the filter type cycles on Tab
+            KeyCode::Tab => {
+                self.filter_mode = match self.filter_mode {
+                    FilterMode::Author => FilterMode::Message,
+                    FilterMode::Message => FilterMode::Date,
+                    FilterMode::Date => FilterMode::Revset,
+                    FilterMode::Revset => FilterMode::Author,
+                };
+                self.status_message = match self.filter_mode {
+                    FilterMode::Author => "Filtering by author...",
+                    FilterMode::Message => "Filtering by message...",
+                    FilterMode::Date => "Filtering by date (YYYY-MM-DD)...",
+                    FilterMode::Revset => "Filtering by revset...",
+                }
+                .to_string();
             }
-            KeyCode::Char(c)
-                if c.is_ascii_alphanumeric()
-                    || c == '-'
-                    || c == '_'
-                    || c == ' '
-                    || c == '.'
-                    || c == '@' =>
-            {
+            KeyCode::Char(c) if !c.is_control() => {
                 self.filter_input.push(c);
             }
             _ => {}
@@ -459,10 +796,8 @@ impl App {
                 self.command_palette_input.push(c);
                 self.filter_command_palette();
             }
-            KeyCode::Up => {
-                if !self.command_palette_results.is_empty() {
-                    self.command_palette_results.rotate_right(1);
-                }
+            KeyCode::Up if !self.command_palette_results.is_empty() => {
+                self.command_palette_results.rotate_right(1);
             }
             KeyCode::Down if !self.command_palette_results.is_empty() => {
                 self.command_palette_results.rotate_left(1);
@@ -479,51 +814,49 @@ impl App {
                 self.stash_diff_content.clear();
                 return false;
             }
-            KeyCode::Char('j') | KeyCode::Down => {
-                if self.selected_stash_index < self.stashes.len().saturating_sub(1) {
-                    self.selected_stash_index += 1;
-                    if self.selected_stash_index >= self.stash_scroll_offset + 10 {
-                        self.stash_scroll_offset = self.selected_stash_index - 10 + 1;
-                    }
-                    if let Some(stash) = self.stashes.get(self.selected_stash_index) {
-                        let stash_name = stash.name.clone();
-                        self.fetch_stash_diff(&stash_name);
-                    }
+            KeyCode::Char('j') | KeyCode::Down
+                if self.selected_stash_index < self.stashes.len().saturating_sub(1) =>
+            {
+                self.selected_stash_index += 1;
+                if self.selected_stash_index >= self.stash_scroll_offset + 10 {
+                    self.stash_scroll_offset = self.selected_stash_index - 10 + 1;
+                }
+                if let Some(stash) = self.stashes.get(self.selected_stash_index) {
+                    let stash_name = stash.id.clone();
+                    self.fetch_stash_diff(&stash_name);
                 }
             }
-            KeyCode::Char('k') | KeyCode::Up => {
-                if self.selected_stash_index > 0 {
-                    self.selected_stash_index = self.selected_stash_index.saturating_sub(1);
-                    if self.selected_stash_index < self.stash_scroll_offset {
-                        self.stash_scroll_offset = self.selected_stash_index.saturating_sub(1);
-                    }
-                    if let Some(stash) = self.stashes.get(self.selected_stash_index) {
-                        let stash_name = stash.name.clone();
-                        self.fetch_stash_diff(&stash_name);
-                    }
+            KeyCode::Char('k') | KeyCode::Up if self.selected_stash_index > 0 => {
+                self.selected_stash_index = self.selected_stash_index.saturating_sub(1);
+                if self.selected_stash_index < self.stash_scroll_offset {
+                    self.stash_scroll_offset = self.selected_stash_index.saturating_sub(1);
+                }
+                if let Some(stash) = self.stashes.get(self.selected_stash_index) {
+                    let stash_name = stash.id.clone();
+                    self.fetch_stash_diff(&stash_name);
                 }
             }
             KeyCode::Enter => {
                 if let Some(stash) = self.stashes.get(self.selected_stash_index) {
-                    let stash_name = stash.name.clone();
+                    let stash_name = stash.id.clone();
                     self.fetch_stash_diff(&stash_name);
                 }
             }
             KeyCode::Char('a') => {
                 if let Some(stash) = self.stashes.get(self.selected_stash_index) {
-                    let stash_name = stash.name.clone();
+                    let stash_name = stash.id.clone();
                     self.apply_stash(Some(&stash_name));
                 }
             }
             KeyCode::Char('d') => {
                 if let Some(stash) = self.stashes.get(self.selected_stash_index) {
-                    let stash_name = stash.name.clone();
+                    let stash_name = stash.id.clone();
                     self.drop_stash(Some(&stash_name));
                 }
             }
             KeyCode::Char('p') => {
                 if let Some(stash) = self.stashes.get(self.selected_stash_index) {
-                    let stash_name = stash.name.clone();
+                    let stash_name = stash.id.clone();
                     self.pop_stash(Some(&stash_name));
                 }
             }
@@ -532,6 +865,70 @@ impl App {
         false
     }
 
+    pub(crate) fn handle_operation_log_key(&mut self, key: KeyEvent) -> bool {
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => {
+                self.view_mode = ViewMode::List;
+            }
+            KeyCode::Char('j') | KeyCode::Down
+                if self.selected_operation_index < self.operations.len().saturating_sub(1) =>
+            {
+                self.selected_operation_index += 1;
+                if self.selected_operation_index >= self.operation_scroll_offset + 10 {
+                    self.operation_scroll_offset = self.selected_operation_index - 10 + 1;
+                }
+            }
+            KeyCode::Char('k') | KeyCode::Up if self.selected_operation_index > 0 => {
+                self.selected_operation_index -= 1;
+                if self.selected_operation_index < self.operation_scroll_offset {
+                    self.operation_scroll_offset = self.selected_operation_index;
+                }
+            }
+            KeyCode::Char('u') => self.undo_selected_operation(),
+            KeyCode::Char('r') => self.redo_operation(),
+            _ => {}
+        }
+        false
+    }
+
+    pub(crate) fn handle_reflog_key(&mut self, key: KeyEvent) -> bool {
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => {
+                self.view_mode = ViewMode::List;
+                self.reflog_diff_content.clear();
+            }
+            KeyCode::Char('j') | KeyCode::Down
+                if self.selected_reflog_index < self.reflog.len().saturating_sub(1) =>
+            {
+                self.selected_reflog_index += 1;
+                if self.selected_reflog_index >= self.reflog_scroll_offset + 10 {
+                    self.reflog_scroll_offset = self.selected_reflog_index - 10 + 1;
+                }
+                self.fetch_reflog_diff();
+            }
+            KeyCode::Char('k') | KeyCode::Up if self.selected_reflog_index > 0 => {
+                self.selected_reflog_index -= 1;
+                if self.selected_reflog_index < self.reflog_scroll_offset {
+                    self.reflog_scroll_offset = self.selected_reflog_index;
+                }
+                self.fetch_reflog_diff();
+            }
+            KeyCode::Enter => self.fetch_reflog_diff(),
+            KeyCode::Char('c') => self.checkout_reflog_entry(),
+            KeyCode::Char('b') => {
+                if let Some(entry) = self.reflog.get(self.selected_reflog_index) {
+                    self.branch_target = Some(entry.id.clone());
+                    self.branch_input.clear();
+                    self.view_mode = ViewMode::InputBranch;
+                    self.status_message = "Enter branch name (or Esc to cancel):".to_string();
+                }
+            }
+            KeyCode::Char('r') => self.reset_to_reflog_entry(),
+            _ => {}
+        }
+        false
+    }
+
     pub(crate) fn execute_command(&mut self, action: &str) {
         match action {
             "toggle_sidebar" => self.toggle_sidebar(),
@@ -588,22 +985,42 @@ impl App {
             }
             "apply_stash" => {
                 if let Some(stash) = self.stashes.get(self.selected_stash_index) {
-                    let stash_name = stash.name.clone();
+                    let stash_name = stash.id.clone();
                     self.apply_stash(Some(&stash_name));
                 }
             }
             "drop_stash" => {
                 if let Some(stash) = self.stashes.get(self.selected_stash_index) {
-                    let stash_name = stash.name.clone();
+                    let stash_name = stash.id.clone();
                     self.drop_stash(Some(&stash_name));
                 }
             }
             "pop_stash" => {
                 if let Some(stash) = self.stashes.get(self.selected_stash_index) {
-                    let stash_name = stash.name.clone();
+                    let stash_name = stash.id.clone();
                     self.pop_stash(Some(&stash_name));
                 }
             }
+            "view_operation_log" => {
+                self.refresh_operation_log();
+                self.view_mode = ViewMode::OperationLog;
+            }
+            "view_reflog" => {
+                self.refresh_reflog();
+                self.view_mode = ViewMode::Reflog;
+            }
+            "undo_operation" => self.undo_selected_operation(),
+            "redo_operation" => self.redo_operation(),
+            "restack" => self.restack(),
+            "split" => self.start_split(),
+            "absorb" => self.absorb(),
+            "resolve_conflicts" => self.start_resolve(),
+            "bisect" => self.start_bisect(),
+            "blame_file" => self.blame_selected_file(),
+            "file_history" => self.file_history_of_selected_file(),
+            "continue_operation" => self.continue_operation(),
+            "abort_operation" => self.abort_operation(),
+            "skip_operation" => self.skip_operation(),
             _ => {
                 self.status_message = format!("Unknown command: {}", action);
             }
@@ -11,7 +11,9 @@ pub mod handlers;
 pub mod render;
 pub mod state;
 
-pub use state::{CommandAction, FilterMode, PanelType, RepoStats, StatusBarMode, ViewMode};
+pub use state::{
+    CommandAction, FilterMode, PanelType, PathAction, RepoStats, StatusBarMode, ViewMode,
+};
 
 pub(crate) use crate::diff::{DiffParser, DiffStats};
 pub(crate) use crate::keybindings::KeyBindings;
@@ -24,14 +26,12 @@ pub(crate) use crossterm::event::{
 };
 pub(crate) use crossterm::execute;
 pub(crate) use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
-pub(crate) use openisl_git::operations::hunk::{
-    get_file_diff_hunks, stage_hunk, stage_hunk_lines, unstage_hunk, unstage_hunk_lines,
-    HunkLineType,
-};
-pub(crate) use openisl_git::operations::{
-    get_stash_list, stash_apply, stash_drop, stash_pop, stash_show, StashEntry,
+pub(crate) use openisl_git::operations::hunk::HunkLineType;
+pub(crate) use openisl_git::{
+    abbreviate_hash, parse_diff, BisectState, BisectVerdict, Blame, BlameOptions, Commit,
+    ConflictChunk, ConflictFile, FileChange, FileDiff, FileStatus, FileVersion, GitRef,
+    HistoryPoint, LogQuery, RepoState, Resolution, SavedWork, SplitFile, Vcs,
 };
-pub(crate) use openisl_git::{get_commit_diff, Commit, FileStatus, GitRef};
 pub(crate) use ratatui::widgets::Clear;
 pub(crate) use ratatui::{
     backend::CrosstermBackend,
@@ -40,13 +40,19 @@ pub(crate) use ratatui::{
     widgets::{Block, BorderType, Borders, List, ListItem, Paragraph, Widget},
     Terminal,
 };
+use std::collections::HashSet;
 pub(crate) use std::io::stdout;
-pub(crate) use std::path::Path;
+
+/// Upper bound on commits fetched for a filter query
+const FILTER_MAX_COMMITS: usize = 1000;
 
 use crate::app::render::{
-    render_branch_search_input, render_command_palette, render_details_view, render_diff_view,
-    render_filter_view, render_help_overlay, render_hunk_staging_view, render_input_view,
-    render_list_view, render_search_view, render_stash_view, render_stats_view,
+    render_blame_view, render_branch_search_input, render_command_palette, render_details_view,
+    render_diff_view, render_file_history_view, render_filter_view, render_help_overlay,
+    render_hunk_staging_view, render_input_view, render_list_view, render_operation_log_view,
+    render_path_input_view, render_reflog_view, render_resolve_edit_view, render_resolve_view,
+    render_search_view, render_split_message_view, render_split_view, render_stash_view,
+    render_stats_view, shows_side_by_side,
 };
 
 pub struct App {
@@ -59,10 +65,21 @@ pub struct App {
     pub theme: Theme,
     pub view_mode: ViewMode,
     pub diff_content: String,
+    /// `diff_content` parsed per file; empty when it holds no diff (e.g. an
+    /// error message)
+    pub diff_files: Vec<FileDiff>,
     pub diff_stats: DiffStats,
+    /// Emphasise the changed words of paired deleted/added lines
+    pub word_diff: bool,
+    /// Show the diff view side by side when the terminal is wide enough
+    pub side_by_side: bool,
+    /// First line of the diff shown, on both sides of a side-by-side diff
+    pub diff_scroll: u16,
+    /// Terminal width at the last draw, which decides the diff layout
+    pub terminal_width: u16,
     pub status_message: String,
     pub branch_input: String,
-    pub repo_path: Option<std::path::PathBuf>,
+    pub vcs: Option<Box<dyn Vcs>>,
     pub keybindings: KeyBindings,
     pub search_query: String,
     pub search_results: Vec<usize>,
@@ -85,10 +102,20 @@ pub struct App {
     pub all_branches: Vec<GitRef>,
     pub selected_branch_index: usize,
     pub branch_scroll_offset: usize,
-    pub stashes: Vec<StashEntry>,
+    pub stashes: Vec<SavedWork>,
     pub selected_stash_index: usize,
     pub stash_scroll_offset: usize,
     pub stash_diff_content: String,
+    pub operations: Vec<HistoryPoint>,
+    pub selected_operation_index: usize,
+    pub operation_scroll_offset: usize,
+    pub reflog: Vec<HistoryPoint>,
+    pub selected_reflog_index: usize,
+    pub reflog_scroll_offset: usize,
+    pub reflog_diff_content: String,
+    /// Commit for the next branch created from `InputBranch`, when it isn't
+    /// the selected commit (e.g. a reflog entry)
+    pub branch_target: Option<String>,
     pub command_palette_input: String,
     pub command_palette_results: Vec<CommandAction>,
     pub hunks: Vec<openisl_git::operations::hunk::Hunk>, // Added for hunk staging
@@ -103,16 +130,50 @@ pub struct App {
     pub repo_ahead: Option<usize>,
     pub repo_behind: Option<usize>,
     pub has_conflicts: bool,
+    /// Rebase, merge, etc. in progress, shown in a banner with its actions
+    pub repo_state: RepoState,
+    /// Commits whose parent has been rewritten since (amended or edited)
+    pub obsolete_commits: HashSet<String>,
+    /// Commit being split, with its files' hunks and the lines picked for
+    /// the first of the new commits. The hunk cursor is `selected_hunk_index`
+    /// and `selected_hunk_line_index`.
+    pub split_commit: Option<String>,
+    pub split_files: Vec<SplitFile>,
+    pub selected_split_file: usize,
+    pub split_message: String,
+    /// Conflicted file being resolved, the region under the cursor, and the
+    /// text of a region being edited by hand
+    pub conflict_file: Option<ConflictFile>,
+    pub selected_conflict_region: usize,
+    pub conflict_edit: String,
+    /// Bisect session in progress; the commit graph is coloured by its marks
+    pub bisect: Option<BisectState>,
+    /// File being blamed and the line under the cursor. Blaming at a parent
+    /// pushes the previous path, options and line onto `blame_history`.
+    pub blame: Option<Blame>,
+    pub selected_blame_line: usize,
+    pub blame_history: Vec<(String, BlameOptions, usize)>,
+    /// Path typed in `PathInput`, and what it is for
+    pub path_input: String,
+    pub path_action: PathAction,
+    /// Versions of the file in the file history view, newest first. The
+    /// selected version's diff, or its full content with
+    /// `file_history_content`, is kept in `file_history_text`.
+    pub file_history: Vec<FileVersion>,
+    pub selected_file_version: usize,
+    pub file_history_content: bool,
+    pub file_history_text: String,
+    pub file_history_scroll: u16,
 }
 
 impl App {
-    pub fn new(
-        commits: Vec<Commit>,
-        current_branch: String,
-        repo_path: Option<std::path::PathBuf>,
-    ) -> Self {
-        let all_branches = if let Some(ref path) = repo_path {
-            openisl_git::get_branches(path, false, true).unwrap_or_default()
+    pub fn new(commits: Vec<Commit>, current_branch: String, vcs: Option<Box<dyn Vcs>>) -> Self {
+        let all_branches: Vec<GitRef> = if let Some(ref vcs) = vcs {
+            vcs.branches()
+                .unwrap_or_default()
+                .into_iter()
+                .map(Into::into)
+                .collect()
         } else {
             Vec::new()
         };
@@ -127,10 +188,15 @@ impl App {
             theme: Theme::dark(),
             view_mode: ViewMode::List,
             diff_content: String::new(),
+            diff_files: Vec::new(),
             diff_stats: DiffStats::default(),
+            word_diff: true,
+            side_by_side: false,
+            diff_scroll: 0,
+            terminal_width: 0,
             status_message: String::new(),
             branch_input: String::new(),
-            repo_path,
+            vcs,
             keybindings: KeyBindings::load().unwrap_or_default(),
             search_query: String::new(),
             search_results: Vec::new(),
@@ -157,6 +223,14 @@ impl App {
             selected_stash_index: 0,
             stash_scroll_offset: 0,
             stash_diff_content: String::new(),
+            operations: Vec::new(),
+            selected_operation_index: 0,
+            operation_scroll_offset: 0,
+            reflog: Vec::new(),
+            selected_reflog_index: 0,
+            reflog_scroll_offset: 0,
+            reflog_diff_content: String::new(),
+            branch_target: None,
             command_palette_input: String::new(),
             command_palette_results: Vec::new(),
             hunks: Vec::new(),                       // Initialized
@@ -171,10 +245,31 @@ impl App {
             repo_ahead: None,
             repo_behind: None,
             has_conflicts: false,
+            repo_state: RepoState::Clean,
+            obsolete_commits: HashSet::new(),
+            split_commit: None,
+            split_files: Vec::new(),
+            selected_split_file: 0,
+            split_message: String::new(),
+            conflict_file: None,
+            selected_conflict_region: 0,
+            conflict_edit: String::new(),
+            bisect: None,
+            blame: None,
+            selected_blame_line: 0,
+            blame_history: Vec::new(),
+            path_input: String::new(),
+            path_action: PathAction::Blame,
+            file_history: Vec::new(),
+            selected_file_version: 0,
+            file_history_content: false,
+            file_history_text: String::new(),
+            file_history_scroll: 0,
         };
         app.calculate_stats();
         app.populate_command_palette();
         app.refresh_sync_state();
+        app.refresh_obsolete_commits();
         app
     }
 
@@ -192,12 +287,16 @@ impl App {
     }
 
     pub fn parse_diff(&mut self) {
+        self.diff_scroll = 0;
+        self.diff_files = parse_diff(&self.diff_content);
         if self.diff_content.is_empty() {
             self.diff_stats = DiffStats::default();
-            return;
+        } else if self.diff_files.is_empty() {
+            let lines = DiffParser::parse(&self.diff_content);
+            self.diff_stats = DiffParser::count_stats(&lines);
+        } else {
+            self.diff_stats = DiffParser::file_diff_stats(&self.diff_files);
         }
-        let lines = DiffParser::parse(&self.diff_content);
-        self.diff_stats = DiffParser::count_stats(&lines);
     }
 
     pub fn calculate_stats(&mut self) {
@@ -238,7 +337,20 @@ impl App {
             return;
         }
 
+        if self.filter_mode == FilterMode::Revset {
+            self.apply_revset_filter();
+            return;
+        }
+
         self.is_filtering = true;
+        if let Some(commits) = self.query_filtered_commits() {
+            self.filtered_commits = commits;
+            self.selected_index = 0;
+            self.scroll_offset = 0;
+            return;
+        }
+
+        // Backends without log queries filter the loaded commits instead
         let query = self.filter_input.to_lowercase();
 
         self.filtered_commits = self
@@ -251,6 +363,7 @@ impl App {
                         || commit.message.to_lowercase().contains(&query)
                 }
                 FilterMode::Date => commit.date.format("%Y-%m-%d").to_string().contains(&query),
+                FilterMode::Revset => false,
             })
             .cloned()
             .collect();
@@ -259,6 +372,73 @@ impl App {
         self.scroll_offset = 0;
     }
 
+    /// Build the backend log query for the current filter. Dates are either
+    /// a single `YYYY-MM-DD` day, a `since..until` range, or a start date in
+    /// any form git understands (`2 weeks ago`).
+    pub fn filter_log_query(&self) -> LogQuery {
+        let input = self.filter_input.trim();
+        let query = LogQuery::new()
+            .max_count(FILTER_MAX_COMMITS)
+            .fixed_strings()
+            .ignore_case();
+
+        match self.filter_mode {
+            FilterMode::Author => query.author(input),
+            FilterMode::Message => query.grep(input),
+            FilterMode::Date => {
+                if chrono::NaiveDate::parse_from_str(input, "%Y-%m-%d").is_ok() {
+                    query
+                        .since(format!("{} 00:00:00", input))
+                        .until(format!("{} 23:59:59", input))
+                } else if let Some((since, until)) = input.split_once("..") {
+                    let query = if since.is_empty() {
+                        query
+                    } else {
+                        query.since(since)
+                    };
+                    if until.is_empty() {
+                        query
+                    } else {
+                        query.until(until)
+                    }
+                } else {
+                    query.since(input)
+                }
+            }
+            // Revsets go through `Vcs::revset_log` instead
+            FilterMode::Revset => query,
+        }
+    }
+
+    /// Revsets are evaluated by the backend only; an invalid or unsupported
+    /// revset leaves the log unfiltered and reports why in the status bar
+    fn apply_revset_filter(&mut self) {
+        let result = match self.vcs.as_ref() {
+            Some(vcs) => vcs.revset_log(&self.filter_input, Some(FILTER_MAX_COMMITS)),
+            None => Err(anyhow::anyhow!("no repository to evaluate revsets against")),
+        };
+
+        match result {
+            Ok(changes) => {
+                self.filtered_commits = changes.into_iter().map(Into::into).collect();
+                self.is_filtering = true;
+            }
+            Err(e) => {
+                self.filtered_commits = self.commits.clone();
+                self.is_filtering = false;
+                self.status_message = handlers::commit_ops::error_status("Revset", &e);
+            }
+        }
+        self.selected_index = 0;
+        self.scroll_offset = 0;
+    }
+
+    fn query_filtered_commits(&self) -> Option<Vec<Commit>> {
+        let vcs = self.vcs.as_ref()?;
+        let changes = vcs.query_log(&self.filter_log_query()).ok()?;
+        Some(changes.into_iter().map(Into::into).collect())
+    }
+
     pub fn clear_filter(&mut self) {
         self.filter_input.clear();
         self.filtered_commits = self.commits.clone();
@@ -647,6 +827,112 @@ impl App {
                 keys: vec!["P".to_string()],
                 context: vec!["stash".to_string()],
             },
+            CommandAction {
+                name: "Operation Log".to_string(),
+                description: "Show repository operations and undo them".to_string(),
+                action: "view_operation_log".to_string(),
+                keys: vec!["O".to_string()],
+                context: vec!["commits".to_string()],
+            },
+            CommandAction {
+                name: "Reflog".to_string(),
+                description: "Browse where HEAD has pointed and recover lost commits".to_string(),
+                action: "view_reflog".to_string(),
+                keys: vec!["L".to_string()],
+                context: vec!["commits".to_string()],
+            },
+            CommandAction {
+                name: "Undo Operation".to_string(),
+                description: "Undo the selected operation, or restore the state before it"
+                    .to_string(),
+                action: "undo_operation".to_string(),
+                keys: vec!["u".to_string()],
+                context: vec!["operations".to_string()],
+            },
+            CommandAction {
+                name: "Absorb".to_string(),
+                description: "Fold staged hunks into the stack commits that last touched them"
+                    .to_string(),
+                action: "absorb".to_string(),
+                keys: Vec::new(),
+                context: vec!["files".to_string(), "commits".to_string()],
+            },
+            CommandAction {
+                name: "Split Commit".to_string(),
+                description: "Split the selected commit by picking lines for a new commit"
+                    .to_string(),
+                action: "split".to_string(),
+                keys: vec!["X".to_string()],
+                context: vec!["commits".to_string()],
+            },
+            CommandAction {
+                name: "Restack".to_string(),
+                description: "Move commits left on rewritten parents onto the new versions"
+                    .to_string(),
+                action: "restack".to_string(),
+                keys: vec!["T".to_string()],
+                context: vec!["commits".to_string()],
+            },
+            CommandAction {
+                name: "Continue Operation".to_string(),
+                description: "Continue the rebase, merge, cherry-pick or revert in progress"
+                    .to_string(),
+                action: "continue_operation".to_string(),
+                keys: vec!["N".to_string()],
+                context: vec![],
+            },
+            CommandAction {
+                name: "Abort Operation".to_string(),
+                description: "Abort the operation in progress (or end a bisect)".to_string(),
+                action: "abort_operation".to_string(),
+                keys: vec!["Z".to_string()],
+                context: vec![],
+            },
+            CommandAction {
+                name: "Skip Commit".to_string(),
+                description: "Skip the commit the operation in progress stopped at".to_string(),
+                action: "skip_operation".to_string(),
+                keys: vec!["K".to_string()],
+                context: vec![],
+            },
+            CommandAction {
+                name: "Bisect".to_string(),
+                description: "Find the commit that introduced a bug by marking commits good or bad"
+                    .to_string(),
+                action: "bisect".to_string(),
+                keys: vec!["B".to_string()],
+                context: vec!["commits".to_string()],
+            },
+            CommandAction {
+                name: "Blame File".to_string(),
+                description: "Show the commit that last changed each line of the selected file"
+                    .to_string(),
+                action: "blame_file".to_string(),
+                keys: vec!["w".to_string()],
+                context: vec!["files".to_string()],
+            },
+            CommandAction {
+                name: "File History".to_string(),
+                description: "List the commits that changed the selected file, across renames"
+                    .to_string(),
+                action: "file_history".to_string(),
+                keys: vec!["H".to_string()],
+                context: vec!["files".to_string()],
+            },
+            CommandAction {
+                name: "Resolve Conflicts".to_string(),
+                description: "Pick ours, theirs, both or base for each conflict region".to_string(),
+                action: "resolve_conflicts".to_string(),
+                keys: vec!["M".to_string()],
+                context: vec!["files".to_string()],
+            },
+            CommandAction {
+                name: "Redo Operation".to_string(),
+                description: "Redo the last undone operation".to_string(),
+                action: "redo_operation".to_string(),
+                keys: vec!["r".to_string()],
+                context: vec!["operations".to_string()],
+            },
             CommandAction {
                 name: "Open in Editor".to_string(),
                 description: "Open selected file in external editor".to_string(),
@@ -665,13 +951,27 @@ impl App {
     }
 
     pub fn format_commit_details(&self, commit: &Commit) -> String {
+        // Only shown when it differs, as with rebased or cherry-picked commits
+        let committer = if commit.committer != commit.author
+            || commit.committer_email != commit.email
+            || commit.committer_date != commit.date
+        {
+            format!(
+                "\nCommitter: {} <{}>\nCommitted: {}",
+                commit.committer, commit.committer_email, commit.committer_date
+            )
+        } else {
+            String::new()
+        };
+
         format!(
-            "Commit: {}\nShort:   {}\nAuthor:  {} <{}>\nDate:    {}\n\n{}\n\nParents: {}",
+            "Commit: {}\nShort:   {}\nAuthor:  {} <{}>\nDate:    {}{}\n\n{}\n\nParents: {}",
             commit.hash,
             commit.short_hash,
             commit.author,
             commit.email,
             commit.date,
+            committer,
             commit.message,
             if commit.parent_hashes.is_empty() {
                 "None (initial commit)".to_string()
@@ -700,10 +1000,27 @@ impl App {
     }
 }
 
+/// Settings the TUI starts with, e.g. from the user's configuration
+#[derive(Debug, Clone, Default)]
+pub struct TuiOptions {
+    /// Open the diff view side by side instead of unified
+    pub side_by_side_diff: bool,
+}
+
 pub fn run_tui(
     commits: Vec<Commit>,
     current_branch: String,
-    repo_path: Option<std::path::PathBuf>,
+    vcs: Option<Box<dyn Vcs>>,
+) -> Result<()> {
+    run_tui_with(commits, current_branch, vcs, TuiOptions::default())
+}
+
+/// `run_tui` with the given starting settings
+pub fn run_tui_with(
+    commits: Vec<Commit>,
+    current_branch: String,
+    vcs: Option<Box<dyn Vcs>>,
+    options: TuiOptions,
 ) -> Result<()> {
     let mut stdout = stdout();
 
@@ -713,10 +1030,12 @@ pub fn run_tui(
     let mut terminal = Terminal::new(backend)?;
     terminal.clear()?;
 
-    let mut app = App::new(commits.clone(), current_branch, repo_path);
+    let mut app = App::new(commits.clone(), current_branch, vcs);
+    app.side_by_side = options.side_by_side_diff;
     app.set_commits(commits);
 
     loop {
+        app.terminal_width = terminal.size()?.width;
         terminal.draw(|frame| match app.view_mode {
             ViewMode::List => render_list_view(&app, frame),
             ViewMode::Details => render_details_view(&app, frame),
@@ -730,6 +1049,16 @@ pub fn run_tui(
             ViewMode::CommandPalette => render_command_palette(&app, frame),
             ViewMode::Stash => render_stash_view(&app, frame),
             ViewMode::HunkStaging => render_hunk_staging_view(&app, frame), // Render hunk staging view
+            ViewMode::OperationLog => render_operation_log_view(&app, frame),
+            ViewMode::Reflog => render_reflog_view(&app, frame),
+            ViewMode::Split => render_split_view(&app, frame),
+            ViewMode::SplitMessage => render_split_message_view(&app, frame),
+            ViewMode::Resolve => render_resolve_view(&app, frame),
+            ViewMode::ResolveEdit => render_resolve_edit_view(&app, frame),
+            ViewMode::Bisect => render_list_view(&app, frame),
+            ViewMode::Blame => render_blame_view(&app, frame),
+            ViewMode::PathInput => render_path_input_view(&app, frame),
+            ViewMode::FileHistory => render_file_history_view(&app, frame),
         })?;
 
         if event::poll(std::time::Duration::from_millis(50))? {
@@ -772,6 +1101,10 @@ mod tests {
                 date: chrono::Utc::now(),
                 parent_hashes: vec![],
                 refs: vec![],
+                committer: "test@example.com".to_string(),
+                committer_email: "test@example.com".to_string(),
+                committer_date: chrono::Utc::now(),
+                trailers: vec![],
             },
             Commit {
                 hash: "def456ghi789abc".to_string(),
@@ -783,6 +1116,10 @@ mod tests {
                 date: chrono::Utc::now(),
                 parent_hashes: vec!["abc123def456789".to_string()],
                 refs: vec![],
+                committer: "test@example.com".to_string(),
+                committer_email: "test@example.com".to_string(),
+                committer_date: chrono::Utc::now(),
+                trailers: vec![],
             },
             Commit {
                 hash: "ghi789jkl012345".to_string(),
@@ -794,6 +1131,10 @@ mod tests {
                 date: chrono::Utc::now(),
                 parent_hashes: vec!["def456ghi789abc".to_string()],
                 refs: vec![],
+                committer: "other@example.com".to_string(),
+                committer_email: "other@example.com".to_string(),
+                committer_date: chrono::Utc::now(),
+                trailers: vec![],
             },
         ]
     }
@@ -811,21 +1152,25 @@ mod tests {
                         content: "line1".to_string(),
                         line_type: HunkLineType::Context, // Full path
                         is_selected: false,
+                        no_newline: false,
                     },
                     HunkLine {
                         content: "line2_removed".to_string(),
                         line_type: HunkLineType::Deletion, // Corrected variant name
                         is_selected: false,
+                        no_newline: false,
                     },
                     HunkLine {
                         content: "line2_added".to_string(),
                         line_type: HunkLineType::Addition, // Corrected variant name
                         is_selected: false,
+                        no_newline: false,
                     },
                     HunkLine {
                         content: "line3".to_string(),
                         line_type: HunkLineType::Context, // Full path
                         is_selected: false,
+                        no_newline: false,
                     },
                 ],
                 is_selected: false,
@@ -842,11 +1187,13 @@ mod tests {
                         content: "line5_removed".to_string(),
                         line_type: HunkLineType::Deletion, // Corrected variant name
                         is_selected: false,
+                        no_newline: false,
                     },
                     HunkLine {
                         content: "line5_added".to_string(),
                         line_type: HunkLineType::Addition, // Corrected variant name
                         is_selected: false,
+                        no_newline: false,
                     },
                 ],
                 is_selected: false,
@@ -859,10 +1206,10 @@ mod tests {
     fn test_hunk_staging_mode_entry_exit() {
         let commits = create_test_commits();
         let mut app = App::new(commits, "main".to_string(), None);
-        app.files = vec![FileStatus {
-            path: "test.rs".to_string(),
-            status: openisl_git::StatusType::Modified,
-        }];
+        app.files = vec![FileStatus::new(
+            "test.rs",
+            openisl_git::StatusType::Modified,
+        )];
         app.active_panel = PanelType::Files;
         app.hunks = mock_hunks();
 
@@ -952,16 +1299,16 @@ mod tests {
     fn test_hunk_staging_actions() {
         let commits = create_test_commits();
         let mut app = App::new(commits, "main".to_string(), None);
-        app.files = vec![FileStatus {
-            path: "test.rs".to_string(),
-            status: openisl_git::StatusType::Modified,
-        }];
+        app.files = vec![FileStatus::new(
+            "test.rs",
+            openisl_git::StatusType::Modified,
+        )];
         app.active_panel = PanelType::Files;
         app.hunks = mock_hunks();
         app.view_mode = ViewMode::HunkStaging;
 
         // Test with no repo path - should show error
-        app.repo_path = None;
+        app.vcs = None;
         app.hunks[0].lines[1].is_selected = true; // Select 'line2_removed'
         app.handle_key(KeyEvent::new(KeyCode::Char('s'), KeyModifiers::NONE));
         assert!(app.status_message.contains("No repository path available"));
@@ -971,7 +1318,7 @@ mod tests {
         assert!(app.status_message.contains("No repository path available"));
 
         // Test with no file selected (set repo path first to test this case)
-        app.repo_path = Some(std::path::PathBuf::from("/mock/repo"));
+        app.vcs = Some(Box::new(openisl_git::GitBackend::new("/mock/repo")));
         app.files.clear();
         app.handle_key(KeyEvent::new(KeyCode::Char('s'), KeyModifiers::NONE));
         assert!(app
@@ -1091,6 +1438,13 @@ mod tests {
             ViewMode::InputBranch,
             ViewMode::BranchSearch,
             ViewMode::Stash,
+            ViewMode::OperationLog,
+            ViewMode::Reflog,
+            ViewMode::Split,
+            ViewMode::Bisect,
+            ViewMode::Blame,
+            ViewMode::PathInput,
+            ViewMode::FileHistory,
         ] {
             app.view_mode = mode;
             app.handle_key(KeyEvent::new(KeyCode::Char('?'), KeyModifiers::NONE));
@@ -1140,7 +1494,7 @@ mod tests {
 
         app.handle_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
         assert_eq!(app.view_mode, ViewMode::List);
-        // With no repo_path, create_branch_at_commit reports an error
+        // With no vcs, create_branch_at_commit reports an error
         assert!(app.status_message.contains("No repository path"));
     }
 
@@ -1307,6 +1661,20 @@ mod tests {
         assert!(details.contains("None (initial commit)"));
     }
 
+    #[test]
+    fn test_format_commit_details_shows_distinct_committer() {
+        let mut commits = create_test_commits();
+        commits[0].committer_date = commits[0].date;
+        let app = App::new(commits.clone(), "main".to_string(), None);
+        assert!(!app
+            .format_commit_details(&commits[0])
+            .contains("Committer:"));
+
+        commits[0].committer = "Maintainer".to_string();
+        let details = app.format_commit_details(&commits[0]);
+        assert!(details.contains("Committer: Maintainer <"));
+    }
+
     #[test]
     fn test_format_commit_details_with_parents() {
         let commits = create_test_commits();
@@ -1380,6 +1748,83 @@ mod tests {
         assert_eq!(app.view_mode, ViewMode::List);
     }
 
+    #[test]
+    fn test_diff_view_toggles_word_diff() {
+        let commits = create_test_commits();
+        let mut app = App::new(commits, "main".to_string(), None);
+        assert!(app.word_diff);
+
+        app.view_mode = ViewMode::Diff;
+        app.handle_key(KeyEvent::new(KeyCode::Char('w'), KeyModifiers::NONE));
+        assert!(!app.word_diff);
+        assert_eq!(app.view_mode, ViewMode::Diff);
+        app.handle_key(KeyEvent::new(KeyCode::Char('w'), KeyModifiers::NONE));
+        assert!(app.word_diff);
+    }
+
+    #[test]
+    fn test_diff_view_side_by_side_and_scroll() {
+        let commits = create_test_commits();
+        let mut app = App::new(commits, "main".to_string(), None);
+        app.diff_content = (1..=30).map(|i| format!("+line {}\n", i)).fold(
+            "diff --git a/f b/f\n--- a/f\n+++ b/f\n@@ -0,0 +1,30 @@\n".to_string(),
+            |d, l| d + &l,
+        );
+        app.parse_diff();
+        app.view_mode = ViewMode::Diff;
+        let press = |app: &mut App, code: KeyCode| {
+            app.handle_key(KeyEvent::new(code, KeyModifiers::NONE));
+        };
+
+        assert!(!app.side_by_side);
+        press(&mut app, KeyCode::Char('s'));
+        assert!(app.side_by_side);
+        assert_eq!(app.status_message, "Side-by-side diff");
+
+        press(&mut app, KeyCode::Char('j'));
+        press(&mut app, KeyCode::PageDown);
+        assert_eq!(app.diff_scroll, 21);
+        // A header and the hunk header come before the 30 lines
+        press(&mut app, KeyCode::End);
+        assert_eq!(app.diff_scroll, 31);
+        press(&mut app, KeyCode::Home);
+        assert_eq!(app.diff_scroll, 0);
+        press(&mut app, KeyCode::Char('k'));
+        assert_eq!(app.diff_scroll, 0);
+
+        app.diff_scroll = 5;
+        app.parse_diff();
+        assert_eq!(app.diff_scroll, 0);
+    }
+
+    #[test]
+    fn test_diff_scroll_clamps_to_side_by_side_rows() {
+        let commits = create_test_commits();
+        let mut app = App::new(commits, "main".to_string(), None);
+        // Ten deletions paired with ten additions
+        let removed: String = (1..=10).map(|i| format!("-old {}\n", i)).collect();
+        let added: String = (1..=10).map(|i| format!("+new {}\n", i)).collect();
+        app.diff_content = format!(
+            "diff --git a/f b/f\n--- a/f\n+++ b/f\n@@ -1,10 +1,10 @@\n{}{}",
+            removed, added
+        );
+        app.parse_diff();
+        app.view_mode = ViewMode::Diff;
+        app.side_by_side = true;
+        let end = KeyEvent::new(KeyCode::End, KeyModifiers::NONE);
+
+        // Too narrow: the unified diff is shown
+        app.terminal_width = 80;
+        app.handle_key(end);
+        assert_eq!(app.diff_scroll, 21);
+
+        app.terminal_width = 120;
+        app.handle_key(end);
+        assert_eq!(app.diff_scroll, 11);
+        let rows = DiffParser::split_rows(&app.diff_files).len();
+        assert_eq!(app.diff_scroll as usize, rows - 1);
+    }
+
     #[test]
     fn test_search_toggle() {
         let commits = create_test_commits();
@@ -1635,12 +2080,14 @@ mod tests {
     }
 
     #[test]
-    fn test_app_new_with_repo_path() {
+    fn test_app_new_with_vcs() {
         let commits = create_test_commits();
-        let repo_path = Some(std::path::PathBuf::from("/test/repo"));
-        let app = App::new(commits, "main".to_string(), repo_path);
+        let vcs: Box<dyn Vcs> = Box::new(openisl_git::GitBackend::new("/test/repo"));
+        let app = App::new(commits, "main".to_string(), Some(vcs));
 
-        assert_eq!(app.repo_path, Some(std::path::PathBuf::from("/test/repo")));
+        let vcs = app.vcs.as_ref().unwrap();
+        assert_eq!(vcs.name(), "git");
+        assert_eq!(vcs.repo_path(), std::path::Path::new("/test/repo"));
     }
 
     #[test]
@@ -2001,7 +2448,7 @@ mod tests {
         let commits = create_test_commits();
         let mut app = App::new(commits, "main".to_string(), None);
 
-        app.repo_path = None;
+        app.vcs = None;
         app.stage_all_files();
         assert_eq!(app.status_message, "No repository path available");
     }
@@ -2011,7 +2458,7 @@ mod tests {
         let commits = create_test_commits();
         let mut app = App::new(commits, "main".to_string(), None);
 
-        app.repo_path = None;
+        app.vcs = None;
         app.unstage_all_files();
         assert_eq!(app.status_message, "No repository path available");
     }
@@ -2022,18 +2469,9 @@ mod tests {
         let mut app = App::new(commits, "main".to_string(), None);
 
         app.files = vec![
-            FileStatus {
-                path: "file1.rs".to_string(),
-                status: openisl_git::StatusType::Modified,
-            },
-            FileStatus {
-                path: "file2.rs".to_string(),
-                status: openisl_git::StatusType::Added,
-            },
-            FileStatus {
-                path: "file3.rs".to_string(),
-                status: openisl_git::StatusType::Untracked,
-            },
+            FileStatus::new("file1.rs", openisl_git::StatusType::Modified),
+            FileStatus::new("file2.rs", openisl_git::StatusType::Added),
+            FileStatus::new("file3.rs", openisl_git::StatusType::Untracked),
         ];
 
         assert_eq!(app.selected_file_index, 0);
@@ -2051,6 +2489,34 @@ mod tests {
         assert_eq!(app.selected_file_index, 0);
     }
 
+    #[test]
+    fn test_toggle_partly_staged_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path();
+        let git = |args: &[&str]| openisl_git::command::run(args, Some(path)).unwrap();
+        git(&["init", "-b", "main"]);
+        git(&["config", "user.email", "test@example.com"]);
+        git(&["config", "user.name", "Test User"]);
+        std::fs::write(path.join("a.txt"), "one\n").unwrap();
+        git(&["add", "a.txt"]);
+        git(&["commit", "-m", "Add a"]);
+        std::fs::write(path.join("a.txt"), "two\n").unwrap();
+        git(&["add", "a.txt"]);
+        std::fs::write(path.join("a.txt"), "three\n").unwrap();
+
+        let vcs: Box<dyn Vcs> = Box::new(openisl_git::GitBackend::new(path));
+        let mut app = App::new(create_test_commits(), "main".to_string(), Some(vcs));
+        app.active_panel = PanelType::Files;
+        app.refresh_files();
+        assert_eq!(app.files[0].short_code(), "MM");
+
+        // The unstaged half is staged first, then the whole file is unstaged
+        app.toggle_file_stage();
+        assert_eq!(app.files[0].short_code(), "M ");
+        app.toggle_file_stage();
+        assert_eq!(app.files[0].short_code(), " M");
+    }
+
     #[test]
     fn test_staging_command_in_palette() {
         let commits = create_test_commits();
@@ -2074,7 +2540,7 @@ mod tests {
         let commits = create_test_commits();
         let mut app = App::new(commits, "main".to_string(), None);
 
-        app.repo_path = None;
+        app.vcs = None;
         app.execute_command("stage_all");
         assert_eq!(app.status_message, "No repository path available");
     }
@@ -2084,7 +2550,7 @@ mod tests {
         let commits = create_test_commits();
         let mut app = App::new(commits, "main".to_string(), None);
 
-        app.repo_path = None;
+        app.vcs = None;
         app.refresh_files();
         assert!(app.files.is_empty());
     }
@@ -2118,4 +2584,591 @@ mod tests {
         app.handle_mouse(mouse_event);
         assert_eq!(app.last_click_position, None);
     }
+
+    #[test]
+    fn test_operation_log_unsupported_backend() {
+        let commits = create_test_commits();
+        let vcs: Box<dyn Vcs> = Box::new(openisl_git::HgBackend::mercurial("/test/repo"));
+        let mut app = App::new(commits, "main".to_string(), Some(vcs));
+
+        app.handle_key(KeyEvent::new(KeyCode::Char('O'), KeyModifiers::NONE));
+        assert_eq!(app.view_mode, ViewMode::OperationLog);
+        assert!(app.operations.is_empty());
+        assert!(app
+            .status_message
+            .contains("does not record an operation log"));
+
+        app.handle_key(KeyEvent::new(KeyCode::Char('u'), KeyModifiers::NONE));
+        assert_eq!(app.status_message, "No operation selected");
+
+        app.handle_key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
+        assert_eq!(app.view_mode, ViewMode::List);
+    }
+
+    #[test]
+    fn test_operation_log_undo_and_redo() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path();
+        let git = |args: &[&str]| openisl_git::command::run(args, Some(path)).unwrap();
+        git(&["init", "-b", "main"]);
+        git(&["config", "user.email", "test@example.com"]);
+        git(&["config", "user.name", "Test User"]);
+        git(&["commit", "--allow-empty", "-m", "First"]);
+
+        let vcs: Box<dyn Vcs> = Box::new(openisl_git::GitBackend::new(path));
+        std::fs::write(path.join("a.txt"), "a\n").unwrap();
+        vcs.stage("a.txt").unwrap();
+        vcs.commit("Second").unwrap();
+        let mut app = App::new(Vec::new(), "main".to_string(), Some(vcs));
+        let head = || git(&["log", "-1", "--format=%s"]).trim().to_string();
+
+        app.handle_key(KeyEvent::new(KeyCode::Char('O'), KeyModifiers::NONE));
+        assert_eq!(app.operations.len(), 1);
+        assert_eq!(app.operations[0].description, "commit Second");
+
+        app.handle_key(KeyEvent::new(KeyCode::Char('u'), KeyModifiers::NONE));
+        assert!(app.status_message.starts_with("Undid operation"));
+        assert_eq!(head(), "First");
+        assert_eq!(app.operations[0].action, "undo");
+
+        app.handle_key(KeyEvent::new(KeyCode::Char('r'), KeyModifiers::NONE));
+        assert_eq!(app.status_message, "Redid the last undone operation");
+        assert_eq!(head(), "Second");
+    }
+
+    #[test]
+    fn test_reflog_view_recovers_lost_commit() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path();
+        let git = |args: &[&str]| openisl_git::command::run(args, Some(path)).unwrap();
+        git(&["init", "-b", "main"]);
+        git(&["config", "user.email", "test@example.com"]);
+        git(&["config", "user.name", "Test User"]);
+        git(&["commit", "--allow-empty", "-m", "First"]);
+        git(&["commit", "--allow-empty", "-m", "Lost"]);
+        git(&["reset", "--hard", "HEAD~1"]);
+
+        let vcs: Box<dyn Vcs> = Box::new(openisl_git::GitBackend::new(path));
+        let mut app = App::new(Vec::new(), "main".to_string(), Some(vcs));
+
+        app.handle_key(KeyEvent::new(KeyCode::Char('L'), KeyModifiers::NONE));
+        assert_eq!(app.view_mode, ViewMode::Reflog);
+        assert_eq!(app.reflog.len(), 3);
+        assert_eq!(app.reflog[0].action, "reset");
+
+        app.handle_key(KeyEvent::new(KeyCode::Char('j'), KeyModifiers::NONE));
+        assert_eq!(app.reflog[1].action, "commit");
+        assert_eq!(app.reflog[1].description, "Lost");
+
+        // Reset main back onto it
+        app.handle_key(KeyEvent::new(KeyCode::Char('r'), KeyModifiers::NONE));
+        assert!(app.status_message.starts_with("Reset to"));
+        let head = git(&["log", "-1", "--format=%s"]);
+        assert_eq!(head.trim(), "Lost");
+
+        // The reset is now the newest entry; branch from it
+        assert_eq!(app.reflog[0].action, "reset");
+        app.handle_key(KeyEvent::new(KeyCode::Char('b'), KeyModifiers::NONE));
+        assert_eq!(app.view_mode, ViewMode::InputBranch);
+        for c in "rescue".chars() {
+            app.handle_key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE));
+        }
+        app.handle_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+        assert!(app.status_message.starts_with("Created branch 'rescue'"));
+        assert!(app.branch_target.is_none());
+        let rescue = git(&["log", "-1", "--format=%s", "rescue"]);
+        assert_eq!(rescue.trim(), "Lost");
+    }
+
+    #[test]
+    fn test_split_commit_by_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path();
+        let git = |args: &[&str]| openisl_git::command::run(args, Some(path)).unwrap();
+        git(&["init", "-b", "main"]);
+        git(&["config", "user.email", "test@example.com"]);
+        git(&["config", "user.name", "Test User"]);
+        let numbers: String = (1..=12).map(|n| format!("{}\n", n)).collect();
+        std::fs::write(path.join("f.txt"), &numbers).unwrap();
+        git(&["add", "f.txt"]);
+        git(&["commit", "-m", "Base"]);
+        let changed = numbers
+            .replace("1\n2\n", "one\n2\n")
+            .replace("12\n", "twelve\n");
+        std::fs::write(path.join("f.txt"), changed).unwrap();
+        git(&["commit", "-am", "Two changes"]);
+
+        let vcs: Box<dyn Vcs> = Box::new(openisl_git::GitBackend::new(path));
+        let commits = vcs.log(None, None, false).unwrap();
+        let commits = commits.into_iter().map(Into::into).collect();
+        let mut app = App::new(commits, "main".to_string(), Some(vcs));
+
+        let press = |app: &mut App, code: KeyCode| {
+            app.handle_key(KeyEvent::new(code, KeyModifiers::NONE));
+        };
+        press(&mut app, KeyCode::Char('X'));
+        assert_eq!(app.view_mode, ViewMode::Split);
+        assert_eq!(app.split_files.len(), 1);
+        assert_eq!(app.split_files[0].hunks.len(), 2);
+
+        // Nothing selected yet
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.view_mode, ViewMode::Split);
+
+        // Take "-1 +one" from the first hunk
+        press(&mut app, KeyCode::Char('a'));
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.view_mode, ViewMode::SplitMessage);
+        for c in "Spell out one?".chars() {
+            press(&mut app, KeyCode::Char(c));
+        }
+        press(&mut app, KeyCode::Enter);
+        assert!(app.status_message.ends_with("into 2 commits"));
+        assert_eq!(app.view_mode, ViewMode::List);
+
+        let log = git(&["log", "--format=%s"]);
+        assert_eq!(log.trim(), "Two changes\nSpell out one?\nBase");
+        let first = git(&["show", "HEAD~1:f.txt"]);
+        assert!(first.starts_with("one\n2\n") && first.ends_with("\n12\n"));
+        assert_eq!(app.commits[1].summary, "Spell out one?");
+    }
+
+    #[test]
+    fn test_absorb_from_command_palette() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path();
+        let git = |args: &[&str]| openisl_git::command::run(args, Some(path)).unwrap();
+        git(&["init", "-b", "main"]);
+        git(&["config", "user.email", "test@example.com"]);
+        git(&["config", "user.name", "Test User"]);
+        git(&["commit", "--allow-empty", "-m", "Trunk"]);
+        git(&["checkout", "-b", "feature"]);
+        std::fs::write(path.join("f.txt"), "one\n").unwrap();
+        git(&["add", "f.txt"]);
+        git(&["commit", "-m", "Add f"]);
+        git(&["commit", "--allow-empty", "-m", "Later"]);
+        std::fs::write(path.join("f.txt"), "ONE\n").unwrap();
+        git(&["add", "f.txt"]);
+
+        let vcs: Box<dyn Vcs> = Box::new(openisl_git::GitBackend::new(path));
+        let mut app = App::new(Vec::new(), "feature".to_string(), Some(vcs));
+        app.execute_command("absorb");
+        assert_eq!(app.status_message, "Absorbed 1 hunk(s)");
+        assert_eq!(git(&["show", "HEAD~1:f.txt"]), "ONE\n");
+        assert_eq!(git(&["status", "--porcelain"]), "");
+    }
+
+    #[test]
+    fn test_resolve_conflict_regions() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path();
+        let git = |args: &[&str]| openisl_git::command::run(args, Some(path)).unwrap();
+        git(&["init", "-b", "main"]);
+        git(&["config", "user.email", "test@example.com"]);
+        git(&["config", "user.name", "Test User"]);
+        git(&["config", "merge.conflictStyle", "diff3"]);
+        std::fs::write(path.join("f.txt"), "a\n1\n2\n3\n4\n5\nb\n").unwrap();
+        git(&["add", "f.txt"]);
+        git(&["commit", "-m", "Base"]);
+        git(&["checkout", "-b", "topic"]);
+        std::fs::write(path.join("f.txt"), "A-topic\n1\n2\n3\n4\n5\nB-topic\n").unwrap();
+        git(&["commit", "-am", "Topic"]);
+        git(&["checkout", "main"]);
+        std::fs::write(path.join("f.txt"), "A-main\n1\n2\n3\n4\n5\nB-main\n").unwrap();
+        git(&["commit", "-am", "Main"]);
+        assert!(openisl_git::command::run(&["merge", "topic"], Some(path)).is_err());
+
+        let vcs: Box<dyn Vcs> = Box::new(openisl_git::GitBackend::new(path));
+        let mut app = App::new(Vec::new(), "main".to_string(), Some(vcs));
+        let press = |app: &mut App, code: KeyCode| {
+            app.handle_key(KeyEvent::new(code, KeyModifiers::NONE));
+        };
+        press(&mut app, KeyCode::Char('M'));
+        assert_eq!(app.view_mode, ViewMode::Resolve);
+        assert_eq!(app.conflict_file.as_ref().unwrap().region_count(), 2);
+
+        // Theirs for the first region moves on to the second
+        press(&mut app, KeyCode::Char('t'));
+        assert_eq!(app.selected_conflict_region, 1);
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.view_mode, ViewMode::Resolve);
+        assert_eq!(app.status_message, "1 conflict(s) left to resolve");
+
+        // Edit the second by hand, starting from ours
+        press(&mut app, KeyCode::Char('e'));
+        assert_eq!(app.view_mode, ViewMode::ResolveEdit);
+        assert_eq!(app.conflict_edit, "B-main\n");
+        press(&mut app, KeyCode::Backspace);
+        for c in "+topic".chars() {
+            press(&mut app, KeyCode::Char(c));
+        }
+        app.handle_key(KeyEvent::new(KeyCode::Char('s'), KeyModifiers::CONTROL));
+        assert_eq!(app.view_mode, ViewMode::Resolve);
+
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.view_mode, ViewMode::List);
+        assert_eq!(
+            app.status_message,
+            "Resolved f.txt; no conflicts left (N continues the merge)"
+        );
+        assert_eq!(
+            std::fs::read_to_string(path.join("f.txt")).unwrap(),
+            "A-topic\n1\n2\n3\n4\n5\nB-main+topic\n"
+        );
+        assert_eq!(git(&["diff", "--name-only", "--diff-filter=U"]), "");
+    }
+
+    #[test]
+    fn test_continue_and_abort_operations() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path();
+        let git = |args: &[&str]| openisl_git::command::run(args, Some(path));
+        git(&["init", "-b", "main"]).unwrap();
+        git(&["config", "user.email", "test@example.com"]).unwrap();
+        git(&["config", "user.name", "Test User"]).unwrap();
+        std::fs::write(path.join("f.txt"), "base\n").unwrap();
+        git(&["add", "f.txt"]).unwrap();
+        git(&["commit", "-m", "Base"]).unwrap();
+        git(&["checkout", "-b", "topic"]).unwrap();
+        std::fs::write(path.join("f.txt"), "topic\n").unwrap();
+        git(&["commit", "-am", "Topic"]).unwrap();
+        git(&["checkout", "main"]).unwrap();
+        std::fs::write(path.join("f.txt"), "main\n").unwrap();
+        git(&["commit", "-am", "Main"]).unwrap();
+        assert!(git(&["merge", "topic"]).is_err());
+
+        let vcs: Box<dyn Vcs> = Box::new(openisl_git::GitBackend::new(path));
+        let mut app = App::new(Vec::new(), "main".to_string(), Some(vcs));
+        assert!(matches!(app.repo_state, RepoState::Merging { .. }));
+        let press = |app: &mut App, c: char| {
+            app.handle_key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE));
+        };
+
+        // Conflicts stop the merge from being committed
+        press(&mut app, 'N');
+        assert!(app.status_message.starts_with("Error continuing"));
+        assert!(matches!(app.repo_state, RepoState::Merging { .. }));
+
+        std::fs::write(path.join("f.txt"), "main and topic\n").unwrap();
+        git(&["add", "f.txt"]).unwrap();
+        press(&mut app, 'N');
+        assert_eq!(app.status_message, "Finished the merge");
+        assert_eq!(app.repo_state, RepoState::Clean);
+        assert!(git(&["rev-parse", "HEAD^2"]).is_ok());
+
+        git(&["checkout", "-b", "other", "HEAD~1"]).unwrap();
+        assert!(git(&["cherry-pick", "topic"]).is_err());
+        app.refresh_sync_state();
+        press(&mut app, 'Z');
+        assert_eq!(app.status_message, "Aborted the cherry-pick");
+        assert_eq!(git(&["status", "--porcelain"]).unwrap(), "");
+    }
+
+    #[test]
+    fn test_bisect_mode_marks_commits() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path();
+        let git = |args: &[&str]| openisl_git::command::run(args, Some(path)).unwrap();
+        git(&["init", "-b", "main"]);
+        git(&["config", "user.email", "test@example.com"]);
+        git(&["config", "user.name", "Test User"]);
+        for i in 1..=4 {
+            git(&["commit", "--allow-empty", "-m", &format!("Commit {}", i)]);
+        }
+        let commit3 = git(&["rev-parse", "HEAD~1"]).trim().to_string();
+
+        let vcs: Box<dyn Vcs> = Box::new(openisl_git::GitBackend::new(path));
+        let mut app = App::new(Vec::new(), "main".to_string(), Some(vcs));
+        let press = |app: &mut App, c: char| {
+            app.handle_key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE));
+        };
+        press(&mut app, 'B');
+        assert_eq!(app.view_mode, ViewMode::Bisect);
+        assert!(app.bisect.is_none());
+
+        // The tip is bad and the root good; the middle commit is checked out
+        press(&mut app, 'b');
+        app.go_to_end();
+        press(&mut app, 'g');
+        let state = app.bisect.clone().unwrap();
+        assert_eq!(state.candidates.len(), 3);
+        let current = state.current.clone().unwrap();
+        assert_eq!(app.selected_commit().unwrap().hash, current);
+        assert!(matches!(app.repo_state, RepoState::Bisecting { .. }));
+
+        // Commit 3 introduced the bug
+        while app.bisect.as_ref().unwrap().first_bad.is_none() {
+            let current = app.bisect.as_ref().unwrap().current.clone().unwrap();
+            press(&mut app, if current == commit3 { 'b' } else { 'g' });
+        }
+        assert_eq!(
+            app.bisect.as_ref().unwrap().first_bad,
+            Some(commit3.clone())
+        );
+        assert_eq!(app.selected_commit().unwrap().hash, commit3);
+        assert!(app.status_message.ends_with("is the first bad commit"));
+
+        press(&mut app, 'r');
+        assert!(app.bisect.is_none());
+        assert_eq!(app.view_mode, ViewMode::List);
+        assert_eq!(app.repo_state, RepoState::Clean);
+        assert_eq!(git(&["log", "-1", "--format=%s"]).trim(), "Commit 4");
+    }
+
+    #[test]
+    fn test_blame_view_digs_past_reformat() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path();
+        let git = |args: &[&str]| openisl_git::command::run(args, Some(path)).unwrap();
+        git(&["init", "-b", "main"]);
+        git(&["config", "user.email", "test@example.com"]);
+        git(&["config", "user.name", "Test User"]);
+        std::fs::write(path.join("a.rs"), "fn a() {\nx();\n}\n").unwrap();
+        git(&["add", "a.rs"]);
+        git(&["commit", "-m", "Add a"]);
+        let first = git(&["rev-parse", "HEAD"]).trim().to_string();
+        std::fs::write(path.join("a.rs"), "fn a() {\n    x();\n}\n").unwrap();
+        git(&["commit", "-am", "Reformat"]);
+        let second = git(&["rev-parse", "HEAD"]).trim().to_string();
+
+        let vcs: Box<dyn Vcs> = Box::new(openisl_git::GitBackend::new(path));
+        let mut app = App::new(Vec::new(), "main".to_string(), Some(vcs));
+        let press = |app: &mut App, code: KeyCode| {
+            app.handle_key(KeyEvent::new(code, KeyModifiers::NONE));
+        };
+        // Nothing is selected in the Files panel, so the path is asked for
+        press(&mut app, KeyCode::Char('w'));
+        assert_eq!(app.view_mode, ViewMode::PathInput);
+        for c in "a.rs".chars() {
+            press(&mut app, KeyCode::Char(c));
+        }
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.view_mode, ViewMode::Blame);
+        let blame = app.blame.as_ref().unwrap();
+        assert_eq!(blame.lines[1].commit, second);
+        assert_eq!(blame.lines[0].commit, first);
+
+        // The reindented line, at the reformat's parent
+        press(&mut app, KeyCode::Char('j'));
+        press(&mut app, KeyCode::Char('p'));
+        let blame = app.blame.as_ref().unwrap();
+        assert_eq!(blame.options.revision.as_deref(), Some(first.as_str()));
+        assert_eq!(blame.lines[1].content, "x();");
+        assert_eq!(app.selected_blame_line, 1);
+        press(&mut app, KeyCode::Backspace);
+        assert_eq!(app.blame.as_ref().unwrap().options.revision, None);
+
+        // Ignoring whitespace sees through the reformat too
+        press(&mut app, KeyCode::Char('w'));
+        let blame = app.blame.as_ref().unwrap();
+        assert!(blame.options.ignore_whitespace);
+        assert_eq!(blame.lines[1].commit, first);
+
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.view_mode, ViewMode::Details);
+        assert_eq!(app.selected_commit().unwrap().hash, first);
+    }
+
+    #[test]
+    fn test_file_history_view_follows_rename() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path();
+        let git = |args: &[&str]| openisl_git::command::run(args, Some(path)).unwrap();
+        git(&["init", "-b", "main"]);
+        git(&["config", "user.email", "test@example.com"]);
+        git(&["config", "user.name", "Test User"]);
+        std::fs::write(path.join("old.rs"), "fn a() {}\nfn b() {}\nfn c() {}\n").unwrap();
+        git(&["add", "old.rs"]);
+        git(&["commit", "-m", "Add old"]);
+        git(&["mv", "old.rs", "new.rs"]);
+        git(&["commit", "-m", "Rename"]);
+        std::fs::write(path.join("new.rs"), "fn a() {}\nfn b() {}\nfn d() {}\n").unwrap();
+        git(&["commit", "-am", "Edit new"]);
+
+        let vcs: Box<dyn Vcs> = Box::new(openisl_git::GitBackend::new(path));
+        let mut app = App::new(Vec::new(), "main".to_string(), Some(vcs));
+        let press = |app: &mut App, code: KeyCode| {
+            app.handle_key(KeyEvent::new(code, KeyModifiers::NONE));
+        };
+        press(&mut app, KeyCode::Char('H'));
+        assert_eq!(app.view_mode, ViewMode::PathInput);
+        assert_eq!(app.path_action, PathAction::FileHistory);
+        for c in "new.rs".chars() {
+            press(&mut app, KeyCode::Char(c));
+        }
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.view_mode, ViewMode::FileHistory);
+        let summaries: Vec<&str> = app
+            .file_history
+            .iter()
+            .map(|v| v.commit.summary.as_str())
+            .collect();
+        assert_eq!(summaries, ["Edit new", "Rename", "Add old"]);
+        assert!(app.file_history_text.contains("+fn d() {}"));
+
+        // Step back to the full content before the rename
+        press(&mut app, KeyCode::Char('['));
+        press(&mut app, KeyCode::Char('['));
+        assert!(app.file_history_content);
+        assert_eq!(app.selected_file_version, 2);
+        assert_eq!(app.file_history[2].path, "old.rs");
+        assert_eq!(app.file_history_text, "fn a() {}\nfn b() {}\nfn c() {}\n");
+        press(&mut app, KeyCode::Char('['));
+        assert_eq!(app.status_message, "This is the oldest version");
+        press(&mut app, KeyCode::Char('v'));
+        assert!(app.file_history_text.contains("+fn c() {}"));
+
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.view_mode, ViewMode::Details);
+        assert_eq!(app.selected_commit().unwrap().summary, "Add old");
+
+        // From the diff of a commit, the history of its first file
+        app.view_mode = ViewMode::Diff;
+        app.diff_content = git(&["show", "--format=", "HEAD"]);
+        press(&mut app, KeyCode::Char('H'));
+        assert_eq!(app.view_mode, ViewMode::FileHistory);
+        assert_eq!(app.file_history.len(), 3);
+    }
+
+    #[test]
+    fn test_obsolete_marker_and_restack() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path();
+        let git = |args: &[&str]| openisl_git::command::run(args, Some(path)).unwrap();
+        git(&["init", "-b", "main"]);
+        git(&["config", "user.email", "test@example.com"]);
+        git(&["config", "user.name", "Test User"]);
+        git(&["commit", "--allow-empty", "-m", "Trunk"]);
+        git(&["checkout", "-b", "part1"]);
+        git(&["commit", "--allow-empty", "-m", "Part 1"]);
+        git(&["checkout", "-b", "part2"]);
+        git(&["commit", "--allow-empty", "-m", "Part 2"]);
+        let part2 = git(&["rev-parse", "HEAD"]).trim().to_string();
+        git(&["checkout", "part1"]);
+        git(&["commit", "--amend", "--allow-empty", "-m", "Part 1 (v2)"]);
+
+        let vcs: Box<dyn Vcs> = Box::new(openisl_git::GitBackend::new(path));
+        let mut app = App::new(Vec::new(), "part1".to_string(), Some(vcs));
+        assert_eq!(app.obsolete_commits, HashSet::from([part2]));
+
+        app.handle_key(KeyEvent::new(KeyCode::Char('T'), KeyModifiers::NONE));
+        assert_eq!(app.status_message, "Restacked 1 commit(s)");
+        assert!(app.obsolete_commits.is_empty());
+        let parent = git(&["log", "-1", "--format=%s", "part2~1"]);
+        assert_eq!(parent.trim(), "Part 1 (v2)");
+    }
+
+    #[test]
+    fn test_error_status_shows_git_error_kind() {
+        use super::handlers::commit_ops::error_status;
+        use anyhow::Context;
+
+        let err = Err::<(), _>(openisl_git::GitError::NothingToCommit)
+            .context("Failed to commit")
+            .unwrap_err();
+        assert_eq!(
+            error_status("Error committing", &err),
+            "Error committing: nothing to commit (stage some changes first)"
+        );
+
+        let err = anyhow::anyhow!("plain failure");
+        assert_eq!(
+            error_status("Error committing", &err),
+            "Error committing: plain failure"
+        );
+    }
+
+    #[test]
+    fn test_filter_log_query() {
+        let commits = create_test_commits();
+        let mut app = App::new(commits, "main".to_string(), None);
+
+        app.filter_mode = FilterMode::Author;
+        app.filter_input = "alice".to_string();
+        let query = app.filter_log_query();
+        assert_eq!(query.author.as_deref(), Some("alice"));
+        assert!(query.fixed_strings && query.ignore_case);
+
+        app.filter_mode = FilterMode::Date;
+        app.filter_input = "2024-01-10".to_string();
+        let query = app.filter_log_query();
+        assert_eq!(query.since.as_deref(), Some("2024-01-10 00:00:00"));
+        assert_eq!(query.until.as_deref(), Some("2024-01-10 23:59:59"));
+
+        app.filter_input = "..2024-02-01".to_string();
+        let query = app.filter_log_query();
+        assert_eq!(query.since, None);
+        assert_eq!(query.until.as_deref(), Some("2024-02-01"));
+    }
+
+    #[test]
+    fn test_filter_queries_repository() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path();
+        let git = |args: &[&str]| openisl_git::command::run(args, Some(path)).unwrap();
+        git(&["init", "-b", "main"]);
+        git(&["config", "user.email", "test@example.com"]);
+        git(&["config", "user.name", "Test User"]);
+        git(&["commit", "--allow-empty", "-m", "Old change"]);
+        git(&["commit", "--allow-empty", "-m", "Fix the (parser)"]);
+
+        let vcs: Box<dyn Vcs> = Box::new(openisl_git::GitBackend::new(path));
+        // Only one commit loaded; the filter still searches the repository
+        let loaded = vcs.log(Some(1), None, false).unwrap();
+        let mut app = App::new(
+            loaded.into_iter().map(Into::into).collect(),
+            "main".to_string(),
+            Some(vcs),
+        );
+
+        app.filter_mode = FilterMode::Message;
+        app.filter_input = "old".to_string();
+        app.apply_filter();
+        assert_eq!(app.filtered_commits.len(), 1);
+        assert_eq!(app.filtered_commits[0].summary, "Old change");
+    }
+
+    #[test]
+    fn test_revset_filter() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path();
+        let git = |args: &[&str]| openisl_git::command::run(args, Some(path)).unwrap();
+        git(&["init", "-b", "main"]);
+        git(&["config", "user.email", "test@example.com"]);
+        git(&["config", "user.name", "Test User"]);
+        git(&["commit", "--allow-empty", "-m", "Published"]);
+        git(&["update-ref", "refs/remotes/origin/main", "HEAD"]);
+        git(&["commit", "--allow-empty", "-m", "Local work"]);
+
+        let vcs: Box<dyn Vcs> = Box::new(openisl_git::GitBackend::new(path));
+        let loaded = vcs.log(None, None, false).unwrap();
+        let mut app = App::new(
+            loaded.into_iter().map(Into::into).collect(),
+            "main".to_string(),
+            Some(vcs),
+        );
+
+        app.view_mode = ViewMode::Filter;
+        for _ in 0..3 {
+            app.handle_key(KeyEvent::new(KeyCode::Tab, KeyModifiers::NONE));
+        }
+        assert_eq!(app.filter_mode, FilterMode::Revset);
+        for c in "draft()".chars() {
+            app.handle_key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE));
+        }
+        assert_eq!(app.filter_input, "draft()");
+        assert_eq!(app.filter_mode, FilterMode::Revset);
+
+        app.handle_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+        assert!(app.is_filtering);
+        assert_eq!(app.filtered_commits.len(), 1);
+        assert_eq!(app.filtered_commits[0].summary, "Local work");
+
+        app.filter_input = "draft(".to_string();
+        app.view_mode = ViewMode::Filter;
+        app.handle_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+        assert!(!app.is_filtering);
+        assert_eq!(app.filtered_commits.len(), 2);
+        assert!(app.status_message.starts_with("Revset:"));
+    }
 }
@@ -104,17 +104,6 @@ pub(crate) fn render_files_panel(app: &App) -> impl Widget + '_ {
         .files
         .iter()
         .map(|file| {
-            let status_char = match file.status {
-                openisl_git::StatusType::Modified => "M",
-                openisl_git::StatusType::Added => "A",
-                openisl_git::StatusType::Deleted => "D",
-                openisl_git::StatusType::Untracked => "?",
-                openisl_git::StatusType::ModifiedStaged => "M*",
-                openisl_git::StatusType::AddedStaged => "A*",
-                openisl_git::StatusType::DeletedStaged => "D*",
-                openisl_git::StatusType::Renamed => "R",
-                openisl_git::StatusType::Conflicted => "C",
-            };
             let status_color = match file.status {
                 openisl_git::StatusType::Modified => app.theme.file_status_modified,
                 openisl_git::StatusType::Added => app.theme.file_status_added,
@@ -127,7 +116,14 @@ pub(crate) fn render_files_panel(app: &App) -> impl Widget + '_ {
                 openisl_git::StatusType::Conflicted => app.theme.error, // Conflicted files
             };
 
-            let content = format!("{} {}", status_char, file.path);
+            // Index and worktree columns, as in `git status --short`
+            let mut content = format!("{} {}", file.short_code(), file.path);
+            if let Some(ref original) = file.original_path {
+                content.push_str(&format!(" <- {}", original));
+            }
+            if let Some(conflict) = file.conflict {
+                content.push_str(&format!(" ({})", conflict.description()));
+            }
             let is_selected = app.selected_file_index
                 == app
                     .files
@@ -192,6 +188,56 @@ pub(crate) fn render_branches_panel(app: &App) -> impl Widget + '_ {
     list
 }
 
+/// Flag a commit that still sits on a rewritten parent
+fn mark_obsolete<'a>(app: &App, mut line: Line<'a>, index: usize) -> Line<'a> {
+    let obsolete = app
+        .tree
+        .nodes()
+        .get(index)
+        .is_some_and(|node| app.obsolete_commits.contains(&node.commit.hash));
+    if obsolete {
+        line.spans.push(Span::styled(
+            " (obsolete)",
+            Style::default().fg(app.theme.warning),
+        ));
+    }
+    line
+}
+
+/// Colour a commit by its bisect status: good (or below a good commit),
+/// bad, skipped, or the one being tested
+fn mark_bisect<'a>(app: &App, mut line: Line<'a>, index: usize) -> Line<'a> {
+    let (Some(state), Some(node)) = (&app.bisect, app.tree.nodes().get(index)) else {
+        return line;
+    };
+    let hash = &node.commit.hash;
+    let (color, tag) = if state.first_bad.as_ref() == Some(hash) {
+        (app.theme.error, Some(" [first bad]"))
+    } else if state.bad.as_ref() == Some(hash) {
+        (app.theme.error, Some(" [bad]"))
+    } else if state.skipped.contains(hash) {
+        (app.theme.warning, Some(" [skipped]"))
+    } else if state.current.as_ref() == Some(hash) {
+        (app.theme.accent, Some(" [testing]"))
+    } else if state.good.contains(hash) {
+        (app.theme.success, Some(" [good]"))
+    } else if !state.candidates.is_empty() && !state.candidates.contains(hash) {
+        (app.theme.success, None)
+    } else {
+        return line;
+    };
+    for span in &mut line.spans {
+        span.style = span.style.fg(color);
+    }
+    if let Some(tag) = tag {
+        line.spans.push(Span::styled(
+            tag,
+            Style::default().fg(color).add_modifier(Modifier::BOLD),
+        ));
+    }
+    line
+}
+
 pub(crate) fn render_commits_panel(app: &App, area: Rect) -> impl Widget + '_ {
     let panel_height = area.height.saturating_sub(2) as usize;
     let visible_count = panel_height.max(1);
@@ -209,6 +255,8 @@ pub(crate) fn render_commits_panel(app: &App, area: Rect) -> impl Widget + '_ {
             let global_index = app.scroll_offset + i;
             let is_selected = global_index == app.selected_index;
 
+            let line = mark_obsolete(app, line, global_index);
+            let line = mark_bisect(app, line, global_index);
             let styled_line = if is_selected {
                 line.style(
                     Style::default()
@@ -251,9 +299,12 @@ pub(crate) fn render_main_content(app: &App, area: Rect, frame: &mut ratatui::Fr
 
     let title = Paragraph::new(format!(
         "openisl - {} - {}",
-        app.repo_path
+        app.vcs
             .as_ref()
-            .and_then(|p| p.file_name().map(|n| n.to_string_lossy().to_string()))
+            .and_then(|v| v
+                .repo_path()
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string()))
             .unwrap_or_else(|| "Unknown".to_string()),
         app.current_branch
     ))
@@ -264,6 +315,7 @@ pub(crate) fn render_main_content(app: &App, area: Rect, frame: &mut ratatui::Fr
     )
     .alignment(Alignment::Left);
     title.render(chunks[0], frame.buffer_mut());
+    render_repo_state_banner(app, chunks[0], frame);
 
     let content_height = chunks[1].height.saturating_sub(2) as usize;
     let visible_count = content_height.max(1);
@@ -280,6 +332,7 @@ pub(crate) fn render_main_content(app: &App, area: Rect, frame: &mut ratatui::Fr
         .map(|(i, line)| {
             let global_index = app.scroll_offset + i;
             let is_selected = global_index == app.selected_index;
+            let line = mark_obsolete(app, line, global_index);
 
             if is_selected {
                 line.style(
@@ -319,6 +372,41 @@ pub(crate) fn render_main_content(app: &App, area: Rect, frame: &mut ratatui::Fr
     status_widget.render(chunks[2], frame.buffer_mut());
 }
 
+/// The operation in progress and its actions, under the title
+fn render_repo_state_banner(app: &App, area: Rect, frame: &mut ratatui::Frame) {
+    if app.repo_state.is_clean() || area.height < 2 {
+        return;
+    }
+    let mut text = match app.bisect {
+        Some(ref bisect) => format!(" Bisecting: {} - B: mark commits | ", bisect.summary()),
+        None if app.has_conflicts => format!(" {} - resolve conflicts (M), then ", app.repo_state),
+        None => format!(" {} - ", app.repo_state),
+    };
+    let mut actions = Vec::new();
+    if app.repo_state.can_continue() {
+        actions.push("N: continue");
+    }
+    if app.repo_state.can_skip() {
+        actions.push("K: skip");
+    }
+    actions.push("Z: abort");
+    text.push_str(&actions.join(" | "));
+    text.push(' ');
+
+    let banner = Paragraph::new(text).style(
+        Style::default()
+            .fg(Color::Black)
+            .bg(app.theme.warning)
+            .add_modifier(Modifier::BOLD),
+    );
+    let row = Rect {
+        y: area.y + 1,
+        height: 1,
+        ..area
+    };
+    banner.render(row, frame.buffer_mut());
+}
+
 pub(crate) fn render_details_view(app: &App, frame: &mut ratatui::Frame) {
     let chunks = Layout::default()
         .direction(Direction::Vertical)
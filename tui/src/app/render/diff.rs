@@ -3,6 +3,16 @@
 //! Contains rendering logic for the diff, input, and search views.
 use super::super::*;
 
+/// Narrowest diff pane (inside its borders) shown side by side; narrower
+/// terminals fall back to the unified diff
+const MIN_SIDE_BY_SIDE_WIDTH: u16 = 100;
+
+/// Whether the diff is laid out side by side in a pane `width` columns wide
+/// (inside its borders)
+pub(crate) fn shows_side_by_side(app: &App, width: u16) -> bool {
+    app.side_by_side && !app.diff_files.is_empty() && width >= MIN_SIDE_BY_SIDE_WIDTH
+}
+
 pub(crate) fn render_diff_view(app: &App, frame: &mut ratatui::Frame) {
     let chunks = Layout::default()
         .direction(Direction::Vertical)
@@ -35,32 +45,57 @@ pub(crate) fn render_diff_view(app: &App, frame: &mut ratatui::Frame) {
     stats_widget.render(chunks[1], frame.buffer_mut());
 
     let dark_theme = app.theme.name == "dark";
+    let area = chunks[2];
+    // Borders take a column on each side
+    let width = area.width.saturating_sub(2);
+    let side_by_side = shows_side_by_side(app, width);
 
-    let diff_widget = if app.diff_content.is_empty() {
-        Paragraph::new(vec![Line::from(
+    let (title, lines) = if app.diff_content.is_empty() {
+        let lines = vec![Line::from(
             "No diff available. Use 'openisl diff' command for staged/working changes.",
-        )])
-        .style(Style::default().fg(app.theme.text))
+        )];
+        ("Diff View".to_string(), lines)
+    } else if side_by_side {
+        let rows = DiffParser::split_rows(&app.diff_files);
+        let lines =
+            DiffParser::to_styled_split_lines(&rows, width as usize, app.word_diff, dark_theme);
+        ("Diff View (old | new)".to_string(), lines)
     } else {
-        let parsed_lines = DiffParser::parse(&app.diff_content);
-        let styled_lines = DiffParser::to_styled_lines(&parsed_lines, dark_theme);
-        Paragraph::new(styled_lines).style(Style::default().fg(app.theme.text))
+        let parsed_lines = match app.diff_files.is_empty() {
+            true => DiffParser::parse(&app.diff_content),
+            false => DiffParser::from_file_diffs(&app.diff_files),
+        };
+        let lines = match app.word_diff {
+            true => DiffParser::to_styled_lines_word_diff(&parsed_lines, dark_theme),
+            false => DiffParser::to_styled_lines(&parsed_lines, dark_theme),
+        };
+        let title = match app.side_by_side && !app.diff_files.is_empty() {
+            true => format!(
+                "Diff View (unified: side by side needs {} columns)",
+                MIN_SIDE_BY_SIDE_WIDTH + 2
+            ),
+            false => "Diff View".to_string(),
+        };
+        (title, lines)
     };
 
-    diff_widget
+    Paragraph::new(lines)
+        .style(Style::default().fg(app.theme.text))
+        .scroll((app.diff_scroll, 0))
         .block(
             Block::default()
-                .title("Diff View")
+                .title(title)
                 .borders(Borders::ALL)
                 .border_type(BorderType::Plain)
                 .style(Style::default().fg(app.theme.border)),
         )
-        .render(chunks[2], frame.buffer_mut());
+        .render(area, frame.buffer_mut());
 
     let help_text = format!(
-        "{}/{}: Back | {}: Help | Theme: {}",
+        "{}/{}: Back | j/k: Scroll | s: Side by side | w: Words {} | {}: Help | Theme: {}",
         app.keybindings.actions.quit,
         app.keybindings.actions.cancel,
+        if app.word_diff { "on" } else { "off" },
         app.keybindings.actions.help,
         app.theme.name()
     );
@@ -1,7 +1,8 @@
 //! Panel rendering
 //!
-//! Contains rendering logic for the command palette, stash view, hunk
-//! staging view, and help/filter/stats overlays.
+//! Contains rendering logic for the command palette, stash view, operation
+//! log, reflog, hunk staging, split, conflict resolution, blame and file
+//! history views, and help/filter/stats overlays.
 use super::super::*;
 use super::render_footer;
 
@@ -100,7 +101,12 @@ pub(crate) fn render_stash_view(app: &App, frame: &mut ratatui::Frame) {
         .iter()
         .enumerate()
         .map(|(i, stash)| {
-            let content = format!("[{}] {} ({})", i, stash.message, stash.name);
+            let content = format!(
+                "[{}] {} ({})",
+                i,
+                stash.message.as_deref().unwrap_or(""),
+                stash.id
+            );
             let is_selected = i == app.selected_stash_index;
             let style = if is_selected {
                 Style::default()
@@ -145,6 +151,125 @@ pub(crate) fn render_stash_view(app: &App, frame: &mut ratatui::Frame) {
     render_footer(app, frame.size(), frame);
 }
 
+pub(crate) fn render_operation_log_view(app: &App, frame: &mut ratatui::Frame) {
+    let area = frame.size();
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(3), Constraint::Length(2)])
+        .split(area);
+
+    let items: Vec<ListItem<'_>> = app
+        .operations
+        .iter()
+        .enumerate()
+        .skip(app.operation_scroll_offset)
+        .map(|(i, operation)| {
+            let style = if i == app.selected_operation_index {
+                Style::default()
+                    .fg(app.theme.selected)
+                    .bg(app.theme.selected_bg)
+            } else {
+                Style::default().fg(app.theme.text)
+            };
+            ListItem::new(Line::from(vec![
+                Span::styled(
+                    format!("{} ", operation.id),
+                    Style::default().fg(app.theme.commit_hash),
+                ),
+                Span::styled(
+                    format!("{} ", operation.timestamp.format("%Y-%m-%d %H:%M")),
+                    Style::default().fg(app.theme.commit_date),
+                ),
+                Span::styled(operation.description.clone(), style),
+            ]))
+        })
+        .collect();
+
+    let title = match app.vcs.as_ref() {
+        Some(vcs) => format!(
+            "Operation Log ({}) - u: undo/restore, r: redo, Esc: back",
+            vcs.name()
+        ),
+        None => "Operation Log".to_string(),
+    };
+    let list = List::new(items).block(
+        Block::default()
+            .title(title)
+            .borders(Borders::ALL)
+            .border_type(BorderType::Plain)
+            .border_style(Style::default().fg(app.theme.border)),
+    );
+
+    frame.render_widget(list, chunks[0]);
+    render_footer(app, area, frame);
+}
+
+pub(crate) fn render_reflog_view(app: &App, frame: &mut ratatui::Frame) {
+    let area = frame.size();
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(3), Constraint::Length(2)])
+        .split(area);
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(45), Constraint::Percentage(55)])
+        .split(rows[0]);
+
+    let items: Vec<ListItem<'_>> = app
+        .reflog
+        .iter()
+        .enumerate()
+        .skip(app.reflog_scroll_offset)
+        .map(|(i, entry)| {
+            let style = if i == app.selected_reflog_index {
+                Style::default()
+                    .fg(app.theme.selected)
+                    .bg(app.theme.selected_bg)
+            } else {
+                Style::default().fg(app.theme.text)
+            };
+            let short_hash = abbreviate_hash(&entry.id);
+            ListItem::new(Line::from(vec![
+                Span::styled(
+                    format!("{} ", short_hash),
+                    Style::default().fg(app.theme.commit_hash),
+                ),
+                Span::styled(
+                    format!("{} ", entry.timestamp.format("%Y-%m-%d %H:%M")),
+                    Style::default().fg(app.theme.commit_date),
+                ),
+                Span::styled(
+                    format!("{} ", entry.action),
+                    Style::default().fg(app.theme.branch_name),
+                ),
+                Span::styled(entry.description.clone(), style),
+            ]))
+        })
+        .collect();
+
+    let list = List::new(items).block(
+        Block::default()
+            .title("Reflog (HEAD) - c: checkout, b: branch, r: reset here, Esc: back")
+            .borders(Borders::ALL)
+            .border_type(BorderType::Plain)
+            .border_style(Style::default().fg(app.theme.border)),
+    );
+
+    let diff_text = Paragraph::new(app.reflog_diff_content.clone())
+        .block(
+            Block::default()
+                .title("Diff")
+                .borders(Borders::ALL)
+                .border_type(BorderType::Plain)
+                .border_style(Style::default().fg(app.theme.border)),
+        )
+        .wrap(ratatui::widgets::Wrap { trim: false });
+
+    frame.render_widget(list, chunks[0]);
+    frame.render_widget(diff_text, chunks[1]);
+    render_footer(app, area, frame);
+}
+
 pub(crate) fn render_hunk_staging_view(app: &App, frame: &mut ratatui::Frame) {
     let chunks = Layout::default()
         .direction(Direction::Vertical)
@@ -237,6 +362,638 @@ pub(crate) fn render_hunk_staging_view(app: &App, frame: &mut ratatui::Frame) {
     help_widget.render(chunks[2], frame.buffer_mut());
 }
 
+pub(crate) fn render_split_view(app: &App, frame: &mut ratatui::Frame) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(2),
+            Constraint::Min(10),
+            Constraint::Length(2),
+        ])
+        .split(frame.size());
+
+    let short_hash = app
+        .split_commit
+        .as_deref()
+        .map(abbreviate_hash)
+        .unwrap_or_default();
+    let file = app.split_files.get(app.selected_split_file);
+    let title = Paragraph::new(format!(
+        "Split {}: {} ({}/{})",
+        short_hash,
+        file.map(|f| f.path.as_str()).unwrap_or_default(),
+        app.selected_split_file + 1,
+        app.split_files.len()
+    ))
+    .style(
+        Style::default()
+            .fg(app.theme.title)
+            .add_modifier(Modifier::BOLD),
+    )
+    .alignment(Alignment::Center);
+    title.render(chunks[0], frame.buffer_mut());
+
+    let mut lines: Vec<Line> = Vec::new();
+    if let Some(file) = file.filter(|f| f.hunks.is_empty()) {
+        let indicator = if file.is_selected { "*" } else { " " };
+        lines.push(Line::from(format!(
+            "{} (binary or mode change: Space takes the whole file)",
+            indicator
+        )));
+    }
+    for (hunk_idx, hunk) in file.iter().flat_map(|f| f.hunks.iter()).enumerate() {
+        lines.push(
+            Line::from(hunk.header.clone()).style(
+                Style::default()
+                    .fg(app.theme.hunk_header)
+                    .add_modifier(Modifier::BOLD),
+            ),
+        );
+        for (line_idx, line) in hunk.lines.iter().enumerate() {
+            let mut style = match line.line_type {
+                HunkLineType::Addition => Style::default().fg(app.theme.diff_added),
+                HunkLineType::Deletion => Style::default().fg(app.theme.diff_removed),
+                HunkLineType::Context => Style::default().fg(app.theme.diff_context),
+            };
+            if hunk_idx == app.selected_hunk_index && line_idx == app.selected_hunk_line_index {
+                style = style.bg(app.theme.selected_bg).add_modifier(Modifier::BOLD);
+            }
+            let indicator = if line.is_selected { "* " } else { "  " };
+            lines.push(Line::from(vec![
+                Span::styled(indicator, style),
+                Span::styled(line.content.clone(), style),
+            ]));
+        }
+    }
+
+    let hunk_list = Paragraph::new(lines)
+        .block(
+            Block::default()
+                .title("Lines for the first commit (*)")
+                .borders(Borders::ALL)
+                .border_type(BorderType::Plain)
+                .style(Style::default().fg(app.theme.border)),
+        )
+        .wrap(ratatui::widgets::Wrap { trim: false });
+    frame.render_widget(hunk_list, chunks[1]);
+
+    let help_text = format!(
+        "j/k: Navigate | Space: Toggle line | a: Toggle hunk | Tab: Next file | Enter: Message | Esc: Cancel | Theme: {}",
+        app.theme.name()
+    );
+    let help_widget = Paragraph::new(help_text)
+        .style(Style::default().fg(app.theme.help))
+        .alignment(Alignment::Center);
+    help_widget.render(chunks[2], frame.buffer_mut());
+}
+
+pub(crate) fn render_split_message_view(app: &App, frame: &mut ratatui::Frame) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(2),
+            Constraint::Length(5),
+            Constraint::Min(10),
+            Constraint::Length(2),
+        ])
+        .split(frame.size());
+
+    let title = Paragraph::new("Split Commit")
+        .style(
+            Style::default()
+                .fg(app.theme.title)
+                .add_modifier(Modifier::BOLD),
+        )
+        .alignment(Alignment::Center);
+    title.render(chunks[0], frame.buffer_mut());
+
+    let prompt = Paragraph::new(
+        "The selected lines become a new commit below the original, which keeps\n\
+         the rest of the change and its message.\n\nMessage for the new commit:",
+    )
+    .style(Style::default().fg(app.theme.text));
+    prompt.render(chunks[1], frame.buffer_mut());
+
+    let input = Paragraph::new(format!("{} |", app.split_message)).style(
+        Style::default()
+            .fg(Color::Green)
+            .add_modifier(Modifier::BOLD),
+    );
+    input.render(chunks[2], frame.buffer_mut());
+
+    let help_text = format!(
+        "{}: Back | {}: Split | Theme: {}",
+        app.keybindings.actions.cancel,
+        app.keybindings.actions.confirm,
+        app.theme.name()
+    );
+    let help_widget = Paragraph::new(help_text)
+        .style(Style::default().fg(app.theme.help))
+        .alignment(Alignment::Center);
+    help_widget.render(chunks[3], frame.buffer_mut());
+}
+
+/// Lines of `text` without their line endings
+fn text_lines(text: &str) -> Vec<Line<'static>> {
+    text.lines()
+        .map(|line| Line::from(line.to_string()))
+        .collect()
+}
+
+pub(crate) fn render_resolve_view(app: &App, frame: &mut ratatui::Frame) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(2),
+            Constraint::Percentage(45),
+            Constraint::Min(6),
+            Constraint::Length(2),
+        ])
+        .split(frame.size());
+    let Some(ref file) = app.conflict_file else {
+        return;
+    };
+    let selected = app.selected_conflict_region;
+    let region = file.regions().nth(selected);
+
+    let title = Paragraph::new(format!(
+        "Resolve {}: conflict {}/{} ({} unresolved)",
+        file.path,
+        selected + 1,
+        file.region_count(),
+        file.unresolved_count()
+    ))
+    .style(
+        Style::default()
+            .fg(app.theme.title)
+            .add_modifier(Modifier::BOLD),
+    )
+    .alignment(Alignment::Center);
+    title.render(chunks[0], frame.buffer_mut());
+
+    // Ours | base | theirs for the region under the cursor
+    if let Some(region) = region {
+        let mut panes = vec![("Ours", Resolution::Ours, &region.ours)];
+        if let Some(ref base) = region.base {
+            panes.push(("Base", Resolution::Base, base));
+        }
+        panes.push(("Theirs", Resolution::Theirs, &region.theirs));
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(vec![Constraint::Ratio(1, panes.len() as u32); panes.len()])
+            .split(chunks[1]);
+        for ((name, resolution, side), column) in panes.into_iter().zip(columns.iter()) {
+            let chosen = match region.resolution {
+                Some(Resolution::Both) => resolution != Resolution::Base,
+                ref chosen => chosen.as_ref() == Some(&resolution),
+            };
+            let label = if side.label.is_empty() {
+                name.to_string()
+            } else {
+                format!("{}: {}", name, side.label)
+            };
+            let border = if chosen {
+                app.theme.success
+            } else {
+                app.theme.border
+            };
+            let pane = Paragraph::new(text_lines(&side.lines.concat()))
+                .style(Style::default().fg(app.theme.text))
+                .block(
+                    Block::default()
+                        .title(if chosen {
+                            format!("{} *", label)
+                        } else {
+                            label
+                        })
+                        .borders(Borders::ALL)
+                        .border_type(BorderType::Plain)
+                        .style(Style::default().fg(border)),
+                );
+            frame.render_widget(pane, *column);
+        }
+    }
+
+    // The whole file as it will be written, scrolled to the region
+    let mut lines: Vec<Line> = Vec::new();
+    let mut region_start = 0;
+    let mut index = 0;
+    for chunk in &file.chunks {
+        match chunk {
+            ConflictChunk::Common(common) => {
+                let style = Style::default().fg(app.theme.diff_context);
+                lines.extend(
+                    common
+                        .iter()
+                        .map(|l| Line::from(l.trim_end().to_string()).style(style)),
+                );
+            }
+            ConflictChunk::Conflict(region) => {
+                let mut style = match region.resolved_text() {
+                    Some(_) => Style::default().fg(app.theme.diff_added),
+                    None => Style::default().fg(app.theme.error),
+                };
+                if index == selected {
+                    region_start = lines.len();
+                    style = style.bg(app.theme.selected_bg);
+                }
+                match region.resolved_text() {
+                    Some(text) if text.is_empty() => {
+                        lines.push(Line::from("(removed)").style(style));
+                    }
+                    Some(text) => {
+                        lines.extend(text_lines(&text).into_iter().map(|l| l.style(style)))
+                    }
+                    None => lines.push(
+                        Line::from(format!(
+                            "<<<<<<< unresolved: {} line(s) ours, {} line(s) theirs",
+                            region.ours.lines.len(),
+                            region.theirs.lines.len()
+                        ))
+                        .style(style),
+                    ),
+                }
+                index += 1;
+            }
+        }
+    }
+    let result = Paragraph::new(lines)
+        .block(
+            Block::default()
+                .title("Result")
+                .borders(Borders::ALL)
+                .border_type(BorderType::Plain)
+                .style(Style::default().fg(app.theme.border)),
+        )
+        .scroll((region_start.saturating_sub(3) as u16, 0));
+    frame.render_widget(result, chunks[2]);
+
+    let help_text = format!(
+        "j/k: Conflict | o/t/b/a: Ours/Theirs/Both/Base | u: Undo | e: Edit | Enter: Write | Esc: Cancel | Theme: {}",
+        app.theme.name()
+    );
+    let help_widget = Paragraph::new(help_text)
+        .style(Style::default().fg(app.theme.help))
+        .alignment(Alignment::Center);
+    help_widget.render(chunks[3], frame.buffer_mut());
+}
+
+pub(crate) fn render_resolve_edit_view(app: &App, frame: &mut ratatui::Frame) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(2),
+            Constraint::Min(10),
+            Constraint::Length(2),
+        ])
+        .split(frame.size());
+
+    let path = app
+        .conflict_file
+        .as_ref()
+        .map(|file| file.path.as_str())
+        .unwrap_or_default();
+    let title = Paragraph::new(format!(
+        "Edit conflict {} in {}",
+        app.selected_conflict_region + 1,
+        path
+    ))
+    .style(
+        Style::default()
+            .fg(app.theme.title)
+            .add_modifier(Modifier::BOLD),
+    )
+    .alignment(Alignment::Center);
+    title.render(chunks[0], frame.buffer_mut());
+
+    let input = Paragraph::new(format!("{}|", app.conflict_edit))
+        .style(Style::default().fg(app.theme.text))
+        .block(
+            Block::default()
+                .title("Text for this region")
+                .borders(Borders::ALL)
+                .border_type(BorderType::Plain)
+                .style(Style::default().fg(app.theme.border)),
+        )
+        .wrap(ratatui::widgets::Wrap { trim: false });
+    frame.render_widget(input, chunks[1]);
+
+    let help_text = format!(
+        "Enter: New line | Ctrl+S: Use this text | Esc: Back | Theme: {}",
+        app.theme.name()
+    );
+    let help_widget = Paragraph::new(help_text)
+        .style(Style::default().fg(app.theme.help))
+        .alignment(Alignment::Center);
+    help_widget.render(chunks[2], frame.buffer_mut());
+}
+
+pub(crate) fn render_blame_view(app: &App, frame: &mut ratatui::Frame) {
+    let area = frame.size();
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(3),
+            Constraint::Length(4),
+            Constraint::Length(2),
+        ])
+        .split(area);
+    let Some(ref blame) = app.blame else {
+        return;
+    };
+
+    let height = chunks[0].height.saturating_sub(2) as usize;
+    let selected = app.selected_blame_line;
+    let offset = (selected + 1).saturating_sub(height.max(1));
+    let language = DiffParser::detect_language(&blame.path);
+    let dark_theme = app.theme.name == "dark";
+    let number_width = blame.lines.len().to_string().len();
+    let items: Vec<ListItem<'_>> = blame
+        .lines
+        .iter()
+        .enumerate()
+        .skip(offset)
+        .take(height)
+        .map(|(i, line)| {
+            // Annotate only the first line of each run from the same commit
+            let first_of_run = i == 0 || blame.lines[i - 1].commit != line.commit;
+            let mut spans = if first_of_run {
+                vec![
+                    Span::styled(
+                        format!("{} ", abbreviate_hash(&line.commit)),
+                        Style::default().fg(app.theme.commit_hash),
+                    ),
+                    Span::styled(
+                        format!("{:<14.14} ", line.author),
+                        Style::default().fg(app.theme.commit_author),
+                    ),
+                    Span::styled(
+                        format!("{} ", line.date.format("%Y-%m-%d")),
+                        Style::default().fg(app.theme.commit_date),
+                    ),
+                ]
+            } else {
+                vec![Span::raw(" ".repeat(35))]
+            };
+            spans.push(Span::styled(
+                format!("{:>number_width$} │ ", line.final_line),
+                Style::default().fg(app.theme.diff_context),
+            ));
+            spans.extend(DiffParser::highlight(&line.content, language, dark_theme));
+            let mut item = Line::from(spans);
+            if i == selected {
+                item = item.style(Style::default().bg(app.theme.selected_bg));
+            }
+            ListItem::new(item)
+        })
+        .collect();
+
+    let mut flags: Vec<&str> = Vec::new();
+    if blame.options.ignore_whitespace {
+        flags.push("-w");
+    }
+    if blame.options.detect_moves {
+        flags.push("-M");
+    }
+    if blame.options.detect_copies {
+        flags.push("-C");
+    }
+    let revision = match blame.options.revision {
+        Some(ref rev) => abbreviate_hash(rev),
+        None => "working tree",
+    };
+    let title = format!(
+        "Blame {} @ {} {}- Enter: commit, p: blame parent, Backspace: back, w/m/c: -w/-M/-C, Esc: back",
+        blame.path,
+        revision,
+        flags.iter().map(|f| format!("{} ", f)).collect::<String>()
+    );
+    let list = List::new(items).block(
+        Block::default()
+            .title(title)
+            .borders(Borders::ALL)
+            .border_type(BorderType::Plain)
+            .border_style(Style::default().fg(app.theme.border)),
+    );
+    frame.render_widget(list, chunks[0]);
+
+    let info = match blame
+        .lines
+        .get(selected)
+        .and_then(|l| blame.commit_of(l).map(|c| (l, c)))
+    {
+        Some((_, commit)) if commit.is_uncommitted() => vec![Line::from("Not committed yet")],
+        Some((line, commit)) => {
+            let mut lines = vec![
+                Line::from(vec![
+                    Span::styled(
+                        format!("{} ", abbreviate_hash(&commit.hash)),
+                        Style::default().fg(app.theme.commit_hash),
+                    ),
+                    Span::styled(
+                        format!("{} <{}> ", commit.author, commit.author_email),
+                        Style::default().fg(app.theme.commit_author),
+                    ),
+                    Span::styled(
+                        commit.date.format("%Y-%m-%d %H:%M").to_string(),
+                        Style::default().fg(app.theme.commit_date),
+                    ),
+                ]),
+                Line::from(commit.summary.clone()),
+            ];
+            if line.original_path != blame.path {
+                lines[1]
+                    .spans
+                    .push(Span::raw(format!(" (from {})", line.original_path)));
+            }
+            lines
+        }
+        None => Vec::new(),
+    };
+    let info = Paragraph::new(info)
+        .style(Style::default().fg(app.theme.text))
+        .block(
+            Block::default()
+                .title("Commit")
+                .borders(Borders::ALL)
+                .border_type(BorderType::Plain)
+                .style(Style::default().fg(app.theme.border)),
+        );
+    frame.render_widget(info, chunks[1]);
+    render_footer(app, area, frame);
+}
+
+pub(crate) fn render_file_history_view(app: &App, frame: &mut ratatui::Frame) {
+    let area = frame.size();
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(3), Constraint::Length(2)])
+        .split(area);
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(40), Constraint::Percentage(60)])
+        .split(rows[0]);
+    let Some(version) = app.file_history.get(app.selected_file_version) else {
+        return;
+    };
+
+    let height = chunks[0].height.saturating_sub(2) as usize;
+    let offset = (app.selected_file_version + 1).saturating_sub(height.max(1));
+    let items: Vec<ListItem<'_>> = app
+        .file_history
+        .iter()
+        .enumerate()
+        .skip(offset)
+        .take(height)
+        .map(|(i, v)| {
+            let mut spans = vec![
+                Span::styled(
+                    format!("{} ", v.commit.short_hash),
+                    Style::default().fg(app.theme.commit_hash),
+                ),
+                Span::styled(
+                    format!("{} ", v.commit.date.format("%Y-%m-%d")),
+                    Style::default().fg(app.theme.commit_date),
+                ),
+                Span::styled(
+                    format!("{} ", v.change.letter()),
+                    Style::default().fg(match v.change {
+                        FileChange::Added => app.theme.success,
+                        FileChange::Deleted => app.theme.error,
+                        FileChange::Modified => app.theme.text,
+                        _ => app.theme.warning,
+                    }),
+                ),
+                Span::raw(v.commit.summary.clone()),
+            ];
+            if let Some(from) = v.change.source() {
+                spans.push(Span::styled(
+                    format!(" ({} → {})", from, v.path),
+                    Style::default().fg(app.theme.accent),
+                ));
+            }
+            let mut item = Line::from(spans);
+            if i == app.selected_file_version {
+                item = item.style(Style::default().bg(app.theme.selected_bg));
+            }
+            ListItem::new(item)
+        })
+        .collect();
+    let list = List::new(items).block(
+        Block::default()
+            .title(format!(
+                "History of {} ({} commits)",
+                app.file_history[0].path,
+                app.file_history.len()
+            ))
+            .borders(Borders::ALL)
+            .border_type(BorderType::Plain)
+            .border_style(Style::default().fg(app.theme.border)),
+    );
+    frame.render_widget(list, chunks[0]);
+
+    let dark_theme = app.theme.name == "dark";
+    let (title, lines) = if app.file_history_content {
+        let title = format!(
+            "{} @ {} - [/]: older/newer, v: diff",
+            version.path, version.commit.short_hash
+        );
+        let lines = if version.change == FileChange::Deleted {
+            vec![Line::from("The file is deleted in this commit")]
+        } else {
+            let language = DiffParser::detect_language(&version.path);
+            let number_width = app.file_history_text.lines().count().to_string().len();
+            app.file_history_text
+                .lines()
+                .enumerate()
+                .map(|(i, line)| {
+                    let mut spans = vec![Span::styled(
+                        format!("{:>number_width$} │ ", i + 1),
+                        Style::default().fg(app.theme.diff_context),
+                    )];
+                    spans.extend(DiffParser::highlight(line, language, dark_theme));
+                    Line::from(spans)
+                })
+                .collect()
+        };
+        (title, lines)
+    } else {
+        let title = format!(
+            "Diff of {} in {} - v: full content, Enter: commit",
+            version.path, version.commit.short_hash
+        );
+        let parsed = DiffParser::from_file_diffs(&parse_diff(&app.file_history_text));
+        let lines = match app.word_diff {
+            true => DiffParser::to_styled_lines_word_diff(&parsed, dark_theme),
+            false => DiffParser::to_styled_lines(&parsed, dark_theme),
+        };
+        (title, lines)
+    };
+    let pane = Paragraph::new(lines)
+        .style(Style::default().fg(app.theme.text))
+        .scroll((app.file_history_scroll, 0))
+        .block(
+            Block::default()
+                .title(title)
+                .borders(Borders::ALL)
+                .border_type(BorderType::Plain)
+                .style(Style::default().fg(app.theme.border)),
+        );
+    frame.render_widget(pane, chunks[1]);
+    render_footer(app, area, frame);
+}
+
+pub(crate) fn render_path_input_view(app: &App, frame: &mut ratatui::Frame) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(2),
+            Constraint::Length(3),
+            Constraint::Min(10),
+            Constraint::Length(2),
+        ])
+        .split(frame.size());
+
+    let (name, purpose) = match app.path_action {
+        PathAction::Blame => ("Blame", "File to blame"),
+        PathAction::FileHistory => ("File History", "File to show the history of"),
+    };
+    let title = Paragraph::new(name)
+        .style(
+            Style::default()
+                .fg(app.theme.title)
+                .add_modifier(Modifier::BOLD),
+        )
+        .alignment(Alignment::Center);
+    title.render(chunks[0], frame.buffer_mut());
+
+    let prompt = Paragraph::new(format!(
+        "{}, relative to the repository root:\n{}",
+        purpose, app.status_message
+    ))
+    .style(Style::default().fg(app.theme.text));
+    prompt.render(chunks[1], frame.buffer_mut());
+
+    let input = Paragraph::new(format!("{} |", app.path_input)).style(
+        Style::default()
+            .fg(Color::Green)
+            .add_modifier(Modifier::BOLD),
+    );
+    input.render(chunks[2], frame.buffer_mut());
+
+    let help_text = format!(
+        "{}: Cancel | {}: {} | Theme: {}",
+        app.keybindings.actions.cancel,
+        app.keybindings.actions.confirm,
+        name,
+        app.theme.name()
+    );
+    let help_widget = Paragraph::new(help_text)
+        .style(Style::default().fg(app.theme.help))
+        .alignment(Alignment::Center);
+    help_widget.render(chunks[3], frame.buffer_mut());
+}
+
 pub(crate) fn render_help_overlay(app: &App, frame: &mut ratatui::Frame) {
     let chunks = Layout::default()
         .direction(Direction::Vertical)
@@ -273,7 +1030,9 @@ Views:
   i            Hunk staging (Diff view, file selected)
   s            Commit stats
   /            Search commits (Branches panel: search branches)
-  f            Filter by author/message/date
+  f            Filter by author/message/date/revset (Tab: type)
+  O            Operation log
+  L            Reflog
   Ctrl+P       Command palette
   ?            This help
 
@@ -285,22 +1044,69 @@ Actions:
   S            Squash commits
   C            Cherry-pick commit
   R            Revert commit
+  T            Restack commits left on rewritten parents
+  X            Split commit (pick lines for a new commit)
+  M            Resolve conflicts (pick ours/theirs/both/base per region)
+  N / K / Z    Continue / skip / abort the rebase, merge, ... in progress
+  B            Bisect (mark commits good/bad in the graph)
+  w            Blame the selected file (or a path you type)
+  H            File history (also from the Diff view)
   r            Re-apply filter
   Ctrl+N/P     Next/prev search result
   Ctrl+U       Unstage all files
   m            Toggle mouse support
   t            Cycle theme
 
+Diff view (Shift+D):
+  j/k, PgUp/PgDn  Scroll (both sides together)
+  s            Toggle side by side (unified when too narrow)
+  w            Toggle highlighting of changed words
+  i            Hunk staging (file selected)
+  H            File history of the file
+
 Hunk staging (i in Diff view):
   j/k or ↑/↓   Move line/hunk
   Space        Toggle line selection
   s / u        Stage / unstage selected
   Esc          Exit
 
+Conflict resolution (M):
+  j/k          Next/prev conflict
+  o / t / b    Take ours / theirs / both
+  a            Take the base (diff3/zdiff3)
+  e            Edit the region (Ctrl+S to keep)
+  Enter        Write and mark resolved
+
+Bisect (B):
+  g / b / s    Mark selected commit good / bad / skipped
+  r            Reset the bisect
+  Esc          Back (the bisect keeps going)
+
+Blame (w):
+  Enter        Show the commit of the line
+  p            Re-blame at the commit's parent
+  Backspace    Back to the previous blame
+  w / m / c    Toggle ignore whitespace / moves / copies
+
+File history (H):
+  j/k          Select a commit that changed the file
+  v / Tab      Toggle the commit's diff / the file's full content
+  [ / ]        Full content of the older / newer version
+  Enter        Show the commit
+
 Stash view:
   a / d / p    Apply / drop / pop stash
   Enter        View stash diff
 
+Operation log (O):
+  u            Undo selected operation (restore the state before it)
+  r            Redo the last undone operation
+
+Reflog (L):
+  Enter        Preview the entry's diff
+  c / b        Checkout / create branch at entry
+  r            Reset to entry (hard; undo from the operation log)
+
 Other:
   ?            Show this help
   q / Esc      Quit or go back"#;
@@ -345,9 +1151,12 @@ pub(crate) fn render_filter_view(app: &App, frame: &mut ratatui::Frame) {
     title.render(chunks[0], frame.buffer_mut());
 
     let filter_info = match app.filter_mode {
-        FilterMode::Author => "Filter by author (press a/m/d to change filter type)",
-        FilterMode::Message => "Filter by message (press a/m/d to change filter type)",
-        FilterMode::Date => "Filter by date YYYY-MM-DD (press a/m/d to change filter type)",
+        FilterMode::Author => "Filter by author (press Tab to change filter type)",
+        FilterMode::Message => "Filter by message (press Tab to change filter type)",
+        FilterMode::Date => "Filter by date YYYY-MM-DD (press Tab to change filter type)",
+        FilterMode::Revset => {
+            "Filter by revset, e.g. draft() & author(alice) (press Tab to change filter type)"
+        }
     };
 
     let filter_prompt = Paragraph::new(format!(
@@ -377,7 +1186,7 @@ pub(crate) fn render_filter_view(app: &App, frame: &mut ratatui::Frame) {
     input_display.render(chunks[2], frame.buffer_mut());
 
     let help_text = format!(
-        "Enter: Apply | Esc: Cancel | a/m/d: Filter type | Theme: {}",
+        "Enter: Apply | Esc: Cancel | Tab: Filter type | Theme: {}",
         app.theme.name()
     );
     let help_widget = Paragraph::new(help_text)
@@ -420,9 +1229,12 @@ Authors:
 
 Top Contributors:
 "#,
-        app.repo_path
+        app.vcs
             .as_ref()
-            .and_then(|p| p.file_name().map(|n| n.to_string_lossy().to_string()))
+            .and_then(|v| v
+                .repo_path()
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string()))
             .unwrap_or_else(|| "Unknown".to_string()),
         app.current_branch,
         app.stats.total_commits,
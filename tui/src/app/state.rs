@@ -30,6 +30,23 @@ pub enum ViewMode {
     CommandPalette,
     Stash,
     HunkStaging,
+    OperationLog,
+    Reflog,
+    Split,
+    SplitMessage,
+    Resolve,
+    ResolveEdit,
+    Bisect,
+    Blame,
+    PathInput,
+    FileHistory,
+}
+
+/// What the path typed in `ViewMode::PathInput` is for
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PathAction {
+    Blame,
+    FileHistory,
 }
 
 /// Represents the filter mode for commit filtering
@@ -38,6 +55,7 @@ pub enum FilterMode {
     Author,
     Message,
     Date,
+    Revset,
 }
 
 /// Represents a command action in the command palette
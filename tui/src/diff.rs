@@ -1,8 +1,11 @@
+use openisl_git::{ChangeKind, FileDiff, HunkLine, HunkLineType};
 use ratatui::prelude::{Color, Line, Modifier, Span, Style};
 use std::collections::HashMap;
+use std::ops::Range;
 use std::path::Path;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct ColoredDiffLine {
     pub content: String,
     pub line_type: DiffLineType,
@@ -742,6 +745,176 @@ fn get_types(language: &str) -> HashMap<&str, SyntaxHighlight> {
     types
 }
 
+/// Byte ranges of a line that changed
+pub type ChangedRanges = Vec<Range<usize>>;
+
+/// Lines longer than this many tokens are not diffed word by word
+const MAX_WORD_DIFF_TOKENS: usize = 400;
+
+/// Split a line into words, runs of whitespace and single punctuation
+/// characters, as byte ranges
+fn tokenize(line: &str) -> Vec<Range<usize>> {
+    let class = |c: char| match c {
+        c if c.is_alphanumeric() || c == '_' => 0,
+        c if c.is_whitespace() => 1,
+        _ => 2,
+    };
+    let mut tokens: Vec<Range<usize>> = Vec::new();
+    let mut previous = None;
+    for (i, c) in line.char_indices() {
+        let current = class(c);
+        match tokens.last_mut() {
+            Some(token) if previous == Some(current) && current != 2 => {
+                token.end = i + c.len_utf8()
+            }
+            _ => tokens.push(i..i + c.len_utf8()),
+        }
+        previous = Some(current);
+    }
+    tokens
+}
+
+/// Byte ranges of `old` and `new` that differ, word by word, after matching
+/// their longest common subsequence of tokens. `None` when the lines share
+/// too little for the emphasis to help, or are too long to compare.
+pub fn word_changes(old: &str, new: &str) -> Option<(ChangedRanges, ChangedRanges)> {
+    let (old_tokens, new_tokens) = (tokenize(old), tokenize(new));
+    if old_tokens.len().max(new_tokens.len()) > MAX_WORD_DIFF_TOKENS {
+        return None;
+    }
+    let (n, m) = (old_tokens.len(), new_tokens.len());
+    let same = |i: usize, j: usize| old[old_tokens[i].clone()] == new[new_tokens[j].clone()];
+    // lengths[i][j]: common tokens of old_tokens[i..] and new_tokens[j..]
+    let mut lengths = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lengths[i][j] = match same(i, j) {
+                true => lengths[i + 1][j + 1] + 1,
+                false => lengths[i + 1][j].max(lengths[i][j + 1]),
+            };
+        }
+    }
+
+    let (mut old_changed, mut new_changed) = (Vec::new(), Vec::new());
+    let mut common = 0;
+    let push = |ranges: &mut Vec<Range<usize>>, token: &Range<usize>| match ranges.last_mut() {
+        Some(last) if last.end == token.start => last.end = token.end,
+        _ => ranges.push(token.clone()),
+    };
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && same(i, j) {
+            common += old_tokens[i].len();
+            i += 1;
+            j += 1;
+        } else if j == m || (i < n && lengths[i + 1][j] >= lengths[i][j + 1]) {
+            push(&mut old_changed, &old_tokens[i]);
+            i += 1;
+        } else {
+            push(&mut new_changed, &new_tokens[j]);
+            j += 1;
+        }
+    }
+    // Mostly rewritten lines would be emphasised from end to end
+    if common * 2 < old.len().max(new.len()) {
+        return None;
+    }
+    Some((old_changed, new_changed))
+}
+
+/// Restyle the parts of `line` in `ranges` (byte offsets into the line's
+/// text), splitting its spans where needed and keeping their own styles
+fn emphasize(line: Line<'static>, ranges: &[Range<usize>], emphasis: Style) -> Line<'static> {
+    if ranges.is_empty() {
+        return line;
+    }
+    let mut spans = Vec::new();
+    let mut offset = 0;
+    for span in line.spans {
+        let text = span.content.as_ref();
+        let end = offset + text.len();
+        // Boundaries of the span's pieces, inside and outside the ranges
+        let mut cuts = vec![0, text.len()];
+        for range in ranges {
+            for cut in [range.start, range.end] {
+                if cut > offset && cut < end && text.is_char_boundary(cut - offset) {
+                    cuts.push(cut - offset);
+                }
+            }
+        }
+        cuts.sort_unstable();
+        cuts.dedup();
+        for piece in cuts.windows(2) {
+            let start = offset + piece[0];
+            let style = match ranges.iter().any(|r| r.start <= start && start < r.end) {
+                true => span.style.patch(emphasis),
+                false => span.style,
+            };
+            spans.push(Span::styled(text[piece[0]..piece[1]].to_string(), style));
+        }
+        offset = end;
+    }
+    Line { spans, ..line }
+}
+
+/// Cut `spans` to `width` columns, padding them with spaces when narrower
+fn fit(spans: Vec<Span<'static>>, width: usize) -> Vec<Span<'static>> {
+    let mut fitted = Vec::new();
+    let mut left = width;
+    for span in spans {
+        if left == 0 {
+            break;
+        }
+        let span_width = span.content.width();
+        if span_width <= left {
+            left -= span_width;
+            fitted.push(span);
+        } else {
+            // A wide character that would straddle the edge is left out
+            let mut cut = String::new();
+            for c in span.content.chars() {
+                let char_width = c.width().unwrap_or(0);
+                if char_width > left {
+                    break;
+                }
+                left -= char_width;
+                cut.push(c);
+            }
+            fitted.push(Span::styled(cut, span.style));
+            break;
+        }
+    }
+    if left > 0 {
+        fitted.push(Span::raw(" ".repeat(left)));
+    }
+    fitted
+}
+
+/// One side of a side-by-side diff row
+#[derive(Debug, Clone, PartialEq)]
+pub struct SplitCell {
+    /// Line number on this side (old on the left, new on the right)
+    pub line_number: usize,
+    /// The line without its `+`/`-`/` ` prefix
+    pub content: String,
+    pub line_type: DiffLineType,
+    /// Changed words, as byte ranges of `content`
+    pub changes: ChangedRanges,
+}
+
+/// A row of a side-by-side diff
+#[derive(Debug, Clone, PartialEq)]
+pub enum SplitRow {
+    /// A file header, hunk header or marker spanning both sides
+    Full(ColoredDiffLine),
+    /// Old and new lines side by side; `None` is a filler where the other
+    /// side has lines this one does not
+    Pair {
+        old: Option<SplitCell>,
+        new: Option<SplitCell>,
+    },
+}
+
 pub struct DiffParser;
 
 impl DiffParser {
@@ -783,7 +956,246 @@ impl DiffParser {
         lines
     }
 
-    pub fn to_styled_lines(lines: &[ColoredDiffLine], dark_theme: bool) -> Vec<Line<'static>> {
+    /// Display lines of parsed file diffs: a header per file naming the
+    /// change, then its hunks. Lines are numbered as in the new file, and
+    /// deletions as in the old one.
+    pub fn from_file_diffs(files: &[FileDiff]) -> Vec<ColoredDiffLine> {
+        let mut lines = Vec::new();
+        let mut push = |content: String, line_type, line_number| {
+            lines.push(ColoredDiffLine {
+                content,
+                line_type,
+                line_number,
+            })
+        };
+
+        for file in files {
+            let title = match (file.change_kind, &file.old_path) {
+                (ChangeKind::Renamed | ChangeKind::Copied, Some(old)) => {
+                    let mut title =
+                        format!("{} {} -> {}", file.change_kind.label(), old, file.path());
+                    if let Some(similarity) = file.similarity {
+                        title.push_str(&format!(" ({}% similar)", similarity));
+                    }
+                    title
+                }
+                (kind, _) => format!("{} {}", kind.label(), file.path()),
+            };
+            push(title, DiffLineType::Header, None);
+            if let (true, Some(old), Some(new)) =
+                (file.mode_changed(), &file.old_mode, &file.new_mode)
+            {
+                push(format!("mode {} -> {}", old, new), DiffLineType::Meta, None);
+            }
+            if file.is_binary {
+                push("Binary file".to_string(), DiffLineType::Meta, None);
+            }
+
+            for hunk in &file.hunks {
+                push(hunk.header.clone(), DiffLineType::HunkHeader, None);
+                let (mut old_line, mut new_line) = (hunk.old_start, hunk.new_start);
+                for line in &hunk.lines {
+                    let (line_type, number) = match line.line_type {
+                        HunkLineType::Addition => {
+                            new_line += 1;
+                            (DiffLineType::Addition, new_line - 1)
+                        }
+                        HunkLineType::Deletion => {
+                            old_line += 1;
+                            (DiffLineType::Deletion, old_line - 1)
+                        }
+                        HunkLineType::Context => {
+                            old_line += 1;
+                            new_line += 1;
+                            (DiffLineType::Context, new_line - 1)
+                        }
+                    };
+                    push(line.content.clone(), line_type, Some(number));
+                    if line.no_newline {
+                        push(
+                            "\\ No newline at end of file".to_string(),
+                            DiffLineType::Meta,
+                            None,
+                        );
+                    }
+                }
+            }
+        }
+        lines
+    }
+
+    /// Rows of a side-by-side diff: context lines on both sides, and each run
+    /// of deletions next to the additions that follow it, padded with fillers
+    /// on the shorter side. Paired lines carry their changed words.
+    pub fn split_rows(files: &[FileDiff]) -> Vec<SplitRow> {
+        let cell = |line: &HunkLine, line_number: usize, line_type: DiffLineType| SplitCell {
+            line_number,
+            content: line.content.get(1..).unwrap_or_default().to_string(),
+            line_type,
+            changes: Vec::new(),
+        };
+        let mut rows = Vec::new();
+        for file in files {
+            // Headers and markers are laid out as in the unified view
+            let headers = Self::from_file_diffs(std::slice::from_ref(file));
+            rows.extend(
+                headers
+                    .into_iter()
+                    .take_while(|line| line.line_type != DiffLineType::HunkHeader)
+                    .map(SplitRow::Full),
+            );
+            for hunk in &file.hunks {
+                rows.push(SplitRow::Full(ColoredDiffLine {
+                    content: hunk.header.clone(),
+                    line_type: DiffLineType::HunkHeader,
+                    line_number: None,
+                }));
+                let (mut old_line, mut new_line) = (hunk.old_start, hunk.new_start);
+                let mut i = 0;
+                while i < hunk.lines.len() {
+                    let line = &hunk.lines[i];
+                    if line.line_type == HunkLineType::Context {
+                        rows.push(SplitRow::Pair {
+                            old: Some(cell(line, old_line, DiffLineType::Context)),
+                            new: Some(cell(line, new_line, DiffLineType::Context)),
+                        });
+                        old_line += 1;
+                        new_line += 1;
+                        i += 1;
+                        continue;
+                    }
+                    let run = |from: usize, line_type: HunkLineType| {
+                        hunk.lines[from..]
+                            .iter()
+                            .take_while(|l| l.line_type == line_type)
+                            .count()
+                    };
+                    let deletions = run(i, HunkLineType::Deletion);
+                    let additions = run(i + deletions, HunkLineType::Addition);
+                    let removed = &hunk.lines[i..i + deletions];
+                    let added = &hunk.lines[i + deletions..i + deletions + additions];
+                    for k in 0..deletions.max(additions) {
+                        let mut old = removed
+                            .get(k)
+                            .map(|l| cell(l, old_line + k, DiffLineType::Deletion));
+                        let mut new = added
+                            .get(k)
+                            .map(|l| cell(l, new_line + k, DiffLineType::Addition));
+                        if let (Some(old), Some(new)) = (&mut old, &mut new) {
+                            if let Some((old_ranges, new_ranges)) =
+                                word_changes(&old.content, &new.content)
+                            {
+                                old.changes = old_ranges;
+                                new.changes = new_ranges;
+                            }
+                        }
+                        rows.push(SplitRow::Pair { old, new });
+                    }
+                    if removed.iter().chain(added).any(|l| l.no_newline) {
+                        rows.push(SplitRow::Full(ColoredDiffLine {
+                            content: "\\ No newline at end of file".to_string(),
+                            line_type: DiffLineType::Meta,
+                            line_number: None,
+                        }));
+                    }
+                    old_line += deletions;
+                    new_line += additions;
+                    i += deletions + additions;
+                }
+            }
+        }
+        rows
+    }
+
+    /// Styled lines of a side-by-side diff `width` columns wide: an old and
+    /// a new line number gutter, each side cut or padded to half the width,
+    /// and changed words tinted when `word_diff` is set
+    pub fn to_styled_split_lines(
+        rows: &[SplitRow],
+        width: usize,
+        word_diff: bool,
+        dark_theme: bool,
+    ) -> Vec<Line<'static>> {
+        let number_width = rows
+            .iter()
+            .filter_map(|row| match row {
+                SplitRow::Pair { old, new } => {
+                    old.iter().chain(new).map(|cell| cell.line_number).max()
+                }
+                SplitRow::Full(_) => None,
+            })
+            .max()
+            .unwrap_or(0)
+            .to_string()
+            .width();
+        // Gutter and space on each side, and a separator between them
+        let side_width = width.saturating_sub(1) / 2;
+        let content_width = side_width.saturating_sub(number_width + 1);
+        let number_style = Style::default().fg(if dark_theme {
+            Color::Rgb(100, 100, 100)
+        } else {
+            Color::Gray
+        });
+        let side = |cell: &Option<SplitCell>| -> Vec<Span<'static>> {
+            let Some(cell) = cell else {
+                let filler = format!("{:width$}", "", width = number_width + 1);
+                return vec![
+                    Span::styled(filler, number_style),
+                    Span::styled("╱".repeat(content_width), number_style),
+                ];
+            };
+            // Tabs are expanded, which moves the changed ranges; those
+            // lines keep their plain colour
+            let has_tabs = cell.content.contains('\t');
+            let content = cell.content.replace('\t', "    ");
+            let style = Self::line_style(&cell.line_type, dark_theme);
+            let mut line = Line::from(Span::styled(content, style));
+            if word_diff && !has_tabs {
+                let emphasis = Self::word_emphasis(&cell.line_type, dark_theme);
+                line = emphasize(line, &cell.changes, emphasis);
+            }
+            let mut spans = vec![Span::styled(
+                format!("{:>number_width$} ", cell.line_number),
+                number_style,
+            )];
+            spans.extend(fit(line.spans, content_width));
+            spans
+        };
+
+        rows.iter()
+            .map(|row| match row {
+                SplitRow::Full(line) => {
+                    let content: String = line.content.chars().take(width).collect();
+                    Line::styled(content, Self::line_style(&line.line_type, dark_theme))
+                }
+                SplitRow::Pair { old, new } => {
+                    let mut spans = side(old);
+                    spans.push(Span::styled("│", number_style));
+                    spans.extend(side(new));
+                    Line::from(spans)
+                }
+            })
+            .collect()
+    }
+
+    /// Totals of parsed file diffs
+    pub fn file_diff_stats(files: &[FileDiff]) -> DiffStats {
+        let additions = files.iter().map(FileDiff::additions).sum();
+        let deletions = files.iter().map(FileDiff::deletions).sum();
+        DiffStats {
+            additions,
+            deletions,
+            files_changed: files.len().max(1),
+            net_change: additions.saturating_sub(deletions),
+            current_file: files
+                .first()
+                .map(|file| file.path().to_string())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Colour of a whole line of the given type
+    fn line_style(line_type: &DiffLineType, dark_theme: bool) -> Style {
         let addition_fg = if dark_theme {
             Color::Rgb(0, 255, 127)
         } else {
@@ -815,27 +1227,99 @@ impl DiffParser {
             Color::DarkGray
         };
 
+        match line_type {
+            DiffLineType::Addition => Style::default()
+                .fg(addition_fg)
+                .add_modifier(Modifier::BOLD),
+            DiffLineType::Deletion => Style::default().fg(deletion_fg).add_modifier(Modifier::DIM),
+            DiffLineType::Header => Style::default().fg(header_fg).add_modifier(Modifier::BOLD),
+            DiffLineType::Meta => Style::default().fg(meta_fg),
+            DiffLineType::HunkHeader => Style::default()
+                .fg(hunk_header_fg)
+                .add_modifier(Modifier::BOLD),
+            DiffLineType::Context => Style::default().fg(context_fg),
+        }
+    }
+
+    /// Emphasis of changed words on lines of the given type
+    fn word_emphasis(line_type: &DiffLineType, dark_theme: bool) -> Style {
+        let bg = match (line_type, dark_theme) {
+            (DiffLineType::Addition, true) => Color::Rgb(0, 95, 45),
+            (DiffLineType::Addition, false) => Color::Rgb(170, 235, 170),
+            (_, true) => Color::Rgb(110, 30, 20),
+            (_, false) => Color::Rgb(255, 190, 190),
+        };
+        Style::default()
+            .bg(bg)
+            .add_modifier(Modifier::BOLD)
+            .remove_modifier(Modifier::DIM)
+    }
+
+    pub fn to_styled_lines(lines: &[ColoredDiffLine], dark_theme: bool) -> Vec<Line<'static>> {
         lines
             .iter()
             .map(|line| {
-                let style = match line.line_type {
-                    DiffLineType::Addition => Style::default()
-                        .fg(addition_fg)
-                        .add_modifier(Modifier::BOLD),
-                    DiffLineType::Deletion => {
-                        Style::default().fg(deletion_fg).add_modifier(Modifier::DIM)
-                    }
-                    DiffLineType::Header => {
-                        Style::default().fg(header_fg).add_modifier(Modifier::BOLD)
-                    }
-                    DiffLineType::Meta => Style::default().fg(meta_fg),
-                    DiffLineType::HunkHeader => Style::default()
-                        .fg(hunk_header_fg)
-                        .add_modifier(Modifier::BOLD),
-                    DiffLineType::Context => Style::default().fg(context_fg),
+                Line::styled(
+                    line.content.clone(),
+                    Self::line_style(&line.line_type, dark_theme),
+                )
+            })
+            .collect()
+    }
+
+    /// Changed parts of each line, word by word: deleted lines are paired in
+    /// order with the added lines that follow them, and each pair is diffed.
+    /// Ranges are byte offsets into the line's content, past its `+`/`-`.
+    pub fn intra_line_changes(lines: &[ColoredDiffLine]) -> Vec<ChangedRanges> {
+        let mut changes = vec![Vec::new(); lines.len()];
+        let mut i = 0;
+        while i < lines.len() {
+            let deletions = lines[i..]
+                .iter()
+                .take_while(|l| l.line_type == DiffLineType::Deletion)
+                .count();
+            let additions = lines[i + deletions..]
+                .iter()
+                .take_while(|l| l.line_type == DiffLineType::Addition)
+                .count();
+            if deletions == 0 || additions == 0 {
+                i += deletions.max(1);
+                continue;
+            }
+            for k in 0..deletions.min(additions) {
+                let (old, new) = (i + k, i + deletions + k);
+                let (Some(old_text), Some(new_text)) =
+                    (lines[old].content.get(1..), lines[new].content.get(1..))
+                else {
+                    continue;
                 };
+                if let Some((old_ranges, new_ranges)) = word_changes(old_text, new_text) {
+                    let shift = |r: Range<usize>| r.start + 1..r.end + 1;
+                    changes[old] = old_ranges.into_iter().map(shift).collect();
+                    changes[new] = new_ranges.into_iter().map(shift).collect();
+                }
+            }
+            i += deletions + additions;
+        }
+        changes
+    }
 
-                Line::styled(line.content.clone(), style)
+    /// `to_styled_lines` with the changed words of paired deletions and
+    /// additions on a tinted background (see `intra_line_changes`)
+    pub fn to_styled_lines_word_diff(
+        lines: &[ColoredDiffLine],
+        dark_theme: bool,
+    ) -> Vec<Line<'static>> {
+        let changes = Self::intra_line_changes(lines);
+        Self::to_styled_lines(lines, dark_theme)
+            .into_iter()
+            .zip(lines.iter().zip(&changes))
+            .map(|(styled, (line, ranges))| {
+                emphasize(
+                    styled,
+                    ranges,
+                    Self::word_emphasis(&line.line_type, dark_theme),
+                )
             })
             .collect()
     }
@@ -990,25 +1474,21 @@ impl DiffParser {
         }
     }
 
+    /// Syntax-highlighted spans of one line of source
+    pub fn highlight(content: &str, language: &str, dark_theme: bool) -> Vec<Span<'static>> {
+        if language == "plaintext" || content.trim().is_empty() {
+            return vec![Span::raw(content.to_string())];
+        }
+        highlight_line(content, language, &get_syntax_colors(dark_theme))
+    }
+
     pub fn apply_syntax_highlighting(
         content: &str,
         language: &str,
         is_addition: bool,
         dark_theme: bool,
     ) -> Line<'static> {
-        let theme_colors = get_syntax_colors(dark_theme);
-
-        let spans: Vec<Span<'static>> = if language == "plaintext" || content.trim().is_empty() {
-            vec![Span::raw(content.to_string())]
-        } else {
-            highlight_line(content, language, &theme_colors)
-                .into_iter()
-                .map(|s| Span::<'static> {
-                    content: s.content,
-                    style: s.style,
-                })
-                .collect()
-        };
+        let spans = Self::highlight(content, language, dark_theme);
 
         let base_style = if is_addition {
             Style::default().add_modifier(Modifier::BOLD)
@@ -1124,6 +1604,156 @@ mod tests {
         assert_eq!(deletions, 1);
     }
 
+    #[test]
+    fn test_from_file_diffs() {
+        let files = openisl_git::parse_diff(
+            "diff --git a/a.sh b/b.sh
+old mode 100644
+new mode 100755
+similarity index 80%
+rename from a.sh
+rename to b.sh
+--- a/a.sh
++++ b/b.sh
+@@ -2,2 +2,2 @@
+ keep
+-old
++new
+\\ No newline at end of file
+",
+        );
+        let lines = DiffParser::from_file_diffs(&files);
+        let contents: Vec<&str> = lines.iter().map(|l| l.content.as_str()).collect();
+        assert_eq!(
+            contents,
+            [
+                "Renamed a.sh -> b.sh (80% similar)",
+                "mode 100644 -> 100755",
+                "@@ -2,2 +2,2 @@",
+                " keep",
+                "-old",
+                "+new",
+                "\\ No newline at end of file",
+            ]
+        );
+        assert_eq!(lines[4].line_number, Some(3));
+        assert_eq!(lines[5].line_number, Some(3));
+        assert_eq!(lines[5].line_type, DiffLineType::Addition);
+
+        let stats = DiffParser::file_diff_stats(&files);
+        assert_eq!((stats.additions, stats.deletions), (1, 1));
+        assert_eq!(stats.current_file, "b.sh");
+    }
+
+    #[test]
+    fn test_word_changes() {
+        let (old, new) = word_changes("let total = count + 1;", "let total = count + 2;").unwrap();
+        assert_eq!(old, vec![(20..21)]);
+        assert_eq!(new, vec![(20..21)]);
+
+        // Insertions only mark the new side
+        let (old, new) = word_changes("call(a, b)", "call(a, x, b)").unwrap();
+        assert!(old.is_empty());
+        assert_eq!(&"call(a, x, b)"[new[0].clone()], "x, ");
+
+        // Unrelated lines are left alone
+        assert!(word_changes("fn main() {", "// a comment about things").is_none());
+    }
+
+    #[test]
+    fn test_intra_line_changes_pair_lines() {
+        let lines = DiffParser::parse(
+            "@@ -1,4 +1,3 @@\n context\n-old value\n-removed entirely\n+new value\n context",
+        );
+        let changes = DiffParser::intra_line_changes(&lines);
+        // `-old value` pairs with `+new value`; the second deletion has no pair
+        assert_eq!(changes[2], vec![(1..4)]);
+        assert_eq!(changes[4], vec![(1..4)]);
+        assert!(changes[3].is_empty());
+        assert!(changes[1].is_empty());
+
+        let styled = DiffParser::to_styled_lines_word_diff(&lines, true);
+        let spans: Vec<&str> = styled[4].spans.iter().map(|s| s.content.as_ref()).collect();
+        assert_eq!(spans, ["+", "new", " value"]);
+        assert!(styled[4].spans[1].style.bg.is_some());
+        assert!(styled[4].spans[2].style.bg.is_none());
+    }
+
+    #[test]
+    fn test_split_rows_align_with_fillers() {
+        let files = openisl_git::parse_diff(
+            "diff --git a/f.txt b/f.txt
+--- a/f.txt
++++ b/f.txt
+@@ -1,4 +1,4 @@
+ same
+-first old
+-second old
++first new
+ tail
++added
+",
+        );
+        let rows = DiffParser::split_rows(&files);
+        let pair = |row: &SplitRow| match row {
+            SplitRow::Pair { old, new } => (
+                old.as_ref().map(|c| (c.line_number, c.content.clone())),
+                new.as_ref().map(|c| (c.line_number, c.content.clone())),
+            ),
+            SplitRow::Full(line) => panic!("unexpected full row {:?}", line.content),
+        };
+        assert!(matches!(&rows[0], SplitRow::Full(l) if l.content == "Modified f.txt"));
+        assert!(matches!(&rows[1], SplitRow::Full(l) if l.line_type == DiffLineType::HunkHeader));
+        let s = |n: usize, text: &str| Some((n, text.to_string()));
+        assert_eq!(pair(&rows[2]), (s(1, "same"), s(1, "same")));
+        assert_eq!(pair(&rows[3]), (s(2, "first old"), s(2, "first new")));
+        // The deletion without a replacement faces a filler
+        assert_eq!(pair(&rows[4]), (s(3, "second old"), None));
+        assert_eq!(pair(&rows[5]), (s(4, "tail"), s(3, "tail")));
+        assert_eq!(pair(&rows[6]), (None, s(4, "added")));
+        assert_eq!(rows.len(), 7);
+        match &rows[3] {
+            SplitRow::Pair {
+                old: Some(old),
+                new: Some(new),
+            } => {
+                assert_eq!(old.changes, vec![(6..9)]);
+                assert_eq!(new.changes, vec![(6..9)]);
+            }
+            _ => panic!("expected a pair"),
+        }
+
+        let lines = DiffParser::to_styled_split_lines(&rows, 41, true, true);
+        let text = |line: &Line| {
+            line.spans
+                .iter()
+                .map(|s| s.content.as_ref())
+                .collect::<String>()
+        };
+        assert_eq!(text(&lines[4]), "3 second old        │  ╱╱╱╱╱╱╱╱╱╱╱╱╱╱╱╱╱╱");
+        assert!(lines.iter().skip(2).all(|l| text(l).chars().count() == 41));
+    }
+
+    #[test]
+    fn test_split_lines_fit_wide_characters() {
+        let files = openisl_git::parse_diff(
+            "diff --git a/f.txt b/f.txt
+--- a/f.txt
++++ b/f.txt
+@@ -1 +1 @@
+-a漢字漢字漢字漢字漢字
++short
+",
+        );
+        let rows = DiffParser::split_rows(&files);
+        let lines = DiffParser::to_styled_split_lines(&rows, 41, false, true);
+        let text: String = lines[2].spans.iter().map(|s| s.content.as_ref()).collect();
+        // Eight wide characters fill 16 of the 17 columns left after `a`; the
+        // ninth would straddle the separator, so a space takes its place
+        assert_eq!(text, "1 a漢字漢字漢字漢字 │1 short             ");
+        assert_eq!(text.width(), 41);
+    }
+
     #[test]
     fn test_diff_stats() {
         let diff = "diff --git a/src/main.rs b/src/main.rs\n@@ -1,3 +1,4 @@\n fn main() {\n+    println!(\"hello\");\n+    println!(\"added\");\n-    println!(\"removed\");\n }";
@@ -4,8 +4,8 @@ pub mod keybindings;
 pub mod theme;
 pub mod tree;
 
-pub use app::{run_tui, App, PanelType, ViewMode};
-pub use diff::{DiffLineType, DiffParser, DiffStats, SyntaxHighlight};
+pub use app::{run_tui, run_tui_with, App, PanelType, TuiOptions, ViewMode};
+pub use diff::{DiffLineType, DiffParser, DiffStats, SplitCell, SplitRow, SyntaxHighlight};
 pub use keybindings::KeyBindings;
 pub use theme::Theme;
 pub use tree::{CommitTree, CommitType, TreeNode};
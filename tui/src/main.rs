@@ -1,14 +1,18 @@
 use anyhow::Context;
-use openisl_git::get_commits;
 use openisl_tui::run_tui;
 
 fn main() -> anyhow::Result<()> {
     let repo_path = std::env::current_dir().context("Not in a directory")?;
 
-    let commits = get_commits(&repo_path, Some(100))?;
+    let vcs = openisl_git::vcs::open(&repo_path)?;
+    let commits = vcs
+        .log(Some(100), None, false)?
+        .into_iter()
+        .map(Into::into)
+        .collect();
     let current_branch = "main".to_string();
 
-    run_tui(commits, current_branch, Some(repo_path))?;
+    run_tui(commits, current_branch, Some(vcs))?;
 
     Ok(())
 }
@@ -298,8 +298,7 @@ pub fn format_tree_node<'a>(
         .commit
         .refs
         .iter()
-        .filter(|r| r.ref_type != openisl_git::RefType::Remote)
-        .filter(|r| !r.name.starts_with("HEAD"))
+        .filter(|r| r.ref_type == openisl_git::RefType::Branch)
         .map(|r| {
             let name = if r.name.starts_with("refs/heads/") {
                 &r.name[11..]
@@ -429,6 +428,10 @@ mod tests {
             date: Utc::now(),
             parent_hashes: parents.iter().map(|s| s.to_string()).collect(),
             refs: vec![],
+            committer: "Test".to_string(),
+            committer_email: "test@example.com".to_string(),
+            committer_date: Utc::now(),
+            trailers: vec![],
         }
     }
 
@@ -587,6 +590,10 @@ mod tests {
                 date: now - chrono::Duration::hours(1),
                 parent_hashes: vec!["c123456789abcde".to_string(), "d123456789abcde".to_string()],
                 refs: vec![],
+                committer: "test@example.com".to_string(),
+                committer_email: "test@example.com".to_string(),
+                committer_date: now - chrono::Duration::hours(1),
+                trailers: vec![],
             },
             Commit {
                 hash: "d123456789abcde".to_string(),
@@ -598,6 +605,10 @@ mod tests {
                 date: now - chrono::Duration::hours(2),
                 parent_hashes: vec!["b123456789abcde".to_string()],
                 refs: vec![],
+                committer: "test@example.com".to_string(),
+                committer_email: "test@example.com".to_string(),
+                committer_date: now - chrono::Duration::hours(2),
+                trailers: vec![],
             },
             Commit {
                 hash: "c123456789abcde".to_string(),
@@ -609,6 +620,10 @@ mod tests {
                 date: now - chrono::Duration::hours(3),
                 parent_hashes: vec!["b123456789abcde".to_string()],
                 refs: vec![],
+                committer: "test@example.com".to_string(),
+                committer_email: "test@example.com".to_string(),
+                committer_date: now - chrono::Duration::hours(3),
+                trailers: vec![],
             },
             Commit {
                 hash: "b123456789abcde".to_string(),
@@ -620,6 +635,10 @@ mod tests {
                 date: now - chrono::Duration::hours(4),
                 parent_hashes: vec!["a123456789abcde".to_string()],
                 refs: vec![],
+                committer: "test@example.com".to_string(),
+                committer_email: "test@example.com".to_string(),
+                committer_date: now - chrono::Duration::hours(4),
+                trailers: vec![],
             },
             Commit {
                 hash: "a123456789abcde".to_string(),
@@ -631,6 +650,10 @@ mod tests {
                 date: now - chrono::Duration::hours(5),
                 parent_hashes: vec![],
                 refs: vec![],
+                committer: "test@example.com".to_string(),
+                committer_email: "test@example.com".to_string(),
+                committer_date: now - chrono::Duration::hours(5),
+                trailers: vec![],
             },
         ];
         let tree = CommitTree::new(commits);
@@ -650,6 +673,10 @@ mod tests {
                 date: now - chrono::Duration::hours(1), // Most recent
                 parent_hashes: vec!["b123456789abcde".to_string()],
                 refs: vec![],
+                committer: "test@example.com".to_string(),
+                committer_email: "test@example.com".to_string(),
+                committer_date: now - chrono::Duration::hours(1),
+                trailers: vec![],
             },
             Commit {
                 hash: "b123456789abcde".to_string(),
@@ -661,6 +688,10 @@ mod tests {
                 date: now - chrono::Duration::hours(2), // Middle
                 parent_hashes: vec!["a123456789abcde".to_string()],
                 refs: vec![],
+                committer: "test@example.com".to_string(),
+                committer_email: "test@example.com".to_string(),
+                committer_date: now - chrono::Duration::hours(2),
+                trailers: vec![],
             },
             Commit {
                 hash: "a123456789abcde".to_string(),
@@ -672,6 +703,10 @@ mod tests {
                 date: now - chrono::Duration::hours(3), // Oldest
                 parent_hashes: vec![],
                 refs: vec![],
+                committer: "test@example.com".to_string(),
+                committer_email: "test@example.com".to_string(),
+                committer_date: now - chrono::Duration::hours(3),
+                trailers: vec![],
             },
         ]
     }